@@ -3,13 +3,35 @@ pub mod track_lib;
 // Imports
 use chrono::Utc;
 use once_cell::sync::Lazy;
+use track_lib::aprs_is;
+use track_lib::arduino::ArduinoStatus;
+use track_lib::database::{FlightRecord, StoredFix};
+use track_lib::export::{ExportFormat, PredictedPath};
+use track_lib::fix_validator::{RejectedFix, ValidationLimits};
+use track_lib::flight_phase::{FlightPhase, FlightStatus, PhaseThresholds};
+use track_lib::fusion::{FusedPosition, FusionWeights};
+use track_lib::horus;
+use track_lib::kalman::KalmanEstimate;
+use track_lib::look_angle::{GroundStation, LookAngles};
+use track_lib::position_time::PositionTime;
+use track_lib::station::{self, StationStatus};
+use track_lib::storage::StorageStatus;
+use track_lib::rotator::{controller::RotatorStatus, rotctld::{self, Rotctld}, serial_rotator::{self, RotatorProtocol, SerialRotator}};
+use track_lib::rfd;
+use track_lib::comment_parser::{CommentRule, TelemetrySeries};
+use track_lib::stream_link::StreamLink;
 use track_lib::tracker::Tracker;
-use track_lib::pred::predictor::{PredictionManager, PredictionParams, PredictionResult};
+use track_lib::poller::SourceSnapshot;
+use track_lib::tracking_source::SourceStatus;
+use track_lib::tracking_type::TrackingType;
+use track_lib::velocity::VelocityEstimate;
+use track_lib::pred::predictor::{PredictionManager, PredictionParams};
 use track_lib::pred::sondhub_predictor::SondeHubPredictor;
-use std::{sync::Mutex, time::{SystemTime, UNIX_EPOCH}};
+use std::{sync::{Arc, Mutex}, time::{SystemTime, UNIX_EPOCH}};
+use tauri::{AppHandle, Emitter};
 use dotenvy::dotenv;
 use std::env;
-use std::fs;
+use std::path::{Path, PathBuf};
 use serde::{Serialize, Deserialize};
 
 pub struct Coords {
@@ -47,6 +69,27 @@ pub struct PredictionData {
     burst: Option<PredictionPoint>,
     landing: Option<PredictionPoint>,
     descent: Vec<PredictionPoint>,
+    // Bearing and distance from the ground station to the predicted landing
+    landing_from_station: Option<LookAngles>,
+    // Flight phase the prediction was run for
+    phase: FlightPhase,
+}
+
+impl From<PredictionPoint> for PositionTime {
+    fn from(p: PredictionPoint) -> Self {
+        PositionTime { lat: p.lat, lon: p.lon, alt: p.alt, last_update: p.time, horiz_vel: 0.0, vert_vel: 0.0, heading: None }
+    }
+}
+
+impl From<PredictionData> for PredictedPath {
+    fn from(data: PredictionData) -> Self {
+        PredictedPath {
+            ascent: data.ascent.into_iter().map(PositionTime::from).collect(),
+            burst: data.burst.map(PositionTime::from),
+            landing: data.landing.map(PositionTime::from),
+            descent: data.descent.into_iter().map(PositionTime::from).collect(),
+        }
+    }
 }
 
 impl Coords {
@@ -61,13 +104,16 @@ impl Coords {
 }
 
 // Globals
+// Server relaying the Iridium modem messages
+const IRIDIUM_BASE_URL: &str = "https://borealis.rci.montana.edu";
+
 pub static IRIDIUM_MODEM: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
 pub static APRS_CALLSIGN: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
 pub static TRACKER: Lazy<Mutex<Tracker>> = Lazy::new(|| Mutex::new(Tracker::new()));
 pub static LOCATION: Lazy<Mutex<Coords>> = Lazy::new(|| Mutex::new(Coords::new()));
 pub static FILTERING_METHOD: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::from("Recent")));
 pub static PREDICTION_MANAGER: Lazy<Mutex<PredictionManager>> = Lazy::new(|| Mutex::new(PredictionManager::new()));
-pub static SONDEHUB_PREDICTOR: Lazy<SondeHubPredictor> = Lazy::new(|| SondeHubPredictor::new());
+pub static SONDEHUB_PREDICTOR: Lazy<SondeHubPredictor> = Lazy::new(SondeHubPredictor::new);
 
 //API Keys
 pub static APRSFI_API_KEY: Lazy<String> = Lazy::new(|| {
@@ -106,7 +152,7 @@ fn utc() -> String {
 // Return the current date
 #[tauri::command]
 fn date() -> String {
-    format!("Date: {}", Utc::now().date_naive().to_string())
+    format!("Date: {}", Utc::now().date_naive())
 }
 
 // Set the Iridium modem ID
@@ -172,101 +218,434 @@ fn set_aprsfi_api_key(key: String) {
 
 // Init APRS with current callsign
 #[tauri::command]
-fn set_aprs() -> bool {
+fn set_aprs() -> Result<bool, String> {
     let aprs_call = APRS_CALLSIGN.lock().unwrap();
     if !aprs_call.is_empty() {
-        // choose override key if present
-        let key = if let Some(o) = APRSFI_API_KEY_OVERRIDE.lock().unwrap().clone() {
-            o
-        } else {
-            APRSFI_API_KEY.as_str().to_string()
-        };
-        TRACKER.lock().unwrap().new_aprs(key.as_str(), aprs_call.as_str());
-        true
+        TRACKER.lock().unwrap().new_aprs(get_aprsfi_api_key().as_str(), aprs_call.as_str()).map_err(|e| e.to_string())?;
+        Ok(true)
     } else {
-        false
+        Ok(false)
     }
 }
 
-///Create the Arduino Module and collect errors into String
-// #[tauri::command]
-// fn set_arduino() -> String{
-//     let mut tracker =TRACKER.lock().unwrap();
-    
-//     if !tracker.is_arduino_active(){
-//         TRACKER.lock().unwrap().new_arduino(None, None);
-//         let t= TRACKER.lock().unwrap().setup_arduino();
-//         let mut r = String::new();
-//         for err in t {
-//             r = format!("{}\nERROR: {:?}\n", r, err);
-//         }
-//         return r;
-//     }
-
-//     "".into()
-// }
+// Stream APRS positions from APRS-IS; without a passcode the connection is receive-only
+#[tauri::command]
+fn set_aprs_is(login: String, passcode: Option<i32>, callsign: String, server: Option<String>) -> Result<bool, String> {
+    if login.is_empty() || callsign.is_empty() {
+        return Ok(false);
+    }
+    let server = server.filter(|s| !s.is_empty()).unwrap_or_else(|| aprs_is::DEFAULT_SERVER.to_string());
+    let passcode = passcode.unwrap_or(aprs_is::RECEIVE_ONLY_PASSCODE);
+    println!("Setting up APRS-IS on {} for {}", server, callsign);
+    TRACKER.lock().unwrap().new_aprs_is(&server, &login, passcode, &callsign).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+// Receive APRS from a KISS TNC over TCP (eg. Direwolf on localhost:8001)
+#[tauri::command]
+fn set_kiss_tcp(address: String, callsign: String) -> Result<bool, String> {
+    if address.is_empty() || callsign.is_empty() {
+        return Ok(false);
+    }
+    println!("Setting up KISS TNC at {} for {}", address, callsign);
+    TRACKER.lock().unwrap().new_kiss(StreamLink::Tcp(address), &callsign).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+// Receive APRS from a serial KISS modem
+#[tauri::command]
+fn set_kiss_serial(port: String, baud: u32, callsign: String) -> Result<bool, String> {
+    if port.is_empty() || callsign.is_empty() {
+        return Ok(false);
+    }
+    println!("Setting up KISS modem on {} ({} baud) for {}", port, baud, callsign);
+    TRACKER.lock().unwrap().new_kiss(StreamLink::Serial { port, baud }, &callsign).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+// Receive the Horus Binary payload `callsign` from horusdemodlib
+#[tauri::command]
+fn set_horus(port: Option<u16>, callsign: String) -> Result<bool, String> {
+    if callsign.is_empty() {
+        return Ok(false);
+    }
+    let port = port.unwrap_or(horus::DEFAULT_PORT);
+    println!("Setting up Horus on UDP port {} for {}", port, callsign);
+    TRACKER.lock().unwrap().new_horus(port, &callsign).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+// Receive UKHAS sentences from a TCP receiver; `fields` names the fields following the altitude
+#[tauri::command]
+fn set_ukhas_tcp(address: String, callsign: String, fields: Option<Vec<String>>) -> Result<bool, String> {
+    if address.is_empty() || callsign.is_empty() {
+        return Ok(false);
+    }
+    println!("Setting up UKHAS receiver at {} for {}", address, callsign);
+    TRACKER.lock().unwrap().new_ukhas(StreamLink::Tcp(address), &callsign, fields.unwrap_or_default()).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+// Receive UKHAS sentences from a serial receiver
+#[tauri::command]
+fn set_ukhas_serial(port: String, baud: u32, callsign: String, fields: Option<Vec<String>>) -> Result<bool, String> {
+    if port.is_empty() || callsign.is_empty() {
+        return Ok(false);
+    }
+    println!("Setting up UKHAS receiver on {} ({} baud) for {}", port, baud, callsign);
+    TRACKER.lock().unwrap().new_ukhas(StreamLink::Serial { port, baud }, &callsign, fields.unwrap_or_default()).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+// Receive NEBP telemetry frames from an RFD900 modem; `layout` names the columns of the payload's frames
+#[tauri::command]
+fn set_rfd(port: String, baud: Option<u32>, layout: Vec<String>) -> Result<bool, String> {
+    if port.is_empty() {
+        return Ok(false);
+    }
+    let baud = baud.unwrap_or(rfd::DEFAULT_BAUD);
+    println!("Setting up RFD modem on {} ({} baud)", port, baud);
+    TRACKER.lock().unwrap().new_rfd(&port, baud, layout).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+// Stop a receiver added from the connections panel
+#[tauri::command]
+fn remove_source(track_type: String, id: String) -> Result<bool, String> {
+    let track_type = TrackingType::from_name(&track_type).ok_or_else(|| format!("Unknown source type {}", track_type))?;
+    Ok(TRACKER.lock().unwrap().remove_source(track_type, &id))
+}
+
+/// Connect the Arduino antenna tracker, on the given port or the first one detected
+#[tauri::command]
+fn connect_arduino(port: Option<String>) -> Result<String, String> {
+    let port = port.filter(|p| !p.is_empty());
+    let pointing = TRACKER.lock().unwrap().pointing();
+    pointing.connect_arduino(port.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn disconnect_arduino() -> Result<(), String> {
+    let pointing = TRACKER.lock().unwrap().pointing();
+    pointing.disconnect_arduino().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn get_arduino_status() -> ArduinoStatus {
+    TRACKER.lock().unwrap().pointing().arduino_status()
+}
+
+/// Drive a Hamlib rotator through rotctld, on `localhost:4533` by default
+#[tauri::command]
+fn connect_rotctld(address: Option<String>) -> Result<(), String> {
+    let address = address.filter(|a| !a.is_empty()).unwrap_or_else(|| rotctld::DEFAULT_ADDRESS.to_string());
+    println!("Setting up rotctld rotator at {}", address);
+    let pointing = TRACKER.lock().unwrap().pointing();
+    pointing.connect_rotator(Box::new(Rotctld::new(&address))).map_err(|e| e.to_string())
+}
+
+/// Drive a GS-232 or EasyComm rotator on a serial port
+#[tauri::command]
+fn connect_serial_rotator(protocol: String, port: String, baud: Option<u32>) -> Result<(), String> {
+    let protocol = RotatorProtocol::from_name(&protocol).ok_or(format!("Unknown rotator protocol: {}", protocol))?;
+    if port.is_empty() {
+        return Err("No serial port given".into());
+    }
+    let baud = baud.unwrap_or(serial_rotator::DEFAULT_BAUD);
+    println!("Setting up {:?} rotator on {} ({} baud)", protocol, port, baud);
+    let pointing = TRACKER.lock().unwrap().pointing();
+    pointing.connect_rotator(Box::new(SerialRotator::new(protocol, &port, baud))).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn disconnect_rotator() -> Result<(), String> {
+    let pointing = TRACKER.lock().unwrap().pointing();
+    pointing.disconnect_rotator().map_err(|e| e.to_string())
+}
+
+/// Commanded and actual pointing of the rotator, None when no rotator is connected
+#[tauri::command]
+fn get_rotator_status() -> Option<RotatorStatus> {
+    TRACKER.lock().unwrap().pointing().rotator_status()
+}
+
+/// Read the actual rotator position, returned with the commanded one
+#[tauri::command]
+fn get_rotator_feedback() -> Result<RotatorStatus, String> {
+    let pointing = TRACKER.lock().unwrap().pointing();
+    pointing.rotator_feedback().map_err(|e| e.to_string())
+}
+
+/// Set where the rotator parks when tracking stops; without azimuth and elevation it stays in place
+#[tauri::command]
+fn set_rotator_park(azimuth: Option<f64>, elevation: Option<f64>) -> Result<(), String> {
+    let park = azimuth.zip(elevation);
+    let pointing = TRACKER.lock().unwrap().pointing();
+    pointing.set_rotator_park(park).map_err(|e| e.to_string())
+}
+
+/// Set the smallest move (degrees) and shortest time between two commands (seconds) of the rotator
+#[tauri::command]
+fn set_rotator_limits(deadband: f64, min_interval: f64) -> Result<(), String> {
+    let pointing = TRACKER.lock().unwrap().pointing();
+    pointing.set_rotator_limits(deadband, min_interval).map_err(|e| e.to_string())
+}
+
+/// Start or stop pointing the antenna at the payload
+#[tauri::command]
+fn set_antenna_tracking(enabled: bool) -> Result<(), String> {
+    // Device I/O happens on the pointing thread, never while holding the tracker
+    let (pointing, angles) = {
+        let tracker = TRACKER.lock().unwrap();
+        (tracker.pointing(), tracker.look_angles().ok())
+    };
+    pointing.set_tracking(enabled).map_err(|e| e.to_string())?;
+    if let Some(angles) = angles {
+        pointing.point(angles.azimuth, angles.elevation);
+    }
+    Ok(())
+}
+
+/// Set the ground station location used for antenna pointing
+#[tauri::command]
+fn set_ground_station(lat: f64, lon: f64, alt: f64) {
+    TRACKER.lock().unwrap().set_ground_station(GroundStation::new(lat, lon, alt));
+}
+
+#[tauri::command]
+fn get_ground_station() -> Option<GroundStation> {
+    TRACKER.lock().unwrap().get_ground_station()
+}
+
+/// Follow the ground station location from an NMEA 0183 GPS on a serial port
+#[tauri::command]
+fn set_station_nmea(port: String, baud: Option<u32>) -> Result<bool, String> {
+    if port.is_empty() {
+        return Ok(false);
+    }
+    let baud = baud.unwrap_or(station::DEFAULT_NMEA_BAUD);
+    println!("Setting up station GPS on {} ({} baud)", port, baud);
+    TRACKER.lock().unwrap().set_station_nmea(&port, baud).map_err(|e| e.to_string())?;
+    Ok(true)
+}
+
+/// Follow the ground station location from gpsd, on `localhost:2947` by default
+#[tauri::command]
+fn set_station_gpsd(address: Option<String>) -> Result<(), String> {
+    let address = address.filter(|a| !a.is_empty()).unwrap_or_else(|| station::DEFAULT_GPSD_ADDRESS.to_string());
+    println!("Setting up station gpsd at {}", address);
+    TRACKER.lock().unwrap().set_station_gpsd(&address).map_err(|e| e.to_string())
+}
+
+/// Current ground station fix and where it comes from
+#[tauri::command]
+fn get_station_status() -> StationStatus {
+    TRACKER.lock().unwrap().station_status()
+}
+
+/// Azimuth, elevation, slant range and ground distance from the ground station to the payload
+#[tauri::command]
+fn get_heading() -> Result<LookAngles, String> {
+    TRACKER.lock().unwrap().look_angles().map_err(|e| e.to_string())
+}
 
 // Init Iridium modem with current ID
 #[tauri::command]
-fn set_iridium() -> bool {
+fn set_iridium() -> Result<bool, String> {
     let modem = IRIDIUM_MODEM.lock().unwrap();
     if !modem.is_empty() {
         println!("Setting up iridium with modem: {}", modem);
-        TRACKER.lock().unwrap().new_iridium("https://borealis.rci.montana.edu", modem.as_str());
-        true
+        TRACKER.lock().unwrap().new_iridium(IRIDIUM_BASE_URL, modem.as_str()).map_err(|e| e.to_string())?;
+        Ok(true)
     } else {
         println!("Cannot set up iridium: modem is empty");
-        false
+        Ok(false)
+    }
+}
+
+// ==================== Flight Commands ====================
+
+// Poll aprs.fi and SondeHub for the callsigns and Iridium for the IMEIs of a flight
+fn start_payload_sources(flight: &FlightRecord) -> Result<(), String> {
+    let key = get_aprsfi_api_key();
+    let mut tracker = TRACKER.lock().unwrap();
+    for call_sign in &flight.call_signs {
+        tracker.new_aprs(&key, call_sign).map_err(|e| e.to_string())?;
+    }
+    for imei in &flight.imeis {
+        tracker.new_iridium(IRIDIUM_BASE_URL, imei).map_err(|e| e.to_string())?;
+    }
+    drop(tracker);
+
+    // Show the first payload in the connection settings
+    if let Some(call_sign) = flight.call_signs.first() {
+        *APRS_CALLSIGN.lock().unwrap() = call_sign.clone();
+    }
+    if let Some(imei) = flight.imeis.first() {
+        *IRIDIUM_MODEM.lock().unwrap() = imei.clone();
     }
+    Ok(())
 }
 
-// Update tracker position
+/// Start a named flight tracking the given callsigns and Iridium IMEIs, ending the current one
 #[tauri::command]
-fn update() -> String {
-    let t = TRACKER.lock().unwrap().update();
-    let mut r = String::new();
-    for err in t {
-        r = format!("{}\nERROR: {:?}\n", r, err);
+fn start_flight(name: String, call_signs: Vec<String>, imeis: Vec<String>) -> Result<FlightRecord, String> {
+    let clean = |ids: Vec<String>| ids.into_iter().map(|id| id.trim().to_string()).filter(|id| !id.is_empty()).collect::<Vec<_>>();
+    let name = if name.trim().is_empty() { Tracker::default_flight_name() } else { name.trim().to_string() };
+    let flight = TRACKER.lock().unwrap()
+        .start_flight(&name, &clean(call_signs), &clean(imeis))
+        .map_err(|e| e.to_string())?;
+    start_payload_sources(&flight)?;
+    Ok(flight)
+}
+
+/// End the current flight and stop polling its sources
+#[tauri::command]
+fn end_flight() -> Result<(), String> {
+    TRACKER.lock().unwrap().end_flight().map_err(|e| e.to_string())
+}
+
+/// Reopen a past or interrupted flight and restart its payload sources
+#[tauri::command]
+fn resume_flight(id: i64) -> Result<FlightRecord, String> {
+    let mut tracker = TRACKER.lock().unwrap();
+    // The payloads of the flight already being recorded are running
+    let restart = tracker.flight_id() != Some(id);
+    let flight = tracker.resume_flight(id).map_err(|e| e.to_string())?;
+    refresh_location(&tracker);
+    drop(tracker);
+    if restart {
+        start_payload_sources(&flight)?;
     }
-    
+    Ok(flight)
+}
+
+// Carry on with the flight the app was closed during, with its payloads and receivers
+fn resume_open_flight() {
+    let open = TRACKER.lock().unwrap().open_flight();
+    match open {
+        Ok(Some(flight)) => {
+            if let Err(e) = resume_flight(flight.id) {
+                eprintln!("Unable to resume flight {}: {}", flight.id, e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Unable to look for an open flight: {}", e),
+    }
+}
+
+/// Every recorded flight, newest first
+#[tauri::command]
+fn get_flights() -> Result<Vec<FlightRecord>, String> {
+    TRACKER.lock().unwrap().flights().map_err(|e| e.to_string())
+}
+
+/// Flight being recorded, None outside of a flight
+#[tauri::command]
+fn get_current_flight() -> Result<Option<FlightRecord>, String> {
+    TRACKER.lock().unwrap().current_flight().map_err(|e| e.to_string())
+}
+
+/// Folder holding the flight database and exports, and whether it is usable
+#[tauri::command]
+fn get_storage_status() -> StorageStatus {
+    TRACKER.lock().unwrap().storage_status()
+}
+
+/// Store the data in `path` from now on, an empty path goes back to the OS data directory
+#[tauri::command]
+fn set_data_dir(path: String) -> Result<StorageStatus, String> {
+    let dir = Some(path.trim()).filter(|p| !p.is_empty()).map(PathBuf::from);
+    TRACKER.lock().unwrap().set_data_dir(dir).map_err(|e| e.to_string())
+}
+
+/// Payload of the `position-update` event pushed to the frontend on every new fix
+#[derive(Serialize, Clone)]
+pub struct PositionUpdate {
+    lat: f64,
+    lon: f64,
+    alt: f64,
+    horiz_vel: f64,
+    vert_vel: f64,
+    heading: Option<f64>,
+    last_update: u64,
+    track_type: String,
+    id: String,
+}
+
+// Recompute the filtered position shown in the UI from the tracker
+fn refresh_location(tracker: &Tracker) {
     let filtering_method = FILTERING_METHOD.lock().unwrap().clone();
-    let estimation_type = match filtering_method.as_str() {
-        "Average" => track_lib::position_time::EstimationType::Average,
-        "Median" => track_lib::position_time::EstimationType::Median,
-        "Recent" => track_lib::position_time::EstimationType::Recent,
-        _ => track_lib::position_time::EstimationType::Recent,
-    };
+    let estimation_type = track_lib::position_time::EstimationType::from_name(&filtering_method);
     
-    let tracker_guard = TRACKER.lock().unwrap();
-    let pos = tracker_guard.get_position();
-    let pos_filtered = tracker_guard.get_position_with_filtering(estimation_type);
-    let velocities = tracker_guard.get_velocities();
-    let last_update = tracker_guard.get_last_update();
+    let pos_filtered = tracker.get_position_with_filtering(estimation_type);
+    let velocities = tracker.get_velocities();
     
-    LOCATION.try_lock().unwrap().update((
+    LOCATION.lock().unwrap().update((
         (pos_filtered.0 * 1000.0).round() / 1000.0,
         (pos_filtered.1 * 1000.0).round() / 1000.0,
         (pos_filtered.2 * 1000.0).round() / 1000.0,
         (velocities.0 * 1000.0).round() / 1000.0,
         (velocities.1 * 1000.0).round() / 1000.0,
     ));
-    
-    println!("Update result: {}", r);
-    println!("Raw position: {:?}", pos);
-    println!("Filtered position: lat={}, lon={}, alt={}", 
-             pos_filtered.0, pos_filtered.1, pos_filtered.2);
-    println!("Stored velocities: horiz_vel={}, vert_vel={}, last_update={}", 
-             velocities.0, velocities.1, last_update);
-    
-    drop(tracker_guard);
+}
+
+// Run on a polling thread after each source update; pushes new fixes to the UI
+fn on_source_polled(app: &AppHandle, snapshot: &SourceSnapshot, new_fix: bool, running: &dyn Fn() -> bool) {
+    if new_fix {
+        let mut tracker = TRACKER.lock().unwrap();
+        // Sources are stopped under the tracker lock, one stopped while waiting for it belongs to an ended flight
+        if !running() {
+            return;
+        }
+        if let Some(rejected) = tracker.record_fix(snapshot) {
+            drop(tracker);
+            if let Err(e) = app.emit("fix-rejected", rejected) {
+                eprintln!("Failed to emit fix-rejected: {}", e);
+            }
+        } else {
+            refresh_location(&tracker);
+            let (lat, lon, alt) = tracker.get_position();
+            let (horiz_vel, vert_vel) = tracker.get_velocities();
+            let payload = PositionUpdate {
+                lat,
+                lon,
+                alt,
+                horiz_vel,
+                vert_vel,
+                heading: tracker.get_course(),
+                last_update: tracker.get_last_update(),
+                track_type: snapshot.tracking_type.to_string(),
+                id: snapshot.id.clone(),
+            };
+            drop(tracker);
+
+            if let Err(e) = app.emit("position-update", payload) {
+                eprintln!("Failed to emit position-update: {}", e);
+            }
+        }
+    }
+
+    if let Err(e) = app.emit("source-status", snapshot.status()) {
+        eprintln!("Failed to emit source-status: {}", e);
+    }
+}
+
+// Latest errors of the sources, the tracked position is refreshed by the polling threads on every fix
+#[tauri::command]
+fn update() -> String {
+    let tracker = TRACKER.lock().unwrap();
+    let mut r = String::new();
+    for err in tracker.source_errors() {
+        r = format!("{}\nERROR: {}\n", r, err);
+    }
     r
 }
 
 // Get full position
 #[tauri::command]
 fn get_position() -> (f64, f64, f64) {
-    let (l1, l2, alt) = TRACKER.try_lock().unwrap().get_position();
-    println!("LOCATION: {}, {}, {}", l1, l2, alt);
+    let (l1, l2, alt) = TRACKER.lock().unwrap().get_position();
     (
         (l1 * 1000.0).round() / 1000.0,
         (l2 * 1000.0).round() / 1000.0,
@@ -284,6 +663,18 @@ fn get_vert_vel() -> f64 {
     TRACKER.lock().unwrap().get_velocities().1
 }
 
+/// Course over ground in degrees, None while standing still or unknown
+#[tauri::command]
+fn get_course() -> Option<f64> {
+    TRACKER.lock().unwrap().get_course()
+}
+
+/// Ground speed, course and smoothed vertical rate over the newest fixes
+#[tauri::command]
+fn get_velocity() -> Option<VelocityEstimate> {
+    TRACKER.lock().unwrap().velocity()
+}
+
 // Get latitude
 #[tauri::command]
 fn get_lat() -> f64 {
@@ -305,7 +696,7 @@ fn get_alt() -> f64 {
 // Get time since last update in seconds
 #[tauri::command]
 fn get_last_update() -> u64 {
-    let last = TRACKER.try_lock().unwrap().get_last_update();
+    let last = TRACKER.lock().unwrap().get_last_update();
     if last != 0 {
         let current_time = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -320,41 +711,17 @@ fn get_last_update() -> u64 {
 //Returns if APRS is currently active
 #[tauri::command]
 fn is_aprs_active() -> bool{
-    let a = TRACKER.try_lock().unwrap().return_aprs();
-    let s = TRACKER.try_lock().unwrap().return_sondehub();
-
-    for aprs in a{
-        if aprs.is_some(){
-            let aprs_unwrapped = aprs.unwrap();
-            if aprs_unwrapped.get_last_update() != 0{
-                return true
-            }
-        }
-    }
-    for sondehub in s {
-        if sondehub.is_some(){
-            let s = sondehub.unwrap();
-            if s.get_last_update() != 0{
-                return true
-            }
-        }
-    }
-    return false;
+    TRACKER.lock().unwrap()
+        .sources_of(&[TrackingType::APRS, TrackingType::SondeHub, TrackingType::KISS, TrackingType::AprsIs])
+        .into_iter().any(|s| s.get_last_update() != 0)
 }
 
 //Returns if Iridium is currently active
 #[tauri::command]
 fn is_iridium_active() -> bool{
-    let a = TRACKER.try_lock().unwrap().return_iridium();
-    for iridium in a {
-        if iridium.is_some(){
-            let a = iridium.unwrap();
-            if a.get_last_update() != 0{
-                return true
-            }
-        }
-    }
-    return false;
+    TRACKER.lock().unwrap()
+        .sources_of(&[TrackingType::Iridium])
+        .into_iter().any(|s| s.get_last_update() != 0)
 }
 
 // Get current filtering method
@@ -366,111 +733,177 @@ fn get_filtering_method() -> String {
 // Set filtering method
 #[tauri::command]
 fn set_filtering_method(method: String) {
+    let estimation_type = track_lib::position_time::EstimationType::from_name(&method);
     *FILTERING_METHOD.lock().unwrap() = method;
+    let mut tracker = TRACKER.lock().unwrap();
+    tracker.set_estimation_type(estimation_type);
+    refresh_location(&tracker);
+}
+
+/// Fixes dropped by the validator, oldest first
+#[tauri::command]
+fn get_rejected_fixes() -> Vec<RejectedFix> {
+    TRACKER.lock().unwrap().rejected_fixes()
+}
+
+#[tauri::command]
+fn get_validation_limits() -> ValidationLimits {
+    TRACKER.lock().unwrap().validation_limits()
+}
+
+/// Replace the bounds fixes must respect to be used
+#[tauri::command]
+fn set_validation_limits(limits: ValidationLimits) {
+    TRACKER.lock().unwrap().set_validation_limits(limits);
+}
+
+/// Detected flight phase with its transition times and the burst point
+#[tauri::command]
+fn get_flight_status() -> FlightStatus {
+    TRACKER.lock().unwrap().flight_status()
+}
+
+/// Replace the thresholds of the flight phase detection
+#[tauri::command]
+fn set_phase_thresholds(thresholds: PhaseThresholds) {
+    TRACKER.lock().unwrap().set_phase_thresholds(thresholds);
+}
+
+/// Quality weighted position of the sources, with the share of each source
+#[tauri::command]
+fn get_fused_position() -> Option<FusedPosition> {
+    TRACKER.lock().unwrap().fused_position()
+}
+
+#[tauri::command]
+fn get_fusion_weights() -> FusionWeights {
+    TRACKER.lock().unwrap().fusion_weights()
+}
+
+/// Set how fix age, declared precision, measured velocities and source reliability weigh in the fusion
+#[tauri::command]
+fn set_fusion_weights(weights: FusionWeights) {
+    TRACKER.lock().unwrap().set_fusion_weights(weights);
+}
+
+/// Smoothed position, velocities and covariance of the Kalman filter
+#[tauri::command]
+fn get_kalman_estimate() -> Option<KalmanEstimate> {
+    TRACKER.lock().unwrap().kalman_estimate()
+}
+
+/// Set the 1-sigma noise (m) the Kalman filter assumes for the fixes of a source type
+#[tauri::command]
+fn set_kalman_noise(track_type: String, horizontal: f64, vertical: f64) -> Result<(), String> {
+    let tracking_type = TrackingType::from_name(&track_type).ok_or(format!("Unknown source type: {}", track_type))?;
+    TRACKER.lock().unwrap().set_measurement_noise(tracking_type, horizontal, vertical).map_err(|e| e.to_string())
 }
 
 /// count of active APRS instances
 #[tauri::command]
 fn get_aprs_count() -> usize {
-    TRACKER.try_lock().unwrap().return_aprs().iter().filter(|a| a.is_some()).count()
+    TRACKER.lock().unwrap().sources_of(&[TrackingType::APRS]).len()
 }
 
 /// count of active Iridium instances
 #[tauri::command]
 fn get_iridium_count() -> usize {
-    TRACKER.try_lock().unwrap().return_iridium().iter().filter(|i| i.is_some()).count()
+    TRACKER.lock().unwrap().sources_of(&[TrackingType::Iridium]).len()
 }
 
 // count of active SondeHub instances
 #[tauri::command]
 fn get_sondehub_count() -> usize {
-    TRACKER.try_lock().unwrap().return_sondehub().iter().filter(|s| s.is_some()).count()
+    TRACKER.lock().unwrap().sources_of(&[TrackingType::SondeHub]).len()
 }
 
 /// Check if APRS instances have legit position data
 #[tauri::command]
 fn get_aprs_validity() -> Vec<bool> {
-    TRACKER.try_lock().unwrap().return_aprs().iter().map(|a| {
-        if let Some(aprs) = a {
-            aprs.get_last_update() != 0
-        } else {
-            false
-        }
-    }).collect()
+    TRACKER.lock().unwrap().sources_of(&[TrackingType::APRS]).into_iter().map(|a| a.get_last_update() != 0).collect()
 }
 
 /// Check if Iridium instances have legit position data
 #[tauri::command]
 fn get_iridium_validity() -> Vec<bool> {
-    TRACKER.try_lock().unwrap().return_iridium().iter().map(|i| {
-        if let Some(iridium) = i {
-            iridium.get_last_update() != 0
-        } else {
-            false
-        }
-    }).collect()
+    TRACKER.lock().unwrap().sources_of(&[TrackingType::Iridium]).into_iter().map(|i| i.get_last_update() != 0).collect()
+}
+
+/// Health report of every registered tracking source
+#[tauri::command]
+fn get_source_health() -> Vec<SourceStatus> {
+    TRACKER.lock().unwrap().source_statuses()
+}
+
+/// Set the rules used to parse the comments of a callsign into sensor channels
+#[tauri::command]
+fn set_comment_rules(callsign: String, rules: Vec<CommentRule>) -> Result<(), String> {
+    TRACKER.lock().unwrap().set_comment_rules(&callsign, rules)
+}
+
+#[tauri::command]
+fn get_comment_rules(callsign: String) -> Vec<CommentRule> {
+    TRACKER.lock().unwrap().get_comment_rules(&callsign)
 }
 
-///Read most recent CSV file from the Launch Data folder and return tracking points
+/// Every sensor channel received so far, one time series per channel
+#[tauri::command]
+fn get_telemetry_series() -> Vec<TelemetrySeries> {
+    TRACKER.lock().unwrap().telemetry_series()
+}
+
+///Return the fixes used for the current flight, or the last recorded one, as tracking points
 #[tauri::command]
 fn get_tracking_history() -> Vec<TrackingPoint> {
-    let current_dir = std::env::current_dir().expect("Could not determine current directory");
-    let folder_path = current_dir.join("Launch Data");
-    
-    if !folder_path.exists() {
-        return vec![];
-    }
-    
-    let mut latest_file = None;
-    let mut latest_time = 0u64;
-    
-    if let Ok(entries) = fs::read_dir(&folder_path) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.extension().map_or(false, |ext| ext == "csv") {
-                if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                    if let Some(time_str) = filename.strip_prefix("data").and_then(|s| s.strip_suffix(".csv")) {
-                        if let Ok(time) = time_str.parse::<u64>() {
-                            if time > latest_time {
-                                latest_time = time;
-                                latest_file = Some(path);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    
-    let mut points = vec![];
-    
-    if let Some(file_path) = latest_file {
-        if let Ok(content) = fs::read_to_string(&file_path) {
-            for line in content.lines().skip(1) {
-                let parts: Vec<&str> = line.split(',').collect();
-                if parts.len() >= 7 {
-                    if let (Ok(lat), Ok(lon), Ok(alt), Ok(time)) = (
-                        parts[1].parse::<f64>(),
-                        parts[2].parse::<f64>(),
-                        parts[3].parse::<f64>(),
-                        parts[6].parse::<u64>()
-                    ) {
-                        let track_type = parts[0].to_string();
-                        points.push(TrackingPoint {
-                            lat,
-                            lon,
-                            alt,
-                            time,
-                            track_type
-                        });
-                    }
-                }
-            }
+    let fixes = match TRACKER.lock().unwrap().stored_fixes(None, None, false) {
+        Ok(fixes) => fixes,
+        Err(e) => {
+            eprintln!("Unable to read tracking history: {}", e);
+            return vec![];
         }
+    };
+    fixes.into_iter().map(|f| TrackingPoint {
+        lat: f.pos_time.lat,
+        lon: f.pos_time.lon,
+        alt: f.pos_time.alt,
+        time: f.pos_time.last_update,
+        track_type: f.track_type,
+    }).collect()
+}
+
+/// Raw fixes of the flight between two unix timestamps, rejected ones included if asked
+#[tauri::command]
+fn get_stored_fixes(from: Option<u64>, to: Option<u64>, include_rejected: bool) -> Result<Vec<StoredFix>, String> {
+    TRACKER.lock().unwrap().stored_fixes(from, to, include_rejected).map_err(|e| e.to_string())
+}
+
+/// Tracked (fused) positions of the flight between two unix timestamps
+#[tauri::command]
+fn get_stored_track(from: Option<u64>, to: Option<u64>) -> Result<Vec<PositionTime>, String> {
+    TRACKER.lock().unwrap().stored_track(from, to).map_err(|e| e.to_string())
+}
+
+/// Export the fixes of the flight to a CSV file, returns the number of rows written
+#[tauri::command]
+fn export_flight_csv(path: String) -> Result<usize, String> {
+    TRACKER.lock().unwrap().export_csv(Path::new(&path)).map_err(|e| e.to_string())
+}
+
+/// Export the tracked path of the flight to KML, GPX, GeoJSON or CZML, with the latest prediction if asked. Returns the number of track points written
+#[tauri::command]
+fn export_flight(path: String, format: ExportFormat, include_prediction: bool) -> Result<usize, String> {
+    let mut tracker = TRACKER.lock().unwrap();
+    let mut export = tracker.flight_export().map_err(|e| e.to_string())?;
+    if include_prediction {
+        let prediction = tracker.latest_prediction().map_err(|e| e.to_string())?;
+        export.prediction = prediction
+            .map(|json| serde_json::from_str::<PredictionData>(&json))
+            .transpose()
+            .map_err(|e| format!("Unable to read the stored prediction: {}", e))?
+            .map(PredictedPath::from);
     }
-    
-    points.sort_by_key(|p| p.time);
-    points
+    drop(tracker);
+    export.write(Path::new(&path), format).map_err(|e| e.to_string())
 }
 
 // ==================== Prediction Commands ====================
@@ -536,6 +969,8 @@ fn run_prediction() -> Result<PredictionData, String> {
     let (lat, lon, alt) = tracker.get_position();
     let (horiz_vel, vert_vel) = tracker.get_velocities();
     let last_update = tracker.get_last_update();
+    let station = tracker.get_ground_station();
+    let flight = tracker.flight_status();
     drop(tracker);
     
     if last_update == 0 {
@@ -549,16 +984,16 @@ fn run_prediction() -> Result<PredictionData, String> {
         last_update,
         horiz_vel,
         vert_vel,
+        heading: None,
     };
     
     // Run prediction using the selected predictor
     let mut manager = PREDICTION_MANAGER.lock().unwrap();
     let predictor_name = manager.get_predictor().to_string();
-    let params = manager.get_params().clone();
     
     let result = match predictor_name.as_str() {
         "SondeHub" => {
-            manager.run_prediction(&current_pos, &*SONDEHUB_PREDICTOR)
+            manager.run_prediction(&current_pos, &*SONDEHUB_PREDICTOR, flight.phase)
         },
         _ => {
             return Err(format!("Unknown predictor: {}", predictor_name));
@@ -569,8 +1004,8 @@ fn run_prediction() -> Result<PredictionData, String> {
         Ok(pred_result) => {
             println!("Prediction completed successfully");
             
-            //check if balloon has already burst
-            let has_burst = current_pos.alt >= params.burst_altitude || current_pos.vert_vel < 0.0;
+            //check if the balloon burst, as detected over the fix history. A floating balloon has not burst yet
+            let has_burst = flight.burst_time.is_some();
             
             // Convert to serializable format
             let mut ascent: Vec<PredictionPoint> = pred_result.ascent.iter().map(|p| PredictionPoint {
@@ -580,13 +1015,16 @@ fn run_prediction() -> Result<PredictionData, String> {
                 time: p.last_update,
             }).collect();
             
-            let burst = pred_result.burst.map(|p| PredictionPoint {
+            // Once descending, the detected burst replaces the predicted one
+            let burst = flight.burst.or(pred_result.burst).map(|p| PredictionPoint {
                 lat: p.lat,
                 lon: p.lon,
                 alt: p.alt,
                 time: p.last_update,
             });
             
+            let landing_from_station = station.zip(pred_result.landing.as_ref()).map(|(s, p)| s.look_angles(p));
+
             let landing = pred_result.landing.map(|p| PredictionPoint {
                 lat: p.lat,
                 lon: p.lon,
@@ -603,17 +1041,22 @@ fn run_prediction() -> Result<PredictionData, String> {
             
             //if burst already, remove ascent points and ensure burst is the starting point for descent
             if has_burst {
-                println!("Balloon has already burst (alt: {}, burst_alt: {}, vert_vel: {}). Clearing ascent trajectory.", 
-                         current_pos.alt, params.burst_altitude, current_pos.vert_vel);
+                println!("Balloon burst at {:?} (phase: {}, alt: {}, vert_vel: {}). Clearing ascent trajectory.",
+                         flight.burst_time, flight.phase, current_pos.alt, current_pos.vert_vel);
                 ascent.clear();
             }
             
-            Ok(PredictionData {
+            let data = PredictionData {
                 ascent,
                 burst,
                 landing,
                 descent,
-            })
+                landing_from_station,
+                phase: flight.phase,
+            };
+            drop(manager);
+            TRACKER.lock().unwrap().record_prediction(&predictor_name, &data);
+            Ok(data)
         },
         Err(e) => {
             println!("Prediction failed: {}", e);
@@ -630,19 +1073,44 @@ pub fn run() {
     
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
+        .setup(|app| {
+            // Let the background pollers push new fixes to the frontend
+            let handle = app.handle().clone();
+            TRACKER.lock().unwrap().set_poll_hook(Arc::new(move |snapshot, new_fix, running| {
+                on_source_polled(&handle, snapshot, new_fix, running)
+            }));
+            resume_open_flight();
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             utc, date, 
             set_irr_modem, get_irr_modem, 
             set_aprs_callsign, get_aprs_callsign, 
             set_aprs, set_iridium,
+            set_aprs_is, set_kiss_tcp, set_kiss_serial, set_horus, remove_source,
+            set_ukhas_tcp, set_ukhas_serial, set_rfd,
+            connect_arduino, disconnect_arduino, get_arduino_status, set_antenna_tracking,
+            set_ground_station, get_ground_station, get_heading,
+            set_station_nmea, set_station_gpsd, get_station_status,
+            connect_rotctld, connect_serial_rotator, disconnect_rotator, get_rotator_status,
+            get_rotator_feedback, set_rotator_park, set_rotator_limits,
             update, 
             get_position, get_lat, get_long, get_alt,
-            get_horiz_vel, get_vert_vel,
+            get_horiz_vel, get_vert_vel, get_course, get_velocity,
             get_last_update, is_aprs_active, is_iridium_active,
-            get_filtering_method, set_filtering_method,
+            get_filtering_method, set_filtering_method, get_kalman_estimate, set_kalman_noise,
+            get_rejected_fixes, get_validation_limits, set_validation_limits,
+            get_fused_position, get_fusion_weights, set_fusion_weights,
+            get_flight_status, set_phase_thresholds,
             get_aprs_count, get_iridium_count, get_sondehub_count,
             get_aprs_validity, get_iridium_validity,
-            get_tracking_history,
+            get_source_health,
+            set_comment_rules,
+            get_comment_rules,
+            get_telemetry_series,
+            get_tracking_history, get_stored_fixes, get_stored_track, export_flight_csv, export_flight,
+            start_flight, end_flight, resume_flight, get_flights, get_current_flight,
+            get_storage_status, set_data_dir,
             set_prediction_params, get_prediction_params,
             set_predictor, get_predictor, run_prediction,
             get_stadia_api_key,
@@ -1,13 +1,17 @@
 use reqwest::blocking::Client;
 use serde_json::Value;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use crate::track_lib::aprs_parser::ambiguity_precision;
 use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::tracking_source::{SourceHealth, TrackingSource};
 use crate::track_lib::tracking_type::TrackingType;
 
+//Seconds between two aprs.fi requests, long enough to respect the API rate limits
+const POLL_INTERVAL_SECS: u64 = 30;
+
 #[derive(Clone)]
 pub struct APRS {
-    active: bool,
     tracking_type: TrackingType,
     api_key: String,
     base_url: String,
@@ -20,28 +24,31 @@ pub struct APRS {
     comment: String,
     symbol: String,
     path: String,
+    precision: Option<f64>,
+    health: SourceHealth,
 }
 
 impl APRS {
     pub fn new(api_key: &str, call_sign: &str) -> Self {
         Self {
-            active: true,
             tracking_type: TrackingType::APRS,
             api_key: api_key.to_string(),
             base_url: "https://api.aprs.fi/api".to_string(),
             call_sign: call_sign.to_string(),
             client: Client::new(),
-            position_time: PositionTime {lat:0.0, lon:0.0, alt:0.0, last_update:0, horiz_vel:0.0, vert_vel:0.0},
+            position_time: PositionTime {lat:0.0, lon:0.0, alt:0.0, last_update:0, horiz_vel:0.0, vert_vel:0.0, heading:None},
             vertical_velocity: 0.0,
             ground_speed: 0.0,
             datetime: 0.0,
             comment: String::new(),
             symbol: String::new(),
             path: String::new(),
+            precision: None,
+            health: SourceHealth::new(),
         }
     }
 
-    pub fn update_position(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn fetch_position(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         let url = format!(
             "{}/get?name={}&what=loc&apikey={}&format=json",
             self.base_url, self.call_sign, self.api_key
@@ -76,8 +83,11 @@ impl APRS {
                 let alt = latest_entry["altitude"].as_f64()
                     .unwrap_or(0.0);
                 
-                self.ground_speed = latest_entry["speed"].as_f64()
+                // aprs.fi reports the speed in km/h
+                self.ground_speed = number(&latest_entry["speed"])
+                    .map(|kmh| kmh / 3.6)
                     .unwrap_or(0.0);
+                let course = number(&latest_entry["course"]);
                 
                 // We dont have vertical velocity in APRS API
                 self.vertical_velocity = 0.0;
@@ -88,6 +98,7 @@ impl APRS {
                         self.datetime = time;
                         // update position_time with velocities
                         self.position_time.update(lat, lon, alt, time as u64, self.ground_speed, self.vertical_velocity);
+                        self.position_time.heading = course;
                     }
                 } else {
                     // fallback update even if time missing
@@ -98,6 +109,7 @@ impl APRS {
                 self.comment = latest_entry["comment"].as_str().unwrap_or("").to_string();
                 self.symbol = latest_entry["symbol"].as_str().unwrap_or("").to_string();
                 self.path = latest_entry["path"].as_str().unwrap_or("").to_string();
+                self.precision = number(&latest_entry["posambiguity"]).and_then(|digits| ambiguity_precision(digits as u8));
                 
                 // Print current position data
                 let current_time = SystemTime::now()
@@ -109,7 +121,7 @@ impl APRS {
 
                 
                 println!(
-                    "APRS Position: Call: {}, Lat: {}, Lon: {}, Alt: {}m, Speed: {} km/h, Last Update: {}s ago",
+                    "APRS Position: Call: {}, Lat: {}, Lon: {}, Alt: {}m, Speed: {:.1} m/s, Last Update: {}s ago",
                     self.call_sign, self.position_time.lat, self.position_time.lon, self.position_time.alt, self.ground_speed, age_seconds
                 );
                 
@@ -120,23 +132,57 @@ impl APRS {
         Err("Failed to parse position data from response".into())
     }
     
-    pub fn get_pos_time(&self) -> PositionTime{
-        self.position_time.clone()
-    }
-
     pub fn get_position(&self) -> (f64, f64, f64) {
         (self.position_time.lat, self.position_time.lon, self.position_time.alt)
     }
     
+    /// Ground speed in m/s
     pub fn get_speed(&self) -> f64 {
         self.ground_speed
     }
-    
-    pub fn get_last_update(&self) -> u64 {
+}
+
+/// Number sent either as a JSON number or as a string
+fn number(value: &Value) -> Option<f64> {
+    value.as_f64().or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+}
+
+impl TrackingSource for APRS {
+    fn id(&self) -> &str {
+        &self.call_sign
+    }
+
+    fn tracking_type(&self) -> TrackingType {
+        self.tracking_type
+    }
+
+    fn update_position(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let result = self.fetch_position();
+        self.health.record(&result);
+        result
+    }
+
+    fn get_pos_time(&self) -> PositionTime {
+        self.position_time.clone()
+    }
+
+    fn get_last_update(&self) -> u64 {
         self.position_time.last_update
     }
-    
-    pub fn get_comment(&self) -> &str {
+
+    fn health(&self) -> SourceHealth {
+        self.health.evaluate(self.position_time.last_update)
+    }
+
+    fn get_comment(&self) -> &str {
         &self.comment
     }
-}
\ No newline at end of file
+
+    fn precision(&self) -> Option<f64> {
+        self.precision
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(POLL_INTERVAL_SECS)
+    }
+}
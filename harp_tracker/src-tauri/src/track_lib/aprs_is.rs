@@ -0,0 +1,307 @@
+use std::{
+    error::Error,
+    io::{ErrorKind, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::track_lib::aprs_parser::{AprsPacket, AprsStation, TelemetryChannel, Tnc2Packet};
+use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::stream_link::{LineBuffer, MAX_LINE_LEN};
+use crate::track_lib::tracking_source::{SourceHealth, TrackingSource};
+use crate::track_lib::tracking_type::TrackingType;
+
+//Default APRS-IS server pool, port 14580 accepts server-side filters
+pub const DEFAULT_SERVER: &str = "rotate.aprs2.net:14580";
+
+//Passcode for receive-only connections
+pub const RECEIVE_ONLY_PASSCODE: i32 = -1;
+
+//Timeouts for the APRS-IS connection (ms)
+const CONNECT_TIMEOUT_MS: u64 = 5000;
+const READ_TIMEOUT_MS: u64 = 200;
+
+//Servers send a keepalive comment every ~20s, reconnect if nothing arrives for this long
+const SILENCE_TIMEOUT_SECS: u64 = 90;
+
+//Packets are pushed by the server, so drain the socket often
+const POLL_INTERVAL_SECS: u64 = 1;
+
+/// Persistent APRS-IS connection filtered server-side on the tracked callsign
+pub struct AprsIs {
+    tracking_type: TrackingType,
+    server: String,
+    login: String,
+    passcode: i32,
+    call_sign: String,
+    stream: Option<TcpStream>,
+    lines: LineBuffer,
+    last_rx: Instant,
+    station: AprsStation,
+    health: SourceHealth,
+}
+
+impl AprsIs {
+    /// `login` and `passcode` identify the ground station, `call_sign` is the payload to track
+    pub fn new(server: &str, login: &str, passcode: i32, call_sign: &str) -> Self {
+        Self {
+            tracking_type: TrackingType::AprsIs,
+            server: server.to_string(),
+            login: login.to_uppercase(),
+            passcode,
+            call_sign: call_sign.to_uppercase(),
+            stream: None,
+            lines: LineBuffer::new(MAX_LINE_LEN),
+            last_rx: Instant::now(),
+            station: AprsStation::new(call_sign),
+            health: SourceHealth::new(),
+        }
+    }
+
+    /// Connect, log in and set the `b/CALLSIGN` budlist filter
+    fn connect(&mut self) -> Result<(), Box<dyn Error>> {
+        let addr = self.server.to_socket_addrs()?.next().ok_or("Could not resolve APRS-IS server")?;
+        let mut stream = TcpStream::connect_timeout(&addr, Duration::from_millis(CONNECT_TIMEOUT_MS))?;
+        stream.set_read_timeout(Some(Duration::from_millis(READ_TIMEOUT_MS)))?;
+
+        // A callsign without SSID also follows its SSIDs
+        let filter = if self.call_sign.contains('-') {
+            self.call_sign.clone()
+        } else {
+            format!("{}*", self.call_sign)
+        };
+        let login = format!(
+            "user {} pass {} vers HARPTracker {} filter b/{}\r\n",
+            self.login, self.passcode, env!("CARGO_PKG_VERSION"), filter
+        );
+        stream.write_all(login.as_bytes())?;
+
+        self.stream = Some(stream);
+        self.lines.clear();
+        self.last_rx = Instant::now();
+        println!("APRS-IS connected to {} with filter b/{}", self.server, filter);
+        Ok(())
+    }
+
+    fn fetch_position(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.stream.is_none() {
+            self.connect()?;
+        }
+
+        let stream = self.stream.as_mut().ok_or("APRS-IS not connected")?;
+        let mut lines = vec![];
+        let result = self.lines.read_lines(stream, &mut lines);
+        for line in lines {
+            if let Err(e) = self.handle_line(&line) {
+                eprintln!("APRS-IS: ignoring packet: {}", e);
+            }
+        }
+        match result {
+            Ok(0) => {}
+            Ok(_) => self.last_rx = Instant::now(),
+            Err(e) => {
+                self.stream = None;
+                return Err(match e.kind() {
+                    ErrorKind::InvalidData => format!("APRS-IS {}, reconnecting", e),
+                    _ => format!("APRS-IS server: {}", e),
+                }.into());
+            }
+        }
+
+        if self.last_rx.elapsed() > Duration::from_secs(SILENCE_TIMEOUT_SECS) {
+            self.stream = None;
+            return Err("APRS-IS connection went silent, reconnecting".into());
+        }
+        Ok(())
+    }
+
+    fn handle_line(&mut self, line: &str) -> Result<(), Box<dyn Error>> {
+        // Server comments, including the login response
+        if line.starts_with('#') {
+            if line.contains("unverified") || line.contains("logresp") {
+                println!("APRS-IS: {}", line.trim_end());
+            }
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+
+        let raw = Tnc2Packet::parse(line)?;
+        if !self.station.wants(&raw.source, &raw.info) {
+            return Ok(());
+        }
+        let packet = AprsPacket::decode(&raw.source, &raw.destination, raw.path, &raw.info)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let source = packet.source.clone();
+        if self.station.handle(packet, now) {
+            let pos = &self.station.position_time;
+            println!("APRS-IS Position: Call: {}, Lat: {}, Lon: {}, Alt: {}m", source, pos.lat, pos.lon, pos.alt);
+        }
+        Ok(())
+    }
+}
+
+impl TrackingSource for AprsIs {
+    fn id(&self) -> &str {
+        &self.call_sign
+    }
+
+    fn tracking_type(&self) -> TrackingType {
+        self.tracking_type
+    }
+
+    fn update_position(&mut self) -> Result<(), Box<dyn Error>> {
+        let result = self.fetch_position();
+        self.health.record(&result);
+        result
+    }
+
+    fn get_pos_time(&self) -> PositionTime {
+        self.station.position_time.clone()
+    }
+
+    fn get_last_update(&self) -> u64 {
+        self.station.position_time.last_update
+    }
+
+    fn health(&self) -> SourceHealth {
+        self.health.evaluate(self.station.position_time.last_update)
+    }
+
+    fn get_comment(&self) -> &str {
+        &self.station.comment
+    }
+
+    /// Latest telemetry of the station, named and scaled by its PARM/UNIT/EQNS definitions
+    fn get_telemetry(&self) -> &[TelemetryChannel] {
+        &self.station.telemetry
+    }
+
+    fn precision(&self) -> Option<f64> {
+        self.station.precision
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(POLL_INTERVAL_SECS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+
+    /// Local APRS-IS server answering the login, then sending the canned lines of each connection in turn
+    fn canned_server(connections: Vec<Vec<String>>) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let mut logins = vec![];
+            for lines in connections {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut login = String::new();
+                BufReader::new(stream.try_clone().unwrap()).read_line(&mut login).unwrap();
+                logins.push(login);
+                stream.write_all(b"# logresp N0CALL unverified, server T2TEST\r\n").unwrap();
+                for line in lines {
+                    stream.write_all(line.as_bytes()).unwrap();
+                }
+                thread::sleep(Duration::from_millis(500));
+            }
+            logins
+        });
+        (address, server)
+    }
+
+    fn poll_until_fix(source: &mut AprsIs, after: u64) {
+        let started = Instant::now();
+        while source.get_last_update() <= after && started.elapsed() < Duration::from_secs(2) {
+            let _ = source.update_position();
+        }
+    }
+
+    #[test]
+    fn logs_in_with_filter_and_decodes_packets() {
+        let (address, server) = canned_server(vec![vec![
+            "OTHER-9>APRS,TCPIP*:!1000.00N/02000.00E>\r\n".to_string(),
+            "N0CALL-11>APRS,WIDE2-1,qAR,GATE:!4903.50N/07201.75W>088/036/A=001234\r\n".to_string(),
+        ]]);
+        let mut source = AprsIs::new(&address, "n0call", 12345, "n0call");
+        poll_until_fix(&mut source, 0);
+
+        let pos = source.get_pos_time();
+        assert!((pos.lat - 49.058333).abs() < 1e-5);
+        assert!((pos.lon + 72.029167).abs() < 1e-5);
+        assert!((pos.alt - 376.1232).abs() < 1e-3);
+
+        drop(source);
+        let logins = server.join().unwrap();
+        assert!(logins[0].starts_with("user N0CALL pass 12345 vers HARPTracker"));
+        assert!(logins[0].trim_end().ends_with("filter b/N0CALL*"));
+    }
+
+    #[test]
+    fn reconnects_after_the_server_closes() {
+        let (address, _server) = canned_server(vec![
+            vec!["N0CALL-11>APRS:!4903.50N/07201.75W>\r\n".to_string()],
+            vec!["N0CALL-11>APRS:!4904.50N/07201.75W>\r\n".to_string()],
+        ]);
+        let mut source = AprsIs::new(&address, "N0CALL", RECEIVE_ONLY_PASSCODE, "N0CALL-11");
+        poll_until_fix(&mut source, 0);
+        assert!((source.get_pos_time().lat - 49.058333).abs() < 1e-5);
+
+        // The first connection closes, the next polls reconnect and pick up the second position
+        let started = Instant::now();
+        while source.get_pos_time().lat < 49.07 && started.elapsed() < Duration::from_secs(3) {
+            let _ = source.update_position();
+        }
+        assert!((source.get_pos_time().lat - 49.075).abs() < 1e-5);
+    }
+
+    #[test]
+    fn returns_while_the_server_floods() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // An unfiltered feed, every line valid and never a pause
+            let line = "OTHER-9>APRS,TCPIP*:!1000.00N/02000.00E>\r\n".repeat(100);
+            while stream.write_all(line.as_bytes()).is_ok() {}
+        });
+        let mut source = AprsIs::new(&address, "N0CALL", RECEIVE_ONLY_PASSCODE, "N0CALL-11");
+
+        for _ in 0..5 {
+            let started = Instant::now();
+            source.update_position().unwrap();
+            assert!(started.elapsed() < Duration::from_secs(2));
+            assert!(source.lines.pending_len() <= MAX_LINE_LEN);
+        }
+        // Every poll got to record its outcome
+        assert_ne!(source.health.last_success, 0);
+        assert_eq!(source.health.consecutive_failures, 0);
+    }
+
+    #[test]
+    fn drops_the_connection_on_an_endless_line() {
+        let (address, _server) = canned_server(vec![vec!["x".repeat(MAX_LINE_LEN + 1)]]);
+        let mut source = AprsIs::new(&address, "N0CALL", RECEIVE_ONLY_PASSCODE, "N0CALL-11");
+
+        let started = Instant::now();
+        let mut result = Ok(());
+        while result.is_ok() && started.elapsed() < Duration::from_secs(2) {
+            result = source.update_position();
+        }
+        assert!(result.unwrap_err().to_string().contains("longer than"));
+        assert!(source.stream.is_none());
+        assert_eq!(source.lines.pending_len(), 0);
+    }
+}
@@ -0,0 +1,783 @@
+use std::error::Error;
+
+use serde::Serialize;
+
+use crate::track_lib::position_time::PositionTime;
+
+//Conversion factors
+const KNOTS_TO_KMH: f64 = 1.852;
+const FEET_TO_METERS: f64 = 0.3048;
+
+//Meters per minute of latitude
+const METERS_PER_MINUTE: f64 = 1852.0;
+
+/** Struct holding a decoded APRS position report.
+
+lat, lon -> Position in decimal degrees
+
+alt -> Altitude in meters, if the report carries one
+
+course -> Course over ground in degrees, if the report carries one
+
+speed -> Ground speed in km/h (same unit as aprs.fi), if the report carries one
+
+ambiguity -> Trailing position digits blanked by the sender (0 to 4)
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct AprsPosition {
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: Option<f64>,
+    pub course: Option<f64>,
+    pub speed: Option<f64>,
+    pub symbol_table: char,
+    pub symbol_code: char,
+    pub comment: String,
+    pub ambiguity: u8,
+}
+
+impl AprsPosition {
+    /// Horizontal 1-sigma in meters declared by position ambiguity, None for a full resolution position
+    pub fn precision(&self) -> Option<f64> {
+        ambiguity_precision(self.ambiguity)
+    }
+
+    /// Build the fix received at `time`, deriving the vertical velocity from the previous fix
+    pub fn to_pos_time(&self, previous: &PositionTime, time: u64) -> PositionTime {
+        let alt = self.alt.unwrap_or(previous.alt);
+        let dt = time.saturating_sub(previous.last_update) as f64;
+        let vert_vel = if previous.last_update != 0 && dt > 0.0 && self.alt.is_some() {
+            (alt - previous.alt) / dt
+        } else {
+            0.0
+        };
+        let horiz_vel = self.speed.map(|kmh| kmh / 3.6).unwrap_or(0.0);
+
+        let mut pos_time = PositionTime::new_with_value(self.lat, self.lon, alt, time, horiz_vel, vert_vel);
+        pos_time.heading = self.course;
+        pos_time
+    }
+}
+
+/** Struct holding a packet in TNC2 text format, as sent by APRS-IS and most software TNCs.
+
+`SOURCE>DESTINATION,PATH1,PATH2:information`
+*/
+#[derive(Debug, Clone)]
+pub struct Tnc2Packet {
+    pub source: String,
+    pub destination: String,
+    pub path: Vec<String>,
+    pub info: String,
+}
+
+impl Tnc2Packet {
+    pub fn parse(line: &str) -> Result<Self, Box<dyn Error>> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let (header, info) = line.split_once(':').ok_or("TNC2 packet without information field")?;
+        let (source, route) = header.split_once('>').ok_or("TNC2 packet without destination")?;
+        let mut route = route.split(',');
+        let destination = route.next().unwrap_or("");
+        if source.is_empty() || destination.is_empty() {
+            return Err(format!("Invalid TNC2 header '{}'", header).into());
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            path: route.map(|p| p.to_string()).collect(),
+            info: info.to_string(),
+        })
+    }
+}
+
+/// Decode the information field of an APRS packet into a position.
+/// The destination address is only used by Mic-E packets, which encode the latitude in it.
+pub fn parse_position(destination: &str, info: &str) -> Result<AprsPosition, Box<dyn Error>> {
+    let data_type = info.chars().next().ok_or("Empty APRS information field")?;
+
+    let mut pos = match data_type {
+        '!' | '=' => parse_position_body(&info[1..]),
+        // Position with a 7 character timestamp before the position
+        '/' | '@' => {
+            let body = info.get(8..).ok_or("APRS timestamped position too short")?;
+            parse_position_body(body)
+        }
+        '`' | '\'' | '\u{1c}' | '\u{1d}' => parse_mic_e(destination, info),
+        _ => Err(format!("Unsupported APRS data type '{}'", data_type).into()),
+    }?;
+
+    // Positions without an encoded altitude may carry one in the comment
+    if pos.alt.is_none() {
+        pos.alt = parse_comment_altitude(&pos.comment);
+    }
+    Ok(pos)
+}
+
+/// 1-sigma in meters of a position with `digits` blanked digits, spread evenly over the blanked range
+pub fn ambiguity_precision(digits: u8) -> Option<f64> {
+    // Blanking hundredths, tenths, units then tens of minutes
+    let minutes = match digits {
+        0 => return None,
+        1 => 0.1,
+        2 => 1.0,
+        3 => 10.0,
+        _ => 60.0,
+    };
+    Some(minutes * METERS_PER_MINUTE / 12f64.sqrt())
+}
+
+/// `/A=aaaaaa` anywhere in the comment, altitude in feet
+pub fn parse_comment_altitude(comment: &str) -> Option<f64> {
+    let start = comment.find("/A=")? + 3;
+    let feet: f64 = comment.get(start..start + 6)?.parse().ok()?;
+    Some(feet * FEET_TO_METERS)
+}
+
+/// A callsign without SSID matches every SSID of that station
+pub fn callsign_matches(source: &str, wanted: &str) -> bool {
+    let source = source.to_uppercase();
+    let wanted = wanted.to_uppercase();
+    if wanted.contains('-') {
+        source == wanted
+    } else {
+        source.split('-').next() == Some(wanted.as_str())
+    }
+}
+
+/// Decode the part of a position report after the data type (and timestamp)
+fn parse_position_body(body: &str) -> Result<AprsPosition, Box<dyn Error>> {
+    let first = body.chars().next().ok_or("Empty APRS position")?;
+    if first.is_ascii_digit() || first == ' ' {
+        parse_uncompressed(body)
+    } else {
+        parse_compressed(body)
+    }
+}
+
+//------------------------Uncompressed Positions------------------------
+
+/// `DDMM.hhN/DDDMM.hhW>comment`
+fn parse_uncompressed(body: &str) -> Result<AprsPosition, Box<dyn Error>> {
+    let bytes = body.as_bytes();
+    if bytes.len() < 19 || !bytes[..19].is_ascii() {
+        return Err("APRS uncompressed position too short".into());
+    }
+
+    let lat = parse_coordinate(&body[0..7], 2, bytes[7] as char, 'N', 'S')?;
+    let symbol_table = bytes[8] as char;
+    let lon = parse_coordinate(&body[9..17], 3, bytes[17] as char, 'E', 'W')?;
+    let symbol_code = bytes[18] as char;
+    let mut comment = &body[19..];
+
+    // Course/speed data extension: `ccc/sss` in degrees and knots
+    let mut course = None;
+    let mut speed = None;
+    if let Some((c, s)) = parse_course_speed(comment) {
+        course = c;
+        speed = Some(s * KNOTS_TO_KMH);
+        comment = &comment[7..];
+    }
+
+    Ok(AprsPosition {
+        lat,
+        lon,
+        alt: None,
+        course,
+        speed,
+        symbol_table,
+        symbol_code,
+        comment: comment.to_string(),
+        // Ambiguity blanks latitude digits with spaces, the longitude follows it
+        ambiguity: body[0..7].bytes().filter(|&b| b == b' ').count() as u8,
+    })
+}
+
+/// Parse the 7 character `ccc/sss` extension at the start of a comment
+fn parse_course_speed(comment: &str) -> Option<(Option<f64>, f64)> {
+    let ext = comment.get(..7)?;
+    if ext.as_bytes()[3] != b'/' {
+        return None;
+    }
+    let course: f64 = ext[..3].parse().ok()?;
+    let speed: f64 = ext[4..].parse().ok()?;
+    // A course of 0 means unknown, 360 is north
+    let course = if course == 0.0 { None } else { Some(course % 360.0) };
+    Some((course, speed))
+}
+
+/// Parse `DDMM.hh` / `DDDMM.hh` with its hemisphere letter. Ambiguity spaces are read as zero.
+fn parse_coordinate(text: &str, deg_len: usize, hemisphere: char, positive: char, negative: char) -> Result<f64, Box<dyn Error>> {
+    let text = text.replace(' ', "0");
+    let degrees: f64 = text[..deg_len].parse()?;
+    let minutes: f64 = text[deg_len..].parse()?;
+    if minutes >= 60.0 {
+        return Err(format!("Invalid APRS minutes '{}'", text).into());
+    }
+
+    let value = degrees + minutes / 60.0;
+    if hemisphere == positive {
+        Ok(value)
+    } else if hemisphere == negative {
+        Ok(-value)
+    } else {
+        Err(format!("Invalid APRS hemisphere '{}'", hemisphere).into())
+    }
+}
+
+//------------------------Compressed Positions------------------------
+
+/// `/YYYYXXXX$csT` with base-91 encoded latitude and longitude
+fn parse_compressed(body: &str) -> Result<AprsPosition, Box<dyn Error>> {
+    let bytes = body.as_bytes();
+    if bytes.len() < 13 || !bytes[..13].is_ascii() {
+        return Err("APRS compressed position too short".into());
+    }
+
+    let symbol_table = bytes[0] as char;
+    let lat = 90.0 - base91(&bytes[1..5])? as f64 / 380926.0;
+    let lon = -180.0 + base91(&bytes[5..9])? as f64 / 190463.0;
+    let symbol_code = bytes[9] as char;
+
+    let (c, s, t) = (bytes[10], bytes[11], bytes[12]);
+    let mut alt = None;
+    let mut course = None;
+    let mut speed = None;
+
+    if c != b' ' {
+        // Bits 3-4 of the compression type give the NMEA source; 2 means GGA, so cs is altitude
+        let gga = t >= 33 && ((t - 33) >> 3) & 0x03 == 2;
+        if gga {
+            let feet = 1.002_f64.powi(base91(&bytes[10..12])? as i32);
+            alt = Some(feet * FEET_TO_METERS);
+        } else if (b'!'..=b'z').contains(&c) && s >= 33 {
+            course = Some((c - 33) as f64 * 4.0);
+            speed = Some((1.08_f64.powi((s - 33) as i32) - 1.0) * KNOTS_TO_KMH);
+        }
+    }
+
+    Ok(AprsPosition {
+        lat,
+        lon,
+        alt,
+        course,
+        speed,
+        symbol_table,
+        symbol_code,
+        comment: body[13..].to_string(),
+        ambiguity: 0,
+    })
+}
+
+/// Decode a base-91 number (each byte is value + 33)
+pub fn base91(bytes: &[u8]) -> Result<u32, Box<dyn Error>> {
+    let mut value: u32 = 0;
+    for &b in bytes {
+        if !(33..=123).contains(&b) {
+            return Err(format!("Invalid base-91 character '{}'", b as char).into());
+        }
+        value = value * 91 + (b - 33) as u32;
+    }
+    Ok(value)
+}
+
+//------------------------Mic-E Positions------------------------
+
+/// Mic-E: latitude and hemisphere flags in the destination, longitude and speed in the information field
+fn parse_mic_e(destination: &str, info: &str) -> Result<AprsPosition, Box<dyn Error>> {
+    // Strip the SSID from the destination callsign
+    let dest = destination.split('-').next().unwrap_or("").as_bytes();
+    let bytes = info.as_bytes();
+    if dest.len() < 6 {
+        return Err("Mic-E destination too short".into());
+    }
+    if bytes.len() < 9 {
+        return Err("Mic-E information field too short".into());
+    }
+
+    // Latitude digits
+    let mut digits = [0u8; 6];
+    for (i, &c) in dest[..6].iter().enumerate() {
+        digits[i] = match c {
+            b'0'..=b'9' => c - b'0',
+            b'A'..=b'J' => c - b'A',
+            b'P'..=b'Y' => c - b'P',
+            // Position ambiguity
+            b'K' | b'L' | b'Z' => 0,
+            _ => return Err(format!("Invalid Mic-E destination character '{}'", c as char).into()),
+        };
+    }
+    let lat_deg = (digits[0] * 10 + digits[1]) as f64;
+    let lat_min = (digits[2] * 10 + digits[3]) as f64 + (digits[4] * 10 + digits[5]) as f64 / 100.0;
+    let is_custom = |c: u8| (b'P'..=b'Z').contains(&c);
+    let north = is_custom(dest[3]);
+    let lon_offset = is_custom(dest[4]);
+    let west = is_custom(dest[5]);
+
+    let mut lat = lat_deg + lat_min / 60.0;
+    if !north {
+        lat = -lat;
+    }
+
+    // Longitude
+    let mut lon_deg = bytes[1] as i32 - 28;
+    if lon_offset {
+        lon_deg += 100;
+    }
+    if (180..=189).contains(&lon_deg) {
+        lon_deg -= 80;
+    } else if (190..=199).contains(&lon_deg) {
+        lon_deg -= 190;
+    }
+    let mut lon_min = bytes[2] as i32 - 28;
+    if lon_min >= 60 {
+        lon_min -= 60;
+    }
+    let lon_hun = bytes[3] as i32 - 28;
+    let mut lon = lon_deg as f64 + (lon_min as f64 + lon_hun as f64 / 100.0) / 60.0;
+    if west {
+        lon = -lon;
+    }
+
+    // Speed (knots) and course (degrees)
+    let sp = bytes[4] as i32 - 28;
+    let dc = bytes[5] as i32 - 28;
+    let se = bytes[6] as i32 - 28;
+    let mut knots = sp * 10 + dc / 10;
+    if knots >= 800 {
+        knots -= 800;
+    }
+    let mut course = (dc % 10) * 100 + se;
+    if course >= 400 {
+        course -= 400;
+    }
+
+    let symbol_code = bytes[7] as char;
+    let symbol_table = bytes[8] as char;
+    let mut comment = String::from_utf8_lossy(&bytes[9..]).to_string();
+
+    // Optional radio type byte before the status text
+    if comment.starts_with(['>', ']', '`', '\'']) {
+        comment.remove(0);
+    }
+
+    // Optional altitude: three base-91 characters followed by '}', in meters above -10000
+    let mut alt = None;
+    if comment.len() >= 4 && comment.as_bytes()[3] == b'}' {
+        if let Ok(value) = base91(&comment.as_bytes()[..3]) {
+            alt = Some(value as f64 - 10000.0);
+            comment = comment[4..].to_string();
+        }
+    }
+
+    Ok(AprsPosition {
+        lat,
+        lon,
+        alt,
+        course: Some(course as f64),
+        speed: Some(knots as f64 * KNOTS_TO_KMH),
+        symbol_table,
+        symbol_code,
+        comment,
+        ambiguity: dest[..6].iter().filter(|&&c| matches!(c, b'K' | b'L' | b'Z')).count() as u8,
+    })
+}
+
+//------------------------Telemetry------------------------
+
+/** Struct holding a raw APRS telemetry report.
+
+`T#sss,a1,a2,a3,a4,a5,bbbbbbbb` or base-91 `|ss1122334455dd|` inside a comment
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct AprsTelemetry {
+    pub sequence: Option<u32>,
+    pub analog: Vec<f64>,
+    pub digital: Option<u8>,
+}
+
+/// Decode a `T#` telemetry information field
+pub fn parse_telemetry(info: &str) -> Result<AprsTelemetry, Box<dyn Error>> {
+    let body = info.strip_prefix("T#").ok_or("Not an APRS telemetry report")?;
+    let mut fields = body.split(',');
+
+    // The sequence may be "MIC" for Mic-E telemetry
+    let sequence = fields.next().and_then(|s| s.trim().parse::<u32>().ok());
+
+    let mut analog = vec![];
+    let mut digital = None;
+    for (i, field) in fields.enumerate() {
+        let field = field.trim();
+        if i < 5 {
+            analog.push(field.parse::<f64>().map_err(|_| format!("Invalid telemetry value '{}'", field))?);
+        } else {
+            // Digital bits, the comment may follow directly after the 8 bits
+            let bits = field.get(..8).ok_or("Telemetry digital field too short")?;
+            digital = Some(u8::from_str_radix(bits, 2)?);
+            break;
+        }
+    }
+    if analog.is_empty() {
+        return Err("Telemetry report without values".into());
+    }
+
+    Ok(AprsTelemetry { sequence, analog, digital })
+}
+
+/// Decode base-91 telemetry between two `|` in a comment
+pub fn parse_comment_telemetry(comment: &str) -> Option<AprsTelemetry> {
+    let start = comment.find('|')?;
+    let end = start + 1 + comment[start + 1..].find('|')?;
+    let data = &comment.as_bytes()[start + 1..end];
+    // Sequence plus 1 to 6 values (5 analog and the digital bits), two characters each
+    if !data.len().is_multiple_of(2) || !(4..=14).contains(&data.len()) {
+        return None;
+    }
+
+    let mut values = data.chunks(2).map(base91).collect::<Result<Vec<u32>, _>>().ok()?.into_iter();
+    let sequence = values.next();
+    let mut analog = vec![];
+    let mut digital = None;
+    for (i, value) in values.enumerate() {
+        if i < 5 {
+            analog.push(value as f64);
+        } else {
+            digital = Some(value as u8);
+        }
+    }
+
+    Some(AprsTelemetry { sequence, analog, digital })
+}
+
+/// Telemetry definition messages, sent to the station the telemetry belongs to
+#[derive(Debug, Clone, PartialEq)]
+pub enum TelemetryDefinition {
+    /// `PARM.` channel names, 5 analog followed by 8 digital
+    Names(Vec<String>),
+    /// `UNIT.` channel units or labels
+    Units(Vec<String>),
+    /// `EQNS.` a, b, c coefficients per analog channel: value = a*x^2 + b*x + c
+    Equations(Vec<[f64; 3]>),
+    /// `BITS.` sense of the digital bits and project title
+    Bits { sense: u8, title: String },
+}
+
+impl TelemetryDefinition {
+    fn parse(text: &str) -> Option<Self> {
+        // Drop a trailing message number
+        let text = text.split('{').next().unwrap_or(text).trim_end();
+        let list = |body: &str| body.split(',').map(|s| s.trim().to_string()).collect::<Vec<String>>();
+
+        if let Some(body) = text.strip_prefix("PARM.") {
+            Some(TelemetryDefinition::Names(list(body)))
+        } else if let Some(body) = text.strip_prefix("UNIT.") {
+            Some(TelemetryDefinition::Units(list(body)))
+        } else if let Some(body) = text.strip_prefix("EQNS.") {
+            let coefficients: Vec<f64> = body.split(',').map(|s| s.trim().parse().unwrap_or(0.0)).collect();
+            Some(TelemetryDefinition::Equations(
+                coefficients.chunks(3).filter(|c| c.len() == 3).map(|c| [c[0], c[1], c[2]]).collect(),
+            ))
+        } else if let Some(body) = text.strip_prefix("BITS.") {
+            let (bits, title) = body.split_once(',').unwrap_or((body, ""));
+            let sense = u8::from_str_radix(bits.get(..8)?, 2).ok()?;
+            Some(TelemetryDefinition::Bits { sense, title: title.trim().to_string() })
+        } else {
+            None
+        }
+    }
+}
+
+/// A named, scaled telemetry value
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TelemetryChannel {
+    pub name: String,
+    pub unit: String,
+    pub value: f64,
+}
+
+/// Definitions received for one station, used to turn raw telemetry into named channels
+#[derive(Debug, Clone, Default)]
+pub struct TelemetryDefinitions {
+    names: Vec<String>,
+    units: Vec<String>,
+    equations: Vec<[f64; 3]>,
+    sense: Option<u8>,
+    title: String,
+}
+
+impl TelemetryDefinitions {
+    pub fn apply(&mut self, definition: TelemetryDefinition) {
+        match definition {
+            TelemetryDefinition::Names(names) => self.names = names,
+            TelemetryDefinition::Units(units) => self.units = units,
+            TelemetryDefinition::Equations(equations) => self.equations = equations,
+            TelemetryDefinition::Bits { sense, title } => {
+                self.sense = Some(sense);
+                self.title = title;
+            }
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Scale and name the values of a telemetry report
+    pub fn channels(&self, telemetry: &AprsTelemetry) -> Vec<TelemetryChannel> {
+        let label = |list: &Vec<String>, i: usize| list.get(i).filter(|s| !s.is_empty()).cloned();
+
+        let mut channels: Vec<TelemetryChannel> = telemetry.analog.iter().enumerate().map(|(i, &raw)| {
+            let [a, b, c] = self.equations.get(i).copied().unwrap_or([0.0, 1.0, 0.0]);
+            TelemetryChannel {
+                name: label(&self.names, i).unwrap_or_else(|| format!("A{}", i + 1)),
+                unit: label(&self.units, i).unwrap_or_default(),
+                value: a * raw * raw + b * raw + c,
+            }
+        }).collect();
+
+        if let Some(bits) = telemetry.digital {
+            for i in 0..8 {
+                // Bit 1 is the most significant bit
+                let mut value = (bits >> (7 - i)) & 1;
+                if let Some(sense) = self.sense {
+                    value = if value == (sense >> (7 - i)) & 1 { 1 } else { 0 };
+                }
+                channels.push(TelemetryChannel {
+                    name: label(&self.names, 5 + i).unwrap_or_else(|| format!("B{}", i + 1)),
+                    unit: label(&self.units, 5 + i).unwrap_or_default(),
+                    value: value as f64,
+                });
+            }
+        }
+        channels
+    }
+}
+
+//------------------------Packets------------------------
+
+/// A decoded APRS packet with everything HARP Tracker uses from it
+#[derive(Debug, Clone)]
+pub struct AprsPacket {
+    pub source: String,
+    pub destination: String,
+    pub path: Vec<String>,
+    pub position: Option<AprsPosition>,
+    pub telemetry: Option<AprsTelemetry>,
+    /// Telemetry definition together with the station it applies to
+    pub definition: Option<(String, TelemetryDefinition)>,
+}
+
+impl AprsPacket {
+    pub fn decode(source: &str, destination: &str, path: Vec<String>, info: &str) -> Result<Self, Box<dyn Error>> {
+        let mut packet = Self {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            path,
+            position: None,
+            telemetry: None,
+            definition: None,
+        };
+
+        if info.starts_with("T#") {
+            packet.telemetry = Some(parse_telemetry(info)?);
+        } else if let Some(message) = info.strip_prefix(':') {
+            // `:ADDRESSEE:text` with a 9 character addressee
+            let addressee = message.get(..9).ok_or("APRS message too short")?.trim().to_string();
+            let text = message.get(10..).ok_or("APRS message without text")?;
+            let definition = TelemetryDefinition::parse(text).ok_or("Unsupported APRS message")?;
+            packet.definition = Some((addressee, definition));
+        } else {
+            let position = parse_position(destination, info)?;
+            packet.telemetry = parse_comment_telemetry(&position.comment);
+            packet.position = Some(position);
+        }
+        Ok(packet)
+    }
+
+    pub fn from_tnc2(line: &str) -> Result<Self, Box<dyn Error>> {
+        let raw = Tnc2Packet::parse(line)?;
+        Self::decode(&raw.source, &raw.destination, raw.path, &raw.info)
+    }
+}
+
+/// Latest decoded state of one tracked station, shared by the packet based APRS sources
+#[derive(Debug, Clone)]
+pub struct AprsStation {
+    call_sign: String,
+    definitions: TelemetryDefinitions,
+    pub position_time: PositionTime,
+    pub comment: String,
+    pub telemetry: Vec<TelemetryChannel>,
+    /// Precision declared by the ambiguity of the last position
+    pub precision: Option<f64>,
+    // Channels of a `T#` report waiting for the next position
+    pending_telemetry: Option<Vec<TelemetryChannel>>,
+}
+
+impl AprsStation {
+    pub fn new(call_sign: &str) -> Self {
+        Self {
+            call_sign: call_sign.to_uppercase(),
+            definitions: TelemetryDefinitions::default(),
+            position_time: PositionTime::new(),
+            comment: String::new(),
+            telemetry: vec![],
+            precision: None,
+            pending_telemetry: None,
+        }
+    }
+
+    /// True if a packet from `source` concerns the station, checked before decoding so other stations are skipped quietly
+    pub fn wants(&self, source: &str, info: &str) -> bool {
+        match info.strip_prefix(':') {
+            // Telemetry definitions are addressed to the station they describe
+            Some(message) => message.get(..9).is_some_and(|addressee| callsign_matches(addressee.trim(), &self.call_sign)),
+            None => callsign_matches(source, &self.call_sign),
+        }
+    }
+
+    /// Apply a packet received at `time`, returns true when it carried a new position of the station
+    pub fn handle(&mut self, packet: AprsPacket, time: u64) -> bool {
+        if let Some((addressee, definition)) = packet.definition {
+            if callsign_matches(&addressee, &self.call_sign) {
+                self.definitions.apply(definition);
+            }
+            return false;
+        }
+        if !callsign_matches(&packet.source, &self.call_sign) {
+            return false;
+        }
+
+        // Telemetry belongs to the packet it came with, a `T#` report is carried over to the next position
+        let channels = packet.telemetry.as_ref().map(|t| self.definitions.channels(t));
+        match packet.position {
+            Some(pos) => {
+                self.telemetry = channels.or_else(|| self.pending_telemetry.take()).unwrap_or_default();
+                self.pending_telemetry = None;
+                self.position_time = pos.to_pos_time(&self.position_time, time);
+                self.precision = pos.precision();
+                self.comment = pos.comment;
+                true
+            }
+            None => {
+                self.telemetry.clear();
+                if channels.is_some() {
+                    self.pending_telemetry = channels;
+                }
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Packets in the formats sent by common balloon trackers (LightAPRS, Mic-E radios, compressed trackers)
+    const UNCOMPRESSED: &str = "N0CALL-11>APLIGA,WIDE2-1,qAR,K0GATE:/201542h4103.45N/08750.12WO090/012/A=045123 4.12V 23C S8";
+    const COMPRESSED_ALTITUDE: &str = "N0CALL-11>APRS,WIDE2-1:=/5L!!<*e7OS]S HAB";
+    const MIC_E: &str = "N0CALL-11>SSRUVT,WIDE2-1,qAR,K0GATE:`(#fnPO>/]#\\f}HAB";
+    const COMMENT_TELEMETRY: &str = "N0CALL-11>APRS:!4903.50N/07201.75WO|!\"!#!$|";
+    const DEFINITIONS: [&str; 3] = [
+        "N0CALL-11>APRS::N0CALL-11:PARM.Vbat,Temp,Pres,A4,A5,Burst",
+        "N0CALL-11>APRS::N0CALL-11:UNIT.V,C,hPa,x,x,on{01",
+        "N0CALL-11>APRS::N0CALL-11:EQNS.0,0.01,0,0,0.5,-50,0,1,0,0,1,0,0,1,0",
+    ];
+    const TELEMETRY: &str = "N0CALL-11>APRS:T#005,412,150,1013,0,0,10000000";
+
+    fn position(line: &str) -> AprsPosition {
+        AprsPacket::from_tnc2(line).unwrap().position.unwrap()
+    }
+
+    #[test]
+    fn decodes_timestamped_position_with_course_speed_and_altitude() {
+        let pos = position(UNCOMPRESSED);
+        assert!((pos.lat - 41.0575).abs() < 1e-6);
+        assert!((pos.lon + 87.835333).abs() < 1e-6);
+        assert_eq!(pos.course, Some(90.0));
+        assert!((pos.speed.unwrap() - 12.0 * KNOTS_TO_KMH).abs() < 1e-9);
+        assert!((pos.alt.unwrap() - 45123.0 * FEET_TO_METERS).abs() < 1e-6);
+        assert_eq!((pos.symbol_table, pos.symbol_code), ('/', 'O'));
+        assert_eq!(pos.comment, "/A=045123 4.12V 23C S8");
+    }
+
+    #[test]
+    fn decodes_compressed_position_with_altitude() {
+        let pos = position(COMPRESSED_ALTITUDE);
+        assert!((pos.lat - 49.5).abs() < 1e-4);
+        assert!((pos.lon + 72.75).abs() < 1e-4);
+        // cs "S]" with a GGA compression type is 10004 ft
+        assert!((pos.alt.unwrap() - 10004.0 * FEET_TO_METERS).abs() < 1.0);
+        assert_eq!(pos.course, None);
+        assert_eq!(pos.comment, " HAB");
+    }
+
+    #[test]
+    fn decodes_mic_e_with_altitude() {
+        let pos = position(MIC_E);
+        assert!((pos.lat - 33.427333).abs() < 1e-5);
+        assert!((pos.lon + 112.129).abs() < 1e-5);
+        assert_eq!(pos.course, Some(251.0));
+        assert!((pos.speed.unwrap() - 25.0 * KNOTS_TO_KMH).abs() < 1e-9);
+        assert_eq!(pos.alt, Some(12000.0));
+        assert_eq!(pos.comment, "HAB");
+    }
+
+    #[test]
+    fn ambiguity_declares_the_precision() {
+        assert_eq!(position(UNCOMPRESSED).precision(), None);
+        assert_eq!(position(COMPRESSED_ALTITUDE).precision(), None);
+
+        // Hundredths and tenths of minutes blanked
+        let pos = position("N0CALL-11>APRS:!4903.  N/07201.  WO");
+        assert_eq!(pos.ambiguity, 2);
+        assert!((pos.lat - 49.05).abs() < 1e-9);
+        assert!((pos.precision().unwrap() - 1852.0 / 12f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn decodes_base91_comment_telemetry() {
+        let packet = AprsPacket::from_tnc2(COMMENT_TELEMETRY).unwrap();
+        assert_eq!(packet.telemetry, Some(AprsTelemetry { sequence: Some(1), analog: vec![2.0, 3.0], digital: None }));
+    }
+
+    #[test]
+    fn rejects_malformed_packets() {
+        assert!(AprsPacket::from_tnc2("N0CALL-11>APRS:!4903.50X/07201.75W>").is_err());
+        assert!(AprsPacket::from_tnc2("N0CALL-11>APRS:!49").is_err());
+        assert!(AprsPacket::from_tnc2("no header").is_err());
+    }
+
+    #[test]
+    fn station_scales_telemetry_with_its_definitions() {
+        let mut station = AprsStation::new("N0CALL-11");
+        for line in DEFINITIONS.iter().chain([&TELEMETRY]) {
+            assert!(!station.handle(AprsPacket::from_tnc2(line).unwrap(), 100));
+        }
+        assert!(station.handle(AprsPacket::from_tnc2(UNCOMPRESSED).unwrap(), 110));
+
+        let channel = |name: &str| station.telemetry.iter().find(|c| c.name == name).cloned().unwrap();
+        assert!((channel("Vbat").value - 4.12).abs() < 1e-9);
+        assert_eq!(channel("Vbat").unit, "V");
+        assert!((channel("Temp").value - 25.0).abs() < 1e-9);
+        assert_eq!(channel("Pres").value, 1013.0);
+        assert_eq!(channel("Burst").value, 1.0);
+
+        // The next position without telemetry does not repeat the old channels
+        assert!(station.handle(AprsPacket::from_tnc2(COMPRESSED_ALTITUDE).unwrap(), 120));
+        assert!(station.telemetry.is_empty());
+    }
+
+    #[test]
+    fn station_filters_on_the_source_before_decoding() {
+        let station = AprsStation::new("N0CALL-11");
+        assert!(station.wants("N0CALL-11", "!4903.50N/07201.75W>"));
+        assert!(!station.wants("OTHER-9", "not even APRS"));
+        assert!(!station.wants("N0CALL-9", "!4903.50N/07201.75W>"));
+        // Definitions may come from another station, they are addressed to ours
+        assert!(station.wants("OTHER", ":N0CALL-11:PARM.Vbat"));
+        assert!(!station.wants("N0CALL-11", ":OTHER    :PARM.Vbat"));
+        assert!(AprsStation::new("N0CALL").wants("N0CALL-9", "!4903.50N/07201.75W>"));
+    }
+}
@@ -1,9 +1,9 @@
 use std::{
-    io::{Read, Write},
+    io::Write,
     sync::{Arc, Mutex},
-    time::SystemTime,
 };
-use serialport::{SerialPort, SerialPortInfo, SerialPortType, COMPort};
+use serde::Serialize;
+use serialport::{SerialPort, SerialPortInfo, SerialPortType};
 
 
 
@@ -20,62 +20,81 @@ const DEFAULT_BAUD: u32 = 9600;
 //Default read timeout (ms)
 const DEFAULT_TIMEOUT_MS: u64 = 100;
 
-/// Arduino Module:
+//Command sent to park the antenna on connection
+const HOME_COMMAND: &str = "0,0";
+
+/// Arduino Module: drives the antenna tracker with `azimuth,elevation` commands in whole degrees
 pub struct Arduino {
     pub active: bool,
     pub tracking: bool,
     pub serial_port: Option<Arc<Mutex<Box<dyn SerialPort + Send>>>>,
-    pub com_port: Option<COMPort>,
-    pub time1: SystemTime,
-    pub time2: SystemTime,
+    pub port_name: Option<String>,
+    last_command: Option<(f64, f64)>,
+    last_error: Option<String>,
+}
 
+/// Serializable state of the antenna tracker for the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct ArduinoStatus {
+    pub connected: bool,
+    pub tracking: bool,
+    pub port: Option<String>,
+    /// Last (azimuth, elevation) sent to the board
+    pub last_command: Option<(f64, f64)>,
+    pub last_error: Option<String>,
 }
 
 impl Arduino {
 
     //-----------------------Initialaztion Functions-----------------------
 
-    pub fn new(
-        serial: Option<Arc<Mutex<Box<dyn SerialPort + Send>>>>,
-        com: Option<COMPort>,
-    ) -> Self {
+    pub fn new() -> Self {
         Self {
-            active: serial.is_some(),
+            active: false,
             tracking: false,
-            serial_port: serial,
-            com_port: com,
-            time1: SystemTime::now(),
-            time2: SystemTime::now(),
-
+            serial_port: None,
+            port_name: None,
+            last_command: None,
+            last_error: None,
         }
     }
 
-    /// Discover & open the first detected Arduino port (blocking). Sets `active=true` on success.
-    pub fn setup(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let arduino_ports = Self::detect_arduino_ports();
-        println!("Arduino Ports: {:?}", arduino_ports);
-
-        if let Some(port_name) = arduino_ports.get(0) {
-            match serialport::new(port_name, DEFAULT_BAUD)
-                .timeout(std::time::Duration::from_millis(DEFAULT_TIMEOUT_MS))
-                .open()
-            {
-                Ok(port) => {
-                    self.serial_port = Some(Arc::new(Mutex::new(port)));
-                    self.active = true;
-                    println!("Arduino Connected at port {port_name:?}");
-                    self.send_command("0,0").unwrap_or(eprintln!("Error sending command to Arduino"));
-                    Ok(())
-                }
-                Err(e) => {
-                    Err(format!("Arduino: Failed to open {port_name}: {e}").into())
-                }
+    /// Open the given port, or the first detected Arduino when `port` is None. Sets `active=true` on success.
+    pub fn connect(&mut self, port: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
+        let port_name = match port {
+            Some(port) => port.to_string(),
+            None => {
+                let arduino_ports = Self::detect_arduino_ports();
+                println!("Arduino Ports: {:?}", arduino_ports);
+                arduino_ports.into_iter().next().ok_or("No Arduino Detected")?
             }
-        } else {
+        };
+
+        let port = serialport::new(&port_name, DEFAULT_BAUD)
+            .timeout(std::time::Duration::from_millis(DEFAULT_TIMEOUT_MS))
+            .open()
+            .map_err(|e| format!("Arduino: Failed to open {port_name}: {e}"))?;
+
+        self.serial_port = Some(Arc::new(Mutex::new(port)));
+        self.port_name = Some(port_name.clone());
+        self.active = true;
+        self.last_error = None;
+        println!("Arduino Connected at port {port_name:?}");
 
-            Err("No Arduino Detected".into())
+        if let Err(e) = self.send_command(HOME_COMMAND) {
+            eprintln!("Error sending command to Arduino: {e}");
+        }
+        Ok(port_name)
+    }
 
+    /// Close the serial port and stop tracking
+    pub fn disconnect(&mut self) {
+        if let Some(port) = &self.port_name {
+            println!("Arduino Disconnected from port {port:?}");
         }
+        self.serial_port = None;
+        self.active = false;
+        self.tracking = false;
     }
 
 
@@ -88,8 +107,6 @@ impl Arduino {
             return Err("No Arduino detected; cannot send command.".into());
         }
         if let Some(mut guard) = self.lock_port() {
-
-            println!("Sending {cmd} to serial port.");
             let cmd = cmd.to_owned() + "\n";
 
             guard.write_all(cmd.as_bytes())?;
@@ -103,21 +120,57 @@ impl Arduino {
     }
 
 
-    pub fn update(&mut self) -> Result<(), Box<dyn std::error::Error>>  {
+    /// Point the antenna at the given azimuth/elevation (degrees), if tracking is on
+    pub fn point(&mut self, azimuth: f64, elevation: f64) -> Result<(), Box<dyn std::error::Error>>  {
         //Return Error if not active
         if !self.active {
-            return Err("No Arduino detected; cannot run update function.".into());
+            return Err("No Arduino detected; cannot point antenna.".into());
+        }
+        if !self.tracking {
+            return Ok(());
         }
 
-        self.send_command("0,0").unwrap_or(eprintln!("Error sending command to Arduino"));
+        // The tracker cannot look below the horizon
+        let azimuth = azimuth.rem_euclid(360.0).round() % 360.0;
+        let elevation = elevation.clamp(0.0, 90.0).round();
+        if self.last_command == Some((azimuth, elevation)) {
+            return Ok(());
+        }
 
-        Ok(())
+        match self.send_command(&format!("{azimuth:.0},{elevation:.0}")) {
+            Ok(()) => {
+                self.last_command = Some((azimuth, elevation));
+                self.last_error = None;
+                Ok(())
+            }
+            Err(e) => {
+                // The board was most likely unplugged
+                self.last_error = Some(e.to_string());
+                self.disconnect();
+                Err(e)
+            }
+        }
     }
 
 
-    pub fn set_tracking(&mut self, val: bool){
-        if !(self.active){eprintln!("Can't set Tracking due to no active Arduino")}
+    pub fn set_tracking(&mut self, val: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if val && !self.active {
+            return Err("Can't set Tracking due to no active Arduino".into());
+        }
         self.tracking = val;
+        // Resend the position on the next fix
+        self.last_command = None;
+        Ok(())
+    }
+
+    pub fn status(&self) -> ArduinoStatus {
+        ArduinoStatus {
+            connected: self.active,
+            tracking: self.tracking,
+            port: self.port_name.clone(),
+            last_command: self.last_command,
+            last_error: self.last_error.clone(),
+        }
     }
 
     //-----------------------Helper Functions-----------------------
@@ -161,43 +214,110 @@ impl Arduino {
         }
     }
 
+}
 
-    /// Private debugging function to stream serial data from the arduino
-    fn stream_data(&mut self) {
-        if !self.active {
-            println!("No Arduino active; cannot stream data.");
-            return;
-        }
-        println!("Starting to stream data from Arduino...");
-        let mut buffer = [0u8; 1024];
-
-        loop {
-            // Scope the lock each loop to avoid holding it across print I/O.
-            let n_res = {
-                if let Some(mut guard) = self.lock_port() {
-                    guard.read(&mut buffer)
-                } else {
-                    eprintln!("Serial port not set.");
-                    break;
-                }
-            };
+impl Default for Arduino {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-            match n_res {
-                Ok(n) if n > 0 => {
-                    let data = String::from_utf8_lossy(&buffer[..n]);
-                    print!("{}", data);
-                }
-                Ok(_) => { /* n == 0: ignore */ }
-                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {
-                    // Continue polling.
-                    continue;
-                }
-                Err(e) => {
-                    eprintln!("Error reading from serial port: {e:?}");
-                    break;
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::{
+        io::{ErrorKind, Read},
+        thread,
+        time::Duration,
+    };
+    use serialport::TTYPort;
+
+    /// Board side of a pseudo-terminal pair, logging every command line
+    fn board() -> (String, Arc<Mutex<Vec<String>>>) {
+        let (mut master, slave) = TTYPort::pair().unwrap();
+        let port = slave.name().unwrap();
+        master.set_timeout(Duration::from_millis(50)).unwrap();
+        let log = Arc::new(Mutex::new(vec![]));
+
+        let commands = log.clone();
+        thread::spawn(move || {
+            // Keeps the pty open until the test ends
+            let _slave = slave;
+            let mut line = vec![];
+            let mut byte = [0u8; 1];
+            loop {
+                match master.read(&mut byte) {
+                    Ok(1) if byte[0] == b'\n' => {
+                        commands.lock().unwrap().push(String::from_utf8_lossy(&line).to_string());
+                        line.clear();
+                    }
+                    Ok(1) => line.push(byte[0]),
+                    Ok(_) => {}
+                    Err(e) if e.kind() == ErrorKind::TimedOut => {}
+                    Err(_) => break,
                 }
             }
-        }
+        });
+        (port, log)
+    }
+
+    fn sent(log: &Arc<Mutex<Vec<String>>>) -> Vec<String> {
+        thread::sleep(Duration::from_millis(200));
+        log.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn sends_whole_degree_commands() {
+        let (port, log) = board();
+        let mut arduino = Arduino::new();
+        arduino.connect(Some(&port)).unwrap();
+
+        // Nothing is sent until tracking is on
+        arduino.point(123.4, 45.6).unwrap();
+        arduino.set_tracking(true).unwrap();
+        arduino.point(123.4, 45.6).unwrap();
+        // Rounds to the same command, not resent
+        arduino.point(123.2, 45.8).unwrap();
+        arduino.point(10.0, -5.0).unwrap();
+        arduino.point(10.0, 95.0).unwrap();
+        arduino.point(359.6, 10.0).unwrap();
+        assert_eq!(arduino.status().last_command, Some((0.0, 10.0)));
+        assert_eq!(sent(&log), ["0,0", "123,46", "10,0", "10,90", "0,10"]);
+
+        // Turning tracking back on sends the position again
+        arduino.point(359.6, 10.0).unwrap();
+        arduino.set_tracking(true).unwrap();
+        assert_eq!(arduino.status().last_command, None);
+        arduino.point(359.6, 10.0).unwrap();
+        assert_eq!(sent(&log).len(), 6);
+    }
+
+    #[test]
+    fn disconnects_when_a_write_fails() {
+        let (mut master, slave) = TTYPort::pair().unwrap();
+        master.set_timeout(Duration::from_millis(50)).unwrap();
+        let mut arduino = Arduino::new();
+        arduino.connect(Some(&slave.name().unwrap())).unwrap();
+        arduino.set_tracking(true).unwrap();
+
+        // The board is unplugged
+        drop(master);
+        drop(slave);
+        assert!(arduino.point(90.0, 10.0).is_err());
+        let status = arduino.status();
+        assert!(!status.connected);
+        assert!(!status.tracking);
+        assert!(status.last_error.is_some());
+        assert_eq!(status.last_command, None);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn errors_without_a_board() {
+        let mut arduino = Arduino::new();
+        assert!(arduino.point(90.0, 10.0).is_err());
+        assert!(arduino.set_tracking(true).is_err());
+        assert!(arduino.send_command(HOME_COMMAND).is_err());
+        assert!(arduino.set_tracking(false).is_ok());
+        assert!(!arduino.status().connected);
+    }
+}
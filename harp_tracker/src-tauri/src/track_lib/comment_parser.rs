@@ -0,0 +1,285 @@
+use std::collections::{HashMap, VecDeque};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::track_lib::aprs_parser::{callsign_matches, TelemetryChannel};
+
+//Most telemetry samples kept in memory, the database keeps the whole flight
+const MAX_SAMPLES: usize = 5000;
+
+//Samples older than this (s) relative to the newest one are dropped
+const MAX_AGE_SECS: u64 = 6 * 3600;
+
+/// How a rule finds its value in a comment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleKind {
+    /// `pattern` is a regular expression, the value is its first capture group (or the whole match)
+    Regex,
+    /// `pattern` is a key such as `T=` or `Batt:`, the value is the number right after it
+    KeyValue,
+}
+
+/** Struct describing how to extract one sensor channel from a payload comment.
+
+channel -> Name of the resulting channel (eg. "Temperature")
+
+unit -> Unit shown next to the value
+
+scale, offset -> Applied to the parsed number: value = raw * scale + offset
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommentRule {
+    pub channel: String,
+    #[serde(default)]
+    pub unit: String,
+    pub kind: RuleKind,
+    pub pattern: String,
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    #[serde(default)]
+    pub offset: f64,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+/// A rule with its regular expression compiled
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    rule: CommentRule,
+    regex: Option<Regex>,
+}
+
+impl CompiledRule {
+    fn new(rule: CommentRule) -> Result<Self, String> {
+        let regex = match rule.kind {
+            RuleKind::Regex => Some(Regex::new(&rule.pattern).map_err(|e| format!("Invalid pattern for {}: {}", rule.channel, e))?),
+            RuleKind::KeyValue => None,
+        };
+        Ok(Self { rule, regex })
+    }
+
+    fn apply(&self, comment: &str) -> Option<TelemetryChannel> {
+        let raw = match &self.regex {
+            Some(regex) => {
+                let captures = regex.captures(comment)?;
+                let text = captures.get(1).or_else(|| captures.get(0))?.as_str();
+                leading_number(text.trim())?
+            }
+            None => {
+                let start = comment.find(&self.rule.pattern)? + self.rule.pattern.len();
+                leading_number(comment[start..].trim_start())?
+            }
+        };
+
+        Some(TelemetryChannel {
+            name: self.rule.channel.clone(),
+            unit: self.rule.unit.clone(),
+            value: raw * self.rule.scale + self.rule.offset,
+        })
+    }
+}
+
+/// Parse the number at the start of `text`, ignoring whatever follows it
+fn leading_number(text: &str) -> Option<f64> {
+    let end = text
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || ((c == '-' || c == '+') && i == 0)))
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+    text[..end].parse().ok()
+}
+
+/// Comment parsing rules for every configured callsign
+#[derive(Debug, Clone, Default)]
+pub struct CommentRules {
+    rules: HashMap<String, Vec<CompiledRule>>,
+}
+
+impl CommentRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the rules of a callsign, an empty list removes them
+    pub fn set(&mut self, call_sign: &str, rules: Vec<CommentRule>) -> Result<(), String> {
+        let compiled = rules.into_iter().map(CompiledRule::new).collect::<Result<Vec<_>, _>>()?;
+        if compiled.is_empty() {
+            self.rules.remove(&call_sign.to_uppercase());
+        } else {
+            self.rules.insert(call_sign.to_uppercase(), compiled);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, call_sign: &str) -> Vec<CommentRule> {
+        self.rules
+            .get(&call_sign.to_uppercase())
+            .map(|rules| rules.iter().map(|r| r.rule.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Turn the comment of a station into named channels using the rules of its callsign
+    pub fn parse(&self, call_sign: &str, comment: &str) -> Vec<TelemetryChannel> {
+        self.rules
+            .iter()
+            .filter(|(wanted, _)| callsign_matches(call_sign, wanted))
+            .flat_map(|(_, rules)| rules.iter().filter_map(|r| r.apply(comment)))
+            .collect()
+    }
+}
+
+/// Sensor channels decoded from one fix
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetrySample {
+    pub time: u64,
+    pub source: String,
+    pub channels: Vec<TelemetryChannel>,
+}
+
+/// Rolling buffer of the telemetry samples of every source, in the order they were received
+#[derive(Debug, Clone, Default)]
+pub struct TelemetryLog {
+    samples: VecDeque<TelemetrySample>,
+}
+
+impl TelemetryLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sample. Returns false if the source already logged telemetry at or after this time
+    pub fn push(&mut self, sample: TelemetrySample) -> bool {
+        if sample.channels.is_empty() || self.samples.iter().rev().any(|s| s.source == sample.source && s.time >= sample.time) {
+            return false;
+        }
+        self.samples.push_back(sample);
+
+        let newest = self.samples.iter().map(|s| s.time).max().unwrap_or(0);
+        while self.samples.len() > MAX_SAMPLES
+            || self.samples.front().is_some_and(|s| s.time + MAX_AGE_SECS < newest)
+        {
+            self.samples.pop_front();
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Every sample, oldest first
+    pub fn samples(&self) -> impl DoubleEndedIterator<Item = &TelemetrySample> {
+        self.samples.iter()
+    }
+}
+
+/// Time series of a single channel of one source, ready for graphing
+#[derive(Debug, Clone, Serialize)]
+pub struct TelemetrySeries {
+    pub source: String,
+    pub channel: String,
+    pub unit: String,
+    /// (unix time, value) pairs in the order they were received
+    pub points: Vec<(u64, f64)>,
+}
+
+/// Group the samples by source and channel name, two payloads reporting the same channel stay apart
+pub fn telemetry_series<'a>(samples: impl IntoIterator<Item = &'a TelemetrySample>) -> Vec<TelemetrySeries> {
+    let mut series: Vec<TelemetrySeries> = vec![];
+    for sample in samples {
+        for channel in &sample.channels {
+            match series.iter_mut().find(|s| s.source == sample.source && s.channel == channel.name) {
+                Some(s) => s.points.push((sample.time, channel.value)),
+                None => series.push(TelemetrySeries {
+                    source: sample.source.clone(),
+                    channel: channel.name.clone(),
+                    unit: channel.unit.clone(),
+                    points: vec![(sample.time, channel.value)],
+                }),
+            }
+        }
+    }
+    series
+}
+
+/// Channels as `name=value` pairs for the launch CSV
+pub fn format_channels(channels: &[TelemetryChannel]) -> String {
+    channels
+        .iter()
+        .map(|c| format!("{}={}", c.name.replace([',', ';', '='], " "), c.value))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time: u64, source: &str, value: f64) -> TelemetrySample {
+        TelemetrySample {
+            time,
+            source: source.to_string(),
+            channels: vec![TelemetryChannel { name: "Temperature".to_string(), unit: "C".to_string(), value }],
+        }
+    }
+
+    #[test]
+    fn key_value_and_regex_rules() {
+        let mut rules = CommentRules::new();
+        rules.set("N0CALL-11", vec![
+            CommentRule { channel: "Temperature".into(), unit: "C".into(), kind: RuleKind::KeyValue, pattern: "T=".into(), scale: 1.0, offset: 0.0 },
+            CommentRule { channel: "Battery".into(), unit: "V".into(), kind: RuleKind::Regex, pattern: r"B(\d+)mV".into(), scale: 0.001, offset: 0.0 },
+        ]).unwrap();
+
+        let channels = rules.parse("N0CALL-11", "HAB T=-41.5 B3700mV");
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[0].value, -41.5);
+        assert!((channels[1].value - 3.7).abs() < 1e-9);
+        assert!(rules.parse("OTHER", "T=10").is_empty());
+    }
+
+    #[test]
+    fn log_skips_repeated_samples() {
+        let mut log = TelemetryLog::new();
+        assert!(log.push(sample(100, "A", 1.0)));
+        // Same fix published again, then an older one
+        assert!(!log.push(sample(100, "A", 1.0)));
+        assert!(!log.push(sample(90, "A", 1.0)));
+        // Other sources keep their own times
+        assert!(log.push(sample(100, "B", 2.0)));
+        assert!(log.push(sample(110, "A", 3.0)));
+        assert!(!log.push(TelemetrySample { time: 120, source: "A".into(), channels: vec![] }));
+        assert_eq!(log.len(), 3);
+
+        let series = telemetry_series(log.samples());
+        assert_eq!(series.len(), 2);
+        assert_eq!((series[0].source.as_str(), series[0].channel.as_str()), ("A", "Temperature"));
+        assert_eq!(series[0].points, vec![(100, 1.0), (110, 3.0)]);
+        assert_eq!((series[1].source.as_str(), series[1].channel.as_str()), ("B", "Temperature"));
+        assert_eq!(series[1].points, vec![(100, 2.0)]);
+    }
+
+    #[test]
+    fn log_is_capped() {
+        let mut log = TelemetryLog::new();
+        for time in 1..=(MAX_SAMPLES as u64 + 10) {
+            log.push(sample(time, "A", 0.0));
+        }
+        assert_eq!(log.len(), MAX_SAMPLES);
+        assert_eq!(log.samples().next().unwrap().time, 11);
+
+        log.push(sample(MAX_SAMPLES as u64 + 10 + MAX_AGE_SECS + 1, "A", 0.0));
+        assert_eq!(log.len(), 1);
+    }
+}
@@ -0,0 +1,610 @@
+use std::{collections::HashMap, error::Error, fs::File, io::Write, path::Path};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+
+use crate::track_lib::aprs_parser::TelemetryChannel;
+use crate::track_lib::comment_parser::{format_channels, TelemetrySample};
+use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::receiver::ReceiverConfig;
+use crate::track_lib::tracking_type::TrackingType;
+
+//Each entry upgrades the schema by one version, the number applied is kept in `PRAGMA user_version`
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE flights (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        started INTEGER NOT NULL,
+        ended INTEGER
+    );
+    CREATE TABLE sources (
+        id INTEGER PRIMARY KEY,
+        flight_id INTEGER NOT NULL REFERENCES flights(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        track_type TEXT NOT NULL,
+        UNIQUE (flight_id, name, track_type)
+    );
+    CREATE TABLE fixes (
+        id INTEGER PRIMARY KEY,
+        flight_id INTEGER NOT NULL REFERENCES flights(id) ON DELETE CASCADE,
+        source_id INTEGER NOT NULL REFERENCES sources(id) ON DELETE CASCADE,
+        time INTEGER NOT NULL,
+        lat REAL NOT NULL,
+        lon REAL NOT NULL,
+        alt REAL NOT NULL,
+        horiz_vel REAL NOT NULL,
+        vert_vel REAL NOT NULL,
+        heading REAL,
+        rejected TEXT
+    );
+    CREATE INDEX fixes_time ON fixes (flight_id, time);
+    CREATE TABLE fused_positions (
+        flight_id INTEGER NOT NULL REFERENCES flights(id) ON DELETE CASCADE,
+        time INTEGER NOT NULL,
+        lat REAL NOT NULL,
+        lon REAL NOT NULL,
+        alt REAL NOT NULL,
+        horiz_vel REAL NOT NULL,
+        vert_vel REAL NOT NULL,
+        heading REAL,
+        PRIMARY KEY (flight_id, time)
+    );
+    CREATE TABLE telemetry (
+        id INTEGER PRIMARY KEY,
+        flight_id INTEGER NOT NULL REFERENCES flights(id) ON DELETE CASCADE,
+        source_id INTEGER NOT NULL REFERENCES sources(id) ON DELETE CASCADE,
+        time INTEGER NOT NULL,
+        channel TEXT NOT NULL,
+        unit TEXT NOT NULL,
+        value REAL NOT NULL
+    );
+    CREATE INDEX telemetry_time ON telemetry (flight_id, time);
+    CREATE TABLE predictions (
+        id INTEGER PRIMARY KEY,
+        flight_id INTEGER NOT NULL REFERENCES flights(id) ON DELETE CASCADE,
+        created INTEGER NOT NULL,
+        predictor TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX predictions_created ON predictions (flight_id, created);",
+    "CREATE TABLE payloads (
+        flight_id INTEGER NOT NULL REFERENCES flights(id) ON DELETE CASCADE,
+        kind TEXT NOT NULL,
+        ident TEXT NOT NULL,
+        PRIMARY KEY (flight_id, kind, ident)
+    );",
+    "CREATE TABLE receivers (
+        flight_id INTEGER NOT NULL REFERENCES flights(id) ON DELETE CASCADE,
+        track_type TEXT NOT NULL,
+        source TEXT NOT NULL,
+        config TEXT NOT NULL,
+        PRIMARY KEY (flight_id, track_type, source)
+    );",
+];
+
+//Kinds of payload identifiers of a flight
+const CALL_SIGN: &str = "callsign";
+const IMEI: &str = "imei";
+
+/** Struct holding a flight of the database.
+
+started, ended -> Unix timestamps, ended is None while the flight is in progress
+
+call_signs, imeis -> Payload identifiers tracked during the flight
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct FlightRecord {
+    pub id: i64,
+    pub name: String,
+    pub started: u64,
+    pub ended: Option<u64>,
+    pub call_signs: Vec<String>,
+    pub imeis: Vec<String>,
+}
+
+/** Struct holding a fix as received from a source.
+
+rejected -> Why the validation dropped the fix, None if it was used
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredFix {
+    pub source: String,
+    pub track_type: String,
+    pub pos_time: PositionTime,
+    pub rejected: Option<String>,
+}
+
+/// Embedded SQLite store of the flights, their sources, fixes, fused positions, telemetry and predictions
+pub struct FlightDatabase {
+    conn: Connection,
+    // Row id of each source already registered in a flight
+    sources: HashMap<(i64, String, TrackingType), i64>,
+}
+
+impl FlightDatabase {
+
+    // ------------------------Initializing Functions------------------------
+
+    /// Open or create the database file and bring its schema up to date
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let conn = Connection::open(path)?;
+        // The write-ahead log keeps the file consistent if the app dies mid-flight
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        // In WAL mode this only syncs at checkpoints, a crash can lose the last commits but not corrupt the file
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        Self::with_connection(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self, Box<dyn Error>> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<Self, Box<dyn Error>> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        Self::migrate(&mut conn)?;
+        Ok(Self { conn, sources: HashMap::new() })
+    }
+
+    /// Apply the migrations the file has not seen yet, each in its own transaction
+    fn migrate(conn: &mut Connection) -> Result<(), Box<dyn Error>> {
+        let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > MIGRATIONS.len() {
+            return Err(format!("Database schema version {} is newer than this version of the app ({})", version, MIGRATIONS.len()).into());
+        }
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", index + 1)?;
+            tx.commit()?;
+            println!("Database migrated to schema version {}", index + 1);
+        }
+        Ok(())
+    }
+
+    // ------------------------Flight Functions------------------------
+
+    /// Create a flight started at `started` tracking the given payloads
+    pub fn start_flight(&mut self, name: &str, started: u64, call_signs: &[String], imeis: &[String]) -> Result<FlightRecord, Box<dyn Error>> {
+        let tx = self.conn.savepoint()?;
+        tx.execute("INSERT INTO flights (name, started) VALUES (?1, ?2)", params![name, started as i64])?;
+        let id = tx.last_insert_rowid();
+        Self::insert_payloads(&tx, id, call_signs, imeis)?;
+        tx.commit()?;
+        self.flight(id)?.ok_or_else(|| "Flight not found after creation".into())
+    }
+
+    /// Track more callsigns and Iridium IMEIs in a flight, the ones already tracked are skipped
+    pub fn add_payloads(&mut self, flight: i64, call_signs: &[String], imeis: &[String]) -> Result<(), Box<dyn Error>> {
+        let tx = self.conn.savepoint()?;
+        Self::insert_payloads(&tx, flight, call_signs, imeis)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Remember the settings of a receiver of a flight, replacing the ones stored for the same source
+    pub fn add_receiver(&mut self, flight: i64, track_type: TrackingType, source: &str, config: &ReceiverConfig) -> Result<(), Box<dyn Error>> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO receivers (flight_id, track_type, source, config) VALUES (?1, ?2, ?3, ?4)",
+            params![flight, track_type.to_string(), source, serde_json::to_string(config)?],
+        )?;
+        Ok(())
+    }
+
+    pub fn remove_receiver(&mut self, flight: i64, track_type: TrackingType, source: &str) -> Result<(), Box<dyn Error>> {
+        self.conn.execute(
+            "DELETE FROM receivers WHERE flight_id = ?1 AND track_type = ?2 AND source = ?3",
+            params![flight, track_type.to_string(), source],
+        )?;
+        Ok(())
+    }
+
+    /// Settings of the receivers of a flight, in the order they were added
+    pub fn receivers(&self, flight: i64) -> Result<Vec<ReceiverConfig>, Box<dyn Error>> {
+        let mut stmt = self.conn.prepare_cached("SELECT config FROM receivers WHERE flight_id = ?1 ORDER BY rowid")?;
+        let configs = stmt.query_map(params![flight], |row| row.get::<_, String>(0))?.collect::<Result<Vec<_>, _>>()?;
+        Ok(configs.iter().map(|c| serde_json::from_str(c)).collect::<Result<Vec<_>, _>>()?)
+    }
+
+    pub fn end_flight(&mut self, flight: i64, ended: u64) -> Result<(), Box<dyn Error>> {
+        self.conn.execute("UPDATE flights SET ended = ?2 WHERE id = ?1", params![flight, ended as i64])?;
+        Ok(())
+    }
+
+    /// Mark an ended flight as in progress again
+    pub fn reopen_flight(&mut self, flight: i64) -> Result<(), Box<dyn Error>> {
+        self.conn.execute("UPDATE flights SET ended = NULL WHERE id = ?1", params![flight])?;
+        Ok(())
+    }
+
+    pub fn flight(&self, flight: i64) -> Result<Option<FlightRecord>, Box<dyn Error>> {
+        let record = self.conn
+            .query_row("SELECT id, name, started, ended FROM flights WHERE id = ?1", params![flight], Self::flight_from_row)
+            .optional()?;
+        record.map(|r| self.with_payloads(r)).transpose()
+    }
+
+    /// Every flight, newest first
+    pub fn flights(&self) -> Result<Vec<FlightRecord>, Box<dyn Error>> {
+        let mut stmt = self.conn.prepare("SELECT id, name, started, ended FROM flights ORDER BY started DESC, id DESC")?;
+        let flights = stmt.query_map([], Self::flight_from_row)?.collect::<Result<Vec<_>, _>>()?;
+        flights.into_iter().map(|f| self.with_payloads(f)).collect()
+    }
+
+    /// Newest flight still in progress, left open when the app was closed during it
+    pub fn open_flight(&self) -> Result<Option<FlightRecord>, Box<dyn Error>> {
+        let flight = self.conn
+            .query_row("SELECT id, name, started, ended FROM flights WHERE ended IS NULL ORDER BY started DESC, id DESC LIMIT 1", [], Self::flight_from_row)
+            .optional()?;
+        flight.map(|f| self.with_payloads(f)).transpose()
+    }
+
+    pub fn latest_flight(&self) -> Result<Option<FlightRecord>, Box<dyn Error>> {
+        let flight = self.conn
+            .query_row("SELECT id, name, started, ended FROM flights ORDER BY started DESC, id DESC LIMIT 1", [], Self::flight_from_row)
+            .optional()?;
+        flight.map(|f| self.with_payloads(f)).transpose()
+    }
+
+    // ------------------------Insert Functions------------------------
+
+    /// Start a batch, the inserts until `commit` are written in one transaction
+    pub fn begin(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.conn.is_autocommit() {
+            self.conn.execute_batch("BEGIN")?;
+        }
+        Ok(())
+    }
+
+    /// Commit the batch started by `begin`, rolling it back if the commit fails
+    pub fn commit(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.conn.is_autocommit() {
+            return Ok(());
+        }
+        if let Err(e) = self.conn.execute_batch("COMMIT") {
+            let _ = self.conn.execute_batch("ROLLBACK");
+            // Sources registered in the batch are gone with it
+            self.sources.clear();
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Store a fix as received, with the reason it was rejected if it was
+    pub fn insert_fix(&mut self, flight: i64, source: &str, track_type: TrackingType, fix: &PositionTime, rejected: Option<&str>) -> Result<(), Box<dyn Error>> {
+        let source_id = self.source_id(flight, source, track_type)?;
+        self.conn.execute(
+            "INSERT INTO fixes (flight_id, source_id, time, lat, lon, alt, horiz_vel, vert_vel, heading, rejected)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![flight, source_id, fix.last_update as i64, fix.lat, fix.lon, fix.alt, fix.horiz_vel, fix.vert_vel, fix.heading, rejected],
+        )?;
+        Ok(())
+    }
+
+    /// Store the tracked position, replacing the one already stored for the same second
+    pub fn insert_fused(&mut self, flight: i64, position: &PositionTime) -> Result<(), Box<dyn Error>> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO fused_positions (flight_id, time, lat, lon, alt, horiz_vel, vert_vel, heading)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![flight, position.last_update as i64, position.lat, position.lon, position.alt, position.horiz_vel, position.vert_vel, position.heading],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_telemetry(&mut self, flight: i64, source: &str, track_type: TrackingType, time: u64, channels: &[TelemetryChannel]) -> Result<(), Box<dyn Error>> {
+        let source_id = self.source_id(flight, source, track_type)?;
+        // A savepoint, so the channels can also be written inside a batch
+        let tx = self.conn.savepoint()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO telemetry (flight_id, source_id, time, channel, unit, value) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            for channel in channels {
+                stmt.execute(params![flight, source_id, time as i64, channel.name, channel.unit, channel.value])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Store a prediction run at `created`, `data` is its JSON serialization
+    pub fn insert_prediction(&mut self, flight: i64, created: u64, predictor: &str, data: &str) -> Result<(), Box<dyn Error>> {
+        self.conn.execute(
+            "INSERT INTO predictions (flight_id, created, predictor, data) VALUES (?1, ?2, ?3, ?4)",
+            params![flight, created as i64, predictor, data],
+        )?;
+        Ok(())
+    }
+
+    // ------------------------Query Functions------------------------
+
+    /// Fixes of a flight between `from` and `to` (inclusive, unbounded if None), oldest first
+    pub fn fixes(&self, flight: i64, from: Option<u64>, to: Option<u64>, include_rejected: bool) -> Result<Vec<StoredFix>, Box<dyn Error>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT s.name, s.track_type, f.lat, f.lon, f.alt, f.time, f.horiz_vel, f.vert_vel, f.heading, f.rejected
+             FROM fixes f JOIN sources s ON s.id = f.source_id
+             WHERE f.flight_id = ?1 AND f.time BETWEEN ?2 AND ?3 AND (?4 OR f.rejected IS NULL)
+             ORDER BY f.time, f.id",
+        )?;
+        let (from, to) = Self::range(from, to);
+        let fixes = stmt
+            .query_map(params![flight, from, to, include_rejected], |row| {
+                Ok(StoredFix {
+                    source: row.get(0)?,
+                    track_type: row.get(1)?,
+                    pos_time: Self::pos_time_from_row(row, 2)?,
+                    rejected: row.get(9)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(fixes)
+    }
+
+    /// Tracked positions of a flight between `from` and `to`, oldest first
+    pub fn fused_positions(&self, flight: i64, from: Option<u64>, to: Option<u64>) -> Result<Vec<PositionTime>, Box<dyn Error>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT lat, lon, alt, time, horiz_vel, vert_vel, heading FROM fused_positions
+             WHERE flight_id = ?1 AND time BETWEEN ?2 AND ?3 ORDER BY time",
+        )?;
+        let (from, to) = Self::range(from, to);
+        let positions = stmt
+            .query_map(params![flight, from, to], |row| Self::pos_time_from_row(row, 0))?
+            .collect::<Result<_, _>>()?;
+        Ok(positions)
+    }
+
+    /// Telemetry of a flight between `from` and `to`, one sample per source and time, oldest first
+    pub fn telemetry(&self, flight: i64, from: Option<u64>, to: Option<u64>) -> Result<Vec<TelemetrySample>, Box<dyn Error>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT t.time, s.name, t.channel, t.unit, t.value
+             FROM telemetry t JOIN sources s ON s.id = t.source_id
+             WHERE t.flight_id = ?1 AND t.time BETWEEN ?2 AND ?3
+             ORDER BY t.time, s.name, t.id",
+        )?;
+        let (from, to) = Self::range(from, to);
+        let mut rows = stmt.query(params![flight, from, to])?;
+
+        let mut samples: Vec<TelemetrySample> = vec![];
+        while let Some(row) = rows.next()? {
+            let time = row.get::<_, i64>(0)? as u64;
+            let source: String = row.get(1)?;
+            let channel = TelemetryChannel { name: row.get(2)?, unit: row.get(3)?, value: row.get(4)? };
+            match samples.last_mut() {
+                Some(sample) if sample.time == time && sample.source == source => sample.channels.push(channel),
+                _ => samples.push(TelemetrySample { time, source, channels: vec![channel] }),
+            }
+        }
+        Ok(samples)
+    }
+
+    /// JSON of the newest prediction of a flight
+    pub fn latest_prediction(&self, flight: i64) -> Result<Option<String>, Box<dyn Error>> {
+        let data = self.conn
+            .query_row(
+                "SELECT data FROM predictions WHERE flight_id = ?1 ORDER BY created DESC, id DESC LIMIT 1",
+                params![flight],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(data)
+    }
+
+    /// Write the used fixes of a flight in the CSV layout of the old `Launch Data` files, returning the rows written
+    pub fn export_csv(&self, flight: i64, path: &Path) -> Result<usize, Box<dyn Error>> {
+        let telemetry: HashMap<(u64, String), Vec<TelemetryChannel>> = self.telemetry(flight, None, None)?
+            .into_iter()
+            .map(|s| ((s.time, s.source), s.channels))
+            .collect();
+        let fixes = self.fixes(flight, None, None, false)?;
+
+        let mut file = File::create(path)?;
+        writeln!(file, "track_type,lat,lon,alt,horiz_vel,vert_vel,time,telemetry")?;
+        for fix in &fixes {
+            let p = &fix.pos_time;
+            let channels = telemetry.get(&(p.last_update, fix.source.clone())).map(|c| format_channels(c)).unwrap_or_default();
+            writeln!(
+                file,
+                "{},{:.6},{:.6},{:.2},{:.2},{:.2},{},{}",
+                fix.track_type, p.lat, p.lon, p.alt, p.horiz_vel, p.vert_vel, p.last_update, channels
+            )?;
+        }
+        Ok(fixes.len())
+    }
+
+    //------------------------Helper Functions------------------------
+
+    /// Id of the source in the flight, registering it on first use
+    fn source_id(&mut self, flight: i64, name: &str, track_type: TrackingType) -> Result<i64, Box<dyn Error>> {
+        let key = (flight, name.to_string(), track_type);
+        if let Some(&id) = self.sources.get(&key) {
+            return Ok(id);
+        }
+        self.conn.execute(
+            "INSERT OR IGNORE INTO sources (flight_id, name, track_type) VALUES (?1, ?2, ?3)",
+            params![flight, name, track_type.to_string()],
+        )?;
+        let id = self.conn.query_row(
+            "SELECT id FROM sources WHERE flight_id = ?1 AND name = ?2 AND track_type = ?3",
+            params![flight, name, track_type.to_string()],
+            |row| row.get(0),
+        )?;
+        self.sources.insert(key, id);
+        Ok(id)
+    }
+
+    fn insert_payloads(conn: &Connection, flight: i64, call_signs: &[String], imeis: &[String]) -> Result<(), Box<dyn Error>> {
+        let mut stmt = conn.prepare_cached("INSERT OR IGNORE INTO payloads (flight_id, kind, ident) VALUES (?1, ?2, ?3)")?;
+        for call_sign in call_signs {
+            stmt.execute(params![flight, CALL_SIGN, call_sign])?;
+        }
+        for imei in imeis {
+            stmt.execute(params![flight, IMEI, imei])?;
+        }
+        Ok(())
+    }
+
+    fn range(from: Option<u64>, to: Option<u64>) -> (i64, i64) {
+        (from.map_or(0, |t| t as i64), to.map_or(i64::MAX, |t| t as i64))
+    }
+
+    fn flight_from_row(row: &Row) -> rusqlite::Result<FlightRecord> {
+        Ok(FlightRecord {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            started: row.get::<_, i64>(2)? as u64,
+            ended: row.get::<_, Option<i64>>(3)?.map(|t| t as u64),
+            call_signs: vec![],
+            imeis: vec![],
+        })
+    }
+
+    /// Fill in the payload identifiers of a flight
+    fn with_payloads(&self, mut flight: FlightRecord) -> Result<FlightRecord, Box<dyn Error>> {
+        let mut stmt = self.conn.prepare_cached("SELECT kind, ident FROM payloads WHERE flight_id = ?1 ORDER BY rowid")?;
+        let mut rows = stmt.query(params![flight.id])?;
+        while let Some(row) = rows.next()? {
+            let kind: String = row.get(0)?;
+            match kind.as_str() {
+                CALL_SIGN => flight.call_signs.push(row.get(1)?),
+                IMEI => flight.imeis.push(row.get(1)?),
+                _ => {}
+            }
+        }
+        Ok(flight)
+    }
+
+    /// Read lat, lon, alt, time, horiz_vel, vert_vel and heading from the columns starting at `first`
+    fn pos_time_from_row(row: &Row, first: usize) -> rusqlite::Result<PositionTime> {
+        let mut pos_time = PositionTime::new_with_value(
+            row.get(first)?,
+            row.get(first + 1)?,
+            row.get(first + 2)?,
+            row.get::<_, i64>(first + 3)? as u64,
+            row.get(first + 4)?,
+            row.get(first + 5)?,
+        );
+        pos_time.heading = row.get(first + 6)?;
+        Ok(pos_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(time: u64, lat: f64) -> PositionTime {
+        PositionTime::new_with_value(lat, -80.0, 1000.0, time, 5.0, 3.0)
+    }
+
+    fn channel(name: &str, value: f64) -> TelemetryChannel {
+        TelemetryChannel { name: name.to_string(), unit: "V".to_string(), value }
+    }
+
+    #[test]
+    fn migrates_to_the_latest_version() {
+        let db = FlightDatabase::open_in_memory().unwrap();
+        let version: usize = db.conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+
+        // A file left at the first version keeps its flights through the later migrations
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute("INSERT INTO flights (name, started) VALUES ('old', 100)", []).unwrap();
+        let mut db = FlightDatabase::with_connection(conn).unwrap();
+        assert_eq!(db.flights().unwrap()[0].name, "old");
+        let flight = db.start_flight("new", 200, &["KD9ABC-11".to_string()], &[]).unwrap();
+        assert_eq!(flight.call_signs, vec!["KD9ABC-11".to_string()]);
+
+        // A schema from a newer app is refused
+        let conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", MIGRATIONS.len() + 1).unwrap();
+        assert!(FlightDatabase::with_connection(conn).is_err());
+    }
+
+    #[test]
+    fn round_trips_inserts() {
+        let mut db = FlightDatabase::open_in_memory().unwrap();
+        let flight = db.start_flight("test", 100, &[], &["300234010000000".to_string()]).unwrap().id;
+
+        db.insert_fix(flight, "KD9ABC-11", TrackingType::APRS, &fix(110, 40.0), None).unwrap();
+        db.insert_fix(flight, "KD9ABC-11", TrackingType::APRS, &fix(120, 41.0), Some("Jump")).unwrap();
+        db.insert_fix(flight, "HORUS", TrackingType::Horus, &fix(130, 42.0), None).unwrap();
+        let used = db.fixes(flight, None, None, false).unwrap();
+        assert_eq!(used.iter().map(|f| f.pos_time.last_update).collect::<Vec<_>>(), vec![110, 130]);
+        assert_eq!(used[1].source, "HORUS");
+        let all = db.fixes(flight, Some(115), None, true).unwrap();
+        assert_eq!(all[0].rejected.as_deref(), Some("Jump"));
+        assert_eq!(all.len(), 2);
+
+        // The tracked position of a second is replaced, not duplicated
+        db.insert_fused(flight, &fix(110, 40.0)).unwrap();
+        db.insert_fused(flight, &fix(110, 40.5)).unwrap();
+        let fused = db.fused_positions(flight, None, None).unwrap();
+        assert_eq!(fused.len(), 1);
+        assert_eq!(fused[0].lat, 40.5);
+
+        db.insert_telemetry(flight, "KD9ABC-11", TrackingType::APRS, 110, &[channel("Battery", 3.7), channel("Solar", 1.2)]).unwrap();
+        db.insert_telemetry(flight, "HORUS", TrackingType::Horus, 130, &[channel("Battery", 3.6)]).unwrap();
+        let telemetry = db.telemetry(flight, None, None).unwrap();
+        assert_eq!(telemetry.len(), 2);
+        assert_eq!(telemetry[0].channels.len(), 2);
+        assert_eq!(telemetry[1].source, "HORUS");
+
+        assert_eq!(db.latest_prediction(flight).unwrap(), None);
+        db.insert_prediction(flight, 150, "ascent", "{\"a\":1}").unwrap();
+        db.insert_prediction(flight, 150, "ascent", "{\"a\":2}").unwrap();
+        assert_eq!(db.latest_prediction(flight).unwrap().as_deref(), Some("{\"a\":2}"));
+    }
+
+    #[test]
+    fn keeps_the_sources_of_open_flights() {
+        let mut db = FlightDatabase::open_in_memory().unwrap();
+        let first = db.start_flight("first", 100, &["KD9ABC-11".to_string()], &[]).unwrap().id;
+        let second = db.start_flight("second", 200, &[], &[]).unwrap().id;
+        assert_eq!(db.open_flight().unwrap().unwrap().id, second);
+        db.end_flight(second, 300).unwrap();
+        assert_eq!(db.open_flight().unwrap().unwrap().id, first);
+        db.end_flight(first, 300).unwrap();
+        assert!(db.open_flight().unwrap().is_none());
+
+        db.add_payloads(first, &["KD9ABC-11".to_string(), "KD9ABC-12".to_string()], &["300234010000000".to_string()]).unwrap();
+        let flight = db.flight(first).unwrap().unwrap();
+        assert_eq!(flight.call_signs, vec!["KD9ABC-11".to_string(), "KD9ABC-12".to_string()]);
+        assert_eq!(flight.imeis.len(), 1);
+
+        let horus = ReceiverConfig::Horus { port: 55672, call_sign: "HORUS".to_string() };
+        let rfd = ReceiverConfig::Rfd { port: "/dev/ttyUSB0".to_string(), baud: 57600, layout: vec!["lat".to_string(), "lon".to_string()] };
+        db.add_receiver(first, TrackingType::Horus, "HORUS", &horus).unwrap();
+        db.add_receiver(first, TrackingType::RFD, "/dev/ttyUSB0", &rfd).unwrap();
+        db.add_receiver(first, TrackingType::Horus, "HORUS", &horus).unwrap();
+        assert_eq!(db.receivers(first).unwrap(), vec![rfd.clone(), horus]);
+        db.remove_receiver(first, TrackingType::Horus, "HORUS").unwrap();
+        assert_eq!(db.receivers(first).unwrap(), vec![rfd]);
+        assert!(db.receivers(second).unwrap().is_empty());
+    }
+
+    #[test]
+    fn commits_and_rolls_back_batches() {
+        let mut db = FlightDatabase::open_in_memory().unwrap();
+        let flight = db.start_flight("test", 100, &[], &[]).unwrap().id;
+
+        db.begin().unwrap();
+        db.insert_fix(flight, "HORUS", TrackingType::Horus, &fix(110, 40.0), None).unwrap();
+        db.insert_telemetry(flight, "HORUS", TrackingType::Horus, 110, &[channel("Battery", 3.7)]).unwrap();
+        db.insert_fused(flight, &fix(110, 40.0)).unwrap();
+        db.commit().unwrap();
+        assert!(db.conn.is_autocommit());
+        assert_eq!(db.fixes(flight, None, None, true).unwrap().len(), 1);
+        assert_eq!(db.telemetry(flight, None, None).unwrap().len(), 1);
+
+        // A batch that can not be committed is dropped along with the sources it registered
+        db.begin().unwrap();
+        db.insert_fix(flight, "KD9ABC-11", TrackingType::APRS, &fix(120, 41.0), None).unwrap();
+        db.conn.execute_batch("PRAGMA defer_foreign_keys = ON; INSERT INTO fused_positions (flight_id, time, lat, lon, alt, horiz_vel, vert_vel) VALUES (99, 0, 0, 0, 0, 0, 0)").unwrap();
+        assert!(db.commit().is_err());
+        assert!(db.conn.is_autocommit());
+        assert_eq!(db.fixes(flight, None, None, true).unwrap().len(), 1);
+        db.insert_fix(flight, "KD9ABC-11", TrackingType::APRS, &fix(120, 41.0), None).unwrap();
+        assert_eq!(db.fixes(flight, None, None, true).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,520 @@
+use std::{error::Error, fmt::Write, fs, path::Path};
+
+use chrono::{DateTime, SecondsFormat};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::track_lib::position_time::PositionTime;
+
+//Name written as the creator of the exported files
+const CREATOR: &str = "HARPTracker";
+
+/// File format of a flight export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Kml,
+    Gpx,
+    GeoJson,
+    Czml,
+}
+
+/** Struct holding a predicted flight path.
+
+ascent, descent -> Predicted points before and after the burst, oldest first
+
+burst, landing -> Predicted burst and landing points
+*/
+#[derive(Debug, Clone, Default)]
+pub struct PredictedPath {
+    pub ascent: Vec<PositionTime>,
+    pub burst: Option<PositionTime>,
+    pub landing: Option<PositionTime>,
+    pub descent: Vec<PositionTime>,
+}
+
+/** Struct holding a flight ready to be written to a file.
+
+name -> Name of the flight, used as the document name
+
+track -> Tracked positions, oldest first
+
+burst -> Highest position before the descent, None until the payload descends
+
+landing -> Last position once the payload landed
+
+prediction -> Latest predicted path, None if not asked for or never run
+*/
+#[derive(Debug, Clone)]
+pub struct FlightExport {
+    pub name: String,
+    pub track: Vec<PositionTime>,
+    pub burst: Option<PositionTime>,
+    pub landing: Option<PositionTime>,
+    pub prediction: Option<PredictedPath>,
+}
+
+impl FlightExport {
+    /// Write the flight to `path` in `format`, returning the number of track points written
+    pub fn write(&self, path: &Path, format: ExportFormat) -> Result<usize, Box<dyn Error>> {
+        let text = match format {
+            ExportFormat::Kml => self.to_kml(),
+            ExportFormat::Gpx => self.to_gpx(),
+            ExportFormat::GeoJson => serde_json::to_string_pretty(&self.to_geojson())?,
+            ExportFormat::Czml => serde_json::to_string_pretty(&self.to_czml())?,
+        };
+        fs::write(path, text)?;
+        Ok(self.track.len())
+    }
+
+    /// KML document with the track as an extruded line and placemarks for the burst and landing
+    pub fn to_kml(&self) -> String {
+        let mut kml = String::new();
+        kml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        kml.push_str("<kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n");
+        let _ = writeln!(kml, "<name>{}</name>", escape(&self.name));
+        kml.push_str(concat!(
+            "<Style id=\"track\"><LineStyle><color>ff0000ff</color><width>3</width></LineStyle>",
+            "<PolyStyle><color>400000ff</color></PolyStyle></Style>\n",
+            "<Style id=\"prediction\"><LineStyle><color>ffff7f00</color><width>2</width></LineStyle></Style>\n",
+        ));
+
+        kml_line(&mut kml, "Flight path", "track", &self.track, true);
+        kml_point(&mut kml, "Burst", self.burst.as_ref());
+        kml_point(&mut kml, "Landing", self.landing.as_ref());
+
+        if let Some(prediction) = &self.prediction {
+            kml.push_str("<Folder>\n<name>Prediction</name>\n");
+            kml_line(&mut kml, "Predicted ascent", "prediction", &prediction.ascent, false);
+            kml_line(&mut kml, "Predicted descent", "prediction", &prediction.descent, false);
+            kml_point(&mut kml, "Predicted burst", prediction.burst.as_ref());
+            kml_point(&mut kml, "Predicted landing", prediction.landing.as_ref());
+            kml.push_str("</Folder>\n");
+        }
+        kml.push_str("</Document>\n</kml>\n");
+        kml
+    }
+
+    /// GPX 1.1 document with the track points, their elevation and time, and waypoints for the burst and landing
+    pub fn to_gpx(&self) -> String {
+        let mut gpx = String::new();
+        gpx.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(gpx, "<gpx version=\"1.1\" creator=\"{}\" xmlns=\"http://www.topografix.com/GPX/1/1\">", CREATOR);
+        let _ = writeln!(gpx, "<metadata><name>{}</name></metadata>", escape(&self.name));
+
+        // The schema wants every waypoint before the tracks
+        gpx_point(&mut gpx, "wpt", "Burst", self.burst.as_ref());
+        gpx_point(&mut gpx, "wpt", "Landing", self.landing.as_ref());
+        if let Some(prediction) = &self.prediction {
+            gpx_point(&mut gpx, "wpt", "Predicted burst", prediction.burst.as_ref());
+            gpx_point(&mut gpx, "wpt", "Predicted landing", prediction.landing.as_ref());
+        }
+
+        gpx_track(&mut gpx, &self.name, &self.track);
+        if let Some(prediction) = &self.prediction {
+            gpx_track(&mut gpx, "Predicted ascent", &prediction.ascent);
+            gpx_track(&mut gpx, "Predicted descent", &prediction.descent);
+        }
+        gpx.push_str("</gpx>\n");
+        gpx
+    }
+
+    /// GeoJSON feature collection with the track as a LineString (a Point while it has one fix), its per point values in `coordinateProperties`, and points for the burst and landing
+    pub fn to_geojson(&self) -> Value {
+        let mut features = vec![];
+        if !self.track.is_empty() {
+            features.push(json!({
+                "type": "Feature",
+                "geometry": geojson_line(&self.track),
+                "properties": {
+                    "name": self.name,
+                    "kind": "track",
+                    "coordinateProperties": {
+                        "time": self.track.iter().map(|p| timestamp(p.last_update)).collect::<Vec<_>>(),
+                        "horiz_vel": self.track.iter().map(|p| p.horiz_vel).collect::<Vec<_>>(),
+                        "vert_vel": self.track.iter().map(|p| p.vert_vel).collect::<Vec<_>>(),
+                        "heading": self.track.iter().map(|p| p.heading).collect::<Vec<_>>(),
+                    },
+                },
+            }));
+        }
+        features.extend(geojson_point("Burst", "burst", self.burst.as_ref()));
+        features.extend(geojson_point("Landing", "landing", self.landing.as_ref()));
+
+        if let Some(prediction) = &self.prediction {
+            for (name, kind, points) in [
+                ("Predicted ascent", "predicted_ascent", &prediction.ascent),
+                ("Predicted descent", "predicted_descent", &prediction.descent),
+            ] {
+                if points.is_empty() {
+                    continue;
+                }
+                features.push(json!({
+                    "type": "Feature",
+                    "geometry": geojson_line(points),
+                    "properties": {
+                        "name": name,
+                        "kind": kind,
+                        "coordinateProperties": { "time": points.iter().map(|p| timestamp(p.last_update)).collect::<Vec<_>>() },
+                    },
+                }));
+            }
+            features.extend(geojson_point("Predicted burst", "predicted_burst", prediction.burst.as_ref()));
+            features.extend(geojson_point("Predicted landing", "predicted_landing", prediction.landing.as_ref()));
+        }
+        json!({ "type": "FeatureCollection", "name": self.name, "features": features })
+    }
+
+    /// CZML document playing back the track over its clock, with the burst and landing appearing when they happened
+    pub fn to_czml(&self) -> Value {
+        let mut document = json!({ "id": "document", "name": self.name, "version": "1.0" });
+        // Samples are offsets from the first one, so they must be in time order and have a time
+        let mut track: Vec<&PositionTime> = self.track.iter().filter(|p| p.last_update != 0).collect();
+        track.sort_by_key(|p| p.last_update);
+        let (Some(first), Some(last)) = (track.first(), track.last()) else {
+            return json!([document]);
+        };
+        // The clock runs over the track, and on to the predicted landing when there is one
+        let end = self.prediction.iter()
+            .flat_map(|p| p.ascent.iter().chain(&p.descent).chain(&p.landing))
+            .map(|p| p.last_update)
+            .fold(last.last_update, u64::max);
+        let interval = czml_interval(first.last_update, end);
+        document["clock"] = json!({
+            "interval": interval,
+            "currentTime": timestamp(first.last_update),
+            "multiplier": 10,
+            "range": "LOOP_STOP",
+            "step": "SYSTEM_CLOCK_MULTIPLIER",
+        });
+
+        let mut packets = vec![document];
+        packets.push(json!({
+            "id": "track",
+            "name": self.name,
+            "availability": interval,
+            "position": {
+                "epoch": timestamp(first.last_update),
+                "cartographicDegrees": track.iter()
+                    .flat_map(|p| [p.last_update.saturating_sub(first.last_update) as f64, p.lon, p.lat, p.alt])
+                    .collect::<Vec<_>>(),
+            },
+            "point": { "pixelSize": 8, "color": { "rgba": [255, 0, 0, 255] } },
+            "path": {
+                "material": { "solidColor": { "color": { "rgba": [255, 0, 0, 255] } } },
+                "width": 3,
+                "leadTime": 0,
+                "trailTime": end.saturating_sub(first.last_update),
+            },
+        }));
+        packets.extend(czml_point("burst", "Burst", self.burst.as_ref(), end));
+        packets.extend(czml_point("landing", "Landing", self.landing.as_ref(), end));
+
+        if let Some(prediction) = &self.prediction {
+            for (id, name, points) in [
+                ("predicted_ascent", "Predicted ascent", &prediction.ascent),
+                ("predicted_descent", "Predicted descent", &prediction.descent),
+            ] {
+                if points.is_empty() {
+                    continue;
+                }
+                packets.push(json!({
+                    "id": id,
+                    "name": name,
+                    "polyline": {
+                        "positions": { "cartographicDegrees": points.iter().flat_map(coordinates).collect::<Vec<_>>() },
+                        "material": { "solidColor": { "color": { "rgba": [0, 127, 255, 255] } } },
+                        "width": 2,
+                    },
+                }));
+            }
+            packets.extend(czml_point("predicted_burst", "Predicted burst", prediction.burst.as_ref(), end));
+            packets.extend(czml_point("predicted_landing", "Predicted landing", prediction.landing.as_ref(), end));
+        }
+        Value::Array(packets)
+    }
+}
+
+//------------------------Helper Functions------------------------
+
+fn kml_line(kml: &mut String, name: &str, style: &str, points: &[PositionTime], extrude: bool) {
+    if points.is_empty() {
+        return;
+    }
+    let _ = writeln!(kml, "<Placemark>\n<name>{}</name>\n<styleUrl>#{}</styleUrl>", escape(name), style);
+    // A LineString needs at least two coordinates
+    if let [p] = points {
+        let _ = writeln!(
+            kml,
+            "<Point>\n<extrude>{}</extrude>\n<altitudeMode>absolute</altitudeMode>\n<coordinates>{:.6},{:.6},{:.1}</coordinates>\n</Point>\n</Placemark>",
+            extrude as u8, p.lon, p.lat, p.alt
+        );
+        return;
+    }
+    let _ = writeln!(kml, "<LineString>\n<extrude>{}</extrude>\n<altitudeMode>absolute</altitudeMode>\n<coordinates>", extrude as u8);
+    for p in points {
+        let _ = writeln!(kml, "{:.6},{:.6},{:.1}", p.lon, p.lat, p.alt);
+    }
+    kml.push_str("</coordinates>\n</LineString>\n</Placemark>\n");
+}
+
+fn kml_point(kml: &mut String, name: &str, point: Option<&PositionTime>) {
+    let Some(p) = point else {
+        return;
+    };
+    let _ = writeln!(kml, "<Placemark>\n<name>{}</name>", escape(name));
+    if let Some(time) = timestamp(p.last_update) {
+        let _ = writeln!(kml, "<TimeStamp><when>{}</when></TimeStamp>", time);
+    }
+    let _ = writeln!(
+        kml,
+        "<Point>\n<altitudeMode>absolute</altitudeMode>\n<coordinates>{:.6},{:.6},{:.1}</coordinates>\n</Point>\n</Placemark>",
+        p.lon, p.lat, p.alt
+    );
+}
+
+fn gpx_track(gpx: &mut String, name: &str, points: &[PositionTime]) {
+    if points.is_empty() {
+        return;
+    }
+    let _ = writeln!(gpx, "<trk>\n<name>{}</name>\n<trkseg>", escape(name));
+    for p in points {
+        gpx_point(gpx, "trkpt", "", Some(p));
+    }
+    gpx.push_str("</trkseg>\n</trk>\n");
+}
+
+/// GPX waypoint or track point, unnamed if `name` is empty
+fn gpx_point(gpx: &mut String, tag: &str, name: &str, point: Option<&PositionTime>) {
+    let Some(p) = point else {
+        return;
+    };
+    let _ = write!(gpx, "<{} lat=\"{:.6}\" lon=\"{:.6}\"><ele>{:.1}</ele>", tag, p.lat, p.lon, p.alt);
+    if let Some(time) = timestamp(p.last_update) {
+        let _ = write!(gpx, "<time>{}</time>", time);
+    }
+    if !name.is_empty() {
+        let _ = write!(gpx, "<name>{}</name>", escape(name));
+    }
+    let _ = writeln!(gpx, "</{}>", tag);
+}
+
+/// Longitude, latitude and altitude, the order of GeoJSON and CZML
+fn coordinates(p: &PositionTime) -> [f64; 3] {
+    [p.lon, p.lat, p.alt]
+}
+
+/// LineString through `points`, or a Point when there is only one since a LineString needs two positions
+fn geojson_line(points: &[PositionTime]) -> Value {
+    match points {
+        [p] => json!({ "type": "Point", "coordinates": coordinates(p) }),
+        _ => json!({ "type": "LineString", "coordinates": points.iter().map(coordinates).collect::<Vec<_>>() }),
+    }
+}
+
+fn geojson_point(name: &str, kind: &str, point: Option<&PositionTime>) -> Option<Value> {
+    point.map(|p| json!({
+        "type": "Feature",
+        "geometry": { "type": "Point", "coordinates": coordinates(p) },
+        "properties": { "name": name, "kind": kind, "time": timestamp(p.last_update) },
+    }))
+}
+
+/// Labelled CZML point shown from its time to `end`, or the whole time if it has none
+fn czml_point(id: &str, name: &str, point: Option<&PositionTime>, end: u64) -> Option<Value> {
+    point.map(|p| {
+        let mut packet = json!({
+            "id": id,
+            "name": name,
+            "position": { "cartographicDegrees": coordinates(p) },
+            "point": { "pixelSize": 10, "color": { "rgba": [255, 255, 0, 255] } },
+            "label": { "text": name, "pixelOffset": { "cartesian2": [0, -20] } },
+        });
+        if p.last_update != 0 && p.last_update <= end {
+            packet["availability"] = json!(czml_interval(p.last_update, end));
+        }
+        packet
+    })
+}
+
+fn czml_interval(start: u64, end: u64) -> String {
+    format!("{}/{}", timestamp(start).unwrap_or_default(), timestamp(end).unwrap_or_default())
+}
+
+/// ISO 8601 UTC time of a unix timestamp, None for a fix without a time
+fn timestamp(secs: u64) -> Option<String> {
+    if secs == 0 {
+        return None;
+    }
+    DateTime::from_timestamp(secs as i64, 0).map(|t| t.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(time: u64, lat: f64, lon: f64, alt: f64) -> PositionTime {
+        PositionTime::new_with_value(lat, lon, alt, time, 5.0, 4.0)
+    }
+
+    fn flight() -> FlightExport {
+        let track = vec![point(1_700_000_000, 40.0, -88.0, 200.0), point(1_700_000_060, 40.01, -87.99, 500.0), point(1_700_000_120, 40.02, -87.98, 300.0)];
+        FlightExport {
+            name: "Flight <1> & co".to_string(),
+            burst: Some(track[1].clone()),
+            landing: Some(track[2].clone()),
+            track,
+            prediction: Some(PredictedPath {
+                ascent: vec![point(1_700_000_000, 40.0, -88.0, 200.0)],
+                burst: Some(point(1_700_000_500, 40.1, -87.9, 30000.0)),
+                landing: Some(point(1_700_001_000, 40.2, -87.8, 250.0)),
+                descent: vec![point(1_700_000_500, 40.1, -87.9, 30000.0), point(1_700_001_000, 40.2, -87.8, 250.0)],
+            }),
+        }
+    }
+
+    fn named<'a>(doc: &'a roxmltree::Document, tag: &str, name: &str) -> roxmltree::Node<'a, 'a> {
+        doc.descendants()
+            .find(|n| n.has_tag_name(tag) && n.children().any(|c| c.has_tag_name("name") && c.text() == Some(name)))
+            .unwrap_or_else(|| panic!("No {} named {}", tag, name))
+    }
+
+    fn child_text<'a>(node: roxmltree::Node<'a, 'a>, tag: &str) -> &'a str {
+        node.descendants().find(|n| n.has_tag_name(tag)).and_then(|n| n.text()).unwrap_or_default()
+    }
+
+    #[test]
+    fn kml_round_trips() {
+        let export = flight();
+        let kml = export.to_kml();
+        let doc = roxmltree::Document::parse(&kml).unwrap();
+        assert_eq!(doc.root_element().tag_name().namespace(), Some("http://www.opengis.net/kml/2.2"));
+        assert_eq!(child_text(doc.root_element(), "name"), export.name);
+
+        let path = named(&doc, "Placemark", "Flight path");
+        assert_eq!(child_text(path, "extrude"), "1");
+        let coordinates: Vec<Vec<f64>> = child_text(path, "coordinates")
+            .split_whitespace()
+            .map(|c| c.split(',').map(|v| v.parse().unwrap()).collect())
+            .collect();
+        assert_eq!(coordinates.len(), export.track.len());
+        for (parsed, p) in coordinates.iter().zip(&export.track) {
+            assert_eq!(parsed, &vec![p.lon, p.lat, p.alt]);
+        }
+
+        let burst = named(&doc, "Placemark", "Burst");
+        assert_eq!(child_text(burst, "when"), "2023-11-14T22:14:20Z");
+        assert_eq!(child_text(burst, "coordinates"), "-87.990000,40.010000,500.0");
+        named(&doc, "Placemark", "Landing");
+
+        let prediction = named(&doc, "Folder", "Prediction");
+        assert_eq!(prediction.children().filter(|n| n.has_tag_name("Placemark")).count(), 4);
+        assert_eq!(child_text(named(&doc, "Placemark", "Predicted descent"), "extrude"), "0");
+
+        // A single point path is not a valid LineString
+        let ascent = named(&doc, "Placemark", "Predicted ascent");
+        assert!(ascent.descendants().all(|n| !n.has_tag_name("LineString")));
+        assert_eq!(child_text(ascent, "coordinates"), "-88.000000,40.000000,200.0");
+    }
+
+    #[test]
+    fn kml_writes_a_single_fix_track_as_a_point() {
+        let mut export = flight();
+        export.track.truncate(1);
+        export.prediction = None;
+        let kml = export.to_kml();
+        let doc = roxmltree::Document::parse(&kml).unwrap();
+        let path = named(&doc, "Placemark", "Flight path");
+        let point = path.children().find(|n| n.has_tag_name("Point")).unwrap();
+        assert_eq!(child_text(point, "extrude"), "1");
+        assert_eq!(child_text(point, "coordinates"), "-88.000000,40.000000,200.0");
+        assert!(path.descendants().all(|n| !n.has_tag_name("LineString")));
+    }
+
+    #[test]
+    fn gpx_round_trips() {
+        let export = flight();
+        let gpx = export.to_gpx();
+        let doc = roxmltree::Document::parse(&gpx).unwrap();
+        let root = doc.root_element();
+        assert_eq!(root.tag_name().namespace(), Some("http://www.topografix.com/GPX/1/1"));
+        assert_eq!(root.attribute("version"), Some("1.1"));
+
+        // Waypoints come before the tracks
+        let elements: Vec<&str> = root.children().filter(|n| n.is_element()).map(|n| n.tag_name().name()).collect();
+        assert_eq!(elements, vec!["metadata", "wpt", "wpt", "wpt", "wpt", "trk", "trk", "trk"]);
+
+        let track = named(&doc, "trk", &export.name);
+        let points: Vec<_> = track.descendants().filter(|n| n.has_tag_name("trkpt")).collect();
+        assert_eq!(points.len(), export.track.len());
+        for (node, p) in points.iter().zip(&export.track) {
+            assert_eq!(node.attribute("lat").unwrap().parse::<f64>().unwrap(), p.lat);
+            assert_eq!(node.attribute("lon").unwrap().parse::<f64>().unwrap(), p.lon);
+            assert_eq!(child_text(*node, "ele").parse::<f64>().unwrap(), p.alt);
+            let time = DateTime::parse_from_rfc3339(child_text(*node, "time")).unwrap();
+            assert_eq!(time.timestamp() as u64, p.last_update);
+        }
+
+        let landing = named(&doc, "wpt", "Predicted landing");
+        assert_eq!(landing.attribute("lat"), Some("40.200000"));
+        assert_eq!(child_text(landing, "ele"), "250.0");
+    }
+
+    #[test]
+    fn geojson_holds_the_track_and_points() {
+        let export = flight();
+        let geojson = export.to_geojson();
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features[0]["geometry"]["type"], "LineString");
+        assert_eq!(features[0]["geometry"]["coordinates"][1], json!([-87.99, 40.01, 500.0]));
+        assert_eq!(features[0]["properties"]["coordinateProperties"]["time"][0], "2023-11-14T22:13:20Z");
+        let kinds: Vec<&str> = features.iter().map(|f| f["properties"]["kind"].as_str().unwrap()).collect();
+        assert_eq!(kinds, vec!["track", "burst", "landing", "predicted_ascent", "predicted_descent", "predicted_burst", "predicted_landing"]);
+
+        // A single point path is not a valid LineString
+        assert_eq!(features[3]["geometry"], json!({ "type": "Point", "coordinates": [-88.0, 40.0, 200.0] }));
+        assert_eq!(features[4]["geometry"]["type"], "LineString");
+    }
+
+    #[test]
+    fn geojson_writes_a_single_fix_track_as_a_point() {
+        let mut export = flight();
+        export.track.truncate(1);
+        export.prediction = None;
+        let geojson = export.to_geojson();
+        let track = &geojson["features"][0];
+        assert_eq!(track["properties"]["kind"], "track");
+        assert_eq!(track["geometry"], json!({ "type": "Point", "coordinates": [-88.0, 40.0, 200.0] }));
+        assert_eq!(track["properties"]["coordinateProperties"]["time"], json!(["2023-11-14T22:13:20Z"]));
+    }
+
+    #[test]
+    fn czml_plays_the_track_in_time_order() {
+        let mut export = flight();
+        // A late fix stored out of order and one without a time
+        export.track.swap(0, 1);
+        export.track.push(point(0, 40.03, -87.97, 250.0));
+        let czml = export.to_czml();
+        let packets = czml.as_array().unwrap();
+        assert_eq!(packets[0]["clock"]["interval"], "2023-11-14T22:13:20Z/2023-11-14T22:30:00Z");
+
+        let track = packets.iter().find(|p| p["id"] == "track").unwrap();
+        assert_eq!(track["position"]["epoch"], "2023-11-14T22:13:20Z");
+        let samples = track["position"]["cartographicDegrees"].as_array().unwrap();
+        let offsets: Vec<f64> = samples.iter().step_by(4).map(|v| v.as_f64().unwrap()).collect();
+        assert_eq!(offsets, vec![0.0, 60.0, 120.0]);
+        assert_eq!(track["path"]["trailTime"], 1000);
+        assert_eq!(packets.iter().find(|p| p["id"] == "burst").unwrap()["availability"], "2023-11-14T22:14:20Z/2023-11-14T22:30:00Z");
+
+        export.track.clear();
+        assert_eq!(export.to_czml().as_array().unwrap().len(), 1);
+    }
+}
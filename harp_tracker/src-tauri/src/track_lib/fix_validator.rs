@@ -0,0 +1,268 @@
+use std::{collections::{HashMap, VecDeque}, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::track_lib::look_angle::ground_distance_m;
+use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::tracking_type::TrackingType;
+
+//Fixes within this many degrees of 0,0 are treated as a missing position
+const NULL_ISLAND_DEG: f64 = 0.1;
+
+//Rejected fixes kept for the UI
+const MAX_REJECTED: usize = 200;
+
+/** Struct holding the bounds a fix must respect to be used.
+
+min_alt, max_alt -> Altitude envelope in meters
+
+max_horiz_speed, max_vert_speed -> Speed gate against the previous accepted fix, m/s
+
+position_tolerance -> Distance in meters always allowed between two fixes, covers the noise of fixes close in time
+
+max_gate_gap -> Seconds after which the previous fix is too old to gate against
+
+max_future, max_age -> Seconds a fix may be ahead of or behind the local clock
+
+max_consecutive_jumps -> Speed gate rejections in a row of one source after which its new track is accepted
+*/
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ValidationLimits {
+    pub min_alt: f64,
+    pub max_alt: f64,
+    pub max_horiz_speed: f64,
+    pub max_vert_speed: f64,
+    pub position_tolerance: f64,
+    pub max_gate_gap: u64,
+    pub max_future: u64,
+    pub max_age: u64,
+    pub max_consecutive_jumps: u32,
+}
+
+impl Default for ValidationLimits {
+    fn default() -> Self {
+        Self {
+            min_alt: -500.0,
+            max_alt: 50_000.0,
+            // Jet stream winds stay under 100 m/s, descents at altitude can pass 60 m/s
+            max_horiz_speed: 150.0,
+            max_vert_speed: 100.0,
+            position_tolerance: 500.0,
+            max_gate_gap: 600,
+            max_future: 60,
+            max_age: 12 * 3600,
+            max_consecutive_jumps: 5,
+        }
+    }
+}
+
+/// Why a fix was not used
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum RejectReason {
+    NullIsland,
+    OutOfRange,
+    Altitude(f64),
+    HorizontalJump(f64),
+    VerticalJump(f64),
+    InFuture(u64),
+    TooOld(u64),
+}
+
+impl Display for RejectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RejectReason::NullIsland => write!(f, "position at 0,0"),
+            RejectReason::OutOfRange => write!(f, "coordinates out of range"),
+            RejectReason::Altitude(alt) => write!(f, "altitude {:.0} m outside the envelope", alt),
+            RejectReason::HorizontalJump(speed) => write!(f, "horizontal jump at {:.0} m/s", speed),
+            RejectReason::VerticalJump(speed) => write!(f, "vertical jump at {:.0} m/s", speed),
+            RejectReason::InFuture(secs) => write!(f, "timestamp {} s in the future", secs),
+            RejectReason::TooOld(secs) => write!(f, "timestamp {} s old", secs),
+        }
+    }
+}
+
+/** Struct holding a fix dropped by the validator.
+
+received -> Unix timestamp when the fix was rejected
+
+reason -> Human readable reason
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedFix {
+    pub source: String,
+    pub track_type: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+    pub last_update: u64,
+    pub received: u64,
+    pub reason: String,
+}
+
+/// Checks new fixes against fixed bounds and the previously accepted fix
+pub struct FixValidator {
+    limits: ValidationLimits,
+    // Speed gate rejections in a row, per source type and id
+    consecutive_jumps: HashMap<(TrackingType, String), u32>,
+    rejected: VecDeque<RejectedFix>,
+}
+
+impl FixValidator {
+    pub fn new() -> Self {
+        Self { limits: ValidationLimits::default(), consecutive_jumps: HashMap::new(), rejected: VecDeque::new() }
+    }
+
+    pub fn set_limits(&mut self, limits: ValidationLimits) {
+        self.limits = limits;
+    }
+
+    pub fn limits(&self) -> ValidationLimits {
+        self.limits
+    }
+
+    /// Check a fix of `source` against the bounds and the accepted fix closest to it in time, returning why it must be dropped
+    pub fn validate(&mut self, fix: &PositionTime, previous: Option<&PositionTime>, tracking_type: TrackingType, source: &str, now: u64) -> Result<(), RejectReason> {
+        Self::check_bounds(fix, &self.limits, now)?;
+
+        let Some(previous) = previous else {
+            return Ok(());
+        };
+        let key = (tracking_type, source.to_string());
+        match Self::check_speed(fix, previous, &self.limits) {
+            // Keep rejecting until enough fixes of this source agree the payload really is somewhere else
+            Err(reason) if self.consecutive_jumps.get(&key).copied().unwrap_or(0) < self.limits.max_consecutive_jumps => {
+                *self.consecutive_jumps.entry(key).or_insert(0) += 1;
+                Err(reason)
+            }
+            _ => {
+                self.consecutive_jumps.remove(&key);
+                Ok(())
+            }
+        }
+    }
+
+    /// Forget the jump counts of the previous flight
+    pub fn reset(&mut self) {
+        self.consecutive_jumps.clear();
+    }
+
+    /// Keep a rejected fix for the UI
+    pub fn record(&mut self, fix: &PositionTime, tracking_type: TrackingType, source: &str, reason: &RejectReason, now: u64) -> RejectedFix {
+        let rejected = RejectedFix {
+            source: source.to_string(),
+            track_type: tracking_type.to_string(),
+            lat: fix.lat,
+            lon: fix.lon,
+            alt: fix.alt,
+            last_update: fix.last_update,
+            received: now,
+            reason: reason.to_string(),
+        };
+        self.rejected.push_back(rejected.clone());
+        if self.rejected.len() > MAX_REJECTED {
+            self.rejected.pop_front();
+        }
+        rejected
+    }
+
+    /// Rejected fixes, oldest first
+    pub fn rejected(&self) -> Vec<RejectedFix> {
+        self.rejected.iter().cloned().collect()
+    }
+
+    //------------------------Checks------------------------
+
+    fn check_bounds(fix: &PositionTime, limits: &ValidationLimits, now: u64) -> Result<(), RejectReason> {
+        if !fix.lat.is_finite() || !fix.lon.is_finite() || fix.lat.abs() > 90.0 || fix.lon.abs() > 180.0 {
+            return Err(RejectReason::OutOfRange);
+        }
+        if fix.lat.abs() < NULL_ISLAND_DEG && fix.lon.abs() < NULL_ISLAND_DEG {
+            return Err(RejectReason::NullIsland);
+        }
+        if !fix.alt.is_finite() || fix.alt < limits.min_alt || fix.alt > limits.max_alt {
+            return Err(RejectReason::Altitude(fix.alt));
+        }
+        if fix.last_update > now.saturating_add(limits.max_future) {
+            return Err(RejectReason::InFuture(fix.last_update - now));
+        }
+        if fix.last_update.saturating_add(limits.max_age) < now {
+            return Err(RejectReason::TooOld(now - fix.last_update));
+        }
+        Ok(())
+    }
+
+    fn check_speed(fix: &PositionTime, previous: &PositionTime, limits: &ValidationLimits) -> Result<(), RejectReason> {
+        let dt = fix.last_update.abs_diff(previous.last_update);
+        if dt > limits.max_gate_gap {
+            return Ok(());
+        }
+        // Fixes of the same second still get one second of travel
+        let dt = dt.max(1) as f64;
+
+        let horizontal = ground_distance_m(previous.lat, previous.lon, fix.lat, fix.lon);
+        if horizontal > limits.position_tolerance + limits.max_horiz_speed * dt {
+            return Err(RejectReason::HorizontalJump(horizontal / dt));
+        }
+        let vertical = (fix.alt - previous.alt).abs();
+        if vertical > limits.position_tolerance + limits.max_vert_speed * dt {
+            return Err(RejectReason::VerticalJump(vertical / dt));
+        }
+        Ok(())
+    }
+}
+
+impl Default for FixValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn fix(lat: f64, time: u64) -> PositionTime {
+        PositionTime::new_with_value(lat, -80.0, 1000.0, time, 0.0, 0.0)
+    }
+
+    #[test]
+    fn rejects_out_of_bounds_fixes() {
+        let mut validator = FixValidator::new();
+        let mut check = |f: PositionTime| validator.validate(&f, None, TrackingType::APRS, "N0CALL", NOW);
+        assert_eq!(check(PositionTime::new_with_value(0.0, 0.0, 1000.0, NOW, 0.0, 0.0)), Err(RejectReason::NullIsland));
+        assert_eq!(check(fix(91.0, NOW)), Err(RejectReason::OutOfRange));
+        assert_eq!(check(PositionTime::new_with_value(40.0, -80.0, 60_000.0, NOW, 0.0, 0.0)), Err(RejectReason::Altitude(60_000.0)));
+        assert_eq!(check(fix(40.0, NOW + 120)), Err(RejectReason::InFuture(120)));
+        assert_eq!(check(fix(40.0, NOW - 13 * 3600)), Err(RejectReason::TooOld(13 * 3600)));
+        assert_eq!(check(fix(40.0, NOW)), Ok(()));
+    }
+
+    #[test]
+    fn huge_limits_do_not_overflow() {
+        let mut validator = FixValidator::new();
+        validator.set_limits(ValidationLimits { max_future: u64::MAX, max_age: u64::MAX, ..ValidationLimits::default() });
+        assert_eq!(validator.validate(&fix(40.0, u64::MAX), None, TrackingType::APRS, "N0CALL", NOW), Ok(()));
+        assert_eq!(validator.validate(&fix(40.0, 1), None, TrackingType::APRS, "N0CALL", NOW), Ok(()));
+    }
+
+    #[test]
+    fn jumps_are_counted_per_source() {
+        let mut validator = FixValidator::new();
+        validator.set_limits(ValidationLimits { max_consecutive_jumps: 2, ..ValidationLimits::default() });
+        let previous = fix(40.0, NOW - 10);
+        let jump = fix(41.0, NOW);
+
+        // Jumps of another source do not count towards this one
+        assert!(validator.validate(&jump, Some(&previous), TrackingType::APRS, "N0CALL", NOW).is_err());
+        assert!(validator.validate(&jump, Some(&previous), TrackingType::SondeHub, "N0CALL", NOW).is_err());
+        assert!(validator.validate(&jump, Some(&previous), TrackingType::APRS, "N0CALL", NOW).is_err());
+        assert!(validator.validate(&jump, Some(&previous), TrackingType::SondeHub, "N0CALL", NOW).is_err());
+
+        // The third jump in a row of a source is its new track
+        assert_eq!(validator.validate(&jump, Some(&previous), TrackingType::APRS, "N0CALL", NOW), Ok(()));
+        assert!(validator.validate(&jump, Some(&previous), TrackingType::APRS, "N0CALL", NOW).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use std::{collections::VecDeque, fmt::Display};
+
+use serde::{Deserialize, Serialize};
+
+use crate::track_lib::position_time::PositionTime;
+
+//Fixes used for the vertical rate, seconds behind the newest one
+const RATE_WINDOW_SECS: u64 = 60;
+
+//Fixes always kept for the vertical rate, for sources reporting less than once a minute
+const MIN_RATE_FIXES: usize = 3;
+
+/// Phase of the flight
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FlightPhase {
+    PreLaunch,
+    Ascent,
+    Float,
+    Descent,
+    Landed,
+}
+
+impl Display for FlightPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FlightPhase::PreLaunch => "Pre-launch",
+            FlightPhase::Ascent => "Ascent",
+            FlightPhase::Float => "Float",
+            FlightPhase::Descent => "Descent",
+            FlightPhase::Landed => "Landed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/** Struct holding the thresholds of the phase detection, each must hold for its duration before the phase changes.
+
+launch_rate, launch_gain -> Climb rate (m/s) and height above the lowest pre-launch fix (m) that mean the balloon left
+
+float_rate, float_secs -> Vertical rate below which a flight above `float_min_alt` is floating
+
+burst_rate, burst_drop -> Sink rate (m/s) and drop below the highest altitude (m) that mean the balloon burst
+
+landed_rate, landed_secs -> Vertical rate below which a descending payload has landed
+
+hold_secs -> How long the launch and burst conditions must hold
+*/
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PhaseThresholds {
+    pub launch_rate: f64,
+    pub launch_gain: f64,
+    pub float_rate: f64,
+    pub float_secs: u64,
+    pub float_min_alt: f64,
+    pub burst_rate: f64,
+    pub burst_drop: f64,
+    pub landed_rate: f64,
+    pub landed_secs: u64,
+    pub hold_secs: u64,
+}
+
+impl Default for PhaseThresholds {
+    fn default() -> Self {
+        Self {
+            launch_rate: 1.5,
+            launch_gain: 100.0,
+            float_rate: 0.5,
+            float_secs: 300,
+            float_min_alt: 5000.0,
+            burst_rate: 3.0,
+            burst_drop: 200.0,
+            landed_rate: 0.5,
+            landed_secs: 120,
+            hold_secs: 30,
+        }
+    }
+}
+
+/// Change of phase and when it happened (unix timestamp of the fix)
+#[derive(Debug, Clone, Serialize)]
+pub struct PhaseTransition {
+    pub from: FlightPhase,
+    pub to: FlightPhase,
+    pub time: u64,
+}
+
+/** Struct holding the detected state of the flight for the frontend.
+
+since -> Unix timestamp the current phase started
+
+vert_rate -> Vertical rate over the last minute of fixes (at least the last three), m/s
+
+burst -> Highest fix before the descent, the estimated burst point. Set once the burst is detected, a float is not a burst
+
+burst_time -> Unix timestamp of the burst point, None until the burst is detected
+
+launch_alt, max_alt -> Meters
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct FlightStatus {
+    pub phase: FlightPhase,
+    pub since: u64,
+    pub vert_rate: Option<f64>,
+    pub launch_alt: Option<f64>,
+    pub max_alt: Option<f64>,
+    pub burst: Option<PositionTime>,
+    pub burst_time: Option<u64>,
+    pub transitions: Vec<PhaseTransition>,
+}
+
+/// Flight phase state machine fed with the accepted fixes in time order
+pub struct FlightPhaseDetector {
+    thresholds: PhaseThresholds,
+    phase: FlightPhase,
+    since: u64,
+    window: VecDeque<PositionTime>,
+    launch_alt: Option<f64>,
+    highest: Option<PositionTime>,
+    // Highest fix when the balloon burst, kept through the descent and landing
+    burst: Option<PositionTime>,
+    // Candidate next phase and the time its condition started holding
+    pending: Option<(FlightPhase, u64)>,
+    transitions: Vec<PhaseTransition>,
+}
+
+impl FlightPhaseDetector {
+    pub fn new() -> Self {
+        Self {
+            thresholds: PhaseThresholds::default(),
+            phase: FlightPhase::PreLaunch,
+            since: 0,
+            window: VecDeque::new(),
+            launch_alt: None,
+            highest: None,
+            burst: None,
+            pending: None,
+            transitions: vec![],
+        }
+    }
+
+    pub fn set_thresholds(&mut self, thresholds: PhaseThresholds) {
+        self.thresholds = thresholds;
+    }
+
+    pub fn thresholds(&self) -> PhaseThresholds {
+        self.thresholds
+    }
+
+    pub fn phase(&self) -> FlightPhase {
+        self.phase
+    }
+
+    /// Start over in pre-launch, eg. for a new flight
+    pub fn reset(&mut self) {
+        let thresholds = self.thresholds;
+        *self = Self::new();
+        self.thresholds = thresholds;
+    }
+
+    /// Feed the next fix, fixes older than the newest one seen are ignored. Returns the transition it caused
+    pub fn update(&mut self, fix: &PositionTime) -> Option<PhaseTransition> {
+        if self.window.back().is_some_and(|last| fix.last_update <= last.last_update) {
+            return None;
+        }
+        let time = fix.last_update;
+        if self.since == 0 {
+            self.since = time;
+        }
+        self.window.push_back(fix.clone());
+        while self.window.len() > MIN_RATE_FIXES && self.window.front().is_some_and(|f| f.last_update + RATE_WINDOW_SECS < time) {
+            self.window.pop_front();
+        }
+        if self.phase == FlightPhase::PreLaunch {
+            // The lowest fix before launch is the ground
+            self.launch_alt = Some(self.launch_alt.map_or(fix.alt, |alt| alt.min(fix.alt)));
+        }
+        if matches!(self.phase, FlightPhase::PreLaunch | FlightPhase::Ascent | FlightPhase::Float)
+            && self.highest.as_ref().is_none_or(|h| fix.alt > h.alt)
+        {
+            self.highest = Some(fix.clone());
+        }
+
+        let candidate = self.rate().and_then(|rate| self.next_phase(fix, rate));
+        let (candidate, started) = match (candidate, self.pending) {
+            (None, _) => {
+                self.pending = None;
+                return None;
+            }
+            (Some(next), Some((pending, started))) if pending == next => (next, started),
+            (Some(next), _) => {
+                self.pending = Some((next, time));
+                (next, time)
+            }
+        };
+
+        let hold = match candidate {
+            FlightPhase::Float => self.thresholds.float_secs,
+            FlightPhase::Landed => self.thresholds.landed_secs,
+            _ => self.thresholds.hold_secs,
+        };
+        if time.saturating_sub(started) < hold {
+            return None;
+        }
+
+        // The phase starts when its condition started holding
+        let transition = PhaseTransition { from: self.phase, to: candidate, time: started };
+        // Only the burst leads from the ascent or a float to the descent
+        if candidate == FlightPhase::Descent && self.burst.is_none() {
+            self.burst = self.highest.clone();
+        }
+        self.phase = candidate;
+        self.since = started;
+        self.pending = None;
+        self.transitions.push(transition.clone());
+        Some(transition)
+    }
+
+    pub fn status(&self) -> FlightStatus {
+        FlightStatus {
+            phase: self.phase,
+            since: self.since,
+            vert_rate: self.rate(),
+            launch_alt: self.launch_alt,
+            max_alt: self.highest.as_ref().map(|h| h.alt),
+            burst: self.burst.clone(),
+            burst_time: self.burst.as_ref().map(|b| b.last_update),
+            transitions: self.transitions.clone(),
+        }
+    }
+
+    //------------------------Helper Functions------------------------
+
+    /// Phase the fix points to, None if it is consistent with the current phase
+    fn next_phase(&self, fix: &PositionTime, rate: f64) -> Option<FlightPhase> {
+        let t = &self.thresholds;
+        let max_alt = self.highest.as_ref().map_or(fix.alt, |h| h.alt);
+        // A drop alone could be a high outlier in the maximum, the payload must also be sinking
+        let burst = rate <= -t.burst_rate || (rate < 0.0 && max_alt - fix.alt >= t.burst_drop);
+        match self.phase {
+            FlightPhase::PreLaunch => {
+                let gain = fix.alt - self.launch_alt.unwrap_or(fix.alt);
+                (rate >= t.launch_rate && gain >= t.launch_gain).then_some(FlightPhase::Ascent)
+            }
+            FlightPhase::Ascent if burst => Some(FlightPhase::Descent),
+            FlightPhase::Ascent => {
+                (rate.abs() < t.float_rate && fix.alt >= t.float_min_alt).then_some(FlightPhase::Float)
+            }
+            FlightPhase::Float if burst => Some(FlightPhase::Descent),
+            FlightPhase::Float => (rate >= t.launch_rate).then_some(FlightPhase::Ascent),
+            FlightPhase::Descent => (rate.abs() < t.landed_rate).then_some(FlightPhase::Landed),
+            FlightPhase::Landed => None,
+        }
+    }
+
+    /// Least squares vertical rate over the window, None with fewer than two fixes
+    fn rate(&self) -> Option<f64> {
+        let n = self.window.len() as f64;
+        let first = self.window.front()?.last_update;
+        let (mut st, mut sa, mut stt, mut sta) = (0.0, 0.0, 0.0, 0.0);
+        for fix in &self.window {
+            let t = (fix.last_update - first) as f64;
+            st += t;
+            sa += fix.alt;
+            stt += t * t;
+            sta += t * fix.alt;
+        }
+        let denominator = n * stt - st * st;
+        (denominator > 0.0).then(|| (n * sta - st * sa) / denominator)
+    }
+}
+
+impl Default for FlightPhaseDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Feed one fix every 10 s following the given (seconds, vertical rate) legs, from the last fix of the detector
+    fn fly(detector: &mut FlightPhaseDetector, legs: &[(u64, f64)]) -> Vec<PhaseTransition> {
+        let mut transitions = vec![];
+        let last = detector.window.back();
+        let mut time = last.map_or(1_700_000_000, |f| f.last_update);
+        let mut alt = last.map_or(300.0, |f| f.alt);
+        for &(secs, rate) in legs {
+            for _ in 0..secs / 10 {
+                time += 10;
+                alt += rate * 10.0;
+                transitions.extend(detector.update(&PositionTime::new_with_value(40.0, -80.0, alt, time, 0.0, rate)));
+            }
+        }
+        transitions
+    }
+
+    #[test]
+    fn detects_launch_burst_and_landing() {
+        let mut detector = FlightPhaseDetector::new();
+        let transitions = fly(&mut detector, &[(120, 0.0), (6000, 5.0), (2000, -10.0), (300, 0.0)]);
+        let phases: Vec<FlightPhase> = transitions.iter().map(|t| t.to).collect();
+        assert_eq!(phases, [FlightPhase::Ascent, FlightPhase::Descent, FlightPhase::Landed]);
+
+        let status = detector.status();
+        let burst = status.burst.unwrap();
+        assert_eq!(status.burst_time, Some(burst.last_update));
+        assert!((burst.alt - 30_300.0).abs() < 1.0);
+        assert!(burst.last_update < transitions[1].time + 10);
+    }
+
+    #[test]
+    fn float_is_not_a_burst() {
+        let mut detector = FlightPhaseDetector::new();
+        fly(&mut detector, &[(120, 0.0), (3000, 5.0), (1200, 0.0)]);
+        let status = detector.status();
+        assert_eq!(status.phase, FlightPhase::Float);
+        assert!(status.burst.is_none() && status.burst_time.is_none());
+
+        // Cut down from the float
+        fly(&mut detector, &[(600, -15.0)]);
+        let status = detector.status();
+        assert_eq!(status.phase, FlightPhase::Descent);
+        assert!(status.burst_time.is_some());
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::track_lib::look_angle::wrap_longitude;
+use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::tracking_type::TrackingType;
+
+/** Struct holding how much each quality factor counts in the fusion.
+
+age_half_life -> Seconds behind the newest fix after which a fix weighs half as much
+
+precision_exponent -> Power of the inverse variance of the declared precision, 0 ignores precision
+
+measured_velocity_factor -> Weight multiplier of fixes whose velocities were reported rather than derived
+
+reliability_exponent -> Power of the historical reliability score, 0 ignores it
+*/
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FusionWeights {
+    pub age_half_life: f64,
+    pub precision_exponent: f64,
+    pub measured_velocity_factor: f64,
+    pub reliability_exponent: f64,
+}
+
+impl Default for FusionWeights {
+    fn default() -> Self {
+        Self { age_half_life: 30.0, precision_exponent: 1.0, measured_velocity_factor: 1.5, reliability_exponent: 2.0 }
+    }
+}
+
+/// Latest fix of one source, ready to be fused
+#[derive(Debug, Clone)]
+pub struct FusionInput {
+    pub source: String,
+    pub tracking_type: TrackingType,
+    pub pos_time: PositionTime,
+    /// Horizontal 1-sigma precision in meters declared with the fix, or the default of its source type
+    pub precision: f64,
+    /// True if the source reported its velocities, false if they were derived from the previous fix
+    pub measured_velocity: bool,
+}
+
+/** Struct holding what one source brought to the fused position.
+
+weight -> Share of the fused position, the weights of all sources add up to 1
+
+age -> Seconds between this fix and the newest fused one
+
+precision -> Declared horizontal 1-sigma in meters
+
+reliability -> Share of the fixes of this source that passed validation, 0 to 1
+*/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceContribution {
+    pub source: String,
+    pub track_type: String,
+    pub weight: f64,
+    pub age: u64,
+    pub precision: f64,
+    pub measured_velocity: bool,
+    pub reliability: f64,
+}
+
+/// Fused position with the contribution of every source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FusedPosition {
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+    pub horiz_vel: f64,
+    pub vert_vel: f64,
+    pub last_update: u64,
+    pub contributions: Vec<SourceContribution>,
+}
+
+impl FusedPosition {
+    pub fn pos_time(&self) -> PositionTime {
+        PositionTime::new_with_value(self.lat, self.lon, self.alt, self.last_update, self.horiz_vel, self.vert_vel)
+    }
+}
+
+/// Validation outcomes of a source
+#[derive(Debug, Clone, Copy, Default)]
+struct Reliability {
+    accepted: u32,
+    rejected: u32,
+}
+
+impl Reliability {
+    /// Accepted share with one accepted and one rejected fix assumed up front, so new sources start at 0.5
+    fn score(&self) -> f64 {
+        (self.accepted as f64 + 1.0) / ((self.accepted + self.rejected) as f64 + 2.0)
+    }
+}
+
+/// Weighted fusion of the latest fix of each source by age, precision, velocity origin and reliability
+pub struct SourceFusion {
+    weights: FusionWeights,
+    // Keyed by type and id, APRS and SondeHub both use the callsign
+    reliability: HashMap<(TrackingType, String), Reliability>,
+}
+
+impl SourceFusion {
+    pub fn new() -> Self {
+        Self { weights: FusionWeights::default(), reliability: HashMap::new() }
+    }
+
+    pub fn set_weights(&mut self, weights: FusionWeights) {
+        self.weights = weights;
+    }
+
+    pub fn weights(&self) -> FusionWeights {
+        self.weights
+    }
+
+    /// Count a validated (`accepted`) or rejected fix of `source` in its reliability
+    pub fn record(&mut self, tracking_type: TrackingType, source: &str, accepted: bool) {
+        let reliability = self.reliability.entry((tracking_type, source.to_string())).or_default();
+        if accepted {
+            reliability.accepted += 1;
+        } else {
+            reliability.rejected += 1;
+        }
+    }
+
+    pub fn reliability(&self, tracking_type: TrackingType, source: &str) -> f64 {
+        self.reliability.get(&(tracking_type, source.to_string())).copied().unwrap_or_default().score()
+    }
+
+    /// Fuse the inputs, None when there are none
+    pub fn fuse(&self, inputs: &[FusionInput]) -> Option<FusedPosition> {
+        let newest = inputs.iter().map(|i| i.pos_time.last_update).max()?;
+
+        let mut contributions = vec![];
+        let mut raw_weights = vec![];
+        for input in inputs {
+            let age = newest - input.pos_time.last_update;
+            let reliability = self.reliability(input.tracking_type, &input.source);
+            let weight = self.weight(input, age, reliability);
+            raw_weights.push(weight);
+            contributions.push(SourceContribution {
+                source: input.source.clone(),
+                track_type: input.tracking_type.to_string(),
+                weight,
+                age,
+                precision: input.precision,
+                measured_velocity: input.measured_velocity,
+                reliability,
+            });
+        }
+
+        // Fall back to equal weights if the factors underflowed
+        let total: f64 = raw_weights.iter().sum();
+        if !(total > 0.0 && total.is_finite()) {
+            raw_weights.iter_mut().for_each(|w| *w = 1.0);
+        }
+        let total: f64 = raw_weights.iter().sum();
+        for (contribution, weight) in contributions.iter_mut().zip(&raw_weights) {
+            contribution.weight = weight / total;
+        }
+
+        let weighted = |value: &dyn Fn(&FusionInput) -> f64| -> f64 {
+            inputs.iter().zip(&contributions).map(|(i, c)| c.weight * value(i)).sum()
+        };
+        // Older fixes are moved up to the newest time along their vertical rate
+        let alt = weighted(&|i| i.pos_time.alt + i.pos_time.vert_vel * (newest - i.pos_time.last_update) as f64);
+        // Longitudes are averaged as offsets from the first input, so fixes on both sides of the antimeridian stay together
+        let lon0 = inputs[0].pos_time.lon;
+        let lon = lon0 + weighted(&|i| wrap_longitude(i.pos_time.lon - lon0));
+
+        Some(FusedPosition {
+            lat: weighted(&|i| i.pos_time.lat),
+            lon: wrap_longitude(lon),
+            alt,
+            horiz_vel: Self::fuse_velocity(inputs, &contributions, |p| p.horiz_vel),
+            vert_vel: Self::fuse_velocity(inputs, &contributions, |p| p.vert_vel),
+            last_update: newest,
+            contributions,
+        })
+    }
+
+    //------------------------Helper Functions------------------------
+
+    fn weight(&self, input: &FusionInput, age: u64, reliability: f64) -> f64 {
+        let w = &self.weights;
+        let age_factor = if w.age_half_life > 0.0 { 0.5f64.powf(age as f64 / w.age_half_life) } else { 1.0 };
+        let variance = input.precision.max(1.0).powi(2);
+        let precision_factor = variance.powf(-w.precision_exponent);
+        let velocity_factor = if input.measured_velocity { w.measured_velocity_factor } else { 1.0 };
+        age_factor * precision_factor * velocity_factor * reliability.powf(w.reliability_exponent)
+    }
+
+    /// Weighted velocity over the sources that have one, preferring measured velocities over derived ones
+    fn fuse_velocity(inputs: &[FusionInput], contributions: &[SourceContribution], get: fn(&PositionTime) -> f64) -> f64 {
+        let has_value = |i: &FusionInput| get(&i.pos_time) != 0.0;
+        let measured = inputs.iter().any(|i| i.measured_velocity && has_value(i));
+
+        let (sum, total) = inputs.iter().zip(contributions)
+            .filter(|(i, _)| has_value(i) && (i.measured_velocity || !measured))
+            .fold((0.0, 0.0), |(sum, total), (i, c)| (sum + c.weight * get(&i.pos_time), total + c.weight));
+        if total > 0.0 { sum / total } else { 0.0 }
+    }
+}
+
+impl Default for SourceFusion {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(tracking_type: TrackingType, source: &str, lat: f64, lon: f64, precision: f64) -> FusionInput {
+        FusionInput {
+            source: source.to_string(),
+            tracking_type,
+            pos_time: PositionTime::new_with_value(lat, lon, 1000.0, 1_700_000_000, 0.0, 0.0),
+            precision,
+            measured_velocity: false,
+        }
+    }
+
+    #[test]
+    fn fuses_across_the_antimeridian() {
+        let fusion = SourceFusion::new();
+        let fused = fusion.fuse(&[
+            input(TrackingType::Horus, "HORUS", 10.0, 179.9, 10.0),
+            input(TrackingType::RFD, "/dev/ttyUSB0", 10.0, -179.9, 10.0),
+        ]).unwrap();
+        assert!((fused.lon.abs() - 180.0).abs() < 1e-9, "{}", fused.lon);
+
+        let fused = fusion.fuse(&[
+            input(TrackingType::Horus, "HORUS", 10.0, 179.8, 10.0),
+            input(TrackingType::RFD, "/dev/ttyUSB0", 10.0, -179.9, 10.0),
+        ]).unwrap();
+        assert!((fused.lon - 179.95).abs() < 1e-9, "{}", fused.lon);
+    }
+
+    #[test]
+    fn declared_precision_sets_the_weight() {
+        let fused = SourceFusion::new().fuse(&[
+            input(TrackingType::APRS, "N0CALL", 40.0, -80.0, 10.0),
+            input(TrackingType::APRS, "N1CALL", 41.0, -80.0, 1000.0),
+        ]).unwrap();
+        assert!(fused.contributions[0].weight > 0.99);
+        assert!((fused.lat - 40.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn reliability_is_kept_per_type_and_id() {
+        let mut fusion = SourceFusion::new();
+        fusion.record(TrackingType::APRS, "N0CALL", false);
+        fusion.record(TrackingType::SondeHub, "N0CALL", true);
+        assert!((fusion.reliability(TrackingType::APRS, "N0CALL") - 1.0 / 3.0).abs() < 1e-9);
+        assert!((fusion.reliability(TrackingType::SondeHub, "N0CALL") - 2.0 / 3.0).abs() < 1e-9);
+    }
+}
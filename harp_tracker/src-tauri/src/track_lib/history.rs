@@ -0,0 +1,171 @@
+use std::collections::VecDeque;
+
+use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::tracking_type::TrackingType;
+
+//Most fixes kept, about 3 hours of flight with a few sources
+const MAX_FIXES: usize = 5000;
+
+//Fixes older than this (s) relative to the newest one are dropped
+const MAX_AGE_SECS: u64 = 6 * 3600;
+
+/// One fix and the type of the source that reported it
+#[derive(Debug, Clone)]
+pub struct HistoricalFix {
+    pub pos_time: PositionTime,
+    pub tracking_type: TrackingType,
+    pub source: String,
+}
+
+/// Rolling buffer of the fixes of every source, ordered by time
+pub struct FixHistory {
+    fixes: VecDeque<HistoricalFix>,
+}
+
+impl FixHistory {
+    pub fn new() -> Self {
+        Self { fixes: VecDeque::new() }
+    }
+
+    /// Insert a fix at its place in time. Returns false if it was already recorded or too old to keep
+    pub fn push(&mut self, pos_time: PositionTime, tracking_type: TrackingType, source: &str) -> bool {
+        if pos_time.last_update == 0 {
+            return false;
+        }
+        let newest = self.fixes.back().map_or(0, |f| f.pos_time.last_update);
+        if pos_time.last_update + MAX_AGE_SECS < newest {
+            return false;
+        }
+
+        let index = self.fixes.partition_point(|f| f.pos_time.last_update <= pos_time.last_update);
+        let duplicate = self.fixes.range(..index).rev()
+            .take_while(|f| f.pos_time.last_update == pos_time.last_update)
+            .any(|f| (f.tracking_type == tracking_type && f.source == source) || (f.pos_time.lat == pos_time.lat && f.pos_time.lon == pos_time.lon && f.pos_time.alt == pos_time.alt));
+        if duplicate {
+            return false;
+        }
+        self.fixes.insert(index, HistoricalFix { pos_time, tracking_type, source: source.to_string() });
+
+        let newest = self.fixes.back().map_or(0, |f| f.pos_time.last_update);
+        while self.fixes.len() > MAX_FIXES
+            || self.fixes.front().is_some_and(|f| f.pos_time.last_update + MAX_AGE_SECS < newest)
+        {
+            self.fixes.pop_front();
+        }
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.fixes.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.fixes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fixes.is_empty()
+    }
+
+    /// Every fix, oldest first
+    pub fn fixes(&self) -> impl DoubleEndedIterator<Item = &HistoricalFix> {
+        self.fixes.iter()
+    }
+
+    /// Fix closest in time to `time`
+    pub fn nearest(&self, time: u64) -> Option<&HistoricalFix> {
+        let index = self.fixes.partition_point(|f| f.pos_time.last_update < time);
+        let after = self.fixes.get(index);
+        let before = index.checked_sub(1).and_then(|i| self.fixes.get(i));
+        match (before, after) {
+            (Some(b), Some(a)) if a.pos_time.last_update - time < time - b.pos_time.last_update => Some(a),
+            (Some(b), _) => Some(b),
+            (None, a) => a,
+        }
+    }
+
+    /// Positions of every fix, oldest first
+    pub fn positions(&self) -> Vec<PositionTime> {
+        self.fixes.iter().map(|f| f.pos_time.clone()).collect()
+    }
+}
+
+impl Default for FixHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(lat: f64, time: u64) -> PositionTime {
+        PositionTime::new_with_value(lat, -80.0, 1000.0, time, 0.0, 0.0)
+    }
+
+    #[test]
+    fn keeps_same_id_fixes_from_different_types() {
+        let mut history = FixHistory::new();
+        assert!(history.push(fix(40.0, 100), TrackingType::APRS, "KD9ABC-11"));
+        assert!(history.push(fix(40.1, 100), TrackingType::AprsIs, "KD9ABC-11"));
+        assert!(!history.push(fix(40.2, 100), TrackingType::APRS, "KD9ABC-11"));
+        assert_eq!(history.len(), 2);
+    }
+
+    fn times(history: &FixHistory) -> Vec<u64> {
+        history.fixes().map(|f| f.pos_time.last_update).collect()
+    }
+
+    #[test]
+    fn orders_out_of_order_fixes_by_time() {
+        let mut history = FixHistory::new();
+        assert!(history.push(fix(40.3, 300), TrackingType::APRS, "A"));
+        assert!(history.push(fix(40.1, 100), TrackingType::APRS, "A"));
+        assert!(history.push(fix(40.2, 200), TrackingType::SondeHub, "B"));
+        assert!(history.push(fix(40.25, 250), TrackingType::APRS, "A"));
+        assert_eq!(times(&history), [100, 200, 250, 300]);
+        // Same position reported by another source at the same time
+        assert!(!history.push(fix(40.2, 200), TrackingType::APRS, "A"));
+        assert!(!history.push(fix(40.0, 0), TrackingType::APRS, "A"));
+    }
+
+    #[test]
+    fn finds_the_nearest_fix() {
+        let mut history = FixHistory::new();
+        assert!(history.nearest(100).is_none());
+        for time in [100, 200, 300] {
+            history.push(fix(40.0 + time as f64 / 1000.0, time), TrackingType::APRS, "A");
+        }
+        let nearest = |time| history.nearest(time).map(|f| f.pos_time.last_update);
+        assert_eq!(nearest(50), Some(100));
+        assert_eq!(nearest(200), Some(200));
+        assert_eq!(nearest(240), Some(200));
+        assert_eq!(nearest(260), Some(300));
+        // Halfway the older fix wins
+        assert_eq!(nearest(250), Some(200));
+        assert_eq!(nearest(1000), Some(300));
+    }
+
+    #[test]
+    fn evicts_beyond_the_maximum_count() {
+        let mut history = FixHistory::new();
+        for time in 1..=MAX_FIXES as u64 + 10 {
+            assert!(history.push(fix(40.0 + time as f64 * 1e-6, time), TrackingType::APRS, "A"));
+        }
+        assert_eq!(history.len(), MAX_FIXES);
+        assert_eq!(history.fixes().next().map(|f| f.pos_time.last_update), Some(11));
+    }
+
+    #[test]
+    fn evicts_and_rejects_fixes_beyond_the_maximum_age() {
+        let mut history = FixHistory::new();
+        history.push(fix(40.0, 1000), TrackingType::APRS, "A");
+        history.push(fix(40.1, 2000), TrackingType::APRS, "A");
+        history.push(fix(40.2, 2000 + MAX_AGE_SECS), TrackingType::APRS, "A");
+        assert_eq!(times(&history), [2000, 2000 + MAX_AGE_SECS]);
+        // A late fix older than the newest one by more than the maximum age is not kept
+        assert!(!history.push(fix(40.3, 1500), TrackingType::APRS, "A"));
+        assert_eq!(history.len(), 2);
+    }
+}
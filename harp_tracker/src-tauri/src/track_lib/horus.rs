@@ -0,0 +1,328 @@
+use std::{
+    error::Error,
+    io::{self, ErrorKind},
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{NaiveTime, Utc};
+use serde_json::Value;
+use socket2::{Domain, Protocol, Socket, Type};
+
+use crate::track_lib::aprs_parser::TelemetryChannel;
+use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::stream_link::MAX_READ_PER_POLL;
+use crate::track_lib::tracking_source::{SourceHealth, TrackingSource};
+use crate::track_lib::tracking_type::TrackingType;
+
+//UDP port horusdemodlib broadcasts its payload summaries on
+pub const DEFAULT_PORT: u16 = 55672;
+
+//Read timeout of the UDP socket (ms)
+const READ_TIMEOUT_MS: u64 = 200;
+
+//Summaries are pushed by the decoder, so drain the socket often
+const POLL_INTERVAL_SECS: u64 = 1;
+
+//Fields of a payload summary that are not sensor readings
+const SUMMARY_FIELDS: [&str; 12] = [
+    "type", "station", "callsign", "latitude", "longitude", "altitude",
+    "speed", "heading", "time", "comment", "model", "custom_field_names",
+];
+
+/// Horus Binary / UKHAS payloads decoded by horusdemodlib and broadcast as JSON over UDP
+pub struct Horus {
+    tracking_type: TrackingType,
+    port: u16,
+    call_sign: String,
+    socket: Option<UdpSocket>,
+    position_time: PositionTime,
+    comment: String,
+    telemetry: Vec<TelemetryChannel>,
+    health: SourceHealth,
+}
+
+impl Horus {
+    /// Only the summaries of `call_sign` are used, the decoder reports every payload it hears
+    pub fn new(port: u16, call_sign: &str) -> Self {
+        Self {
+            tracking_type: TrackingType::Horus,
+            port,
+            call_sign: call_sign.to_uppercase(),
+            socket: None,
+            position_time: PositionTime::new(),
+            comment: String::new(),
+            telemetry: vec![],
+            health: SourceHealth::new(),
+        }
+    }
+
+    fn fetch_position(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.socket.is_none() {
+            let socket = bind_shared(self.port)?;
+            socket.set_read_timeout(Some(Duration::from_millis(READ_TIMEOUT_MS)))?;
+            println!("Horus listening on UDP port {}", self.port);
+            self.socket = Some(socket);
+        }
+
+        // Steady traffic on the shared port must not hold the poller, the rest waits for the next poll
+        let mut buffer = [0u8; 8192];
+        let mut total = 0;
+        while total < MAX_READ_PER_POLL {
+            let socket = self.socket.as_ref().ok_or("Horus socket not open")?;
+            match socket.recv_from(&mut buffer) {
+                Ok((n, _)) => {
+                    total += n;
+                    if let Err(e) = self.handle_datagram(&buffer[..n]) {
+                        eprintln!("Horus: ignoring datagram: {}", e);
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::TimedOut || e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    self.socket = None;
+                    return Err(e.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_datagram(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let summary: Value = serde_json::from_slice(data)?;
+        if summary["type"].as_str() != Some("PAYLOAD_SUMMARY") {
+            return Ok(());
+        }
+
+        let call_sign = summary["callsign"].as_str().ok_or("Payload summary without callsign")?;
+        if !call_sign.eq_ignore_ascii_case(&self.call_sign) {
+            return Ok(());
+        }
+
+        let lat = summary["latitude"].as_f64().ok_or("Payload summary without latitude")?;
+        let lon = summary["longitude"].as_f64().ok_or("Payload summary without longitude")?;
+        let alt = summary["altitude"].as_f64().unwrap_or(0.0);
+        // No fix yet on the payload GPS
+        if lat == 0.0 && lon == 0.0 {
+            return Ok(());
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let time = summary["time"].as_str()
+            .and_then(|t| timestamp_from_utc_time(t, now))
+            .unwrap_or(now);
+
+        // Unknown values are sent as -1
+        let horiz_vel = summary["speed"].as_f64().filter(|&s| s >= 0.0).map(|kmh| kmh / 3.6).unwrap_or(0.0);
+        let previous = &self.position_time;
+        let dt = time.saturating_sub(previous.last_update) as f64;
+        let vert_vel = if previous.last_update != 0 && dt > 0.0 { (alt - previous.alt) / dt } else { 0.0 };
+
+        self.position_time.update(lat, lon, alt, time, horiz_vel, vert_vel);
+        self.position_time.heading = summary["heading"].as_f64().filter(|&h| h >= 0.0);
+        self.comment = summary["comment"].as_str().unwrap_or("").to_string();
+        self.telemetry = summary_channels(&summary);
+
+        println!(
+            "Horus Position: Call: {}, Lat: {}, Lon: {}, Alt: {}m, Channels: {}",
+            call_sign, lat, lon, alt, self.telemetry.len()
+        );
+        Ok(())
+    }
+}
+
+/// Bind the summary port so other listeners (chasemapper, a second tracker) can bind it too.
+/// horusdemodlib broadcasts its summaries, so each socket gets its own copy
+fn bind_shared(port: u16) -> io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)).into())?;
+    Ok(socket.into())
+}
+
+/// Sensor readings of a payload summary: sats, battery, temperature, SNR and the custom fields
+fn summary_channels(summary: &Value) -> Vec<TelemetryChannel> {
+    let standard = [
+        ("sats", "Satellites", ""),
+        ("batt_voltage", "Battery", "V"),
+        ("temp", "Temperature", "°C"),
+        ("snr", "SNR", "dB"),
+        ("f_centre", "Frequency", "Hz"),
+    ];
+    let mut channels: Vec<TelemetryChannel> = standard.iter()
+        .filter_map(|&(key, name, unit)| {
+            let value = summary[key].as_f64().filter(|&v| v != -1.0)?;
+            Some(TelemetryChannel { name: name.to_string(), unit: unit.to_string(), value })
+        })
+        .collect();
+
+    // Custom fields are listed by name, but older decoders only add them as extra keys
+    let custom: Vec<String> = match summary["custom_field_names"].as_array() {
+        Some(names) => names.iter().filter_map(|n| n.as_str().map(str::to_string)).collect(),
+        None => summary.as_object()
+            .map(|fields| fields.keys()
+                .filter(|k| !SUMMARY_FIELDS.contains(&k.as_str()) && !standard.iter().any(|(key, _, _)| key == k))
+                .cloned()
+                .collect())
+            .unwrap_or_default(),
+    };
+    for name in custom {
+        if let Some(value) = summary[name.as_str()].as_f64() {
+            channels.push(TelemetryChannel { name, unit: String::new(), value });
+        }
+    }
+    channels
+}
+
+/// Turn a `HH:MM:SS` UTC time of day into a unix timestamp close to `now`
+pub fn timestamp_from_utc_time(time: &str, now: u64) -> Option<u64> {
+    let time = NaiveTime::parse_from_str(time.trim(), "%H:%M:%S").ok()?;
+    let today = chrono::DateTime::<Utc>::from_timestamp(now as i64, 0)?.date_naive();
+    let timestamp = today.and_time(time).and_utc().timestamp();
+
+    // Fixes close to midnight may belong to the previous or the next day
+    let timestamp = match timestamp - now as i64 {
+        d if d > 12 * 3600 => timestamp - 24 * 3600,
+        d if d < -12 * 3600 => timestamp + 24 * 3600,
+        _ => timestamp,
+    };
+    u64::try_from(timestamp).ok()
+}
+
+impl TrackingSource for Horus {
+    fn id(&self) -> &str {
+        &self.call_sign
+    }
+
+    fn tracking_type(&self) -> TrackingType {
+        self.tracking_type
+    }
+
+    fn update_position(&mut self) -> Result<(), Box<dyn Error>> {
+        let result = self.fetch_position();
+        self.health.record(&result);
+        result
+    }
+
+    fn get_pos_time(&self) -> PositionTime {
+        self.position_time.clone()
+    }
+
+    fn get_last_update(&self) -> u64 {
+        self.position_time.last_update
+    }
+
+    fn health(&self) -> SourceHealth {
+        self.health.evaluate(self.position_time.last_update)
+    }
+
+    fn get_comment(&self) -> &str {
+        &self.comment
+    }
+
+    fn get_telemetry(&self) -> &[TelemetryChannel] {
+        &self.telemetry
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(POLL_INTERVAL_SECS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A port nobody is listening on
+    fn free_port() -> u16 {
+        UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+    }
+
+    /// Payload summary as sent by horusdemodlib
+    fn summary(call_sign: &str, lat: f64, time: &str) -> String {
+        format!(
+            r#"{{"type": "PAYLOAD_SUMMARY", "station": "N0CALL", "callsign": "{}", "latitude": {}, "longitude": -93.2, "altitude": 12000, "speed": 36.0, "heading": 90.0, "time": "{}", "comment": "HORUS", "model": "Horus Binary v2", "sats": 9, "batt_voltage": 3.1, "temp": -21.0, "snr": -1, "custom_field_names": ["ascent_rate"], "ascent_rate": 5.2}}"#,
+            call_sign, lat, time
+        )
+    }
+
+    fn send(port: u16, datagram: &str) {
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(datagram.as_bytes(), ("127.0.0.1", port)).unwrap();
+    }
+
+    #[test]
+    fn receives_summaries_from_a_udp_sender() {
+        let port = free_port();
+        let mut horus = Horus::new(port, "horusbinary");
+        // Opens the socket, nothing to read yet
+        horus.update_position().unwrap();
+        assert_eq!(horus.get_last_update(), 0);
+
+        send(port, &summary("OTHER", 45.0, "12:00:00"));
+        send(port, "not json");
+        send(port, r#"{"type": "MODEM_STATS", "snr": 10.0}"#);
+        send(port, &summary("HORUSBINARY", 44.9, "12:00:05"));
+        std::thread::sleep(Duration::from_millis(50));
+        horus.update_position().unwrap();
+
+        let pos = horus.get_pos_time();
+        assert_eq!(pos.lat, 44.9);
+        assert_eq!(pos.lon, -93.2);
+        assert_eq!(pos.alt, 12000.0);
+        assert!((pos.horiz_vel - 10.0).abs() < 1e-9);
+        assert_eq!(pos.heading, Some(90.0));
+        assert_eq!(pos.last_update % 86400, 12 * 3600 + 5);
+        assert_eq!(horus.id(), "HORUSBINARY");
+        assert_eq!(horus.get_comment(), "HORUS");
+
+        let names: Vec<&str> = horus.get_telemetry().iter().map(|c| c.name.as_str()).collect();
+        // The -1 SNR means unknown
+        assert_eq!(names, ["Satellites", "Battery", "Temperature", "ascent_rate"]);
+    }
+
+    #[test]
+    fn bounds_the_datagrams_read_per_poll() {
+        let port = free_port();
+        let mut horus = Horus::new(port, "HORUSBINARY");
+        horus.update_position().unwrap();
+
+        // Padded summaries, together more than a single poll reads
+        let padding = "x".repeat(4000);
+        let count = MAX_READ_PER_POLL / 4000 + 4;
+        for i in 0..count {
+            let datagram = summary("HORUSBINARY", 40.0 + i as f64 * 0.01, "12:00:05").replacen('{', &format!(r#"{{"pad": "{}", "#, padding), 1);
+            send(port, &datagram);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+
+        horus.update_position().unwrap();
+        let last = 40.0 + (count - 1) as f64 * 0.01;
+        assert!(horus.get_pos_time().lat < last - 1e-9);
+        horus.update_position().unwrap();
+        assert!((horus.get_pos_time().lat - last).abs() < 1e-9);
+    }
+
+    #[test]
+    fn port_is_shared_with_other_listeners() {
+        let port = free_port();
+        let mut horus = Horus::new(port, "HORUSBINARY");
+        horus.update_position().unwrap();
+        // chasemapper or a second tracker listening for the same broadcasts
+        let other = bind_shared(port).unwrap();
+        assert_eq!(other.local_addr().unwrap().port(), port);
+    }
+
+    #[test]
+    fn summary_time_is_placed_on_the_nearest_day() {
+        // 2024-01-01 00:10:00 UTC
+        let now = 1704067800;
+        assert_eq!(timestamp_from_utc_time("00:09:00", now), Some(now - 60));
+        assert_eq!(timestamp_from_utc_time("23:59:00", now), Some(now - 11 * 60));
+        assert_eq!(timestamp_from_utc_time("bad", now), None);
+    }
+}
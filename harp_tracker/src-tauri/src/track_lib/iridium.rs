@@ -1,15 +1,18 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use reqwest::blocking::Client;
 use serde_json::Value;
 
 use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::tracking_source::{SourceHealth, TrackingSource};
 use crate::track_lib::tracking_type::TrackingType;
 
+//Seconds between two Borealis requests, Iridium packets are polled more often than APRS
+const POLL_INTERVAL_SECS: u64 = 10;
+
 
 #[derive(Clone)]
 pub struct Iridium {
-    active: bool,
     tracking_type: TrackingType,
     base_url: String,
     modem: String,
@@ -17,24 +20,24 @@ pub struct Iridium {
     position_time: PositionTime,
     vertical_velocity: f64,
     ground_speed: f64,
+    health: SourceHealth,
 }
 
 impl Iridium {
     pub fn new(base_url: &str, modem: &str) -> Self {
         Self {
-            active: true,
             tracking_type: TrackingType::Iridium,
             base_url: base_url.to_string(),
             modem: modem.to_string(),
             client: Client::new(),
-            position_time: PositionTime {lat:0.0, lon:0.0, alt:0.0, last_update:0, horiz_vel:0.0, vert_vel:0.0},
+            position_time: PositionTime {lat:0.0, lon:0.0, alt:0.0, last_update:0, horiz_vel:0.0, vert_vel:0.0, heading:None},
             vertical_velocity: 0.0,
             ground_speed: 0.0,
-
+            health: SourceHealth::new(),
         }
     }
 
-    pub fn update_position(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn fetch_position(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         let url = format!(
             "{}/api/meta/flights?modem_name={}",
             self.base_url, self.modem
@@ -91,10 +94,6 @@ impl Iridium {
         Ok(())
     }
 
-    pub fn get_pos_time(&self) -> PositionTime{
-        self.position_time.clone()
-    }
-    
     pub fn get_position(&self) -> (f64, f64, f64) {
         (self.position_time.lat, self.position_time.lon, self.position_time.alt)
     }
@@ -102,9 +101,35 @@ impl Iridium {
     pub fn get_speed(&self) -> f64 {
         self.ground_speed
     }
-    
-    pub fn get_last_update(&self) -> u64 {
+}
+
+impl TrackingSource for Iridium {
+    fn id(&self) -> &str {
+        &self.modem
+    }
+
+    fn tracking_type(&self) -> TrackingType {
+        self.tracking_type
+    }
+
+    fn update_position(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let result = self.fetch_position();
+        self.health.record(&result);
+        result
+    }
+
+    fn get_pos_time(&self) -> PositionTime {
+        self.position_time.clone()
+    }
+
+    fn get_last_update(&self) -> u64 {
         self.position_time.last_update
     }
 
+    fn health(&self) -> SourceHealth {
+        self.health.evaluate(self.position_time.last_update)
+    }
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(POLL_INTERVAL_SECS)
+    }
 }
@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::track_lib::history::FixHistory;
+use crate::track_lib::look_angle::{local_offset_m, offset_position};
+use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::tracking_type::TrackingType;
+use crate::track_lib::velocity::course_of;
+
+//Process noise: horizontal acceleration (m/s^2) and vertical jerk (m/s^3) spectral densities
+const HORIZ_ACCEL_NOISE: f64 = 0.05;
+const VERT_JERK_NOISE: f64 = 0.0005;
+
+//Noise of the vertical rate reported by some sources (m/s)
+const VERT_VEL_SIGMA: f64 = 2.0;
+
+//Noise used when the source of the fixes is unknown (m)
+const UNKNOWN_SOURCE_NOISE: (f64, f64) = (25.0, 30.0);
+
+//Initial uncertainty of the unmeasured states
+const INITIAL_VEL_SIGMA: f64 = 20.0;
+const INITIAL_ACC_SIGMA: f64 = 2.0;
+
+//Distance from the projection origin (m) past which the filter moves its origin to the estimate, the projection drifts over longer ones
+const RECENTER_DISTANCE_M: f64 = 50_000.0;
+
+/// Default measurement noise of each source as (horizontal, vertical) 1-sigma in meters
+pub fn default_measurement_noise(tracking_type: TrackingType) -> (f64, f64) {
+    match tracking_type {
+        // Positions are rounded to ~18 m by the uncompressed APRS format
+        TrackingType::APRS | TrackingType::KISS | TrackingType::AprsIs => (25.0, 30.0),
+        TrackingType::Iridium => (15.0, 25.0),
+        TrackingType::SondeHub => (20.0, 30.0),
+        TrackingType::Horus | TrackingType::UKHAS => (10.0, 20.0),
+        TrackingType::RFD => (8.0, 15.0),
+    }
+}
+
+/** Struct holding the smoothed state of the payload.
+
+horiz_vel -> Ground speed in m/s
+
+course -> Course over ground in degrees from true north, None when standing still
+
+vert_vel, vert_acc -> m/s and m/s^2, positive up
+
+last_update -> Unix timestamp of the last fix folded into the estimate
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct KalmanEstimate {
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+    pub horiz_vel: f64,
+    pub course: Option<f64>,
+    pub vert_vel: f64,
+    pub vert_acc: f64,
+    pub last_update: u64,
+    pub covariance: KalmanCovariance,
+}
+
+/** Struct holding the covariance of the filter, the three axes are filtered independently.
+
+east, north -> [position (m), velocity (m/s)]
+
+up -> [altitude (m), vertical velocity (m/s), vertical acceleration (m/s^2)]
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct KalmanCovariance {
+    pub east: [[f64; 2]; 2],
+    pub north: [[f64; 2]; 2],
+    pub up: [[f64; 3]; 3],
+}
+
+/// Linear Kalman filter on one axis, the first state is the measured position
+#[derive(Debug, Clone, Copy)]
+struct Axis<const N: usize> {
+    x: [f64; N],
+    p: [[f64; N]; N],
+}
+
+impl<const N: usize> Axis<N> {
+    fn new(position: f64, position_var: f64, initial_sigmas: [f64; N]) -> Self {
+        let mut x = [0.0; N];
+        x[0] = position;
+        let mut p = [[0.0; N]; N];
+        for (i, sigma) in initial_sigmas.iter().enumerate() {
+            p[i][i] = sigma * sigma;
+        }
+        p[0][0] = position_var;
+        Self { x, p }
+    }
+
+    /// Propagate by `dt` seconds with a polynomial model driven by white noise on its highest derivative
+    fn predict(&mut self, dt: f64, noise: f64) {
+        if dt <= 0.0 {
+            return;
+        }
+        // Transition: x_i += sum_k x_(i+k) dt^k / k!
+        let mut f = [[0.0; N]; N];
+        for (i, row) in f.iter_mut().enumerate() {
+            let mut term = 1.0;
+            for (k, cell) in row.iter_mut().skip(i).enumerate() {
+                if k > 0 {
+                    term *= dt / k as f64;
+                }
+                *cell = term;
+            }
+        }
+
+        self.x = std::array::from_fn(|i| (0..N).map(|j| f[i][j] * self.x[j]).sum());
+
+        // P = F P F^T + Q
+        let fp: [[f64; N]; N] = std::array::from_fn(|i| std::array::from_fn(|j| (0..N).map(|k| f[i][k] * self.p[k][j]).sum()));
+        self.p = std::array::from_fn(|i| {
+            std::array::from_fn(|j| (0..N).map(|k| fp[i][k] * f[j][k]).sum::<f64>() + noise * process_noise(N, i, j, dt))
+        });
+    }
+
+    /// Scalar measurement of state `index` with variance `r`
+    fn update(&mut self, index: usize, z: f64, r: f64) {
+        let innovation = z - self.x[index];
+        let s = self.p[index][index] + r;
+        if s <= 0.0 {
+            return;
+        }
+        let gain: [f64; N] = std::array::from_fn(|i| self.p[i][index] / s);
+        for (xi, k) in self.x.iter_mut().zip(gain) {
+            *xi += k * innovation;
+        }
+        // P = (I - K H) P
+        let row = self.p[index];
+        for (i, k) in gain.iter().enumerate() {
+            for (j, pj) in row.iter().enumerate() {
+                self.p[i][j] -= k * pj;
+            }
+        }
+    }
+}
+
+/// Element (i, j) of the discrete white noise covariance of an N state polynomial model, per unit spectral density
+fn process_noise(n: usize, i: usize, j: usize, dt: f64) -> f64 {
+    // Integral of (dt^(n-1-i) / (n-1-i)!) (dt^(n-1-j) / (n-1-j)!) over the step
+    let a = (n - 1 - i) as i32;
+    let b = (n - 1 - j) as i32;
+    let factorial = |k: i32| (1..=k).product::<i32>() as f64;
+    dt.powi(a + b + 1) / ((a + b + 1) as f64 * factorial(a) * factorial(b))
+}
+
+/// Filter state right after a measurement
+#[derive(Debug, Clone, Copy)]
+struct State {
+    east: Axis<2>,
+    north: Axis<2>,
+    up: Axis<3>,
+    time: u64,
+}
+
+/// Constant-velocity horizontal and constant-acceleration vertical Kalman filter over the fixes of every source.
+/// Late fixes are handled by running the filter again over the `FixHistory` of the tracker
+pub struct KalmanFilter {
+    origin: Option<(f64, f64)>,
+    state: Option<State>,
+    noise: HashMap<TrackingType, (f64, f64)>,
+}
+
+impl KalmanFilter {
+    pub fn new() -> Self {
+        Self { origin: None, state: None, noise: HashMap::new() }
+    }
+
+    /// Forget every fix, the next one restarts the filter
+    pub fn reset(&mut self) {
+        self.origin = None;
+        self.state = None;
+    }
+
+    /// Override the (horizontal, vertical) 1-sigma noise in meters of a source type
+    pub fn set_measurement_noise(&mut self, tracking_type: TrackingType, horizontal: f64, vertical: f64) {
+        self.noise.insert(tracking_type, (horizontal, vertical));
+    }
+
+    pub fn measurement_noise(&self, tracking_type: TrackingType) -> (f64, f64) {
+        self.noise.get(&tracking_type).copied().unwrap_or_else(|| default_measurement_noise(tracking_type))
+    }
+
+    /// Fold a fix just added to `history` with the noise of its source type.
+    /// A fix older than the filter state runs the filter again over the whole history
+    pub fn add_fix(&mut self, history: &FixHistory, pos_time: &PositionTime, tracking_type: TrackingType) {
+        if pos_time.last_update == 0 {
+            return;
+        }
+        if self.state.is_some_and(|state| pos_time.last_update < state.time) {
+            self.rebuild(history);
+            return;
+        }
+        let noise = self.measurement_noise(tracking_type);
+        self.add_measurement(pos_time, noise);
+    }
+
+    /// Run the filter from scratch over every fix of `history`, eg. after a late fix or a noise change
+    pub fn rebuild(&mut self, history: &FixHistory) {
+        self.reset();
+        for fix in history.fixes() {
+            let noise = self.measurement_noise(fix.tracking_type);
+            self.add_measurement(&fix.pos_time, noise);
+        }
+    }
+
+    /// Smoothed position and velocity after the latest fix
+    pub fn estimate(&self) -> Option<KalmanEstimate> {
+        let state = self.state.as_ref()?;
+        let (lat, lon) = self.unproject(state.east.x[0], state.north.x[0]);
+        Some(KalmanEstimate {
+            lat,
+            lon,
+            alt: state.up.x[0],
+            horiz_vel: state.east.x[1].hypot(state.north.x[1]),
+            course: course_of(state.east.x[1], state.north.x[1]),
+            vert_vel: state.up.x[1],
+            vert_acc: state.up.x[2],
+            last_update: state.time,
+            covariance: KalmanCovariance { east: state.east.p, north: state.north.p, up: state.up.p },
+        })
+    }
+
+    /// Run a fresh filter over fixes of unknown sources
+    pub fn smooth(pos_time: &[PositionTime]) -> Option<PositionTime> {
+        let mut fixes: Vec<&PositionTime> = pos_time.iter().filter(|f| f.last_update != 0).collect();
+        fixes.sort_by_key(|f| f.last_update);
+        let mut filter = Self::new();
+        for fix in fixes {
+            filter.add_measurement(fix, UNKNOWN_SOURCE_NOISE);
+        }
+        filter.pos_time()
+    }
+
+    /// The estimate as a `PositionTime`
+    pub fn pos_time(&self) -> Option<PositionTime> {
+        self.estimate().map(|e| {
+            let mut pos_time = PositionTime::new_with_value(e.lat, e.lon, e.alt, e.last_update, e.horiz_vel, e.vert_vel);
+            pos_time.heading = e.course;
+            pos_time
+        })
+    }
+
+    //------------------------Helper Functions------------------------
+
+    /// Fold a fix no older than the filter state with the given (horizontal, vertical) 1-sigma noise
+    fn add_measurement(&mut self, pos: &PositionTime, noise: (f64, f64)) {
+        let (horizontal, vertical) = noise;
+        let (r_h, r_v) = (horizontal * horizontal, vertical * vertical);
+        self.recenter();
+        let (east, north) = self.project(pos.lat, pos.lon);
+
+        let Some(mut state) = self.state else {
+            let mut up = Axis::new(pos.alt, r_v, [0.0, INITIAL_VEL_SIGMA, INITIAL_ACC_SIGMA]);
+            up.x[1] = pos.vert_vel;
+            self.state = Some(State {
+                east: Axis::new(east, r_h, [0.0, INITIAL_VEL_SIGMA]),
+                north: Axis::new(north, r_h, [0.0, INITIAL_VEL_SIGMA]),
+                up,
+                time: pos.last_update,
+            });
+            return;
+        };
+
+        let dt = pos.last_update.saturating_sub(state.time) as f64;
+        state.east.predict(dt, HORIZ_ACCEL_NOISE);
+        state.north.predict(dt, HORIZ_ACCEL_NOISE);
+        state.up.predict(dt, VERT_JERK_NOISE);
+
+        state.east.update(0, east, r_h);
+        state.north.update(0, north, r_h);
+        state.up.update(0, pos.alt, r_v);
+        // Zero means the source did not report a vertical rate
+        if pos.vert_vel != 0.0 {
+            state.up.update(1, pos.vert_vel, VERT_VEL_SIGMA * VERT_VEL_SIGMA);
+        }
+        state.time = state.time.max(pos.last_update);
+        self.state = Some(state);
+    }
+
+    /// Move the projection origin to the estimate once it has drifted too far from it.
+    /// East distances scale with the cosine of the origin latitude, so the east axis is rescaled to the new one
+    fn recenter(&mut self) {
+        let (Some(state), Some((lat0, lon0))) = (self.state.as_mut(), self.origin) else {
+            return;
+        };
+        if state.east.x[0].hypot(state.north.x[0]) < RECENTER_DISTANCE_M {
+            return;
+        }
+        let (lat, lon) = offset_position(lat0, lon0, state.east.x[0], state.north.x[0]);
+        let scale = lat.to_radians().cos() / lat0.to_radians().cos();
+        state.east.x = [0.0, state.east.x[1] * scale];
+        state.east.p = state.east.p.map(|row| row.map(|v| v * scale * scale));
+        state.north.x[0] = 0.0;
+        self.origin = Some((lat, lon));
+    }
+
+    /// Equirectangular projection around the first fix, or the estimate it was last moved to (m)
+    fn project(&mut self, lat: f64, lon: f64) -> (f64, f64) {
+        let (lat0, lon0) = *self.origin.get_or_insert((lat, lon));
+        local_offset_m(lat0, lon0, lat, lon)
+    }
+
+    fn unproject(&self, east: f64, north: f64) -> (f64, f64) {
+        let (lat0, lon0) = self.origin.unwrap_or_default();
+        offset_position(lat0, lon0, east, north)
+    }
+}
+
+impl Default for KalmanFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::track_lib::look_angle::{ground_distance_m, EARTH_RADIUS_M};
+
+    fn fix(time: u64, lat: f64, alt: f64) -> PositionTime {
+        PositionTime::new_with_value(lat, -80.0, alt, time, 0.0, 0.0)
+    }
+
+    // Push every fix to a history and the filter, like the tracker does
+    fn run(fixes: &[PositionTime]) -> KalmanEstimate {
+        let mut history = FixHistory::new();
+        let mut filter = KalmanFilter::new();
+        for (i, f) in fixes.iter().enumerate() {
+            if history.push(f.clone(), TrackingType::Horus, &format!("source{}", i % 2)) {
+                filter.add_fix(&history, f, TrackingType::Horus);
+            }
+        }
+        filter.estimate().unwrap()
+    }
+
+    #[test]
+    fn late_fix_matches_in_order_run() {
+        let in_order: Vec<PositionTime> = (0..10).map(|i| fix(1000 + i * 10, 40.0 + i as f64 * 1e-4, 1000.0 + i as f64 * 50.0)).collect();
+        let mut late = in_order.clone();
+        late.swap(6, 8);
+
+        let expected = run(&in_order);
+        let estimate = run(&late);
+        assert_eq!(estimate.last_update, 1090);
+        assert!((estimate.lat - expected.lat).abs() < 1e-9);
+        assert!((estimate.alt - expected.alt).abs() < 1e-6);
+        assert!((estimate.vert_vel - expected.vert_vel).abs() < 1e-6);
+        assert!((estimate.vert_vel - 5.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn rebuild_applies_new_noise() {
+        let mut history = FixHistory::new();
+        let mut filter = KalmanFilter::new();
+        for i in 0..5 {
+            let f = fix(1000 + i * 10, 40.0, 1000.0 + (i % 2) as f64 * 100.0);
+            history.push(f.clone(), TrackingType::RFD, "rfd");
+            filter.add_fix(&history, &f, TrackingType::RFD);
+        }
+        let before = filter.estimate().unwrap().covariance.up[0][0];
+        filter.set_measurement_noise(TrackingType::RFD, 100.0, 200.0);
+        filter.rebuild(&history);
+        assert!(filter.estimate().unwrap().covariance.up[0][0] > before);
+    }
+
+    #[test]
+    fn smooth_sorts_its_fixes() {
+        let fixes = vec![fix(1020, 40.002, 1200.0), fix(1000, 40.0, 1000.0), fix(1010, 40.001, 1100.0)];
+        let smoothed = KalmanFilter::smooth(&fixes).unwrap();
+        assert_eq!(smoothed.last_update, 1020);
+        assert!(smoothed.alt > 1100.0);
+    }
+
+    #[test]
+    fn keeps_the_ground_speed_over_a_long_drift() {
+        // 20 m/s north and 20 m/s east from 60N for five hours, about 360 km north, fixes every minute
+        let (east_vel, north_vel) = (20.0, 20.0);
+        let (mut lat, mut lon) = (60.0, 10.0);
+        let mut fixes = vec![];
+        for step in 0..=1800u64 {
+            if step % 6 == 0 {
+                fixes.push(PositionTime::new_with_value(lat, lon, 20000.0, 1000 + step * 10, 0.0, 0.0));
+            }
+            lat += (north_vel * 10.0 / EARTH_RADIUS_M).to_degrees();
+            lon += (east_vel * 10.0 / (EARTH_RADIUS_M * lat.to_radians().cos())).to_degrees();
+        }
+        let last = fixes.last().unwrap().clone();
+        assert!(last.lat > 63.0);
+
+        let estimate = run(&fixes);
+        assert!((estimate.horiz_vel - east_vel.hypot(north_vel)).abs() < 0.2, "ground speed {}", estimate.horiz_vel);
+        assert!((estimate.course.unwrap() - 45.0).abs() < 0.5, "course {:?}", estimate.course);
+        assert!(ground_distance_m(estimate.lat, estimate.lon, last.lat, last.lon) < 50.0);
+    }
+}
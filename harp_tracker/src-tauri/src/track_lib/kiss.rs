@@ -0,0 +1,383 @@
+use std::{
+    error::Error,
+    io::Read,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::track_lib::aprs_parser::{AprsPacket, AprsStation, TelemetryChannel};
+use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::stream_link::{self, StreamLink};
+use crate::track_lib::tracking_source::{SourceHealth, TrackingSource};
+use crate::track_lib::tracking_type::TrackingType;
+
+//-------KISS special bytes-------
+const FEND: u8 = 0xC0;
+const FESC: u8 = 0xDB;
+const TFEND: u8 = 0xDC;
+const TFESC: u8 = 0xDD;
+
+//AX.25 UI frame control field and "no layer 3" protocol id
+const AX25_UI: u8 = 0x03;
+const AX25_NO_L3: u8 = 0xF0;
+
+//An AX.25 frame is at most about 330 bytes, anything longer is line noise or not KISS at all
+const MAX_FRAME_LEN: usize = 400;
+
+//The TNC pushes frames as they are heard, so drain it often
+const POLL_INTERVAL_SECS: u64 = 1;
+
+/// APRS reception from a local KISS TNC, independent of any internet connection
+pub struct KissTnc {
+    tracking_type: TrackingType,
+    link: StreamLink,
+    call_sign: String,
+    stream: Option<Box<dyn Read + Send>>,
+    decoder: KissDecoder,
+    station: AprsStation,
+    health: SourceHealth,
+}
+
+impl KissTnc {
+    pub fn new(link: StreamLink, call_sign: &str) -> Self {
+        Self {
+            tracking_type: TrackingType::KISS,
+            link,
+            call_sign: call_sign.to_uppercase(),
+            stream: None,
+            decoder: KissDecoder::new(),
+            station: AprsStation::new(call_sign),
+            health: SourceHealth::new(),
+        }
+    }
+
+    fn fetch_position(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.stream.is_none() {
+            self.stream = Some(self.link.open()?);
+            println!("KISS TNC connected: {:?}", self.link);
+        }
+
+        // Drain whatever the TNC has buffered
+        let stream = self.stream.as_mut().ok_or("KISS TNC not connected")?;
+        let mut frames = vec![];
+        let result = stream_link::drain(stream, |bytes| {
+            frames.extend(self.decoder.push(bytes));
+            Ok(())
+        });
+        for frame in frames {
+            if let Err(e) = self.handle_frame(&frame) {
+                eprintln!("KISS: ignoring frame: {}", e);
+            }
+        }
+        if let Err(e) = result {
+            self.stream = None;
+            return Err(format!("KISS TNC: {}", e).into());
+        }
+        Ok(())
+    }
+
+    /// Decode one KISS frame and update the station if the packet concerns our callsign
+    fn handle_frame(&mut self, frame: &[u8]) -> Result<(), Box<dyn Error>> {
+        let (&command, data) = frame.split_first().ok_or("Empty KISS frame")?;
+        // Low nibble 0 is a data frame, the high nibble is the TNC port
+        if command & 0x0F != 0 {
+            return Ok(());
+        }
+
+        let frame = Ax25Frame::decode(data)?;
+        let info = String::from_utf8_lossy(&frame.info);
+        if !self.station.wants(&frame.source, &info) {
+            return Ok(());
+        }
+        let packet = AprsPacket::decode(&frame.source, &frame.destination, frame.path, &info)?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let source = packet.source.clone();
+        if self.station.handle(packet, now) {
+            let pos = &self.station.position_time;
+            println!("KISS Position: Call: {}, Lat: {}, Lon: {}, Alt: {}m", source, pos.lat, pos.lon, pos.alt);
+        }
+        Ok(())
+    }
+}
+
+impl TrackingSource for KissTnc {
+    fn id(&self) -> &str {
+        &self.call_sign
+    }
+
+    fn tracking_type(&self) -> TrackingType {
+        self.tracking_type
+    }
+
+    fn update_position(&mut self) -> Result<(), Box<dyn Error>> {
+        let result = self.fetch_position();
+        self.health.record(&result);
+        result
+    }
+
+    fn get_pos_time(&self) -> PositionTime {
+        self.station.position_time.clone()
+    }
+
+    fn get_last_update(&self) -> u64 {
+        self.station.position_time.last_update
+    }
+
+    fn health(&self) -> SourceHealth {
+        self.health.evaluate(self.station.position_time.last_update)
+    }
+
+    fn get_comment(&self) -> &str {
+        &self.station.comment
+    }
+
+    /// Latest telemetry of the station, named and scaled by its PARM/UNIT/EQNS definitions
+    fn get_telemetry(&self) -> &[TelemetryChannel] {
+        &self.station.telemetry
+    }
+
+    fn precision(&self) -> Option<f64> {
+        self.station.precision
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(POLL_INTERVAL_SECS)
+    }
+}
+
+//------------------------KISS Framing------------------------
+
+/// Streaming KISS decoder, returns complete frames (command byte included) as bytes arrive
+pub struct KissDecoder {
+    buffer: Vec<u8>,
+    in_frame: bool,
+    escaped: bool,
+}
+
+impl KissDecoder {
+    pub fn new() -> Self {
+        Self { buffer: vec![], in_frame: false, escaped: false }
+    }
+
+    pub fn push(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = vec![];
+        for &byte in data {
+            match byte {
+                FEND => {
+                    if self.in_frame && !self.buffer.is_empty() {
+                        frames.push(std::mem::take(&mut self.buffer));
+                    }
+                    self.in_frame = true;
+                    self.escaped = false;
+                }
+                _ if !self.in_frame => {}
+                FESC => self.escaped = true,
+                _ => {
+                    let byte = match (self.escaped, byte) {
+                        (true, TFEND) => FEND,
+                        (true, TFESC) => FESC,
+                        _ => byte,
+                    };
+                    self.escaped = false;
+                    self.buffer.push(byte);
+                    // Drop the frame and wait for the next FEND rather than buffering without end
+                    if self.buffer.len() > MAX_FRAME_LEN {
+                        self.buffer.clear();
+                        self.in_frame = false;
+                    }
+                }
+            }
+        }
+        frames
+    }
+}
+
+impl Default for KissDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//------------------------AX.25------------------------
+
+/// Addresses and information field of an AX.25 UI frame
+#[derive(Debug, Clone)]
+pub struct Ax25Frame {
+    pub destination: String,
+    pub source: String,
+    pub path: Vec<String>,
+    pub info: Vec<u8>,
+}
+
+impl Ax25Frame {
+    pub fn decode(data: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut addresses = vec![];
+        let mut offset = 0;
+        loop {
+            let field = data.get(offset..offset + 7).ok_or("AX.25 address field truncated")?;
+            addresses.push(field);
+            offset += 7;
+            // The extension bit marks the last address
+            if field[6] & 0x01 == 1 {
+                break;
+            }
+        }
+        if addresses.len() < 2 {
+            return Err("AX.25 frame without source address".into());
+        }
+
+        let control = *data.get(offset).ok_or("AX.25 frame without control field")?;
+        let pid = *data.get(offset + 1).ok_or("AX.25 frame without protocol id")?;
+        if control != AX25_UI || pid != AX25_NO_L3 {
+            return Err("Not an AX.25 UI frame".into());
+        }
+
+        Ok(Self {
+            destination: decode_address(addresses[0]),
+            source: decode_address(addresses[1]),
+            // H bit: the packet has been repeated through this digipeater
+            path: addresses[2..].iter()
+                .map(|field| format!("{}{}", decode_address(field), if field[6] & 0x80 != 0 { "*" } else { "" }))
+                .collect(),
+            info: data[offset + 2..].to_vec(),
+        })
+    }
+}
+
+/// Shifted ASCII callsign followed by the SSID byte
+fn decode_address(field: &[u8]) -> String {
+    let call: String = field[..6].iter().map(|b| (b >> 1) as char).collect();
+    let call = call.trim_end();
+    let ssid = (field[6] >> 1) & 0x0F;
+    if ssid == 0 {
+        call.to_string()
+    } else {
+        format!("{}-{}", call, ssid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        net::TcpListener,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+
+    /// AX.25 address field: callsign shifted left, SSID byte with the extension bit on the last address
+    fn address(call: &str, last: bool) -> Vec<u8> {
+        let (call, ssid) = call.split_once('-').map_or((call, 0), |(c, s)| (c, s.parse::<u8>().unwrap()));
+        let mut field: Vec<u8> = format!("{:<6}", call).bytes().map(|b| b << 1).collect();
+        field.push(0x60 | (ssid << 1) | last as u8);
+        field
+    }
+
+    /// KISS data frame of an APRS UI packet, escaped as a TNC sends it
+    fn kiss_frame(source: &str, destination: &str, info: &[u8]) -> Vec<u8> {
+        let mut ax25 = address(destination, false);
+        ax25.extend(address(source, true));
+        ax25.extend([AX25_UI, AX25_NO_L3]);
+        ax25.extend_from_slice(info);
+
+        let mut frame = vec![FEND, 0x00];
+        for byte in ax25 {
+            match byte {
+                FEND => frame.extend([FESC, TFEND]),
+                FESC => frame.extend([FESC, TFESC]),
+                _ => frame.push(byte),
+            }
+        }
+        frame.push(FEND);
+        frame
+    }
+
+    /// Serve the recorded frames on a local TCP port, like Direwolf, and poll a KISS source until it has a fix
+    fn replay(call_sign: &str, frames: Vec<Vec<u8>>) -> KissTnc {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            for frame in frames {
+                stream.write_all(&frame).unwrap();
+            }
+            // Keep the connection open while the source drains it
+            thread::sleep(Duration::from_secs(2));
+        });
+
+        let mut tnc = KissTnc::new(StreamLink::Tcp(address), call_sign);
+        let started = Instant::now();
+        while tnc.get_last_update() == 0 && started.elapsed() < Duration::from_secs(2) {
+            tnc.update_position().unwrap();
+        }
+        tnc
+    }
+
+    #[test]
+    fn decodes_uncompressed_position() {
+        let frame = kiss_frame("N0CALL-11", "APRS", b"!4903.50N/07201.75W>088/036/A=001234 HARP");
+        let tnc = replay("N0CALL-11", vec![frame]);
+        let pos = tnc.get_pos_time();
+        assert!((pos.lat - 49.058333).abs() < 1e-5);
+        assert!((pos.lon + 72.029167).abs() < 1e-5);
+        assert!((pos.alt - 376.1232).abs() < 1e-3);
+        assert_eq!(pos.heading, Some(88.0));
+        assert_eq!(tnc.get_comment(), "/A=001234 HARP");
+    }
+
+    #[test]
+    fn decodes_compressed_position() {
+        let frame = kiss_frame("N0CALL-11", "APRS", b"!/5L!!<*e7>7P[");
+        let pos = replay("N0CALL-11", vec![frame]).get_pos_time();
+        assert!((pos.lat - 49.5).abs() < 1e-4);
+        assert!((pos.lon + 72.75).abs() < 1e-4);
+        assert_eq!(pos.heading, Some(88.0));
+    }
+
+    #[test]
+    fn decodes_mic_e_position() {
+        // 33 25.64N 112 07.74W, 25 knots, course 251
+        let frame = kiss_frame("N0CALL-11", "SSRUVT", b"`(#fnPO>/");
+        let pos = replay("N0CALL-11", vec![frame]).get_pos_time();
+        assert!((pos.lat - 33.427333).abs() < 1e-5);
+        assert!((pos.lon + 112.129).abs() < 1e-5);
+        assert_eq!(pos.heading, Some(251.0));
+        assert!((pos.horiz_vel - 25.0 * 1.852 / 3.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn ignores_other_stations() {
+        let frames = vec![
+            kiss_frame("N0CALL-11", "APRS", b"!4903.50N/07201.75W>"),
+            kiss_frame("OTHER-9", "APRS", b"!1000.00N/02000.00E>"),
+        ];
+        let pos = replay("N0CALL", frames).get_pos_time();
+        assert!((pos.lat - 49.058333).abs() < 1e-5);
+    }
+
+    #[test]
+    fn decoder_unescapes_split_frames() {
+        let mut decoder = KissDecoder::new();
+        assert!(decoder.push(&[FEND, 0x00, 0x01, FESC]).is_empty());
+        let frames = decoder.push(&[TFEND, FESC, TFESC, FEND]);
+        assert_eq!(frames, vec![vec![0x00, 0x01, FEND, FESC]]);
+    }
+
+    #[test]
+    fn decoder_drops_overlong_frames() {
+        let mut decoder = KissDecoder::new();
+        let mut noise = vec![FEND];
+        noise.extend(std::iter::repeat_n(0x55, MAX_FRAME_LEN * 4));
+        assert!(decoder.push(&noise).is_empty());
+        assert!(decoder.buffer.is_empty());
+
+        // The next frame after the noise still decodes
+        let frames = decoder.push(&[FEND, 0x00, 0x01, FEND]);
+        assert_eq!(frames, vec![vec![0x00, 0x01]]);
+    }
+}
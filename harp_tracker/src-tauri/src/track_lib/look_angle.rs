@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::track_lib::position_time::PositionTime;
+
+//-------WGS84 ellipsoid-------
+const WGS84_A: f64 = 6378137.0;
+const WGS84_F: f64 = 1.0 / 298.257223563;
+
+//Mean Earth radius used for every ground distance and local projection of the tracker (m)
+pub const EARTH_RADIUS_M: f64 = 6371008.8;
+
+/** Struct holding the location of the ground station.
+
+lat, lon -> Degrees
+
+alt -> Meters above sea level, the same reference as the payload altitudes
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GroundStation {
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+}
+
+/** Struct holding the direction and distance from the ground station to the payload.
+
+azimuth -> Degrees clockwise from true north, 0 to 360
+
+elevation -> Degrees above the local horizon, negative once the payload drops below it (Earth curvature included)
+
+slant_range -> Straight line distance in meters
+
+ground_distance -> Great circle distance in meters between the station and the point under the payload
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LookAngles {
+    pub azimuth: f64,
+    pub elevation: f64,
+    pub slant_range: f64,
+    pub ground_distance: f64,
+}
+
+impl GroundStation {
+    pub fn new(lat: f64, lon: f64, alt: f64) -> Self {
+        Self { lat, lon, alt }
+    }
+
+    /// Look angles from the station to the target
+    pub fn look_angles(&self, target: &PositionTime) -> LookAngles {
+        let (east, north, up) = self.enu(target);
+        let horizontal = east.hypot(north);
+        LookAngles {
+            azimuth: east.atan2(north).to_degrees().rem_euclid(360.0),
+            elevation: up.atan2(horizontal).to_degrees(),
+            slant_range: horizontal.hypot(up),
+            ground_distance: ground_distance_m(self.lat, self.lon, target.lat, target.lon),
+        }
+    }
+
+    /// Offset of the target in the local east/north/up frame of the station (m)
+    fn enu(&self, target: &PositionTime) -> (f64, f64, f64) {
+        let (x0, y0, z0) = to_ecef(self.lat, self.lon, self.alt);
+        let (x1, y1, z1) = to_ecef(target.lat, target.lon, target.alt);
+        let (dx, dy, dz) = (x1 - x0, y1 - y0, z1 - z0);
+
+        let (sin_lat, cos_lat) = self.lat.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = self.lon.to_radians().sin_cos();
+        let east = -sin_lon * dx + cos_lon * dy;
+        let north = -sin_lat * cos_lon * dx - sin_lat * sin_lon * dy + cos_lat * dz;
+        let up = cos_lat * cos_lon * dx + cos_lat * sin_lon * dy + sin_lat * dz;
+        (east, north, up)
+    }
+}
+
+/// Haversine distance in meters
+pub fn ground_distance_m(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let dlat = (lat2 - lat1).to_radians();
+    let dlon = (lon2 - lon1).to_radians();
+    let a = (dlat / 2.0).sin().powi(2) + lat1.to_radians().cos() * lat2.to_radians().cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * a.sqrt().atan2((1.0 - a).sqrt())
+}
+
+/// Bring a longitude or longitude difference into [-180, 180)
+pub fn wrap_longitude(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// East and north offset in meters of a point from an origin, equirectangular so only valid over a few hundred kilometers
+pub fn local_offset_m(origin_lat: f64, origin_lon: f64, lat: f64, lon: f64) -> (f64, f64) {
+    // The short way across the antimeridian
+    let east = wrap_longitude(lon - origin_lon).to_radians() * origin_lat.to_radians().cos() * EARTH_RADIUS_M;
+    let north = (lat - origin_lat).to_radians() * EARTH_RADIUS_M;
+    (east, north)
+}
+
+/// Inverse of `local_offset_m`, the latitude and longitude at an east and north offset from the origin
+pub fn offset_position(origin_lat: f64, origin_lon: f64, east: f64, north: f64) -> (f64, f64) {
+    let lat = origin_lat + (north / EARTH_RADIUS_M).to_degrees();
+    let lon = origin_lon + (east / (EARTH_RADIUS_M * origin_lat.to_radians().cos())).to_degrees();
+    (lat, wrap_longitude(lon))
+}
+
+/// Geodetic coordinates to Earth-centered Earth-fixed (m)
+fn to_ecef(lat: f64, lon: f64, alt: f64) -> (f64, f64, f64) {
+    let e2 = WGS84_F * (2.0 - WGS84_F);
+    let (sin_lat, cos_lat) = lat.to_radians().sin_cos();
+    let (sin_lon, cos_lon) = lon.to_radians().sin_cos();
+    let n = WGS84_A / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+    (
+        (n + alt) * cos_lat * cos_lon,
+        (n + alt) * cos_lat * sin_lon,
+        (n * (1.0 - e2) + alt) * sin_lat,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ground_distance_of_a_degree_of_latitude() {
+        let distance = ground_distance_m(45.0, -93.0, 46.0, -93.0);
+        assert!((distance - EARTH_RADIUS_M.to_radians()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn local_offset_round_trips_and_agrees_with_the_ground_distance() {
+        let (east, north) = local_offset_m(45.0, -93.0, 45.01, -92.98);
+        assert!((east.hypot(north) - ground_distance_m(45.0, -93.0, 45.01, -92.98)).abs() < 1.0);
+        let (lat, lon) = offset_position(45.0, -93.0, east, north);
+        assert!((lat - 45.01).abs() < 1e-12 && (lon + 92.98).abs() < 1e-12);
+    }
+
+    #[test]
+    fn local_offset_takes_the_short_way_across_the_antimeridian() {
+        let (east, north) = local_offset_m(0.0, 179.99, 0.0, -179.99);
+        assert!((east - ground_distance_m(0.0, 179.99, 0.0, -179.99)).abs() < 1.0);
+        assert!(east > 0.0 && east < 3000.0 && north == 0.0);
+        let (west, _) = local_offset_m(0.0, -179.99, 0.0, 179.99);
+        assert!((west + east).abs() < 1e-6);
+
+        let (lat, lon) = offset_position(0.0, 179.99, east, north);
+        assert!(lat.abs() < 1e-12 && (lon + 179.99).abs() < 1e-9);
+    }
+
+    #[test]
+    fn look_angles_to_a_payload_overhead_and_to_the_north() {
+        let station = GroundStation::new(45.0, -93.0, 300.0);
+        let overhead = station.look_angles(&PositionTime::new_with_value(45.0, -93.0, 10_300.0, 1, 0.0, 0.0));
+        assert!((overhead.elevation - 90.0).abs() < 1e-6);
+        assert!((overhead.slant_range - 10_000.0).abs() < 1e-3);
+
+        let north = station.look_angles(&PositionTime::new_with_value(45.5, -93.0, 20_000.0, 1, 0.0, 0.0));
+        assert!(north.azimuth < 1e-6 || north.azimuth > 360.0 - 1e-6);
+        assert!(north.elevation > 0.0);
+    }
+}
@@ -1,8 +1,32 @@
 pub mod aprs;
+pub mod aprs_parser;
+pub mod aprs_is;
+pub mod comment_parser;
+pub mod kiss;
+pub mod horus;
+pub mod ukhas;
+pub mod rfd;
+pub mod stream_link;
+pub mod receiver;
 pub mod iridium;
 pub mod sondehub;
 pub mod tracker;
 pub mod tracking_type;
+pub mod tracking_source;
+pub mod poller;
 pub mod position_time;
-// pub mod arduino;
+pub mod kalman;
+pub mod history;
+pub mod database;
+pub mod storage;
+pub mod export;
+pub mod fix_validator;
+pub mod fusion;
+pub mod flight_phase;
+pub mod arduino;
+pub mod velocity;
+pub mod look_angle;
+pub mod station;
+pub mod rotator;
+pub mod pointing;
 pub mod pred;
\ No newline at end of file
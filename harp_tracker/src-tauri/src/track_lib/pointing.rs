@@ -0,0 +1,371 @@
+use std::{
+    error::Error,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::track_lib::arduino::{Arduino, ArduinoStatus};
+use crate::track_lib::rotator::controller::{Rotator, RotatorController, RotatorStatus};
+
+/// Request handled by the pointing thread, the ones with a `Sender` wait for its answer
+enum PointingCommand {
+    /// Newest look angles (azimuth, elevation) of the payload
+    Target(f64, f64),
+    ConnectArduino(Option<String>, Sender<Result<String, String>>),
+    DisconnectArduino(Sender<Result<(), String>>),
+    ConnectRotator(Box<dyn Rotator>, Sender<Result<(), String>>),
+    DisconnectRotator(Sender<Result<(), String>>),
+    RotatorLimits(f64, f64, Sender<Result<(), String>>),
+    RotatorPark(Option<(f64, f64)>, Sender<Result<(), String>>),
+    RotatorFeedback(Sender<Result<RotatorStatus, String>>),
+    Tracking(bool, Sender<Result<(), String>>),
+}
+
+/// State of the antenna tracker and the rotator, published by the pointing thread after every command
+#[derive(Debug, Clone)]
+struct PointingStatus {
+    arduino: ArduinoStatus,
+    rotator: Option<RotatorStatus>,
+}
+
+/// Points the Arduino antenna tracker and the rotator from their own thread,
+/// so a slow or unplugged device never holds up the tracker
+#[derive(Clone)]
+pub struct AntennaPointing {
+    commands: Arc<Mutex<Option<Sender<PointingCommand>>>>,
+    status: Arc<Mutex<PointingStatus>>,
+}
+
+impl AntennaPointing {
+    pub fn new() -> Self {
+        Self {
+            commands: Arc::new(Mutex::new(None)),
+            status: Arc::new(Mutex::new(PointingStatus { arduino: Arduino::new().status(), rotator: None })),
+        }
+    }
+
+    /// Send the look angles of the newest position. Only the latest one is kept while a device is busy
+    pub fn point(&self, azimuth: f64, elevation: f64) {
+        // Nothing to point before a device is connected
+        if let Some(commands) = self.commands.lock().unwrap().as_ref() {
+            let _ = commands.send(PointingCommand::Target(azimuth, elevation));
+        }
+    }
+
+    /// Connect the Arduino antenna tracker, on the given port or the first one detected
+    pub fn connect_arduino(&self, port: Option<&str>) -> Result<String, Box<dyn Error>> {
+        let port = port.map(str::to_string);
+        self.request(|reply| PointingCommand::ConnectArduino(port, reply))
+    }
+
+    pub fn disconnect_arduino(&self) -> Result<(), Box<dyn Error>> {
+        self.request(PointingCommand::DisconnectArduino)
+    }
+
+    /// Connect a rotator, replacing the previous one
+    pub fn connect_rotator(&self, rotator: Box<dyn Rotator>) -> Result<(), Box<dyn Error>> {
+        self.request(|reply| PointingCommand::ConnectRotator(rotator, reply))
+    }
+
+    pub fn disconnect_rotator(&self) -> Result<(), Box<dyn Error>> {
+        self.request(PointingCommand::DisconnectRotator)
+    }
+
+    /// Set the smallest move (degrees) and shortest time between commands (s) of the rotator
+    pub fn set_rotator_limits(&self, deadband: f64, min_interval_secs: f64) -> Result<(), Box<dyn Error>> {
+        self.request(|reply| PointingCommand::RotatorLimits(deadband, min_interval_secs, reply))
+    }
+
+    /// Set the position the rotator goes to when tracking stops, None to leave it where it is
+    pub fn set_rotator_park(&self, park: Option<(f64, f64)>) -> Result<(), Box<dyn Error>> {
+        self.request(|reply| PointingCommand::RotatorPark(park, reply))
+    }
+
+    /// Read the actual rotator position and return the commanded and actual pointing
+    pub fn rotator_feedback(&self) -> Result<RotatorStatus, Box<dyn Error>> {
+        self.request(PointingCommand::RotatorFeedback)
+    }
+
+    /// Start or stop tracking, for the Arduino and the rotator alike
+    pub fn set_tracking(&self, val: bool) -> Result<(), Box<dyn Error>> {
+        self.request(|reply| PointingCommand::Tracking(val, reply))
+    }
+
+    pub fn arduino_status(&self) -> ArduinoStatus {
+        self.status.lock().unwrap().arduino.clone()
+    }
+
+    pub fn rotator_status(&self) -> Option<RotatorStatus> {
+        self.status.lock().unwrap().rotator.clone()
+    }
+
+    //-----------------------Helper Functions-----------------------
+
+    /// Queue a command, starting the pointing thread on first use
+    fn send(&self, command: PointingCommand) -> Result<(), Box<dyn Error>> {
+        let mut commands = self.commands.lock().unwrap();
+        if commands.is_none() {
+            let (sender, receiver) = mpsc::channel();
+            let worker = PointingWorker {
+                arduino: Arduino::new(),
+                rotator: None,
+                target: None,
+                arduino_pending: false,
+                rotator_pending: false,
+                status: self.status.clone(),
+            };
+            thread::Builder::new()
+                .name("antenna-pointing".to_string())
+                .spawn(move || worker.run(receiver))
+                .map_err(|e| format!("Unable to start pointing thread: {}", e))?;
+            *commands = Some(sender);
+        }
+        commands.as_ref().ok_or("Pointing thread not running")?
+            .send(command)
+            .map_err(|_| "Pointing thread stopped".into())
+    }
+
+    /// Queue a command and wait for the pointing thread to answer it
+    fn request<T>(&self, command: impl FnOnce(Sender<Result<T, String>>) -> PointingCommand) -> Result<T, Box<dyn Error>> {
+        let (reply, answer) = mpsc::channel();
+        self.send(command(reply))?;
+        Ok(answer.recv().map_err(|_| "Pointing thread stopped")??)
+    }
+}
+
+impl Default for AntennaPointing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Owner of the devices, running on the pointing thread
+struct PointingWorker {
+    arduino: Arduino,
+    rotator: Option<RotatorController>,
+    target: Option<(f64, f64)>,
+    // The target still has to be sent to the device
+    arduino_pending: bool,
+    rotator_pending: bool,
+    status: Arc<Mutex<PointingStatus>>,
+}
+
+impl PointingWorker {
+    fn run(mut self, commands: Receiver<PointingCommand>) {
+        loop {
+            // A rate limited rotator gets the newest target as soon as it may move again
+            let command = match self.rotator_wait() {
+                Some(wait) => match commands.recv_timeout(wait) {
+                    Ok(command) => Some(command),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+                None => match commands.recv() {
+                    Ok(command) => Some(command),
+                    Err(_) => break,
+                },
+            };
+            if let Some(command) = command {
+                self.handle(command);
+            }
+            // Targets queued while a device was busy are replaced by the newest one
+            while let Ok(command) = commands.try_recv() {
+                self.handle(command);
+            }
+
+            self.apply_target();
+            self.publish();
+        }
+    }
+
+    fn handle(&mut self, command: PointingCommand) {
+        match command {
+            PointingCommand::Target(azimuth, elevation) => {
+                self.target = Some((azimuth, elevation));
+                self.arduino_pending = true;
+                self.rotator_pending = true;
+            }
+            PointingCommand::ConnectArduino(port, reply) => {
+                let result = self.arduino.connect(port.as_deref()).map_err(|e| e.to_string());
+                self.answer(reply, result);
+            }
+            PointingCommand::DisconnectArduino(reply) => {
+                self.arduino.disconnect();
+                self.answer(reply, Ok(()));
+            }
+            PointingCommand::ConnectRotator(rotator, reply) => {
+                self.rotator = Some(RotatorController::new(rotator));
+                self.answer(reply, Ok(()));
+            }
+            PointingCommand::DisconnectRotator(reply) => {
+                self.rotator = None;
+                self.answer(reply, Ok(()));
+            }
+            PointingCommand::RotatorLimits(deadband, min_interval_secs, reply) => {
+                let result = self.rotator().map(|r| r.set_limits(deadband, min_interval_secs));
+                self.answer(reply, result);
+            }
+            PointingCommand::RotatorPark(park, reply) => {
+                let result = self.rotator().map(|r| r.set_park(park));
+                self.answer(reply, result);
+            }
+            PointingCommand::RotatorFeedback(reply) => {
+                let result = self.rotator().and_then(|r| {
+                    r.refresh_position().map_err(|e| e.to_string())?;
+                    Ok(r.status())
+                });
+                self.answer(reply, result);
+            }
+            PointingCommand::Tracking(val, reply) => {
+                let result = self.set_tracking(val).map_err(|e| e.to_string());
+                self.answer(reply, result);
+            }
+        }
+    }
+
+    fn set_tracking(&mut self, val: bool) -> Result<(), Box<dyn Error>> {
+        if val && !self.arduino.active && self.rotator.is_none() {
+            return Err("No antenna tracker or rotator connected".into());
+        }
+        if self.arduino.active || !val {
+            self.arduino.set_tracking(val)?;
+        }
+        if let Some(rotator) = self.rotator.as_mut() {
+            rotator.set_tracking(val)?;
+        }
+        // Point at the last known position right away
+        self.arduino_pending = self.target.is_some();
+        self.rotator_pending = self.target.is_some();
+        Ok(())
+    }
+
+    /// Send the newest target to the devices that are tracking
+    fn apply_target(&mut self) {
+        let Some((azimuth, elevation)) = self.target else {
+            return;
+        };
+
+        if self.arduino_pending {
+            self.arduino_pending = false;
+            if self.arduino.tracking {
+                if let Err(e) = self.arduino.point(azimuth, elevation) {
+                    eprintln!("Arduino: {}", e);
+                }
+            }
+        }
+
+        if self.rotator_pending {
+            match self.rotator.as_mut() {
+                Some(rotator) => match rotator.update(azimuth, elevation) {
+                    Ok(handled) => self.rotator_pending = !handled,
+                    Err(e) => {
+                        // Tried again with the next position
+                        self.rotator_pending = false;
+                        eprintln!("Rotator: {}", e);
+                    }
+                },
+                None => self.rotator_pending = false,
+            }
+        }
+    }
+
+    /// Time until the rotator may take the pending target, None when nothing is waiting
+    fn rotator_wait(&self) -> Option<std::time::Duration> {
+        let rotator = self.rotator.as_ref().filter(|r| r.tracking)?;
+        self.rotator_pending.then(|| rotator.next_command_in())
+    }
+
+    fn rotator(&mut self) -> Result<&mut RotatorController, String> {
+        self.rotator.as_mut().ok_or_else(|| "No rotator connected".to_string())
+    }
+
+    /// Publish the new state before answering, so the caller reads it back
+    fn answer<T>(&self, reply: Sender<Result<T, String>>, result: Result<T, String>) {
+        self.publish();
+        let _ = reply.send(result);
+    }
+
+    fn publish(&self) {
+        *self.status.lock().unwrap() = PointingStatus {
+            arduino: self.arduino.status(),
+            rotator: self.rotator.as_ref().map(|r| r.status()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use crate::track_lib::rotator::rotctld::{tests::fake_rotctld, Rotctld};
+
+    /// Wait until the fake rotctld logged `count` commands
+    fn wait_for(log: &Mutex<Vec<String>>, count: usize) -> Vec<String> {
+        let started = Instant::now();
+        while log.lock().unwrap().len() < count && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        log.lock().unwrap().clone()
+    }
+
+    fn tracking_rotator(address: &str, min_interval: f64) -> AntennaPointing {
+        let pointing = AntennaPointing::new();
+        pointing.connect_rotator(Box::new(Rotctld::new(address))).unwrap();
+        pointing.set_rotator_limits(0.0, min_interval).unwrap();
+        pointing.set_tracking(true).unwrap();
+        pointing
+    }
+
+    #[test]
+    fn last_target_of_a_burst_is_applied_after_the_rate_limit() {
+        let (address, log) = fake_rotctld(Duration::ZERO);
+        let pointing = tracking_rotator(&address, 0.3);
+
+        pointing.point(10.0, 10.0);
+        assert_eq!(wait_for(&log, 2), ["P 10.0 10.0", "p"]);
+
+        // Inside the rate limit: only the newest target is kept, and sent once the limit is over
+        pointing.point(20.0, 20.0);
+        pointing.point(30.0, 30.0);
+        assert_eq!(wait_for(&log, 4)[2..], ["P 30.0 30.0", "p"]);
+        thread::sleep(Duration::from_millis(400));
+        assert_eq!(log.lock().unwrap().len(), 4);
+        assert_eq!(pointing.rotator_status().unwrap().commanded, Some((30.0, 30.0)));
+    }
+
+    #[test]
+    fn slow_rotator_does_not_block_the_caller() {
+        let (address, log) = fake_rotctld(Duration::from_millis(300));
+        let pointing = tracking_rotator(&address, 0.0);
+
+        let started = Instant::now();
+        for azimuth in 0..20 {
+            pointing.point(azimuth as f64 * 10.0, 45.0);
+        }
+        assert!(started.elapsed() < Duration::from_millis(100));
+        // The status is readable while the rotator is busy
+        assert!(pointing.rotator_status().is_some());
+
+        // Targets queued behind the one in flight are skipped for the newest
+        let started = Instant::now();
+        while !log.lock().unwrap().contains(&"P 190.0 45.0".to_string()) && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        // Its readback
+        thread::sleep(Duration::from_millis(400));
+        let commands = log.lock().unwrap().clone();
+        assert!(commands.len() <= 4, "{:?}", commands);
+        assert_eq!(commands[commands.len() - 2..], ["P 190.0 45.0", "p"]);
+    }
+
+    #[test]
+    fn tracking_needs_a_device() {
+        let pointing = AntennaPointing::new();
+        assert!(pointing.set_tracking(true).is_err());
+        assert!(pointing.rotator_feedback().is_err());
+        assert!(!pointing.arduino_status().connected);
+    }
+}
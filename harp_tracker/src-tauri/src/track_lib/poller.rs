@@ -0,0 +1,238 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::track_lib::aprs_parser::TelemetryChannel;
+use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::tracking_source::{SourceHealth, SourceStatus, TrackingSource};
+use crate::track_lib::tracking_type::TrackingType;
+
+//How often a sleeping poller checks whether it has been stopped
+const STOP_CHECK_MS: u64 = 250;
+
+/** Callback run on the polling thread after every update attempt, with whether a new fix arrived.
+
+The last argument tells whether the source is still running, check it after taking any lock the source is stopped under
+*/
+pub type PollHook = Arc<dyn Fn(&SourceSnapshot, bool, &dyn Fn() -> bool) + Send + Sync>;
+
+/// Copy of the state of a tracking source, published by its polling thread
+#[derive(Debug, Clone)]
+pub struct SourceSnapshot {
+    pub id: String,
+    pub tracking_type: TrackingType,
+    pub pos_time: PositionTime,
+    pub health: SourceHealth,
+    pub comment: String,
+    pub telemetry: Vec<TelemetryChannel>,
+    pub precision: Option<f64>,
+}
+
+impl SourceSnapshot {
+    pub fn capture(source: &dyn TrackingSource) -> Self {
+        Self {
+            id: source.id().to_string(),
+            tracking_type: source.tracking_type(),
+            pos_time: source.get_pos_time(),
+            health: source.health(),
+            comment: source.get_comment().to_string(),
+            telemetry: source.get_telemetry().to_vec(),
+            precision: source.precision(),
+        }
+    }
+
+    pub fn get_last_update(&self) -> u64 {
+        self.pos_time.last_update
+    }
+
+    pub fn status(&self) -> SourceStatus {
+        SourceStatus {
+            id: self.id.clone(),
+            tracking_type: self.tracking_type.to_string(),
+            last_update: self.pos_time.last_update,
+            // Re-evaluate so a source that stopped reporting turns stale between polls
+            health: self.health.evaluate(self.pos_time.last_update),
+        }
+    }
+}
+
+/// A tracking source running on its own thread at its own poll interval
+pub struct PolledSource {
+    snapshot: Arc<Mutex<SourceSnapshot>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl PolledSource {
+    /// Move the source onto a new polling thread
+    pub fn spawn(mut source: Box<dyn TrackingSource>, hook: Option<PollHook>) -> io::Result<Self> {
+        let snapshot = Arc::new(Mutex::new(SourceSnapshot::capture(source.as_ref())));
+        let stop = Arc::new(AtomicBool::new(false));
+        let interval = source.poll_interval();
+
+        let shared = snapshot.clone();
+        let stopped = stop.clone();
+        thread::Builder::new()
+            .name(format!("poll-{}-{}", source.tracking_type(), source.id()))
+            .spawn(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    let started = Instant::now();
+                    let previous = source.get_last_update();
+
+                    if let Err(e) = source.update_position() {
+                        eprintln!("{} {} poll failed: {}", source.tracking_type(), source.id(), e);
+                    }
+
+                    let current = SourceSnapshot::capture(source.as_ref());
+                    let new_fix = current.get_last_update() != 0 && current.get_last_update() != previous;
+                    *shared.lock().unwrap() = current.clone();
+
+                    if let Some(hook) = &hook {
+                        hook(&current, new_fix, &|| !stopped.load(Ordering::Relaxed));
+                    }
+
+                    // Sleep in short steps so a stop request is picked up quickly
+                    while !stopped.load(Ordering::Relaxed) && started.elapsed() < interval {
+                        thread::sleep(Duration::from_millis(STOP_CHECK_MS).min(interval.saturating_sub(started.elapsed())));
+                    }
+                }
+            })?;
+
+        Ok(Self { snapshot, stop })
+    }
+
+    pub fn snapshot(&self) -> SourceSnapshot {
+        self.snapshot.lock().unwrap().clone()
+    }
+
+    /// Ask the polling thread to exit after its current update
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for PolledSource {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Counts its updates, a new fix arrives every second update
+    struct StubSource {
+        updates: Arc<AtomicUsize>,
+        pos_time: PositionTime,
+        interval: Duration,
+    }
+
+    impl StubSource {
+        fn spawn(interval: Duration, hook: Option<PollHook>) -> (PolledSource, Arc<AtomicUsize>) {
+            let updates = Arc::new(AtomicUsize::new(0));
+            let source = StubSource { updates: updates.clone(), pos_time: PositionTime::new(), interval };
+            (PolledSource::spawn(Box::new(source), hook).unwrap(), updates)
+        }
+    }
+
+    impl TrackingSource for StubSource {
+        fn id(&self) -> &str {
+            "STUB"
+        }
+
+        fn tracking_type(&self) -> TrackingType {
+            TrackingType::APRS
+        }
+
+        fn update_position(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+            let count = self.updates.fetch_add(1, Ordering::SeqCst) + 1;
+            self.pos_time.update(40.0, -88.0, 1000.0, 1000 + count as u64 / 2, 0.0, 0.0);
+            Ok(())
+        }
+
+        fn get_pos_time(&self) -> PositionTime {
+            self.pos_time.clone()
+        }
+
+        fn get_last_update(&self) -> u64 {
+            self.pos_time.last_update
+        }
+
+        fn health(&self) -> SourceHealth {
+            SourceHealth::new()
+        }
+
+        fn poll_interval(&self) -> Duration {
+            self.interval
+        }
+    }
+
+    #[test]
+    fn hook_reports_new_fixes_only_when_the_fix_changes() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let recorded = events.clone();
+        let hook: PollHook = Arc::new(move |snapshot: &SourceSnapshot, new_fix: bool, running: &dyn Fn() -> bool| {
+            assert!(running());
+            recorded.lock().unwrap().push((snapshot.get_last_update(), new_fix));
+        });
+        let (poller, _updates) = StubSource::spawn(Duration::from_millis(10), Some(hook));
+        thread::sleep(Duration::from_millis(150));
+        poller.stop();
+        thread::sleep(Duration::from_millis(50));
+
+        let events = events.lock().unwrap();
+        assert!(events.len() >= 4, "only {} polls", events.len());
+        let mut previous = 0;
+        for &(last_update, new_fix) in events.iter() {
+            assert_eq!(new_fix, last_update != previous, "fix {} after {}", last_update, previous);
+            previous = last_update;
+        }
+    }
+
+    #[test]
+    fn snapshot_follows_the_latest_update() {
+        let (poller, updates) = StubSource::spawn(Duration::from_millis(10), None);
+        thread::sleep(Duration::from_millis(100));
+        poller.stop();
+        thread::sleep(Duration::from_millis(50));
+
+        let count = updates.load(Ordering::SeqCst);
+        assert!(count > 1);
+        let snapshot = poller.snapshot();
+        assert_eq!(snapshot.id, "STUB");
+        assert_eq!(snapshot.get_last_update(), 1000 + count as u64 / 2);
+        assert_eq!(snapshot.pos_time.lat, 40.0);
+    }
+
+    #[test]
+    fn stops_polling_once_dropped() {
+        let (poller, updates) = StubSource::spawn(Duration::from_millis(10), None);
+        thread::sleep(Duration::from_millis(50));
+        drop(poller);
+        // Let an update already under way finish
+        thread::sleep(Duration::from_millis(20));
+        let count = updates.load(Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(2 * STOP_CHECK_MS));
+        assert_eq!(updates.load(Ordering::SeqCst), count);
+        // The thread has exited and dropped its source
+        assert_eq!(Arc::strong_count(&updates), 1);
+    }
+
+    #[test]
+    fn a_sleeping_poller_exits_soon_after_stop() {
+        let (poller, updates) = StubSource::spawn(Duration::from_secs(60), None);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(updates.load(Ordering::SeqCst), 1);
+        poller.stop();
+        thread::sleep(Duration::from_millis(2 * STOP_CHECK_MS));
+        assert_eq!(updates.load(Ordering::SeqCst), 1);
+        assert_eq!(Arc::strong_count(&updates), 1);
+    }
+}
@@ -1,8 +1,14 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
+use crate::track_lib::kalman::KalmanFilter;
 
 
 
+//Defaults of the estimators working over the fix history
+pub const DEFAULT_LAST_N: usize = 10;
+pub const DEFAULT_MEDIAN_WINDOW_SECS: u64 = 120;
+pub const DEFAULT_EWMA_TAU_SECS: f64 = 30.0;
+
 /** Struct to handle position and time data.
 
 lat -> Latitude 
@@ -12,8 +18,12 @@ lon -> Longitude
 alt -> Altitude in meters
 
 last_update -> Unix timestamp of the last update
+
+horiz_vel, vert_vel -> Ground speed and vertical rate in m/s
+
+heading -> Course over ground in degrees from true north, None if unknown
 */
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PositionTime{
     
     pub lat: f64,
@@ -22,22 +32,60 @@ pub struct PositionTime{
     pub last_update: u64,
     pub horiz_vel: f64,
     pub vert_vel: f64,
+    #[serde(default)]
+    pub heading: Option<f64>,
     
 }
 
 
 
+/** Estimation method of the tracked position.
+
+Average, Median, Recent -> Over the latest fix of each source
+
+Kalman -> Filter over every fix received
+
+Weighted -> Fusion of the latest fix of each source by quality, done by the Tracker which knows the sources
+
+LastNMean -> Mean of the last N fixes in the history
+
+WindowMedian -> Median of the fixes of the last given seconds
+
+Ewma -> Exponentially weighted moving average with the given time constant in seconds
+*/
 #[derive(Debug, Clone, Copy)]
 pub enum EstimationType {
     Average,
     Median,
-    Recent
-
-    // Future additions:
-    // Kalman,
-    // LastNMean,
+    Recent,
+    Kalman,
+    Weighted,
+    LastNMean(usize),
+    WindowMedian(u64),
+    Ewma(f64),
 }
 
+impl EstimationType {
+    /// Method selected by its name in the UI, the history methods with their default parameters. Unknown names are `Recent`
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "Average" => EstimationType::Average,
+            "Median" => EstimationType::Median,
+            "Recent" => EstimationType::Recent,
+            "Kalman" => EstimationType::Kalman,
+            "Weighted" => EstimationType::Weighted,
+            "LastNMean" => EstimationType::LastNMean(DEFAULT_LAST_N),
+            "WindowMedian" => EstimationType::WindowMedian(DEFAULT_MEDIAN_WINDOW_SECS),
+            "EWMA" => EstimationType::Ewma(DEFAULT_EWMA_TAU_SECS),
+            _ => EstimationType::Recent,
+        }
+    }
+
+    /// True for the methods computed over the fix history rather than the latest fix of each source
+    pub fn uses_history(&self) -> bool {
+        matches!(self, EstimationType::LastNMean(_) | EstimationType::WindowMedian(_) | EstimationType::Ewma(_))
+    }
+}
 
 
 impl PositionTime {
@@ -45,11 +93,11 @@ impl PositionTime {
 
     //------------------------Initializing Functions------------------------
     pub fn new() -> Self {
-        Self {lat:0.0, lon:0.0, alt:0.0, last_update:0, horiz_vel:0.0, vert_vel:0.0}
+        Self {lat:0.0, lon:0.0, alt:0.0, last_update:0, horiz_vel:0.0, vert_vel:0.0, heading:None}
     }
 
     pub fn new_with_value(lat:f64,lon:f64,alt:f64,last_update:u64,horiz_vel:f64,vert_vel:f64) -> Self {
-        Self {lat, lon, alt, last_update, horiz_vel, vert_vel}
+        Self {lat, lon, alt, last_update, horiz_vel, vert_vel, heading: None}
     }
     
     pub fn update(&mut self, lat:f64, lon:f64, alt: f64, last_update:u64, horiz_vel:f64, vert_vel:f64){
@@ -59,6 +107,8 @@ impl PositionTime {
         self.last_update = last_update;
         self.horiz_vel = horiz_vel;
         self.vert_vel = vert_vel;
+        // Sources that know the course set it after the update
+        self.heading = None;
     }
 
 
@@ -75,6 +125,12 @@ impl PositionTime {
             EstimationType::Average => Some(PositionTime::average(data)),
             EstimationType::Median => Some(PositionTime::median(data)),
             EstimationType::Recent => Some(PositionTime::recent(data)),
+            EstimationType::Kalman => KalmanFilter::smooth(&data),
+            // Without the sources of the fixes there is nothing to weigh
+            EstimationType::Weighted => Some(PositionTime::recent(data)),
+            EstimationType::LastNMean(n) => Some(PositionTime::last_n_mean(data, n)),
+            EstimationType::WindowMedian(window) => Some(PositionTime::window_median(data, window)),
+            EstimationType::Ewma(tau) => PositionTime::ewma(data, tau),
         }
     }
 
@@ -117,7 +173,7 @@ impl PositionTime {
             1 => sorted[0].clone(),
             2 => Self::average(vec![sorted[0].clone(), sorted[1].clone()]),
             _ => {
-                if len % 2 == 0 {
+                if len.is_multiple_of(2) {
                     let mid1 = sorted[len / 2 - 1].clone();
                     let mid2 = sorted[len / 2].clone();
                     Self::average(vec![mid1, mid2])
@@ -165,6 +221,79 @@ pub fn recent(pos_time: Vec<PositionTime>) -> PositionTime{
     best.clone()
     }
 
+    //------------------------History Estimation Functions------------------------
+
+    /// Mean of the `n` most recent fixes, timestamped with the newest one
+    pub fn last_n_mean(pos_time: Vec<PositionTime>, n: usize) -> PositionTime {
+        let sorted = Self::sorted(pos_time);
+        let window = &sorted[sorted.len().saturating_sub(n.max(1))..];
+        Self::combine(window, |values| values.iter().sum::<f64>() / values.len() as f64)
+    }
+
+    /// Component-wise median of the fixes within `window` seconds of the newest one
+    pub fn window_median(pos_time: Vec<PositionTime>, window: u64) -> PositionTime {
+        let sorted = Self::sorted(pos_time);
+        let newest = sorted.last().map_or(0, |p| p.last_update);
+        let start = sorted.partition_point(|p| p.last_update + window < newest);
+        Self::combine(&sorted[start..], |values| {
+            let mut values = values.to_vec();
+            values.sort_by(f64::total_cmp);
+            let mid = values.len() / 2;
+            if values.len().is_multiple_of(2) { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] }
+        })
+    }
+
+    /// Exponentially weighted moving average in time, each fix weighted by `1 - exp(-dt / tau)`. None without fixes
+    pub fn ewma(pos_time: Vec<PositionTime>, tau: f64) -> Option<PositionTime> {
+        let sorted = Self::sorted(pos_time);
+        let (first, rest) = sorted.split_first()?;
+        let mut smoothed = first.clone();
+        for fix in rest {
+            let dt = fix.last_update.saturating_sub(smoothed.last_update) as f64;
+            // Fixes of the same second from different sources get an equal share
+            let alpha = if dt > 0.0 && tau > 0.0 { 1.0 - (-dt / tau).exp() } else { 0.5 };
+            let blend = |old: f64, new: f64| old + alpha * (new - old);
+
+            smoothed.lat = blend(smoothed.lat, fix.lat);
+            smoothed.lon = blend(smoothed.lon, fix.lon);
+            smoothed.alt = blend(smoothed.alt, fix.alt);
+            // Zero velocities are missing, not measured
+            if fix.horiz_vel != 0.0 {
+                smoothed.horiz_vel = if smoothed.horiz_vel == 0.0 { fix.horiz_vel } else { blend(smoothed.horiz_vel, fix.horiz_vel) };
+            }
+            if fix.vert_vel != 0.0 {
+                smoothed.vert_vel = if smoothed.vert_vel == 0.0 { fix.vert_vel } else { blend(smoothed.vert_vel, fix.vert_vel) };
+            }
+            smoothed.heading = fix.heading.or(smoothed.heading);
+            smoothed.last_update = fix.last_update;
+        }
+        Some(smoothed)
+    }
+
+    /// Reduce each component of the fixes with `reduce`, leaving out missing (zero) velocities
+    fn combine(pos_time: &[PositionTime], reduce: impl Fn(&[f64]) -> f64) -> PositionTime {
+        let component = |get: fn(&PositionTime) -> f64, skip_zero: bool| {
+            let values: Vec<f64> = pos_time.iter().map(get).filter(|v| !skip_zero || *v != 0.0).collect();
+            if values.is_empty() { 0.0 } else { reduce(&values) }
+        };
+        PositionTime::new_with_value(
+            component(|p| p.lat, false),
+            component(|p| p.lon, false),
+            component(|p| p.alt, false),
+            pos_time.iter().map(|p| p.last_update).max().unwrap_or(0),
+            component(|p| p.horiz_vel, true),
+            component(|p| p.vert_vel, true),
+        )
+    }
+
+    fn sorted(pos_time: Vec<PositionTime>) -> Vec<PositionTime> {
+        let mut sorted = pos_time;
+        if Self::check_sort(&sorted) {
+            Self::quick_sort(&mut sorted);
+        }
+        sorted
+    }
+
     //------------------------Sort Functions------------------------
 
     pub fn quick_sort(pos_time: &mut [PositionTime]) {
@@ -195,7 +324,7 @@ pub fn recent(pos_time: Vec<PositionTime>) -> PositionTime{
         Self::quick_sort(&mut right[1..]); //Skip pivot
     }
 
-    fn check_sort(pos_time: &Vec<PositionTime>) -> bool{
+    fn check_sort(pos_time: &[PositionTime]) -> bool{
         // Returns true if array needs sorting (is NOT sorted)
         // Returns false if array is already sorted
         if pos_time.len() <= 1 {
@@ -207,7 +336,66 @@ pub fn recent(pos_time: Vec<PositionTime>) -> PositionTime{
                 return true; // Found an inversion, array needs sorting
             }
         }
-        return false; // Array is already sorted
+        false // Array is already sorted
+    }
+
+}
+
+impl Default for PositionTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(time: u64, alt: f64) -> PositionTime {
+        PositionTime::new_with_value(40.0, -80.0, alt, time, 0.0, 0.0)
+    }
+
+    #[test]
+    fn ewma_of_nothing_is_none() {
+        assert!(PositionTime::ewma(vec![], 30.0).is_none());
+        assert!(PositionTime::return_valid_pos_time(vec![], EstimationType::Ewma(30.0)).is_none());
+    }
+
+    #[test]
+    fn ewma_weighs_by_elapsed_time() {
+        assert_eq!(PositionTime::ewma(vec![fix(100, 1000.0)], 30.0).unwrap().alt, 1000.0);
+
+        // One time constant later the new fix gets 1 - 1/e of the weight
+        let smoothed = PositionTime::ewma(vec![fix(130, 2000.0), fix(100, 1000.0)], 30.0).unwrap();
+        assert!((smoothed.alt - (1000.0 + 1000.0 * (1.0 - (-1.0f64).exp()))).abs() < 1e-9);
+        assert_eq!(smoothed.last_update, 130);
+
+        // Fixes of the same second share the weight
+        let same_second = PositionTime::ewma(vec![fix(100, 1000.0), fix(100, 2000.0)], 30.0).unwrap();
+        assert_eq!(same_second.alt, 1500.0);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn ewma_keeps_measured_velocities() {
+        let mut moving = fix(110, 1000.0);
+        moving.vert_vel = 5.0;
+        let smoothed = PositionTime::ewma(vec![moving, fix(120, 1050.0)], 30.0).unwrap();
+        assert_eq!(smoothed.vert_vel, 5.0);
+    }
+
+    #[test]
+    fn last_n_mean_uses_the_newest_fixes() {
+        let fixes = vec![fix(130, 400.0), fix(100, 100.0), fix(120, 300.0), fix(110, 200.0)];
+        let mean = PositionTime::last_n_mean(fixes, 2);
+        assert_eq!(mean.alt, 350.0);
+        assert_eq!(mean.last_update, 130);
+    }
+
+    #[test]
+    fn window_median_ignores_old_fixes_and_outliers() {
+        let fixes = vec![fix(0, 9000.0), fix(100, 1000.0), fix(110, 5000.0), fix(120, 1020.0), fix(130, 1010.0)];
+        let median = PositionTime::window_median(fixes, 60);
+        assert_eq!(median.alt, 1015.0);
+        assert_eq!(median.last_update, 130);
+    }
+}
@@ -1,3 +1,4 @@
+use super::super::flight_phase::FlightPhase;
 use super::super::position_time::PositionTime;
 use std::error::Error;
 
@@ -82,13 +83,32 @@ impl PredictionManager {
         &self.params
     }
     
-    /// Run prediction using the selected predictor
+    /// Run prediction using the selected predictor, in the mode matching the flight phase
     pub fn run_prediction(
         &mut self,
         current_pos: &PositionTime,
         predictor: &dyn Predictor,
+        phase: FlightPhase,
     ) -> Result<PredictionResult, Box<dyn Error>> {
-        let result = predictor.predict(current_pos, &self.params)?;
+        let mut params = self.params.clone();
+        match phase {
+            // Still climbing, possibly past the expected burst altitude
+            FlightPhase::PreLaunch | FlightPhase::Ascent => {
+                params.burst_altitude = params.burst_altitude.max(current_pos.alt + 1.0);
+            }
+            // No more climbing: descent from the current position, as if the balloon burst now when floating
+            FlightPhase::Float | FlightPhase::Descent => {
+                params.burst_altitude = current_pos.alt + 1.0;
+            }
+            // Nothing left to predict, the payload is where it landed
+            FlightPhase::Landed => {
+                let result = PredictionResult { ascent: vec![], burst: None, landing: Some(current_pos.clone()), descent: vec![] };
+                self.last_result = Some(result.clone());
+                return Ok(result);
+            }
+        }
+
+        let result = predictor.predict(current_pos, &params)?;
         self.last_result = Some(result.clone());
         Ok(result)
     }
@@ -108,6 +108,7 @@ impl SondeHubPredictor {
             last_update: h.timestamp,
             horiz_vel: 0.0,
             vert_vel: 0.0,
+            heading: None,
         })
     }
 
@@ -155,7 +156,7 @@ impl SondeHubPredictor {
 
         let mut launch_lon = current_pos.lon;
         if launch_lon < 0.0 {
-            launch_lon = 360.0 + launch_lon;
+            launch_lon += 360.0;
         }
 
         let request = PredictionRequest {
@@ -194,7 +195,6 @@ impl SondeHubPredictor {
         let mut ascent = Vec::new();
         let mut descent = Vec::new();
         let mut burst: Option<PositionTime> = None;
-        let mut landing: Option<PositionTime> = None;
 
         for stage in &pred_stages {
             let is_ascent = stage.stage.to_lowercase().contains("ascent");
@@ -204,7 +204,7 @@ impl SondeHubPredictor {
                 // Normalize longitude
                 let mut lon = point.longitude;
                 if lon > 180.0 {
-                    lon = lon - 360.0;
+                    lon -= 360.0;
                 }
 
                 // Parse the datetime string to u64 timestamp
@@ -216,11 +216,12 @@ impl SondeHubPredictor {
                 // Map TawhiriPoint to internal PositionTime
                 let pt = PositionTime {
                     lat: point.latitude,
-                    lon: lon,
+                    lon,
                     alt: point.altitude,
                     last_update: ts,
                     horiz_vel: 0.0,
                     vert_vel: 0.0,
+                    heading: None,
                 };
 
                 if is_ascent {
@@ -234,7 +235,7 @@ impl SondeHubPredictor {
             }
         }
 
-        landing = descent.last().cloned();
+        let landing = descent.last().cloned();
 
         Ok(PredictionResult { ascent, burst, descent, landing })
     }
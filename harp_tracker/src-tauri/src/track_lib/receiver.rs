@@ -0,0 +1,57 @@
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+
+use crate::track_lib::{aprs_is::AprsIs, horus::Horus, kiss::KissTnc, rfd::{self, Rfd}, stream_link::StreamLink, tracking_source::TrackingSource, ukhas::Ukhas};
+
+/** Settings of a receiver added from the connections panel, stored with the flight so resuming it restarts the receiver.
+
+Payloads polled from the internet (aprs.fi, SondeHub, Iridium) are stored as the flight's callsigns and IMEIs instead
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReceiverConfig {
+    AprsIs { server: String, login: String, passcode: i32, call_sign: String },
+    Kiss { link: StreamLink, call_sign: String },
+    Horus { port: u16, call_sign: String },
+    Ukhas { link: StreamLink, call_sign: String, field_names: Vec<String> },
+    Rfd { port: String, baud: u32, layout: Vec<String> },
+}
+
+impl ReceiverConfig {
+    /// Create the tracking source described by the settings
+    pub fn build(&self) -> Result<Box<dyn TrackingSource>, Box<dyn Error>> {
+        Ok(match self {
+            ReceiverConfig::AprsIs { server, login, passcode, call_sign } => Box::new(AprsIs::new(server, login, *passcode, call_sign)),
+            ReceiverConfig::Kiss { link, call_sign } => Box::new(KissTnc::new(link.clone(), call_sign)),
+            ReceiverConfig::Horus { port, call_sign } => Box::new(Horus::new(*port, call_sign)),
+            ReceiverConfig::Ukhas { link, call_sign, field_names } => Box::new(Ukhas::new(link.clone(), call_sign, field_names.clone())),
+            ReceiverConfig::Rfd { port, baud, layout } => {
+                rfd::check_layout(layout)?;
+                Box::new(Rfd::new(port, *baud, layout.clone()))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_json() {
+        let configs = vec![
+            ReceiverConfig::Kiss { link: StreamLink::Tcp("localhost:8001".to_string()), call_sign: "KD9ABC-11".to_string() },
+            ReceiverConfig::Ukhas {
+                link: StreamLink::Serial { port: "/dev/ttyUSB0".to_string(), baud: 9600 },
+                call_sign: "HARP".to_string(),
+                field_names: vec!["Battery".to_string()],
+            },
+            ReceiverConfig::Horus { port: 55672, call_sign: "HORUS".to_string() },
+        ];
+        for config in configs {
+            let json = serde_json::to_string(&config).unwrap();
+            assert_eq!(serde_json::from_str::<ReceiverConfig>(&json).unwrap(), config);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use std::{
+    error::Error,
+    io::{ErrorKind, Read},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::track_lib::aprs_parser::TelemetryChannel;
+use crate::track_lib::horus::timestamp_from_utc_time;
+use crate::track_lib::look_angle::ground_distance_m;
+use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::stream_link::{LineBuffer, StreamLink, MAX_LINE_LEN};
+use crate::track_lib::tracking_source::{SourceHealth, TrackingSource};
+use crate::track_lib::tracking_type::TrackingType;
+
+//Factory default baud rate of the RFD900 serial interface
+pub const DEFAULT_BAUD: u32 = 57600;
+
+//Frames are pushed by the modem, so drain it often
+const POLL_INTERVAL_SECS: u64 = 1;
+
+//Range error of a GPS fix at HDOP 1 (m), scales the `hdop` column into a precision
+const GPS_UERE_M: f64 = 5.0;
+
+/** Struct holding a decoded telemetry frame.
+
+time -> `hh:mm:ss` UTC or unix timestamp, None if the layout has no time column
+
+ground_speed, vert_speed -> m/s, None if the frame does not carry them
+
+hdop -> Horizontal dilution of precision of the payload GPS, None if the frame does not carry it
+
+sensors -> Every other numeric column, named after the layout
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct RfdFrame {
+    pub time: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+    pub ground_speed: Option<f64>,
+    pub vert_speed: Option<f64>,
+    pub hdop: Option<f64>,
+    pub sensors: Vec<TelemetryChannel>,
+}
+
+/// Check a frame layout before listening to the modem.
+/// The columns depend on the flight software of each payload, so the layout comes from the user
+/// (eg. `time,lat,lon,alt,sats,temp`) rather than a built-in frame format
+pub fn check_layout(layout: &[String]) -> Result<(), Box<dyn Error>> {
+    for column in ["lat", "lon", "alt"] {
+        if !layout.iter().any(|c| c == column) {
+            return Err(format!("RFD frame layout needs a {} column", column).into());
+        }
+    }
+    Ok(())
+}
+
+impl RfdFrame {
+    /// Parse a comma separated frame according to `layout`, columns named `""` or `_` are skipped
+    pub fn parse(line: &str, layout: &[String]) -> Result<Self, Box<dyn Error>> {
+        let fields: Vec<&str> = line.trim().split(',').map(str::trim).collect();
+        if fields.len() < layout.len() {
+            return Err(format!("RFD frame with {} fields, expected {}", fields.len(), layout.len()).into());
+        }
+
+        let mut frame = RfdFrame { time: None, lat: f64::NAN, lon: f64::NAN, alt: f64::NAN, ground_speed: None, vert_speed: None, hdop: None, sensors: vec![] };
+        for (name, field) in layout.iter().zip(fields) {
+            match name.as_str() {
+                "" | "_" => {}
+                "time" => frame.time = Some(field.to_string()),
+                "lat" => frame.lat = field.parse()?,
+                "lon" => frame.lon = field.parse()?,
+                "alt" => frame.alt = field.parse()?,
+                // Missing speeds are derived from the previous fix by the source
+                "ground_speed" => frame.ground_speed = field.parse().ok(),
+                "vert_speed" => frame.vert_speed = field.parse().ok(),
+                "hdop" => frame.hdop = field.parse().ok(),
+                _ => {
+                    // Sensor that failed to read on the payload, keep the rest of the frame
+                    if let Ok(value) = field.parse() {
+                        frame.sensors.push(TelemetryChannel { name: name.clone(), unit: String::new(), value });
+                    }
+                }
+            }
+        }
+
+        if frame.lat.is_nan() || frame.lon.is_nan() || frame.alt.is_nan() {
+            return Err("RFD frame layout needs lat, lon and alt columns".into());
+        }
+        Ok(frame)
+    }
+
+    /// Unix timestamp of the frame, `now` when the frame has no usable time
+    fn timestamp(&self, now: u64) -> u64 {
+        match &self.time {
+            Some(time) if time.contains(':') => timestamp_from_utc_time(time, now).unwrap_or(now),
+            Some(time) => time.parse().unwrap_or(now),
+            None => now,
+        }
+    }
+}
+
+/// NEBP payload telemetry received through an RFD900 modem on a serial port
+pub struct Rfd {
+    tracking_type: TrackingType,
+    port: String,
+    link: StreamLink,
+    layout: Vec<String>,
+    stream: Option<Box<dyn Read + Send>>,
+    lines: LineBuffer,
+    position_time: PositionTime,
+    telemetry: Vec<TelemetryChannel>,
+    precision: Option<f64>,
+    health: SourceHealth,
+}
+
+impl Rfd {
+    pub fn new(port: &str, baud: u32, layout: Vec<String>) -> Self {
+        Self {
+            tracking_type: TrackingType::RFD,
+            port: port.to_string(),
+            link: StreamLink::Serial { port: port.to_string(), baud },
+            layout,
+            stream: None,
+            lines: LineBuffer::new(MAX_LINE_LEN),
+            position_time: PositionTime::new(),
+            telemetry: vec![],
+            precision: None,
+            health: SourceHealth::new(),
+        }
+    }
+
+    fn fetch_position(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.stream.is_none() {
+            self.stream = Some(self.link.open()?);
+            self.lines.clear();
+            println!("RFD modem connected: {:?}", self.link);
+        }
+
+        let stream = self.stream.as_mut().ok_or("RFD modem not connected")?;
+        let mut lines = vec![];
+        let result = self.lines.read_lines(stream, &mut lines);
+        for line in lines {
+            if let Err(e) = self.handle_line(&line) {
+                eprintln!("RFD: rejecting frame: {}", e);
+            }
+        }
+        match result {
+            // Noise on the link, the next line may be fine
+            Err(e) if e.kind() == ErrorKind::InvalidData => eprintln!("RFD: dropping {}", e),
+            Err(e) => {
+                self.stream = None;
+                return Err(format!("RFD modem: {}", e).into());
+            }
+            Ok(_) => {}
+        }
+        Ok(())
+    }
+
+    fn handle_line(&mut self, line: &str) -> Result<(), Box<dyn Error>> {
+        let frame = RfdFrame::parse(line, &self.layout)?;
+        // No fix yet on the payload GPS
+        if frame.lat == 0.0 && frame.lon == 0.0 {
+            return Ok(());
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let time = frame.timestamp(now);
+        let previous = &self.position_time;
+        let dt = time.saturating_sub(previous.last_update) as f64;
+        let has_previous = previous.last_update != 0 && dt > 0.0;
+        let horiz_vel = frame.ground_speed.unwrap_or(if has_previous {
+            ground_distance_m(previous.lat, previous.lon, frame.lat, frame.lon) / dt
+        } else {
+            0.0
+        });
+        let vert_vel = frame.vert_speed
+            .unwrap_or(if has_previous { (frame.alt - previous.alt) / dt } else { 0.0 });
+
+        self.position_time.update(frame.lat, frame.lon, frame.alt, time, horiz_vel, vert_vel);
+        self.telemetry = frame.sensors;
+        self.precision = frame.hdop.map(|hdop| hdop * GPS_UERE_M);
+
+        println!(
+            "RFD Position: Port: {}, Lat: {}, Lon: {}, Alt: {}m, Channels: {}",
+            self.port, frame.lat, frame.lon, frame.alt, self.telemetry.len()
+        );
+        Ok(())
+    }
+}
+
+impl TrackingSource for Rfd {
+    fn id(&self) -> &str {
+        &self.port
+    }
+
+    fn tracking_type(&self) -> TrackingType {
+        self.tracking_type
+    }
+
+    fn update_position(&mut self) -> Result<(), Box<dyn Error>> {
+        let result = self.fetch_position();
+        self.health.record(&result);
+        result
+    }
+
+    fn get_pos_time(&self) -> PositionTime {
+        self.position_time.clone()
+    }
+
+    fn get_last_update(&self) -> u64 {
+        self.position_time.last_update
+    }
+
+    fn health(&self) -> SourceHealth {
+        self.health.evaluate(self.position_time.last_update)
+    }
+
+    fn get_telemetry(&self) -> &[TelemetryChannel] {
+        &self.telemetry
+    }
+
+    fn precision(&self) -> Option<f64> {
+        self.precision
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(POLL_INTERVAL_SECS)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use serialport::{SerialPort, TTYPort};
+
+    fn layout(columns: &str) -> Vec<String> {
+        columns.split(',').map(str::to_string).collect()
+    }
+
+    /// Modem side of a pseudo-terminal pair, and an RFD source reading the other side
+    fn modem(columns: &str) -> (TTYPort, Rfd) {
+        let (master, slave) = TTYPort::pair().unwrap();
+        let port = slave.name().unwrap();
+        let mut rfd = Rfd::new(&port, DEFAULT_BAUD, layout(columns));
+        // Opens the port, nothing received yet
+        rfd.update_position().unwrap();
+        drop(slave);
+        (master, rfd)
+    }
+
+    fn replay(master: &mut TTYPort, rfd: &mut Rfd, frames: &str) {
+        master.write_all(frames.as_bytes()).unwrap();
+        master.flush().unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        rfd.update_position().unwrap();
+    }
+
+    #[test]
+    fn replays_frames_over_a_pty() {
+        let (mut master, mut rfd) = modem("time,lat,lon,alt,ground_speed,_,sats,temp");
+        replay(&mut master, &mut rfd, "1700000000,44.90,-93.20,1000,12.5,x,9,-5.5\r\n");
+
+        let pos = rfd.get_pos_time();
+        assert_eq!((pos.lat, pos.lon, pos.alt), (44.90, -93.20, 1000.0));
+        assert_eq!(pos.last_update, 1700000000);
+        assert_eq!(pos.horiz_vel, 12.5);
+        let names: Vec<&str> = rfd.get_telemetry().iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["sats", "temp"]);
+
+        // Noise, a short frame and a frame split over two reads
+        replay(&mut master, &mut rfd, "~~garbage~~\n1700000010,44.91\n1700000020,44.92,-93.20,");
+        assert_eq!(rfd.get_last_update(), 1700000000);
+        replay(&mut master, &mut rfd, "1100,,x,9,-6.0\n");
+        let pos = rfd.get_pos_time();
+        assert_eq!(pos.last_update, 1700000020);
+        assert_eq!(pos.alt, 1100.0);
+        assert!((pos.vert_vel - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn missing_speed_is_derived_from_the_previous_fix() {
+        let (mut master, mut rfd) = modem("time,lat,lon,alt");
+        replay(&mut master, &mut rfd, "1700000000,45.0,-93.0,1000\n1700000010,45.001,-93.0,1050\n");
+
+        let pos = rfd.get_pos_time();
+        assert!((pos.horiz_vel - 11.12).abs() < 0.01, "{}", pos.horiz_vel);
+        assert!((pos.vert_vel - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn layout_needs_a_position() {
+        assert!(check_layout(&layout("time,lat,lon,alt,temp")).is_ok());
+        assert!(check_layout(&layout("time,lat,lon,temp")).is_err());
+        assert!(RfdFrame::parse("1,2,3", &layout("lat,lon,alt,temp")).is_err());
+    }
+}
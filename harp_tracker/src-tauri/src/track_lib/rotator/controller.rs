@@ -0,0 +1,213 @@
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+//Default smallest move worth commanding (degrees)
+pub const DEFAULT_DEADBAND_DEG: f64 = 2.0;
+
+//Default shortest time between two commands, most rotators cannot follow faster
+pub const DEFAULT_MIN_INTERVAL_SECS: f64 = 2.0;
+
+//Longest time between two commands the UI can set, keeps the Duration in range
+pub const MAX_MIN_INTERVAL_SECS: f64 = 3600.0;
+
+pub trait Rotator: Send {
+    /// Short description of the rotator and how it is reached
+    fn name(&self) -> String;
+
+    /// Command the rotator to the given azimuth/elevation (degrees)
+    fn point(&mut self, azimuth: f64, elevation: f64) -> Result<(), Box<dyn Error>>;
+
+    /// Read back the actual azimuth/elevation (degrees)
+    fn position(&mut self) -> Result<(f64, f64), Box<dyn Error>>;
+}
+
+/** Struct holding the state of the rotator for the frontend.
+
+commanded -> Last (azimuth, elevation) sent to the rotator
+
+actual -> Last (azimuth, elevation) read back from the rotator
+
+park -> Position the rotator returns to when tracking stops
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct RotatorStatus {
+    pub name: String,
+    pub tracking: bool,
+    pub commanded: Option<(f64, f64)>,
+    pub actual: Option<(f64, f64)>,
+    pub park: Option<(f64, f64)>,
+    pub deadband: f64,
+    pub min_interval: f64,
+    pub last_error: Option<String>,
+}
+
+/// Drives a rotator from look angles, skipping moves smaller than the deadband and rate limiting commands
+pub struct RotatorController {
+    rotator: Box<dyn Rotator>,
+    pub tracking: bool,
+    deadband: f64,
+    min_interval: Duration,
+    last_sent: Option<Instant>,
+    commanded: Option<(f64, f64)>,
+    actual: Option<(f64, f64)>,
+    park: Option<(f64, f64)>,
+    last_error: Option<String>,
+}
+
+impl RotatorController {
+    pub fn new(rotator: Box<dyn Rotator>) -> Self {
+        Self {
+            rotator,
+            tracking: false,
+            deadband: DEFAULT_DEADBAND_DEG,
+            min_interval: Duration::from_secs_f64(DEFAULT_MIN_INTERVAL_SECS),
+            last_sent: None,
+            commanded: None,
+            actual: None,
+            park: None,
+            last_error: None,
+        }
+    }
+
+    pub fn set_limits(&mut self, deadband: f64, min_interval_secs: f64) {
+        self.deadband = deadband.max(0.0);
+        // Out of range values from the UI would panic the pointing thread
+        let min_interval_secs = if min_interval_secs.is_nan() { 0.0 } else { min_interval_secs.clamp(0.0, MAX_MIN_INTERVAL_SECS) };
+        self.min_interval = Duration::from_secs_f64(min_interval_secs);
+    }
+
+    pub fn set_park(&mut self, park: Option<(f64, f64)>) {
+        self.park = park;
+    }
+
+    /// Start or stop tracking, the rotator goes to its park position when tracking stops
+    pub fn set_tracking(&mut self, val: bool) -> Result<(), Box<dyn Error>> {
+        self.tracking = val;
+        // Let the next position through regardless of the deadband
+        self.commanded = None;
+        self.last_sent = None;
+
+        match self.park {
+            Some((azimuth, elevation)) if !val => {
+                let result = self.rotator.point(azimuth, elevation);
+                if result.is_ok() {
+                    self.commanded = Some((azimuth, elevation));
+                }
+                self.last_error = result.as_ref().err().map(|e| e.to_string());
+                result
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Read the actual position back from the rotator
+    pub fn refresh_position(&mut self) -> Result<(f64, f64), Box<dyn Error>> {
+        let result = self.rotator.position();
+        match &result {
+            Ok(position) => self.actual = Some(*position),
+            Err(e) => self.last_error = Some(e.to_string()),
+        }
+        result
+    }
+
+    /// Time left before the rate limit lets the next command through
+    pub fn next_command_in(&self) -> Duration {
+        self.last_sent.map_or(Duration::ZERO, |t| self.min_interval.saturating_sub(t.elapsed()))
+    }
+
+    /// Point the rotator at the look angles if tracking is on, then read back its position.
+    /// Returns false when the rate limit held the target back, it has to be sent again later
+    pub fn update(&mut self, azimuth: f64, elevation: f64) -> Result<bool, Box<dyn Error>> {
+        if !self.tracking {
+            return Ok(true);
+        }
+
+        // Rotators cannot look below the horizon
+        let target = (azimuth.rem_euclid(360.0), elevation.clamp(0.0, 90.0));
+        let small_move = self.commanded.is_some_and(|c| angle_between(c.0, target.0) < self.deadband && (c.1 - target.1).abs() < self.deadband);
+        if !small_move && !self.next_command_in().is_zero() {
+            return Ok(false);
+        }
+
+        let result = if small_move {
+            Ok(())
+        } else {
+            self.rotator.point(target.0, target.1).map(|_| {
+                self.commanded = Some(target);
+                self.last_sent = Some(Instant::now());
+            })
+        };
+        let result = result.and_then(|_| self.rotator.position().map(|p| self.actual = Some(p)));
+
+        self.last_error = result.as_ref().err().map(|e| e.to_string());
+        result.map(|_| true)
+    }
+
+    pub fn status(&self) -> RotatorStatus {
+        RotatorStatus {
+            name: self.rotator.name(),
+            tracking: self.tracking,
+            commanded: self.commanded,
+            actual: self.actual,
+            park: self.park,
+            deadband: self.deadband,
+            min_interval: self.min_interval.as_secs_f64(),
+            last_error: self.last_error.clone(),
+        }
+    }
+}
+
+/// Smallest difference between two azimuths (degrees)
+fn angle_between(a: f64, b: f64) -> f64 {
+    let diff = (a - b).rem_euclid(360.0);
+    diff.min(360.0 - diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::track_lib::rotator::rotctld::{tests::fake_rotctld, Rotctld};
+
+    #[test]
+    fn skips_moves_inside_the_deadband() {
+        let (address, log) = fake_rotctld(Duration::ZERO);
+        let mut controller = RotatorController::new(Box::new(Rotctld::new(&address)));
+        controller.set_limits(2.0, 0.0);
+        controller.set_tracking(true).unwrap();
+
+        assert!(controller.update(359.5, 10.0).unwrap());
+        // One degree across north is a small move, not a turn all the way round
+        assert!(controller.update(0.5, 10.5).unwrap());
+        assert!(controller.update(-0.5, 11.5).unwrap());
+        assert_eq!(controller.status().commanded, Some((359.5, 10.0)));
+        assert_eq!(controller.status().actual, Some((359.5, 10.0)));
+
+        assert!(controller.update(2.0, 10.0).unwrap());
+        assert!(controller.update(2.0, 12.5).unwrap());
+        assert_eq!(*log.lock().unwrap(), ["P 359.5 10.0", "p", "p", "p", "P 2.0 10.0", "p", "P 2.0 12.5", "p"]);
+    }
+
+    #[test]
+    fn limits_out_of_range_intervals() {
+        let (address, _log) = fake_rotctld(Duration::ZERO);
+        let mut controller = RotatorController::new(Box::new(Rotctld::new(&address)));
+        controller.set_limits(2.0, 1e20);
+        assert_eq!(controller.status().min_interval, MAX_MIN_INTERVAL_SECS);
+        controller.set_limits(2.0, f64::INFINITY);
+        assert_eq!(controller.status().min_interval, MAX_MIN_INTERVAL_SECS);
+        controller.set_limits(2.0, f64::NAN);
+        assert_eq!(controller.status().min_interval, 0.0);
+        controller.set_limits(2.0, -5.0);
+        assert_eq!(controller.status().min_interval, 0.0);
+    }
+
+    #[test]
+    fn measures_azimuth_the_short_way() {
+        assert_eq!(angle_between(359.5, 0.5), 1.0);
+        assert_eq!(angle_between(0.5, 359.5), 1.0);
+        assert_eq!(angle_between(10.0, 190.0), 180.0);
+        assert_eq!(angle_between(-10.0, 350.0), 0.0);
+    }
+}
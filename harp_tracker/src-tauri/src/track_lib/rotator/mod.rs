@@ -0,0 +1,3 @@
+pub mod controller;
+pub mod rotctld;
+pub mod serial_rotator;
\ No newline at end of file
@@ -0,0 +1,168 @@
+use std::{
+    error::Error,
+    io::{BufRead, BufReader, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use super::controller::Rotator;
+
+//Default rotctld endpoint
+pub const DEFAULT_ADDRESS: &str = "localhost:4533";
+
+//Timeouts for the rotctld connection (ms)
+const CONNECT_TIMEOUT_MS: u64 = 2000;
+const READ_TIMEOUT_MS: u64 = 1000;
+
+/// Hamlib `rotctld` client, speaking the default (non extended) protocol
+pub struct Rotctld {
+    address: String,
+    stream: Option<BufReader<TcpStream>>,
+}
+
+impl Rotctld {
+    pub fn new(address: &str) -> Self {
+        Self { address: address.to_string(), stream: None }
+    }
+
+    fn connect(&mut self) -> Result<(), Box<dyn Error>> {
+        let addr = self.address.to_socket_addrs()?.next().ok_or("Could not resolve rotctld address")?;
+        let stream = TcpStream::connect_timeout(&addr, Duration::from_millis(CONNECT_TIMEOUT_MS))?;
+        stream.set_read_timeout(Some(Duration::from_millis(READ_TIMEOUT_MS)))?;
+        stream.set_nodelay(true)?;
+        self.stream = Some(BufReader::new(stream));
+        println!("rotctld connected at {}", self.address);
+        Ok(())
+    }
+
+    /// Send a command and return its `lines` reply lines, a `RPRT` error code ends the reply early
+    fn command(&mut self, command: &str, lines: usize) -> Result<Vec<String>, Box<dyn Error>> {
+        if self.stream.is_none() {
+            self.connect()?;
+        }
+        let result = self.exchange(command, lines);
+        if result.is_err() {
+            // Start from a clean connection rather than reading a stale reply
+            self.stream = None;
+        }
+        result
+    }
+
+    fn exchange(&mut self, command: &str, lines: usize) -> Result<Vec<String>, Box<dyn Error>> {
+        let stream = self.stream.as_mut().ok_or("rotctld not connected")?;
+        stream.get_mut().write_all(format!("{}\n", command).as_bytes())?;
+
+        let mut reply = vec![];
+        while reply.len() < lines {
+            let mut line = String::new();
+            if stream.read_line(&mut line)? == 0 {
+                return Err("rotctld closed the connection".into());
+            }
+            let line = line.trim().to_string();
+            if let Some(code) = line.strip_prefix("RPRT ") {
+                if code.trim() != "0" {
+                    return Err(format!("rotctld error {} for '{}'", code.trim(), command).into());
+                }
+                break;
+            }
+            reply.push(line);
+        }
+        Ok(reply)
+    }
+}
+
+impl Rotator for Rotctld {
+    fn name(&self) -> String {
+        format!("rotctld {}", self.address)
+    }
+
+    fn point(&mut self, azimuth: f64, elevation: f64) -> Result<(), Box<dyn Error>> {
+        self.command(&format!("P {:.1} {:.1}", azimuth, elevation), 1)?;
+        Ok(())
+    }
+
+    fn position(&mut self) -> Result<(f64, f64), Box<dyn Error>> {
+        let reply = self.command("p", 2)?;
+        match reply.as_slice() {
+            [azimuth, elevation] => Ok((azimuth.parse()?, elevation.parse()?)),
+            _ => Err("Unexpected rotctld position reply".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::{
+        net::TcpListener,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    /// Stand-in for rotctld: answers `P` and `p` like Hamlib and logs every command it gets.
+    /// Each command waits `delay` first, like a rotator that is slow to answer
+    pub(crate) fn fake_rotctld(delay: Duration) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let log = Arc::new(Mutex::new(vec![]));
+
+        let commands = log.clone();
+        thread::spawn(move || {
+            let mut position = (0.0, 0.0);
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { break };
+                let mut writer = stream.try_clone().unwrap();
+                for line in BufReader::new(stream).lines() {
+                    let Ok(line) = line else { break };
+                    thread::sleep(delay);
+                    commands.lock().unwrap().push(line.clone());
+                    let reply = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                        ["P", azimuth, elevation] => match (azimuth.parse::<f64>(), elevation.parse::<f64>()) {
+                            (Ok(azimuth), Ok(elevation)) if elevation <= 90.0 => {
+                                position = (azimuth, elevation);
+                                "RPRT 0\n".to_string()
+                            }
+                            _ => "RPRT -1\n".to_string(),
+                        },
+                        ["p"] => format!("{:.6}\n{:.6}\n", position.0, position.1),
+                        _ => "RPRT -4\n".to_string(),
+                    };
+                    if writer.write_all(reply.as_bytes()).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        (address, log)
+    }
+
+    #[test]
+    fn points_and_reads_back() {
+        let (address, log) = fake_rotctld(Duration::ZERO);
+        let mut rotator = Rotctld::new(&address);
+
+        rotator.point(123.45, 30.0).unwrap();
+        assert_eq!(rotator.position().unwrap(), (123.5, 30.0));
+        assert_eq!(*log.lock().unwrap(), ["P 123.5 30.0", "p"]);
+    }
+
+    #[test]
+    fn error_code_is_reported_and_the_connection_reopened() {
+        let (address, _) = fake_rotctld(Duration::ZERO);
+        let mut rotator = Rotctld::new(&address);
+
+        let error = rotator.point(10.0, 95.0).unwrap_err();
+        assert!(error.to_string().contains("error -1"), "{}", error);
+        assert!(rotator.stream.is_none());
+
+        rotator.point(10.0, 45.0).unwrap();
+        assert_eq!(rotator.position().unwrap(), (10.0, 45.0));
+    }
+
+    #[test]
+    fn unreachable_rotctld_fails() {
+        // Nothing listens on a port freed right away
+        let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+        assert!(Rotctld::new(&address).position().is_err());
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    error::Error,
+    io::{ErrorKind, Read, Write},
+    time::{Duration, Instant},
+};
+
+use serialport::SerialPort;
+
+use super::controller::Rotator;
+
+//Default baud rate of GS-232 and EasyComm controllers
+pub const DEFAULT_BAUD: u32 = 9600;
+
+//Read timeout of the serial port and longest wait for a position reply (ms)
+const READ_TIMEOUT_MS: u64 = 100;
+const REPLY_TIMEOUT_MS: u64 = 1000;
+
+/// Serial rotator command set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotatorProtocol {
+    /// Yaesu GS-232A/B: `W aaa eee`, position read with `C2`
+    Gs232,
+    /// EasyComm II: `AZ123.4 EL45.6`, position read with `AZ EL`
+    EasyComm,
+}
+
+impl RotatorProtocol {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace(['-', ' ', '_'], "").as_str() {
+            "gs232" | "gs232a" | "gs232b" | "yaesu" => Some(RotatorProtocol::Gs232),
+            "easycomm" | "easycomm2" | "easycommii" => Some(RotatorProtocol::EasyComm),
+            _ => None,
+        }
+    }
+
+    fn point_command(&self, azimuth: f64, elevation: f64) -> String {
+        match self {
+            RotatorProtocol::Gs232 => format!("W{:03.0} {:03.0}\r", azimuth, elevation),
+            RotatorProtocol::EasyComm => format!("AZ{:.1} EL{:.1}\n", azimuth, elevation),
+        }
+    }
+
+    fn position_command(&self) -> &'static str {
+        match self {
+            RotatorProtocol::Gs232 => "C2\r",
+            RotatorProtocol::EasyComm => "AZ EL\n",
+        }
+    }
+
+    /// Parse a position reply: `+0aaa+0eee` (GS-232A), `AZ=aaa  EL=eee` (GS-232B) or `AZ123.4 EL45.6` (EasyComm)
+    pub fn parse_position(&self, reply: &str) -> Option<(f64, f64)> {
+        let reply = reply.trim();
+        let numbers: Vec<f64> = match self {
+            RotatorProtocol::Gs232 if reply.starts_with('+') => reply
+                .split('+')
+                .filter(|s| !s.is_empty())
+                .filter_map(|s| s.trim().parse().ok())
+                .collect(),
+            _ => {
+                let upper = reply.to_ascii_uppercase();
+                ["AZ", "EL"].iter()
+                    .filter_map(|key| {
+                        let rest = upper[upper.find(key)? + 2..].trim_start_matches(['=', ' ']);
+                        let end = rest.find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-')).unwrap_or(rest.len());
+                        rest[..end].parse().ok()
+                    })
+                    .collect()
+            }
+        };
+        match numbers.as_slice() {
+            [azimuth, elevation] => Some((*azimuth, *elevation)),
+            _ => None,
+        }
+    }
+}
+
+/// Rotator controller on a serial port
+pub struct SerialRotator {
+    protocol: RotatorProtocol,
+    port: String,
+    baud: u32,
+    serial: Option<Box<dyn SerialPort>>,
+}
+
+impl SerialRotator {
+    pub fn new(protocol: RotatorProtocol, port: &str, baud: u32) -> Self {
+        Self { protocol, port: port.to_string(), baud, serial: None }
+    }
+
+    fn serial(&mut self) -> Result<&mut Box<dyn SerialPort>, Box<dyn Error>> {
+        if self.serial.is_none() {
+            let serial = serialport::new(&self.port, self.baud)
+                .timeout(Duration::from_millis(READ_TIMEOUT_MS))
+                .open()?;
+            println!("{:?} rotator connected at {} ({} baud)", self.protocol, self.port, self.baud);
+            self.serial = Some(serial);
+        }
+        self.serial.as_mut().ok_or_else(|| "Rotator not connected".into())
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), Box<dyn Error>> {
+        let result = self.serial()?.write_all(command.as_bytes());
+        if result.is_err() {
+            self.serial = None;
+        }
+        Ok(result?)
+    }
+
+    /// Read one reply line, waiting at most `REPLY_TIMEOUT_MS`
+    fn read_reply(&mut self) -> Result<String, Box<dyn Error>> {
+        let started = Instant::now();
+        let mut reply = vec![];
+        let mut byte = [0u8; 1];
+        while started.elapsed() < Duration::from_millis(REPLY_TIMEOUT_MS) {
+            match self.serial()?.read(&mut byte) {
+                Ok(1) if byte[0] == b'\r' || byte[0] == b'\n' => {
+                    if !reply.is_empty() {
+                        return Ok(String::from_utf8_lossy(&reply).to_string());
+                    }
+                }
+                Ok(1) => reply.push(byte[0]),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::TimedOut || e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => {
+                    self.serial = None;
+                    return Err(e.into());
+                }
+            }
+        }
+        Err("Rotator did not answer the position request".into())
+    }
+}
+
+impl Rotator for SerialRotator {
+    fn name(&self) -> String {
+        format!("{:?} {}", self.protocol, self.port)
+    }
+
+    fn point(&mut self, azimuth: f64, elevation: f64) -> Result<(), Box<dyn Error>> {
+        let command = self.protocol.point_command(azimuth, elevation);
+        self.send(&command)
+    }
+
+    fn position(&mut self) -> Result<(f64, f64), Box<dyn Error>> {
+        // Drop anything left over, eg. the echo of the last command
+        if let Ok(serial) = self.serial() {
+            let _ = serial.clear(serialport::ClearBuffer::Input);
+        }
+        self.send(self.protocol.position_command())?;
+        let reply = self.read_reply()?;
+        self.protocol.parse_position(&reply).ok_or_else(|| format!("Unexpected rotator reply: {}", reply).into())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+    };
+    use serialport::TTYPort;
+    use crate::track_lib::pointing::AntennaPointing;
+
+    /// Controller side of a pseudo-terminal pair, answering position requests with `reply`
+    /// and logging every command. A None reply leaves the rotator silent
+    fn controller(reply: Option<&'static str>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let (mut master, slave) = TTYPort::pair().unwrap();
+        let port = slave.name().unwrap();
+        master.set_timeout(Duration::from_millis(50)).unwrap();
+        let log = Arc::new(Mutex::new(vec![]));
+
+        let commands = log.clone();
+        thread::spawn(move || {
+            // Keeps the pty open until the test ends
+            let _slave = slave;
+            let mut line = vec![];
+            let mut byte = [0u8; 1];
+            loop {
+                match master.read(&mut byte) {
+                    Ok(1) if byte[0] == b'\r' || byte[0] == b'\n' => {
+                        let command = String::from_utf8_lossy(&line).to_string();
+                        line.clear();
+                        if let (Some(reply), "C2" | "AZ EL") = (reply, command.as_str()) {
+                            let _ = master.write_all(reply.as_bytes());
+                        }
+                        commands.lock().unwrap().push(command);
+                    }
+                    Ok(1) => line.push(byte[0]),
+                    Ok(_) => {}
+                    Err(e) if e.kind() == ErrorKind::TimedOut => {}
+                    Err(_) => break,
+                }
+            }
+        });
+        (port, log)
+    }
+
+    #[test]
+    fn gs232_commands_and_feedback() {
+        let (port, log) = controller(Some("AZ=123  EL=045\r\n"));
+        let mut rotator = SerialRotator::new(RotatorProtocol::Gs232, &port, DEFAULT_BAUD);
+
+        rotator.point(123.4, 45.2).unwrap();
+        assert_eq!(rotator.position().unwrap(), (123.0, 45.0));
+        assert_eq!(*log.lock().unwrap(), ["W123 045", "C2"]);
+    }
+
+    #[test]
+    fn easycomm_commands_and_feedback() {
+        let (port, log) = controller(Some("AZ123.4 EL45.2\n"));
+        let mut rotator = SerialRotator::new(RotatorProtocol::EasyComm, &port, DEFAULT_BAUD);
+
+        rotator.point(123.4, 45.2).unwrap();
+        assert_eq!(rotator.position().unwrap(), (123.4, 45.2));
+        assert_eq!(*log.lock().unwrap(), ["AZ123.4 EL45.2", "AZ EL"]);
+    }
+
+    #[test]
+    fn silent_rotator_only_holds_up_the_pointing_thread() {
+        let (port, log) = controller(None);
+        let pointing = AntennaPointing::new();
+        pointing.connect_rotator(Box::new(SerialRotator::new(RotatorProtocol::Gs232, &port, DEFAULT_BAUD))).unwrap();
+        pointing.set_rotator_limits(0.0, 0.0).unwrap();
+        pointing.set_tracking(true).unwrap();
+
+        // Each readback waits for the reply timeout, new targets are queued meanwhile
+        let started = Instant::now();
+        for azimuth in 0..10 {
+            pointing.point(azimuth as f64 * 10.0, 10.0);
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert!(started.elapsed() < Duration::from_millis(500));
+
+        thread::sleep(Duration::from_millis(REPLY_TIMEOUT_MS * 2 + 500));
+        let status = pointing.rotator_status().unwrap();
+        assert_eq!(status.commanded, Some((90.0, 10.0)));
+        assert!(status.last_error.unwrap().contains("did not answer"));
+        assert!(log.lock().unwrap().iter().filter(|c| c.starts_with('W')).count() <= 3);
+    }
+
+    #[test]
+    fn replies_and_names() {
+        assert_eq!(RotatorProtocol::Gs232.parse_position("+0123+0045"), Some((123.0, 45.0)));
+        assert_eq!(RotatorProtocol::EasyComm.parse_position("AZ-1.5 EL0.0"), Some((-1.5, 0.0)));
+        assert_eq!(RotatorProtocol::Gs232.parse_position("?>"), None);
+        assert_eq!(RotatorProtocol::from_name("GS-232B"), Some(RotatorProtocol::Gs232));
+        assert_eq!(RotatorProtocol::from_name("easycomm ii"), Some(RotatorProtocol::EasyComm));
+    }
+}
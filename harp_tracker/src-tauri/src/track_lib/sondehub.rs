@@ -1,14 +1,17 @@
 use chrono::{DateTime, Utc};
 use reqwest::blocking::Client;
 use serde_json::Value;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::tracking_source::{SourceHealth, TrackingSource};
 use crate::track_lib::tracking_type::TrackingType;
 
+//Seconds between two SondeHub requests
+const POLL_INTERVAL_SECS: u64 = 20;
+
 #[derive(Clone)]
 pub struct SondeHub {
-    active: bool,
     tracking_type: TrackingType,
     base_url: String,
     call_sign: String,
@@ -16,23 +19,24 @@ pub struct SondeHub {
     position_time: PositionTime,
     ground_speed: f64,
     comment: String,
+    health: SourceHealth,
 }
 
 impl SondeHub {
     pub fn new(call_sign: &str) -> Self {
         Self {
-            active: true,
             tracking_type:TrackingType::SondeHub,
             base_url: "https://api.v2.sondehub.org/amateur?callsign=".to_string(),
             call_sign: call_sign.to_string(),
             client: Client::new(),
-            position_time: PositionTime {lat:0.0, lon:0.0, alt:0.0, last_update:0, horiz_vel:0.0, vert_vel:0.0},
+            position_time: PositionTime {lat:0.0, lon:0.0, alt:0.0, last_update:0, horiz_vel:0.0, vert_vel:0.0, heading:None},
             ground_speed: 0.0,
             comment: String::new(),
+            health: SourceHealth::new(),
         }
     }
 
-    pub fn update_position(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    fn fetch_position(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         // Build request URL using the configured base_url and callsign
         let url = format!("{}{}", self.base_url, self.call_sign);
         let response: Value = self.client.get(&url).send()?.json()?;
@@ -54,19 +58,21 @@ impl SondeHub {
                 let datetime: DateTime<Utc> = datetime.parse().unwrap_or_else(|_| Utc::now());
                 let dte = datetime.timestamp() as u64;
 
-                // Try to extract horizontal and vertical speeds from commonly used keys
-                let horiz = call["ground_speed"].as_f64()
-                    .or_else(|| call["speed"].as_f64())
-                    .or_else(|| call["ascent_rate"].as_f64())
+                // Try to extract horizontal and vertical speeds (m/s) from commonly used keys
+                let horiz = call["vel_h"].as_f64()
+                    .or_else(|| call["ground_speed"].as_f64())
                     .or_else(|| call["hspd"].as_f64())
                     .unwrap_or(0.0);
 
-                let vert = call["vertical_velocity"].as_f64()
-                    .or_else(|| call["vel_h"].as_f64())
+                let vert = call["vel_v"].as_f64()
+                    .or_else(|| call["vertical_velocity"].as_f64())
+                    .or_else(|| call["ascent_rate"].as_f64())
                     .unwrap_or(0.0);
 
                 self.ground_speed = horiz;
                 self.position_time.update(lat, lon, alt, dte, horiz, vert);
+                self.position_time.heading = call["heading"].as_f64();
+                self.comment = call["comment"].as_str().unwrap_or_default().to_string();
 
                 let current_time = SystemTime::now()
                     .duration_since(UNIX_EPOCH)
@@ -87,10 +93,6 @@ impl SondeHub {
         Err("No SondeHub telemetry data found for this callsign".into())
     }
    
-    pub fn get_pos_time(&self) -> PositionTime{
-        self.position_time.clone()
-    }
-   
     pub fn get_position(&self) -> (f64, f64, f64) {
         (self.position_time.lat, self.position_time.lon, self.position_time.alt)
     }
@@ -98,12 +100,40 @@ impl SondeHub {
     pub fn get_speed(&self) -> f64 {
         self.ground_speed
     }
+}
+
+impl TrackingSource for SondeHub {
+    fn id(&self) -> &str {
+        &self.call_sign
+    }
+
+    fn tracking_type(&self) -> TrackingType {
+        self.tracking_type
+    }
 
-    pub fn get_last_update(&self) -> u64 {
+    fn update_position(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let result = self.fetch_position();
+        self.health.record(&result);
+        result
+    }
+
+    fn get_pos_time(&self) -> PositionTime {
+        self.position_time.clone()
+    }
+
+    fn get_last_update(&self) -> u64 {
         self.position_time.last_update
     }
 
-    pub fn get_comment(&self) -> &str {
+    fn health(&self) -> SourceHealth {
+        self.health.evaluate(self.position_time.last_update)
+    }
+
+    fn get_comment(&self) -> &str {
         &self.comment
     }
-}
\ No newline at end of file
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(POLL_INTERVAL_SECS)
+    }
+}
@@ -0,0 +1,391 @@
+use std::{
+    error::Error,
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::track_lib::look_angle::GroundStation;
+use crate::track_lib::stream_link::StreamLink;
+
+//Default gpsd endpoint
+pub const DEFAULT_GPSD_ADDRESS: &str = "localhost:2947";
+
+//Default baud rate of NMEA 0183 GPS receivers
+pub const DEFAULT_NMEA_BAUD: u32 = 4800;
+
+//Timeouts for the gpsd connection (ms)
+const CONNECT_TIMEOUT_MS: u64 = 2000;
+const READ_TIMEOUT_MS: u64 = 500;
+
+//Wait before reopening a receiver that failed
+const RETRY_SECS: u64 = 2;
+
+/** Struct holding the latest location of the ground station.
+
+source -> Where the fix comes from ("Manual", "NMEA /dev/ttyUSB0", "gpsd localhost:2947")
+
+last_update -> Unix timestamp of the fix
+
+satellites -> Satellites used, when the receiver reports it
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct StationFix {
+    pub station: GroundStation,
+    pub source: String,
+    pub last_update: u64,
+    pub satellites: Option<u32>,
+}
+
+/// Serializable state of the station location for the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct StationStatus {
+    pub fix: Option<StationFix>,
+    /// Error of the GPS receiver, cleared on its next fix
+    pub last_error: Option<String>,
+}
+
+/// Keeps track of where the ground station is, from a manual entry or a live GPS receiver
+pub struct StationLocator {
+    fix: Arc<Mutex<Option<StationFix>>>,
+    last_error: Arc<Mutex<Option<String>>>,
+    // Bumped whenever the receiver is replaced or stopped, a thread from an older generation publishes nothing
+    generation: Arc<AtomicU64>,
+}
+
+impl StationLocator {
+    pub fn new() -> Self {
+        Self { fix: Arc::new(Mutex::new(None)), last_error: Arc::new(Mutex::new(None)), generation: Arc::new(AtomicU64::new(0)) }
+    }
+
+    /// Use a fixed location, stopping any GPS receiver
+    pub fn set_manual(&mut self, station: GroundStation) {
+        self.stop_receiver();
+        *self.fix.lock().unwrap() = Some(StationFix { station, source: "Manual".to_string(), last_update: now(), satellites: None });
+    }
+
+    /// Follow an NMEA 0183 GPS on a serial port
+    pub fn start_nmea(&mut self, port: &str, baud: u32) -> io::Result<()> {
+        let link = StreamLink::Serial { port: port.to_string(), baud };
+        let source = format!("NMEA {}", port);
+        self.start_receiver(source, move |fix, running| read_nmea(&link, fix, running))
+    }
+
+    /// Follow the fixes of a gpsd daemon
+    pub fn start_gpsd(&mut self, address: &str) -> io::Result<()> {
+        let address = address.to_string();
+        let source = format!("gpsd {}", address);
+        self.start_receiver(source, move |fix, running| read_gpsd(&address, fix, running))
+    }
+
+    pub fn current(&self) -> Option<GroundStation> {
+        self.fix.lock().unwrap().as_ref().map(|f| f.station)
+    }
+
+    pub fn fix(&self) -> Option<StationFix> {
+        self.fix.lock().unwrap().clone()
+    }
+
+    pub fn status(&self) -> StationStatus {
+        StationStatus { fix: self.fix(), last_error: self.last_error.lock().unwrap().clone() }
+    }
+
+    /// Run `read` on its own thread until replaced, reopening the receiver whenever it fails
+    fn start_receiver<F>(&mut self, source: String, mut read: F) -> io::Result<()>
+    where
+        F: FnMut(&mut dyn FnMut(GroundStation, Option<u32>), &dyn Fn() -> bool) -> Result<(), Box<dyn Error>> + Send + 'static,
+    {
+        let own = self.stop_receiver();
+        let generation = self.generation.clone();
+
+        let fix = self.fix.clone();
+        let last_error = self.last_error.clone();
+        thread::Builder::new()
+            .name(format!("station-{}", source))
+            .spawn(move || {
+                let running = || generation.load(Ordering::SeqCst) == own;
+                while running() {
+                    // Checked while holding the lock, so a manual location or a newer receiver is never overwritten
+                    let mut publish = |station: GroundStation, satellites: Option<u32>| {
+                        let mut fix = fix.lock().unwrap();
+                        if running() {
+                            *fix = Some(StationFix { station, source: source.clone(), last_update: now(), satellites });
+                            *last_error.lock().unwrap() = None;
+                        }
+                    };
+                    if let Err(e) = read(&mut publish, &running) {
+                        eprintln!("Station {}: {}", source, e);
+                        let mut last_error = last_error.lock().unwrap();
+                        if running() {
+                            *last_error = Some(e.to_string());
+                        }
+                        drop(last_error);
+                        thread::sleep(Duration::from_secs(RETRY_SECS));
+                    }
+                }
+            })?;
+        Ok(())
+    }
+
+    /// Retire the current receiver thread and return the new generation
+    fn stop_receiver(&mut self) -> u64 {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        *self.last_error.lock().unwrap() = None;
+        generation
+    }
+}
+
+impl Default for StationLocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for StationLocator {
+    fn drop(&mut self) {
+        self.stop_receiver();
+    }
+}
+
+//------------------------NMEA 0183------------------------
+
+/// Read NMEA sentences from the GPS until it fails or the receiver is stopped
+fn read_nmea(link: &StreamLink, publish: &mut dyn FnMut(GroundStation, Option<u32>), running: &dyn Fn() -> bool) -> Result<(), Box<dyn Error>> {
+    let mut stream = link.open()?;
+    println!("Station GPS connected: {:?}", link);
+    let mut parser = NmeaParser::new();
+    let mut pending = vec![];
+    let mut buffer = [0u8; 512];
+
+    while running() {
+        match stream.read(&mut buffer) {
+            Ok(0) => return Err("GPS closed the connection".into()),
+            Ok(n) => pending.extend_from_slice(&buffer[..n]),
+            Err(e) if e.kind() == ErrorKind::TimedOut || e.kind() == ErrorKind::WouldBlock => continue,
+            Err(e) => return Err(e.into()),
+        }
+        while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            if let Some(station) = parser.parse(&String::from_utf8_lossy(&line)) {
+                publish(station, parser.satellites);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// GGA/RMC parser, RMC carries no altitude so the last GGA altitude is kept
+pub struct NmeaParser {
+    alt: f64,
+    pub satellites: Option<u32>,
+}
+
+impl NmeaParser {
+    pub fn new() -> Self {
+        Self { alt: 0.0, satellites: None }
+    }
+
+    /// Parse one sentence, returning the location if it is a valid GGA or RMC fix
+    pub fn parse(&mut self, line: &str) -> Option<GroundStation> {
+        let sentence = line.trim().strip_prefix('$')?;
+        let (body, checksum) = sentence.rsplit_once('*')?;
+        let expected = u8::from_str_radix(checksum.get(..2)?, 16).ok()?;
+        if body.bytes().fold(0u8, |acc, b| acc ^ b) != expected {
+            return None;
+        }
+
+        let fields: Vec<&str> = body.split(',').collect();
+        // Talker id (GP, GN, GL, ...) followed by the sentence type
+        match fields.first()?.get(2..)? {
+            "GGA" => {
+                // Fix quality 0 means no fix
+                if fields.get(6)?.parse::<u32>().ok()? == 0 {
+                    return None;
+                }
+                let lat = nmea_degrees(fields.get(2)?, fields.get(3)?)?;
+                let lon = nmea_degrees(fields.get(4)?, fields.get(5)?)?;
+                self.satellites = fields.get(7)?.parse().ok();
+                self.alt = fields.get(9)?.parse().unwrap_or(self.alt);
+                Some(GroundStation::new(lat, lon, self.alt))
+            }
+            "RMC" => {
+                // Status V means the receiver has no valid fix
+                if *fields.get(2)? != "A" {
+                    return None;
+                }
+                let lat = nmea_degrees(fields.get(3)?, fields.get(4)?)?;
+                let lon = nmea_degrees(fields.get(5)?, fields.get(6)?)?;
+                Some(GroundStation::new(lat, lon, self.alt))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Default for NmeaParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `ddmm.mmmm` or `dddmm.mmmm` plus hemisphere to signed decimal degrees
+fn nmea_degrees(value: &str, hemisphere: &str) -> Option<f64> {
+    let value: f64 = value.parse().ok()?;
+    let degrees = (value / 100.0).trunc() + (value % 100.0) / 60.0;
+    match hemisphere {
+        "N" | "E" => Some(degrees),
+        "S" | "W" => Some(-degrees),
+        _ => None,
+    }
+}
+
+//------------------------gpsd------------------------
+
+/// Read TPV reports from gpsd until it fails or the receiver is stopped
+fn read_gpsd(address: &str, publish: &mut dyn FnMut(GroundStation, Option<u32>), running: &dyn Fn() -> bool) -> Result<(), Box<dyn Error>> {
+    let addr = address.to_socket_addrs()?.next().ok_or("Could not resolve gpsd address")?;
+    let mut stream = TcpStream::connect_timeout(&addr, Duration::from_millis(CONNECT_TIMEOUT_MS))?;
+    stream.set_read_timeout(Some(Duration::from_millis(READ_TIMEOUT_MS)))?;
+    stream.write_all(b"?WATCH={\"enable\":true,\"json\":true};\n")?;
+    println!("Station gpsd connected at {}", address);
+
+    let mut reader = BufReader::new(stream);
+    let mut satellites = None;
+    let mut line = String::new();
+    while running() {
+        match reader.read_line(&mut line) {
+            Ok(0) => return Err("gpsd closed the connection".into()),
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::TimedOut || e.kind() == ErrorKind::WouldBlock => continue,
+            Err(e) => return Err(e.into()),
+        }
+        // A partial line stays in `line` until the rest arrives
+        if !line.ends_with('\n') {
+            continue;
+        }
+        let report: Value = serde_json::from_str(line.trim()).unwrap_or(Value::Null);
+        line.clear();
+
+        match report["class"].as_str() {
+            Some("SKY") => {
+                if let Some(used) = report["uSat"].as_u64() {
+                    satellites = Some(used as u32);
+                }
+            }
+            // Mode 2 is a 2D fix, 3 a 3D fix
+            Some("TPV") if report["mode"].as_u64().unwrap_or(0) >= 2 => {
+                if let (Some(lat), Some(lon)) = (report["lat"].as_f64(), report["lon"].as_f64()) {
+                    let alt = report["altMSL"].as_f64().or_else(|| report["alt"].as_f64()).unwrap_or(0.0);
+                    publish(GroundStation::new(lat, lon, alt), satellites);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    // Wrap a sentence body with its `$` and checksum
+    fn sentence(body: &str) -> String {
+        format!("${}*{:02X}\r\n", body, body.bytes().fold(0u8, |acc, b| acc ^ b))
+    }
+
+    #[test]
+    fn parses_gga_with_altitude_and_satellites() {
+        let mut parser = NmeaParser::new();
+        let station = parser.parse("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47").unwrap();
+        assert!((station.lat - 48.1173).abs() < 1e-6);
+        assert!((station.lon - 11.516667).abs() < 1e-6);
+        assert_eq!(station.alt, 545.4);
+        assert_eq!(parser.satellites, Some(8));
+    }
+
+    #[test]
+    fn rejects_bad_checksums() {
+        let mut parser = NmeaParser::new();
+        assert!(parser.parse("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*48").is_none());
+        // Corrupted digit with the original checksum
+        assert!(parser.parse("$GPGGA,123519,4807.038,N,01131.900,E,1,08,0.9,545.4,M,46.9,M,,*47").is_none());
+        assert!(parser.parse("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,").is_none());
+        assert!(parser.parse("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*ZZ").is_none());
+    }
+
+    #[test]
+    fn ignores_sentences_without_a_fix() {
+        let mut parser = NmeaParser::new();
+        assert!(parser.parse(&sentence("GPGGA,123519,4807.038,N,01131.000,E,0,00,,,M,,M,,")).is_none());
+        assert!(parser.parse(&sentence("GPRMC,123519,V,4807.038,N,01131.000,E,,,230394,,")).is_none());
+        assert!(parser.parse(&sentence("GPGSV,3,1,11,03,03,111,00,04,15,270,00,06,01,010,00,13,06,292,00")).is_none());
+    }
+
+    #[test]
+    fn rmc_keeps_the_gga_altitude() {
+        let mut parser = NmeaParser::new();
+        parser.parse(&sentence("GNGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,")).unwrap();
+        let station = parser.parse("$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A").unwrap();
+        assert!((station.lat - 48.1173).abs() < 1e-6);
+        assert_eq!(station.alt, 545.4);
+    }
+
+    #[test]
+    fn southern_and_western_hemispheres_are_negative() {
+        let mut parser = NmeaParser::new();
+        let station = parser.parse(&sentence("GPGGA,000000,3352.000,S,15112.000,W,1,05,1.0,10.0,M,,M,,")).unwrap();
+        assert!((station.lat + 33.866667).abs() < 1e-6);
+        assert!((station.lon + 151.2).abs() < 1e-6);
+        assert!(parser.parse(&sentence("GPGGA,000000,3352.000,X,15112.000,W,1,05,1.0,10.0,M,,M,,")).is_none());
+    }
+
+    #[test]
+    fn replaced_receiver_stops_publishing() {
+        // Stand-in gpsd that keeps reporting the same fix
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                thread::spawn(move || {
+                    let report = "{\"class\":\"TPV\",\"mode\":3,\"lat\":10.0,\"lon\":20.0,\"altMSL\":30.0}\n";
+                    while stream.write_all(report.as_bytes()).is_ok() {
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                });
+            }
+        });
+
+        let mut locator = StationLocator::new();
+        locator.start_gpsd(&address).unwrap();
+        for _ in 0..200 {
+            if locator.current().is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(locator.fix().unwrap().source, format!("gpsd {}", address));
+
+        locator.set_manual(GroundStation::new(1.0, 2.0, 3.0));
+        thread::sleep(Duration::from_millis(100));
+        let fix = locator.fix().unwrap();
+        assert_eq!(fix.source, "Manual");
+        assert_eq!(fix.station.lat, 1.0);
+    }
+}
@@ -0,0 +1,179 @@
+use std::{env, error::Error, fs, path::{Path, PathBuf}};
+
+use serde::{Deserialize, Serialize};
+
+//Folder of the app under the OS data and config directories
+const APP_DIR_NAME: &str = "HARPTracker";
+
+//Settings file under the config directory, holds the user chosen data folder
+const SETTINGS_FILE: &str = "storage.json";
+
+//Environment variable (also read from .env) overriding the default data folder
+pub const DATA_DIR_ENV: &str = "HARP_DATA_DIR";
+
+/// Where the data folder comes from, in order of precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DataDirSource {
+    Custom,
+    Environment,
+    Default,
+}
+
+/** Struct holding where the data lives for the frontend.
+
+data_dir -> Folder of the flight database and exports, None if it could not be determined
+
+source -> Whether the folder was chosen by the user, the environment or the OS
+
+error -> Why the folder is unusable, None if it is usable
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageStatus {
+    pub data_dir: Option<PathBuf>,
+    pub source: DataDirSource,
+    pub error: Option<String>,
+}
+
+/// Persisted storage settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct StorageSettings {
+    data_dir: Option<PathBuf>,
+}
+
+/// Resolves the data folder: the user override, then `HARP_DATA_DIR`, then the OS data directory
+pub struct StorageLocation {
+    custom: Option<PathBuf>,
+    // Last folder written to and why it was unusable, None if it was
+    checked: Option<(PathBuf, Option<String>)>,
+}
+
+impl StorageLocation {
+    /// Load the user override saved by a previous run, if any
+    pub fn load() -> Self {
+        let custom = Self::settings_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| match serde_json::from_str::<StorageSettings>(&text) {
+                Ok(settings) => settings.data_dir,
+                Err(e) => {
+                    eprintln!("Ignoring unreadable storage settings: {}", e);
+                    None
+                }
+            });
+        Self { custom, checked: None }
+    }
+
+    /// Folder the data goes to and where that choice comes from, without touching the disk
+    pub fn resolve(&self) -> (Option<PathBuf>, DataDirSource) {
+        if let Some(custom) = &self.custom {
+            return (Some(custom.clone()), DataDirSource::Custom);
+        }
+        if let Some(dir) = env::var_os(DATA_DIR_ENV).filter(|d| !d.is_empty()) {
+            return (Some(PathBuf::from(dir)), DataDirSource::Environment);
+        }
+        (dirs::data_dir().map(|d| d.join(APP_DIR_NAME)), DataDirSource::Default)
+    }
+
+    /// The data folder, created and checked for writing on first use
+    pub fn data_dir(&mut self) -> Result<PathBuf, Box<dyn Error>> {
+        let (dir, _) = self.resolve();
+        let dir = dir.ok_or("No data directory on this system, choose a data folder in the settings")?;
+        // A folder that failed is tried again, the user may have fixed it since
+        if !matches!(&self.checked, Some((checked, None)) if *checked == dir) {
+            self.check(&dir)?;
+        }
+        Ok(dir)
+    }
+
+    /// Use `dir` for the data from now on and remember it, None goes back to the default
+    pub fn set_custom(&mut self, dir: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = &dir {
+            if !dir.is_absolute() {
+                return Err(format!("Data folder must be an absolute path: {}", dir.display()).into());
+            }
+            self.check(dir)?;
+        }
+        let settings_path = Self::settings_path().ok_or("No config directory to save the data folder in")?;
+        if let Some(parent) = settings_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&settings_path, serde_json::to_string_pretty(&StorageSettings { data_dir: dir.clone() })?)?;
+        self.custom = dir;
+        Ok(())
+    }
+
+    /// Where the data goes and whether it is usable, from the last write check without touching the disk again
+    pub fn status(&self) -> StorageStatus {
+        let (data_dir, source) = self.resolve();
+        let error = match (&data_dir, &self.checked) {
+            (None, _) => Some("No data directory on this system".to_string()),
+            (Some(dir), Some((checked, error))) if checked == dir => error.clone(),
+            // Not written to yet, only report what can be seen without creating anything
+            (Some(dir), _) => match fs::metadata(dir) {
+                Ok(meta) if !meta.is_dir() => Some(format!("Data folder {} is not a folder", dir.display())),
+                Ok(meta) if meta.permissions().readonly() => Some(format!("Data folder {} is not writable", dir.display())),
+                _ => None,
+            },
+        };
+        StorageStatus { data_dir, source, error }
+    }
+
+    //------------------------Helper Functions------------------------
+
+    /// Prepare the folder and remember the outcome for `status`
+    fn check(&mut self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        let result = Self::prepare(dir);
+        self.checked = Some((dir.to_path_buf(), result.as_ref().err().map(|e| e.to_string())));
+        result
+    }
+
+    fn settings_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join(APP_DIR_NAME).join(SETTINGS_FILE))
+    }
+
+    /// Create the folder and check it can be written to
+    fn prepare(dir: &Path) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(dir).map_err(|e| format!("Unable to create data folder {}: {}", dir.display(), e))?;
+        let probe = dir.join(".write_test");
+        fs::write(&probe, b"").map_err(|e| format!("Data folder {} is not writable: {}", dir.display(), e))?;
+        // The probe is only there to test the permissions
+        let _ = fs::remove_file(probe);
+        Ok(())
+    }
+}
+
+impl Default for StorageLocation {
+    fn default() -> Self {
+        Self::load()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_does_not_write() {
+        let dir = env::temp_dir().join(format!("harp-storage-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let mut storage = StorageLocation { custom: Some(dir.clone()), checked: None };
+
+        let status = storage.status();
+        assert_eq!(status.source, DataDirSource::Custom);
+        assert!(status.error.is_none());
+        assert!(!dir.exists());
+
+        assert_eq!(storage.data_dir().unwrap(), dir);
+        assert!(dir.is_dir());
+        assert!(storage.status().error.is_none());
+
+        // A file where the folder should be is reported without creating anything
+        let file = dir.join("not-a-folder");
+        fs::write(&file, b"").unwrap();
+        storage.custom = Some(file.clone());
+        assert!(storage.status().error.is_some());
+        assert!(storage.data_dir().is_err());
+        assert!(storage.status().error.is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    error::Error,
+    io::{self, ErrorKind, Read},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+//Timeouts for the receiver link (ms)
+const CONNECT_TIMEOUT_MS: u64 = 2000;
+const READ_TIMEOUT_MS: u64 = 200;
+
+//Upper bound of bytes drained from a receiver in a single poll, one that never pauses must not hold its poller
+pub const MAX_READ_PER_POLL: usize = 64 * 1024;
+
+//Longest line kept while waiting for its end, anything longer is noise
+pub const MAX_LINE_LEN: usize = 512;
+
+/// How to reach a receiver that streams raw bytes (KISS TNC, UKHAS gateway, ...)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum StreamLink {
+    /// TCP socket, eg. Direwolf on `localhost:8001`
+    Tcp(String),
+    /// Serial port
+    Serial { port: String, baud: u32 },
+}
+
+impl StreamLink {
+    /// Open the link, reads time out after a short delay so pollers can drain it without blocking
+    pub fn open(&self) -> Result<Box<dyn Read + Send>, Box<dyn Error>> {
+        match self {
+            StreamLink::Tcp(address) => {
+                let addr = address.to_socket_addrs()?.next().ok_or("Could not resolve receiver address")?;
+                let stream = TcpStream::connect_timeout(&addr, Duration::from_millis(CONNECT_TIMEOUT_MS))?;
+                stream.set_read_timeout(Some(Duration::from_millis(READ_TIMEOUT_MS)))?;
+                Ok(Box::new(stream))
+            }
+            StreamLink::Serial { port, baud } => {
+                let serial = serialport::new(port, *baud)
+                    .timeout(Duration::from_millis(READ_TIMEOUT_MS))
+                    .open()?;
+                Ok(Box::new(serial))
+            }
+        }
+    }
+}
+
+/// Read what the link has buffered, at most `MAX_READ_PER_POLL` bytes, handing each chunk to `handle`. Returns the bytes read
+///
+/// Stops at the first error of `handle`, a link closed by the other end is an `UnexpectedEof` error
+pub fn drain<R: Read + ?Sized>(stream: &mut R, mut handle: impl FnMut(&[u8]) -> io::Result<()>) -> io::Result<usize> {
+    let mut buffer = [0u8; 4096];
+    let mut total = 0;
+    while total < MAX_READ_PER_POLL {
+        match stream.read(&mut buffer) {
+            Ok(0) => return Err(io::Error::new(ErrorKind::UnexpectedEof, "closed the connection")),
+            Ok(n) => {
+                total += n;
+                handle(&buffer[..n])?;
+            }
+            Err(e) if e.kind() == ErrorKind::TimedOut || e.kind() == ErrorKind::WouldBlock => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(total)
+}
+
+/// Splits the bytes of a receiver into lines, keeping an unfinished line for the next poll
+pub struct LineBuffer {
+    pending: Vec<u8>,
+    max_len: usize,
+}
+
+impl LineBuffer {
+    pub fn new(max_len: usize) -> Self {
+        Self { pending: vec![], max_len }
+    }
+
+    /// Drain the link like `drain`, collecting the complete lines into `lines` as they arrive
+    pub fn read_lines<R: Read + ?Sized>(&mut self, stream: &mut R, lines: &mut Vec<String>) -> io::Result<usize> {
+        drain(stream, |bytes| self.push(bytes, lines))
+    }
+
+    /// Add received bytes, collecting the lines they complete into `lines` without their end of line and skipping empty ones
+    ///
+    /// An unfinished line longer than `max_len` is dropped with an `InvalidData` error
+    pub fn push(&mut self, bytes: &[u8], lines: &mut Vec<String>) -> io::Result<()> {
+        self.pending.extend_from_slice(bytes);
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n' || b == b'\r') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line[..end]);
+            if !line.trim().is_empty() {
+                lines.push(line.into_owned());
+            }
+        }
+        if self.pending.len() > self.max_len {
+            self.pending.clear();
+            return Err(io::Error::new(ErrorKind::InvalidData, format!("line longer than {} bytes", self.max_len)));
+        }
+        Ok(())
+    }
+
+    /// Bytes of the unfinished line
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_lines_across_pushes() {
+        let mut buffer = LineBuffer::new(16);
+        let mut lines = vec![];
+        buffer.push(b"one\r\ntw", &mut lines).unwrap();
+        assert_eq!(lines, vec!["one"]);
+        assert_eq!(buffer.pending_len(), 2);
+        buffer.push(b"o\n\nthree\r", &mut lines).unwrap();
+        assert_eq!(lines, vec!["one", "two", "three"]);
+        assert_eq!(buffer.pending_len(), 0);
+    }
+
+    #[test]
+    fn drops_a_line_that_never_ends() {
+        let mut buffer = LineBuffer::new(16);
+        let mut lines = vec![];
+        let error = buffer.push(b"ok\nxxxxxxxxxxxxxxxxxxxx", &mut lines).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert_eq!(lines, vec!["ok"]);
+        assert_eq!(buffer.pending_len(), 0);
+    }
+
+    #[test]
+    fn bounds_the_read_of_an_endless_link() {
+        let mut chunks = 0;
+        let total = drain(&mut io::repeat(b'x'), |_| {
+            chunks += 1;
+            Ok(())
+        }).unwrap();
+        assert!((MAX_READ_PER_POLL..MAX_READ_PER_POLL + 4096).contains(&total));
+        assert!(chunks > 1);
+
+        let error = drain(&mut io::empty(), |_| Ok(())).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+}
@@ -1,432 +1,694 @@
-use std::{fs::{self, File, OpenOptions}, io, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
-use io::Write;
-
-
-use chrono::Utc;
-// use serialport::{COMPort, SerialPort};
-
-use crate::track_lib::{position_time::EstimationType, pred::sondhub_predictor::SondeHubPredictor, tracking_type::TrackingType};
-
-use super::{aprs::APRS, iridium::Iridium, sondehub::SondeHub, position_time::PositionTime};
-
-
-
-
-pub struct Tracker {
-    active: bool,
-    
-    aprs: Vec<Option<APRS>>,
-    iridium: Vec<Option<Iridium>>,
-    sondehub: Vec<Option<SondeHub>>,
-    
-    //Arduino and calculations modules have been commented out or removed to be worked on in the future
-    // arduino: Option<Arduino>,
-    // tracking: bool,
-    predictor: Option<SondeHubPredictor>,
-
-    position_time: PositionTime,
-    csv_path: Option<PathBuf>
-}
-
-impl Tracker{
-    
-    
-    // ------------------------Initializing Functions------------------------
-    
-    /// Create a new Tracker
-    pub fn new() -> Self{
-        Self { active:false, aprs: vec![],  iridium: vec![], sondehub: vec![], predictor: Some(SondeHubPredictor::new()), position_time: PositionTime {lat:0.0, lon:0.0, alt:0.0, last_update:0, horiz_vel:0.0, vert_vel:0.0}, csv_path: None}
-    }
-
-    /// Create a new APRS Module
-    pub fn new_aprs(&mut self, api_key: &str, call_sign: &str){
-        self.aprs.push(Some(APRS::new(api_key, call_sign)));
-        // Also create a corresponding SondeHub
-        self.sondehub.push(Some(SondeHub::new(call_sign)));
-        if !self.active{self.csv_path = self.create_folder()}
-        self.active = true;
-    }
-
-    /// Create a new Iridium Module
-    pub fn new_iridium(&mut self, base_url: &str, modem: &str){
-        self.iridium.push(Some(Iridium::new(base_url, modem)));
-        if !self.active{self.csv_path = self.create_folder()}
-        self.active = true;
-    }
-
-    /// Create a new SondeHub Module
-    pub fn new_sondehub(&mut self, call_sign: &str){
-        self.sondehub.push(Some(SondeHub::new(call_sign)));
-        if !self.active{self.csv_path = self.create_folder()}
-        self.active = true;
-    }
-
-    // /// Create a new Arduino Module [In Progress]
-    // pub fn new_arduino(&mut self, serial: Option<Arc<Mutex<Box<dyn SerialPort + Send>>>>,com: Option<COMPort>){
-    //     self.arduino = Some(Arduino::new(serial, com));
-    // }
-
-    // /// Setup Arduino
-    // pub fn setup_arduino(&mut self) -> Result<(), Box<dyn std::error::Error>>{
-    //     self.arduino.as_mut().unwrap().setup()
-    // }
-
-    /// Function to set tracking on or off
-    // pub fn set_tracking(&mut self, val: bool){
-    //     self.arduino.as_mut().unwrap().set_tracking(val);            
-    // }
-
-
-    // ------------------------Tracking Modules Return Functions------------------------
-
-
-    pub fn return_aprs(&mut self) -> Vec<Option<APRS>>{
-        self.aprs.clone()
-    }
-    pub fn return_iridium(&mut self) -> Vec<Option<Iridium>>{
-        self.iridium.clone()
-    }
-    pub fn return_sondehub(&mut self) -> Vec<Option<SondeHub>>{
-        self.sondehub.clone()
-    }
-    // pub fn is_arduino_active(&self) -> bool {
-    //     if self.aprs.is_some(){
-    //         return self.arduino.as_ref().unwrap().active;
-    //     }
-    //     return false;
-    // }
-
-    // ------------------------Update Helper Functions------------------------
-
-    fn update_aprs(&mut self) -> Vec<Result<(), Box<dyn std::error::Error + 'static>>>{
-
-        if self.aprs.len() > 0 {
-            let mut e = vec![];
-
-            for aprs in &mut self.aprs{
-                if aprs.is_some(){
-                    e.push(aprs.as_mut().unwrap().update_position());
-                }
-            }
-            return e;
-        }
-        // Ok(())
-        return vec![Err("Error updating APRS".into())]
-    }
-
-    fn update_iridium(&mut self) -> Vec<Result<(), Box<dyn std::error::Error + 'static>>> {
-        let mut e = vec![];
-        for iridium in &mut self.iridium{
-            if iridium.is_some(){
-                e.push(iridium.as_mut().unwrap().update_position());
-            }
-        }
-        if e.is_empty() {
-            e.push(Err("Error updating Iridium".into()));
-        }
-        e
-    }
-
-    fn update_sondehub(&mut self) -> Vec<Result<(), Box<dyn std::error::Error + 'static>>> {
-        let mut e = vec![];
-        for sondehub in &mut self.sondehub{
-            if sondehub.is_some(){
-                e.push(sondehub.as_mut().unwrap().update_position());
-            }
-        }
-        if e.is_empty() {
-            e.push(Err("Error updating Sondehub".into()));
-        }
-        e
-    }
-
-    // fn update_arduino(&mut self) -> Result<(), Box<(dyn std::error::Error + 'static)>>{
-    //     if self.arduino.is_some(){
-    //         return self.arduino.as_mut().unwrap().update();
-    //     }
-    //     // Ok(())
-    //     return Err("Error updating Arduino".into());
-    // }        
-
-    /// Internal function to update all modules on the tracker and return the errors as a vector
-    fn update_tracker(&mut self) -> Vec<Result<(), Box<dyn std::error::Error + 'static>>> {
-
-        let mut v: Vec<Result<(), Box<dyn std::error::Error + 'static>>> = vec![];
-        
-        // Ensure APRS runs first so SondeHub updates can use APRS-provided velocities
-        v.extend(self.update_aprs());
-        v.extend(self.update_sondehub());
-        v.extend(self.update_iridium());
-        // v.push(self.update_arduino());
-
-        v
-
-    }
-
-    /// Creates a data storage folder, if not already existing
-    fn create_folder(&self) -> Option<PathBuf> {
-        let folder_name = format!("Launch Data");
-
-        let current_dir = std::env::current_dir().expect("Could not determine current directory");
-        let folder_path = current_dir.join(folder_name);
-
-        fs::create_dir_all(&folder_path).expect("Unable to create csv data directory");
-        
-        let file_path: PathBuf = folder_path.join(format!("data{:?}.csv", Utc::now().timestamp()));
-
-        let mut f = File::create(&file_path).expect("Unable to create CSV file");
-
-        f.write("track_type,lat,lon,alt,horiz_vel,vert_vel,time\n".as_bytes()).expect("Unable to write to CSV file");
-
-        println!("Folder created at: {:?}", file_path);
-        Some(file_path)
-    }
-
-    /// Function to write the data to csv
-    fn write_to_csv(track_type:TrackingType,pos_time:PositionTime,csv_path:Option<PathBuf>) -> io::Result<TrackingType> {
-        let mut file = OpenOptions::new()
-            .append(true)
-            .open(csv_path.unwrap())?;
-        writeln!(
-            file,
-            "{},{:.6},{:.6},{:.2},{:.2},{:.2},{}",
-            track_type, pos_time.lat, pos_time.lon, pos_time.alt, pos_time.horiz_vel, pos_time.vert_vel, pos_time.last_update
-        )?;
-        Ok(track_type)
-        
-    }
-
-
-
-
-    // ------------------------Public Functions------------------------
-    
-    pub fn update(&mut self) -> Vec<Box<dyn std::error::Error>> {
-        //Error collection to display to users (only soft errors)
-        let mut err: Vec<Box<dyn std::error::Error>> = vec![];
-
-        //Collect soft errors from update_tracker
-        for opt in self.update_tracker() {
-            if let Err(e) = opt {
-                err.push(e);
-            }
-        }
-
-        let mut positions_src: Vec<(PositionTime, TrackingType)> = vec![];
-        eprintln!("DEBUG: APRS vector length: {}", self.aprs.len());
-        if self.aprs.len() > 0 {
-            for (idx, aprs) in self.aprs.iter().enumerate(){
-                eprintln!("DEBUG: APRS[{}] is_some: {}", idx, aprs.is_some());
-                if let Some(aprs) = aprs {
-                    let t = aprs.get_last_update();
-                    eprintln!("DEBUG: APRS[{}] last_update: {}", idx, t);
-                    if t != 0 {
-                        let pt = aprs.get_pos_time();
-                        eprintln!("DEBUG: APRS[{}] pos_time: {:?}", idx, pt);
-                        positions_src.push((pt.clone(), TrackingType::APRS));
-                        eprintln!("{:?}", Self::write_to_csv(TrackingType::APRS, pt, self.csv_path.clone()));
-                    }
-                }
-
-            }
-        }
-
-        eprintln!("DEBUG: SondeHub vector length: {}", self.sondehub.len());
-        if self.sondehub.len() > 0 {
-            for (idx, sondehub) in self.sondehub.iter().enumerate(){
-                eprintln!("DEBUG: SondeHub[{}] is_some: {}", idx, sondehub.is_some());
-                if let Some(sondehub) = sondehub {
-                    let t = sondehub.get_last_update();
-                    eprintln!("DEBUG: SondeHub[{}] last_update: {}", idx, t);
-                    if t != 0 {
-                        let pt = sondehub.get_pos_time();
-                        eprintln!("DEBUG: SondeHub[{}] pos_time: {:?}", idx, pt);
-                        positions_src.push((pt.clone(), TrackingType::SondeHub));
-                        eprintln!("{:?}", Self::write_to_csv(TrackingType::SondeHub, pt, self.csv_path.clone()));
-                    }
-                }
-            }
-        }
-
-        if self.iridium.len() > 0 {
-            for (idx, iridium) in self.iridium.iter().enumerate(){
-                eprintln!("DEBUG: Iridium[{}] is_some: {}", idx, iridium.is_some());
-                if let Some(iridium) = iridium {
-                    let t = iridium.get_last_update();
-                    eprintln!("DEBUG: Iridium[{}] last_update: {}", idx, t);
-                    if t != 0 {
-                        let pt = iridium.get_pos_time();
-                        eprintln!("DEBUG: Iridium[{}] pos_time: {:?}", idx, pt);
-                        positions_src.push((pt.clone(), TrackingType::Iridium));
-                        eprintln!("{:?}", Self::write_to_csv(TrackingType::Iridium, pt, self.csv_path.clone()));
-                    }
-                }
-            }
-        }
-
-        eprintln!("Collected {} positions for filtering", positions_src.len());
-
-        // Sort by time ascending
-        positions_src.sort_by_key(|(p, _)| p.last_update);
-
-        // Helper: haversine distance in meters
-        fn haversine_m(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
-            let to_rad = |deg: f64| deg * std::f64::consts::PI / 180.0;
-            let r = 6371000.0_f64; // Earth radius in meters
-            let dlat = to_rad(lat2 - lat1);
-            let dlon = to_rad(lon2 - lon1);
-            let a = (dlat / 2.0).sin().powi(2) + to_rad(lat1).cos() * to_rad(lat2).cos() * (dlon / 2.0).sin().powi(2);
-            let c = 2.0 * a.sqrt().atan2((1.0 - a).sqrt());
-            r * c
-        }
-
-        // Compute velocities from previous point when missing; skip SondeHub if it's the first point
-        let mut positions_for_filter: Vec<PositionTime> = Vec::new();
-        for i in 0..positions_src.len() {
-            let mut curr = positions_src[i].0.clone();
-            let src = positions_src[i].1;
-            if i == 0 {
-                // first point: if it's SondeHub and lacks velocities, skip it
-                if src == TrackingType::SondeHub && curr.horiz_vel == 0.0 && curr.vert_vel == 0.0 {
-                    continue;
-                }
-                positions_for_filter.push(curr);
-                continue;
-            }
-
-            let prev = &positions_src[i - 1].0;
-            let dt = (curr.last_update as f64) - (prev.last_update as f64);
-            if dt > 0.0 {
-                if curr.horiz_vel == 0.0 {
-                    let dist = haversine_m(prev.lat, prev.lon, curr.lat, curr.lon);
-                    curr.horiz_vel = dist / dt; // m/s
-                }
-                if curr.vert_vel == 0.0 {
-                    curr.vert_vel = (curr.alt - prev.alt) / dt; // m/s
-                }
-            }
-
-            positions_for_filter.push(curr);
-        }
-
-        // Collect best velocity data from all positions at the most recent timestamp
-        let mut best_horiz_vel = 0.0;
-        let mut best_vert_vel = 0.0;
-        if let Some(most_recent_time) = positions_for_filter.last().map(|p| p.last_update) {
-            for pos in &positions_for_filter {
-                if pos.last_update == most_recent_time {
-                    if pos.horiz_vel != 0.0 && best_horiz_vel == 0.0 {
-                        best_horiz_vel = pos.horiz_vel;
-                    }
-                    if pos.vert_vel != 0.0 && best_vert_vel == 0.0 {
-                        best_vert_vel = pos.vert_vel;
-                    }
-                }
-            }
-            eprintln!("Best velocities from most recent timestamp: horiz={}, vert={}", best_horiz_vel, best_vert_vel);
-        }
-
-        let most_recent_position: Option<PositionTime> = PositionTime::return_valid_pos_time(positions_for_filter, EstimationType::Recent);
-
-        //Update struct and log to CSV if we have a new update
-        if let Some(new_pos) = most_recent_position.clone() {
-            eprintln!("Updating position_time to: {:?}", new_pos);
-            
-            // Preserve existing velocities if new position has zero velocities
-            let mut updated_pos = new_pos;
-            
-            // First try to use the best velocities from positions at the same timestamp
-            if updated_pos.horiz_vel == 0.0 {
-                if best_horiz_vel != 0.0 {
-                    updated_pos.horiz_vel = best_horiz_vel;
-                    eprintln!("Using best horiz_vel from same timestamp: {}", updated_pos.horiz_vel);
-                } else if self.position_time.horiz_vel != 0.0 {
-                    updated_pos.horiz_vel = self.position_time.horiz_vel;
-                    eprintln!("Preserving previous horiz_vel: {}", updated_pos.horiz_vel);
-                }
-            }
-            
-            if updated_pos.vert_vel == 0.0 {
-                if best_vert_vel != 0.0 {
-                    updated_pos.vert_vel = best_vert_vel;
-                    eprintln!("Using best vert_vel from same timestamp: {}", updated_pos.vert_vel);
-                } else if self.position_time.vert_vel != 0.0 {
-                    updated_pos.vert_vel = self.position_time.vert_vel;
-                    eprintln!("Preserving previous vert_vel: {}", updated_pos.vert_vel);
-                }
-            }
-            
-            self.position_time = updated_pos;
-        } else {
-            eprintln!("No valid position found to update");
-        }
-
-        err
-    }
-    pub fn get_position_with_filtering(&self, method: EstimationType) -> (f64, f64, f64, f64, f64) {
-        let mut positions: Vec<PositionTime> = vec![];
-        if self.aprs.len() > 0 {
-            for aprs in &self.aprs{
-                if let Some(aprs) = aprs {
-                    let t = aprs.get_last_update();
-                    if t != 0 {
-                        positions.push(aprs.get_pos_time());
-                    }
-                }
-
-            }
-        }
-
-
-        if self.iridium.len() > 0 {
-            for iridium in &self.iridium{
-                if let Some(iridium) = iridium {
-                    let t = iridium.get_last_update();
-                    if t != 0 {
-                        positions.push(iridium.get_pos_time());
-                    }
-                }
-            }
-        }
-
-        if self.sondehub.len() > 0 {
-            for sondehub in &self.sondehub{
-                if let Some(sondehub) = sondehub {
-                    let t = sondehub.get_last_update();
-                    if t != 0 {
-                        positions.push(sondehub.get_pos_time());
-                    }
-                }
-            }
-        }
-
-        if let Some(filtered_pos) = PositionTime::return_valid_pos_time(positions, method) {
-            (filtered_pos.lat, filtered_pos.lon, filtered_pos.alt, filtered_pos.horiz_vel, filtered_pos.vert_vel)
-        } else {
-            (self.position_time.lat, self.position_time.lon, self.position_time.alt, self.position_time.horiz_vel, self.position_time.vert_vel)
-        }
-    }
-
-
-
-
-    /// Function to print the data of the Tracker
-    pub fn print(&self){
-                // Print current position data
-                let current_time = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs();
-                
-                let age_seconds = current_time.saturating_sub(self.position_time.last_update);
-
-        println!("Latitude: {}, Longitude: {}, Altitude: {}, Last Update: {}s ago", self.position_time.lat, self.position_time.lon, self.position_time.alt, age_seconds);
-    }
-
-    // ------------------------Getter Functions------------------------
-
-    pub fn get_position(&self)->(f64,f64,f64){return (self.position_time.lat,self.position_time.lon,self.position_time.alt);}
-    pub fn get_velocities(&self) -> (f64, f64) {(self.position_time.horiz_vel, self.position_time.vert_vel)}
-    pub fn get_last_update(&self)->u64{return self.position_time.last_update;}
-
-}
\ No newline at end of file
+use std::{collections::HashMap, error::Error, path::{Path, PathBuf}, time::{SystemTime, UNIX_EPOCH}};
+
+
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::track_lib::{look_angle::{GroundStation, LookAngles}, pointing::AntennaPointing, station::{StationLocator, StationStatus}, storage::{StorageLocation, StorageStatus}, comment_parser::{telemetry_series, CommentRule, CommentRules, TelemetryLog, TelemetrySample, TelemetrySeries}, database::{FlightDatabase, FlightRecord, StoredFix}, export::FlightExport, fix_validator::{FixValidator, RejectedFix, ValidationLimits}, flight_phase::{FlightPhase, FlightPhaseDetector, FlightStatus, PhaseThresholds}, fusion::{FusedPosition, FusionInput, FusionWeights, SourceFusion}, history::FixHistory, kalman::{KalmanEstimate, KalmanFilter}, position_time::EstimationType, poller::{PollHook, PolledSource, SourceSnapshot}, tracking_source::{SourceStatus, TrackingSource}, tracking_type::TrackingType, velocity::{estimate_velocity, VelocityEstimate}};
+
+use super::{aprs::APRS, receiver::ReceiverConfig, stream_link::StreamLink, iridium::Iridium, sondehub::SondeHub, position_time::PositionTime};
+
+
+
+
+//Name of the flight database in the data folder
+const DATABASE_FILE: &str = "flights.sqlite";
+
+pub struct Tracker {
+    active: bool,
+    
+    //Registry of every active receiver, each polled on its own thread
+    sources: Vec<PolledSource>,
+    poll_hook: Option<PollHook>,
+
+    //Rules turning payload comments into sensor channels, and the channels logged so far
+    comment_rules: CommentRules,
+    telemetry: TelemetryLog,
+    
+    //Antenna tracker and rotator, pointed from the ground station at every new position by their own thread
+    pointing: AntennaPointing,
+    station: StationLocator,
+
+    //Checks dropping bad fixes, and the time of the last rejected fix of each source by type and id
+    validator: FixValidator,
+    rejected_at: HashMap<(TrackingType, String), u64>,
+
+    //Quality weighted fusion of the latest fix of each source, its last result, and whether it is the tracked position
+    fusion: SourceFusion,
+    fused: Option<FusedPosition>,
+    estimation: EstimationType,
+
+    //Phase of the flight detected from the accepted fixes
+    flight: FlightPhaseDetector,
+
+    //Ground speed, course and vertical rate over the newest accepted fixes
+    velocity: Option<VelocityEstimate>,
+
+    //Every accepted fix and the Kalman filter fed with them, whatever the selected estimation
+    history: FixHistory,
+    kalman: KalmanFilter,
+    position_time: PositionTime,
+
+    //Store of every fix, fused position, telemetry and prediction, and the flight being recorded
+    storage: StorageLocation,
+    database: Option<FlightDatabase>,
+    flight_id: Option<i64>,
+}
+
+impl Tracker{
+    
+    
+    // ------------------------Initializing Functions------------------------
+    
+    /// Create a new Tracker
+    pub fn new() -> Self{
+        Self { active:false, sources: vec![], poll_hook: None, comment_rules: CommentRules::new(), telemetry: TelemetryLog::new(), pointing: AntennaPointing::new(), station: StationLocator::new(), validator: FixValidator::new(), rejected_at: HashMap::new(), fusion: SourceFusion::new(), fused: None, estimation: EstimationType::Recent, flight: FlightPhaseDetector::new(), velocity: None, history: FixHistory::new(), kalman: KalmanFilter::new(), position_time: PositionTime {lat:0.0, lon:0.0, alt:0.0, last_update:0, horiz_vel:0.0, vert_vel:0.0, heading:None}, storage: StorageLocation::load(), database: None, flight_id: None}
+    }
+
+    /// Set the callback run by the polling threads after each update attempt
+    pub fn set_poll_hook(&mut self, hook: PollHook){
+        self.poll_hook = Some(hook);
+    }
+
+    /// Register any tracking source with the Tracker and start polling it
+    pub fn add_source(&mut self, source: Box<dyn TrackingSource>) -> Result<(), Box<dyn Error>>{
+        if self.flight_id.is_none(){self.start_default_flight()}
+        let polled = PolledSource::spawn(source, self.poll_hook.clone())
+            .map_err(|e| format!("Unable to start polling thread: {}", e))?;
+        self.sources.push(polled);
+        self.active = true;
+        Ok(())
+    }
+
+    /// Start a receiver and store its settings with the flight, so resuming the flight restarts it
+    fn add_receiver(&mut self, config: ReceiverConfig) -> Result<(), Box<dyn Error>>{
+        self.add_source(config.build()?)?;
+        if let Some(snapshot) = self.sources.last().map(|s| s.snapshot()) {
+            self.store(|db, flight| db.add_receiver(flight, snapshot.tracking_type, &snapshot.id, &config));
+        }
+        Ok(())
+    }
+
+    /// Stop the polling thread of a source. Returns false if no such source is running
+    pub fn remove_source(&mut self, tracking_type: TrackingType, id: &str) -> bool{
+        let mut removed = vec![];
+        self.sources.retain(|s| {
+            let snapshot = s.snapshot();
+            let matches = snapshot.tracking_type == tracking_type && snapshot.id.eq_ignore_ascii_case(id);
+            if matches {
+                removed.push(snapshot.id);
+            }
+            !matches
+        });
+        for source in &removed {
+            self.store(|db, flight| db.remove_receiver(flight, tracking_type, source));
+        }
+        self.active = !self.sources.is_empty();
+        !removed.is_empty()
+    }
+
+    /// Create a new APRS Module
+    pub fn new_aprs(&mut self, api_key: &str, call_sign: &str) -> Result<(), Box<dyn Error>>{
+        self.add_source(Box::new(APRS::new(api_key, call_sign)))?;
+        // Also create a corresponding SondeHub
+        self.add_source(Box::new(SondeHub::new(call_sign)))?;
+        self.store(|db, flight| db.add_payloads(flight, &[call_sign.to_string()], &[]));
+        Ok(())
+    }
+
+    /// Create a new Iridium Module
+    pub fn new_iridium(&mut self, base_url: &str, modem: &str) -> Result<(), Box<dyn Error>>{
+        self.add_source(Box::new(Iridium::new(base_url, modem)))?;
+        self.store(|db, flight| db.add_payloads(flight, &[], &[modem.to_string()]));
+        Ok(())
+    }
+
+    /// Create a new SondeHub Module
+    pub fn new_sondehub(&mut self, call_sign: &str) -> Result<(), Box<dyn Error>>{
+        self.add_source(Box::new(SondeHub::new(call_sign)))
+    }
+
+    /// Create a new APRS-IS streaming Module
+    pub fn new_aprs_is(&mut self, server: &str, login: &str, passcode: i32, call_sign: &str) -> Result<(), Box<dyn Error>>{
+        self.add_receiver(ReceiverConfig::AprsIs { server: server.to_string(), login: login.to_string(), passcode, call_sign: call_sign.to_string() })
+    }
+
+    /// Create a new KISS TNC Module for local APRS reception
+    pub fn new_kiss(&mut self, link: StreamLink, call_sign: &str) -> Result<(), Box<dyn Error>>{
+        self.add_receiver(ReceiverConfig::Kiss { link, call_sign: call_sign.to_string() })
+    }
+
+    /// Set the comment parsing rules of a callsign, an empty list removes them
+    pub fn set_comment_rules(&mut self, call_sign: &str, rules: Vec<CommentRule>) -> Result<(), String>{
+        self.comment_rules.set(call_sign, rules)
+    }
+
+    pub fn get_comment_rules(&self, call_sign: &str) -> Vec<CommentRule>{
+        self.comment_rules.get(call_sign)
+    }
+
+    /// Create a new Horus Module listening to horusdemodlib on the given UDP port
+    pub fn new_horus(&mut self, port: u16, call_sign: &str) -> Result<(), Box<dyn Error>>{
+        self.add_receiver(ReceiverConfig::Horus { port, call_sign: call_sign.to_string() })
+    }
+
+    /// Create a new UKHAS Module reading `$$` sentences from a serial or TCP receiver
+    pub fn new_ukhas(&mut self, link: StreamLink, call_sign: &str, field_names: Vec<String>) -> Result<(), Box<dyn Error>>{
+        self.add_receiver(ReceiverConfig::Ukhas { link, call_sign: call_sign.to_string(), field_names })
+    }
+
+    /// Create a new RFD900 Module reading NEBP telemetry frames laid out as `layout`
+    pub fn new_rfd(&mut self, port: &str, baud: u32, layout: Vec<String>) -> Result<(), Box<dyn Error>>{
+        self.add_receiver(ReceiverConfig::Rfd { port: port.to_string(), baud, layout })
+    }
+
+    /// Handle on the antenna tracker and rotator. Device I/O waits on the pointing thread, so use it without holding the tracker
+    pub fn pointing(&self) -> AntennaPointing{
+        self.pointing.clone()
+    }
+
+    /// Set a fixed location for the ground station, replacing any GPS receiver
+    pub fn set_ground_station(&mut self, station: GroundStation){
+        self.station.set_manual(station);
+    }
+
+    /// Follow the ground station location from an NMEA GPS on a serial port
+    pub fn set_station_nmea(&mut self, port: &str, baud: u32) -> Result<(), Box<dyn Error>>{
+        Ok(self.station.start_nmea(port, baud)?)
+    }
+
+    /// Follow the ground station location from gpsd
+    pub fn set_station_gpsd(&mut self, address: &str) -> Result<(), Box<dyn Error>>{
+        Ok(self.station.start_gpsd(address)?)
+    }
+
+
+    /// Set the (horizontal, vertical) 1-sigma noise in meters the Kalman filter assumes for a source type
+    pub fn set_measurement_noise(&mut self, tracking_type: TrackingType, horizontal: f64, vertical: f64) -> Result<(), Box<dyn Error>>{
+        if !(horizontal > 0.0 && vertical > 0.0){
+            return Err("Measurement noise must be positive".into());
+        }
+        self.kalman.set_measurement_noise(tracking_type, horizontal, vertical);
+        self.kalman.rebuild(&self.history);
+        self.refresh();
+        Ok(())
+    }
+
+
+    pub fn set_validation_limits(&mut self, limits: ValidationLimits){
+        self.validator.set_limits(limits);
+    }
+
+    pub fn set_phase_thresholds(&mut self, thresholds: PhaseThresholds){
+        self.flight.set_thresholds(thresholds);
+    }
+
+    /// Set how fix age, precision, velocity origin and reliability weigh in the fusion, and fuse again
+    pub fn set_fusion_weights(&mut self, weights: FusionWeights){
+        self.fusion.set_weights(weights);
+        self.refresh();
+    }
+
+    /// Select the estimation method, the fused position only becomes the tracked one with `Weighted` and the filtered one with `Kalman`,
+    /// the history methods estimate it over every fix kept
+    pub fn set_estimation_type(&mut self, estimation: EstimationType){
+        self.estimation = estimation;
+        self.refresh();
+    }
+
+
+    // ------------------------Tracking Modules Return Functions------------------------
+
+
+    /// Latest published state of every source
+    pub fn snapshots(&self) -> Vec<SourceSnapshot>{
+        self.sources.iter().map(|s| s.snapshot()).collect()
+    }
+
+    /// Latest published state of the sources of the given tracking types
+    pub fn sources_of(&self, types: &[TrackingType]) -> Vec<SourceSnapshot>{
+        self.snapshots().into_iter().filter(|s| types.contains(&s.tracking_type)).collect()
+    }
+
+    /// Latest state of every source whose latest fix was not rejected
+    fn accepted_snapshots(&self) -> Vec<SourceSnapshot>{
+        self.snapshots().into_iter()
+            .filter(|s| self.rejected_at.get(&(s.tracking_type, s.id.clone())) != Some(&s.get_last_update()))
+            .collect()
+    }
+
+    /// Health and identification of every registered source
+    pub fn source_statuses(&self) -> Vec<SourceStatus>{
+        self.snapshots().iter().map(|s| s.status()).collect()
+    }
+
+    /// Every sensor channel logged so far, as one time series per channel
+    pub fn telemetry_series(&self) -> Vec<TelemetrySeries>{
+        telemetry_series(self.telemetry.samples())
+    }
+
+    /// Errors reported by the sources on their last update attempt
+    pub fn source_errors(&self) -> Vec<String>{
+        self.snapshots().into_iter()
+            .filter_map(|s| s.health.last_error.map(|e| format!("{} {}: {}", s.tracking_type, s.id, e)))
+            .collect()
+    }
+
+    pub fn get_ground_station(&self) -> Option<GroundStation>{
+        self.station.current()
+    }
+
+    /// Fixes dropped by the validator, oldest first
+    pub fn rejected_fixes(&self) -> Vec<RejectedFix>{
+        self.validator.rejected()
+    }
+
+    pub fn validation_limits(&self) -> ValidationLimits{
+        self.validator.limits()
+    }
+
+    pub fn fusion_weights(&self) -> FusionWeights{
+        self.fusion.weights()
+    }
+
+    // ------------------------Flight Functions------------------------
+
+    /// Start recording a new flight, ending the current one and stopping its sources
+    pub fn start_flight(&mut self, name: &str, call_signs: &[String], imeis: &[String]) -> Result<FlightRecord, Box<dyn Error>>{
+        self.end_flight()?;
+        let started = Utc::now().timestamp() as u64;
+        let flight = self.open_database()?.start_flight(name, started, call_signs, imeis)?;
+        self.reset_flight_state();
+        self.flight_id = Some(flight.id);
+        println!("Started flight {} ({})", flight.name, flight.id);
+        Ok(flight)
+    }
+
+    /// End the current flight and stop its sources, the last position stays shown
+    pub fn end_flight(&mut self) -> Result<(), Box<dyn Error>>{
+        let Some(flight) = self.flight_id.take() else {
+            return Ok(());
+        };
+        // Dropping the sources stops their polling threads
+        self.sources.clear();
+        self.active = false;
+        let ended = Utc::now().timestamp() as u64;
+        self.open_database()?.end_flight(flight, ended)?;
+        println!("Ended flight {}", flight);
+        Ok(())
+    }
+
+    /// Continue a past or interrupted flight, reloading its fixes so the track carries on where it stopped
+    pub fn resume_flight(&mut self, id: i64) -> Result<FlightRecord, Box<dyn Error>>{
+        if self.flight_id != Some(id) {
+            let db = self.open_database()?;
+            db.flight(id)?.ok_or_else(|| format!("Flight {} not found", id))?;
+            let fixes = db.fixes(id, None, None, true)?;
+            let telemetry = db.telemetry(id, None, None)?;
+            let last_position = db.fused_positions(id, None, None)?.pop();
+            let receivers = db.receivers(id)?;
+
+            // Only one flight may be open, the current one ends before the resumed one reopens
+            self.end_flight()?;
+            self.open_database()?.reopen_flight(id)?;
+            self.reset_flight_state();
+            println!("Resuming flight {} with {} stored fixes", id, fixes.len());
+            self.replay(fixes);
+            for sample in telemetry {
+                self.telemetry.push(sample);
+            }
+            // Before any fused position the newest fix stands in
+            if let Some(position) = last_position.or_else(|| self.history.fixes().next_back().map(|f| f.pos_time.clone())) {
+                self.position_time = position;
+            }
+            self.flight_id = Some(id);
+            // A receiver that is missing now should not keep the rest of the flight from resuming
+            for config in receivers {
+                if let Err(e) = self.add_receiver(config.clone()) {
+                    eprintln!("Unable to restart receiver {:?}: {}", config, e);
+                }
+            }
+        }
+        self.open_database()?.flight(id)?.ok_or_else(|| format!("Flight {} not found", id).into())
+    }
+
+    /// Newest flight left in progress when the app was closed, to carry on with at startup
+    pub fn open_flight(&mut self) -> Result<Option<FlightRecord>, Box<dyn Error>>{
+        self.open_database()?.open_flight()
+    }
+
+    /// Id of the flight being recorded, None outside of a flight
+    pub fn flight_id(&self) -> Option<i64>{
+        self.flight_id
+    }
+
+    /// Flight being recorded, None outside of a flight
+    pub fn current_flight(&mut self) -> Result<Option<FlightRecord>, Box<dyn Error>>{
+        let Some(flight) = self.flight_id else {
+            return Ok(None);
+        };
+        self.open_database()?.flight(flight)
+    }
+
+    /// Every recorded flight, newest first
+    pub fn flights(&mut self) -> Result<Vec<FlightRecord>, Box<dyn Error>>{
+        self.open_database()?.flights()
+    }
+
+    /// Where the data lives and whether the folder is usable
+    pub fn storage_status(&self) -> StorageStatus{
+        self.storage.status()
+    }
+
+    /// Move the data to `dir` (None for the default folder), the database is reopened there on next use
+    pub fn set_data_dir(&mut self, dir: Option<PathBuf>) -> Result<StorageStatus, Box<dyn Error>>{
+        if self.flight_id.is_some() {
+            return Err("End the current flight before changing the data folder".into());
+        }
+        self.storage.set_custom(dir)?;
+        self.database = None;
+        Ok(self.storage.status())
+    }
+
+    /// Fixes of the shown flight between `from` and `to` (unbounded if None), oldest first
+    pub fn stored_fixes(&mut self, from: Option<u64>, to: Option<u64>, include_rejected: bool) -> Result<Vec<StoredFix>, Box<dyn Error>>{
+        let Some(flight) = self.shown_flight()? else {
+            return Ok(vec![]);
+        };
+        self.open_database()?.fixes(flight, from, to, include_rejected)
+    }
+
+    /// Tracked positions of the shown flight between `from` and `to`, oldest first
+    pub fn stored_track(&mut self, from: Option<u64>, to: Option<u64>) -> Result<Vec<PositionTime>, Box<dyn Error>>{
+        let Some(flight) = self.shown_flight()? else {
+            return Ok(vec![]);
+        };
+        self.open_database()?.fused_positions(flight, from, to)
+    }
+
+    /// Write the shown flight to a CSV file, returning the number of fixes written
+    pub fn export_csv(&mut self, path: &Path) -> Result<usize, Box<dyn Error>>{
+        let flight = self.shown_flight()?.ok_or("No flight recorded yet")?;
+        self.open_database()?.export_csv(flight, path)
+    }
+
+    /// Tracked path of the shown flight with the burst and landing detected over it, without prediction
+    pub fn flight_export(&mut self) -> Result<FlightExport, Box<dyn Error>>{
+        let flight = self.shown_flight()?.ok_or("No flight recorded yet")?;
+        let mut detector = FlightPhaseDetector::new();
+        detector.set_thresholds(self.flight.thresholds());
+
+        let database = self.open_database()?;
+        let name = database.flight(flight)?.map(|f| f.name).unwrap_or_default();
+        let track = database.fused_positions(flight, None, None)?;
+        for position in &track {
+            detector.update(position);
+        }
+        let status = detector.status();
+        let landing = if status.phase == FlightPhase::Landed { track.last().cloned() } else { None };
+        Ok(FlightExport { name, track, burst: status.burst, landing, prediction: None })
+    }
+
+    /// JSON of the newest prediction of the shown flight
+    pub fn latest_prediction(&mut self) -> Result<Option<String>, Box<dyn Error>>{
+        let Some(flight) = self.shown_flight()? else {
+            return Ok(None);
+        };
+        self.open_database()?.latest_prediction(flight)
+    }
+
+    /// Keep a prediction of the current flight, serialized to JSON
+    pub fn record_prediction(&mut self, predictor: &str, prediction: &impl Serialize){
+        let created = Utc::now().timestamp() as u64;
+        match serde_json::to_string(prediction) {
+            Ok(data) => self.store(|db, flight| db.insert_prediction(flight, created, predictor, &data)),
+            Err(e) => eprintln!("Unable to serialize prediction: {}", e),
+        }
+    }
+
+    /// Detected flight phase, its transitions and the burst point once descending
+    pub fn flight_status(&self) -> FlightStatus{
+        self.flight.status()
+    }
+
+    /// Last fused position with the weight of every source in it
+    pub fn fused_position(&self) -> Option<FusedPosition>{
+        self.fused.clone()
+    }
+
+    /// Smoothed state and covariance of the Kalman filter
+    pub fn kalman_estimate(&self) -> Option<KalmanEstimate>{
+        self.kalman.estimate()
+    }
+
+    /// Latest ground station fix, with the receiver error if the GPS stopped answering
+    pub fn station_status(&self) -> StationStatus{
+        self.station.status()
+    }
+
+    /// Azimuth, elevation and range from the ground station to the current position
+    pub fn look_angles(&self) -> Result<LookAngles, Box<dyn Error>>{
+        let station = self.station.current().ok_or("Ground station location not set")?;
+        if self.position_time.last_update == 0{
+            return Err("No position received yet".into());
+        }
+        Ok(station.look_angles(&self.position_time))
+    }
+
+    // ------------------------Update Helper Functions------------------------
+
+    /// Open the flight database in the data folder if it is not open yet
+    fn open_database(&mut self) -> Result<&mut FlightDatabase, Box<dyn Error>> {
+        if self.database.is_none() {
+            let path = self.storage.data_dir()?.join(DATABASE_FILE);
+            println!("Opening flight database at: {:?}", path);
+            self.database = Some(FlightDatabase::open(&path)?);
+        }
+        self.database.as_mut().ok_or_else(|| "Flight database not open".into())
+    }
+
+    /// Start recording an unnamed flight when a source is added outside of any flight
+    fn start_default_flight(&mut self) {
+        if let Err(e) = self.start_flight(&Self::default_flight_name(), &[], &[]) {
+            eprintln!("Unable to start flight recording: {}", e);
+        }
+    }
+
+    pub fn default_flight_name() -> String {
+        format!("Flight {}", Utc::now().format("%Y-%m-%d %H:%M UTC"))
+    }
+
+    /// Forget the fixes and filter states of the previous flight, keeping the settings
+    fn reset_flight_state(&mut self) {
+        let weights = self.fusion.weights();
+        self.fusion = SourceFusion::new();
+        self.fusion.set_weights(weights);
+        self.fused = None;
+        self.velocity = None;
+        self.history.clear();
+        self.kalman.reset();
+        self.validator.reset();
+        self.flight.reset();
+        self.rejected_at.clear();
+        self.telemetry.clear();
+        self.position_time = PositionTime::new();
+    }
+
+    /// Feed the stored fixes of a flight back into the history, filters and source reliabilities
+    fn replay(&mut self, fixes: Vec<StoredFix>) {
+        for fix in fixes {
+            let Some(tracking_type) = TrackingType::from_name(&fix.track_type) else {
+                continue;
+            };
+            self.fusion.record(tracking_type, &fix.source, fix.rejected.is_none());
+            if fix.rejected.is_none() && self.history.push(fix.pos_time.clone(), tracking_type, &fix.source) {
+                self.kalman.add_fix(&self.history, &fix.pos_time, tracking_type);
+                self.flight.update(&fix.pos_time);
+            }
+        }
+        self.velocity = estimate_velocity(self.history.fixes().rev().map(|f| &f.pos_time));
+    }
+
+    /// Run `write` on the database for the current flight, tracking goes on without storage if it fails
+    fn store(&mut self, write: impl FnOnce(&mut FlightDatabase, i64) -> Result<(), Box<dyn Error>>) {
+        if let (Some(db), Some(flight)) = (self.database.as_mut(), self.flight_id) {
+            if let Err(e) = write(db, flight) {
+                eprintln!("Database error: {}", e);
+            }
+        }
+    }
+
+    /// Flight being recorded, or the last one recorded before this run
+    fn shown_flight(&mut self) -> Result<Option<i64>, Box<dyn Error>> {
+        if self.flight_id.is_some() {
+            return Ok(self.flight_id);
+        }
+        Ok(self.open_database()?.latest_flight()?.map(|f| f.id))
+    }
+
+
+
+
+    // ------------------------Public Functions------------------------
+    
+    /// Log a new fix published by a polling thread and refresh the tracked position. Returns the fix if it was rejected
+    pub fn record_fix(&mut self, snapshot: &SourceSnapshot) -> Option<RejectedFix> {
+        // The fix, its telemetry and the new tracked position cost a single commit
+        self.store(|db, _| db.begin());
+        let rejected = self.apply_fix(snapshot);
+        self.store(|db, _| db.commit());
+        rejected
+    }
+
+    /// Refresh the tracked position with a new fix, see `record_fix`
+    fn apply_fix(&mut self, snapshot: &SourceSnapshot) -> Option<RejectedFix> {
+        // Telemetry decoded by the source, then the channels parsed from its comment
+        let mut channels = snapshot.telemetry.clone();
+        channels.extend(self.comment_rules.parse(&snapshot.id, &snapshot.comment));
+        // Logged once per fix time, so a source repeating its cached telemetry is not stored twice
+        if self.telemetry.push(TelemetrySample { time: snapshot.get_last_update(), source: snapshot.id.clone(), channels: channels.clone() }) {
+            self.store(|db, flight| db.insert_telemetry(flight, &snapshot.id, snapshot.tracking_type, snapshot.get_last_update(), &channels));
+        }
+
+        // Sensor channels stay useful when the position is bad, the position itself is dropped
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let previous = self.history.nearest(snapshot.get_last_update()).map(|f| f.pos_time.clone());
+        if let Err(reason) = self.validator.validate(&snapshot.pos_time, previous.as_ref(), snapshot.tracking_type, &snapshot.id, now) {
+            eprintln!("Rejecting {}[{}] fix: {}", snapshot.tracking_type, snapshot.id, reason);
+            self.rejected_at.insert((snapshot.tracking_type, snapshot.id.clone()), snapshot.get_last_update());
+            self.fusion.record(snapshot.tracking_type, &snapshot.id, false);
+            let reason_text = reason.to_string();
+            self.store(|db, flight| db.insert_fix(flight, &snapshot.id, snapshot.tracking_type, &snapshot.pos_time, Some(&reason_text)));
+            return Some(self.validator.record(&snapshot.pos_time, snapshot.tracking_type, &snapshot.id, &reason, now));
+        }
+        self.rejected_at.remove(&(snapshot.tracking_type, snapshot.id.clone()));
+        self.fusion.record(snapshot.tracking_type, &snapshot.id, true);
+
+        if self.history.push(snapshot.pos_time.clone(), snapshot.tracking_type, &snapshot.id) {
+            self.kalman.add_fix(&self.history, &snapshot.pos_time, snapshot.tracking_type);
+            self.flight.update(&snapshot.pos_time);
+        }
+        self.store(|db, flight| db.insert_fix(flight, &snapshot.id, snapshot.tracking_type, &snapshot.pos_time, None));
+        self.refresh();
+        None
+    }
+
+    /// Recompute the tracked position by fusing the latest fix of every source
+    pub fn refresh(&mut self) {
+        let mut inputs: Vec<FusionInput> = vec![];
+        let snapshots = self.accepted_snapshots();
+        for snapshot in snapshots {
+            if snapshot.get_last_update() != 0 {
+                inputs.push(FusionInput {
+                    measured_velocity: snapshot.pos_time.horiz_vel != 0.0 || snapshot.pos_time.vert_vel != 0.0,
+                    precision: snapshot.precision.unwrap_or_else(|| self.kalman.measurement_noise(snapshot.tracking_type).0),
+                    source: snapshot.id,
+                    tracking_type: snapshot.tracking_type,
+                    pos_time: snapshot.pos_time,
+                });
+            }
+        }
+
+        // Velocities come from the history of every source rather than from the latest fix of each
+        self.velocity = estimate_velocity(self.history.fixes().rev().map(|f| &f.pos_time));
+
+        // The fusion and the filter are always kept for the UI, they only replace the newest fix as tracked position when selected
+        let fused = self.fusion.fuse(&inputs);
+        let tracked = match self.estimation {
+            EstimationType::Weighted => fused.as_ref().map(|f| f.pos_time()),
+            EstimationType::Kalman => self.kalman.pos_time(),
+            method if method.uses_history() => PositionTime::return_valid_pos_time(self.history.positions(), method),
+            _ => PositionTime::return_valid_pos_time(inputs.iter().map(|i| i.pos_time.clone()).collect(), EstimationType::Recent),
+        };
+        if fused.is_some() {
+            self.fused = fused;
+        }
+
+        //Update struct if we have a new tracked position
+        if let Some(mut updated_pos) = tracked {
+            // The filter estimates its own velocity and course
+            if let Some(velocity) = self.velocity.filter(|_| !matches!(self.estimation, EstimationType::Kalman)) {
+                updated_pos.horiz_vel = velocity.ground_speed;
+                updated_pos.vert_vel = velocity.vert_rate;
+                updated_pos.heading = velocity.course;
+            }
+
+            // Preserve existing velocities if no source has one
+            if updated_pos.horiz_vel == 0.0 && self.position_time.horiz_vel != 0.0 {
+                updated_pos.horiz_vel = self.position_time.horiz_vel;
+            }
+            if updated_pos.vert_vel == 0.0 && self.position_time.vert_vel != 0.0 {
+                updated_pos.vert_vel = self.position_time.vert_vel;
+            }
+
+            self.position_time = updated_pos;
+            let position = self.position_time.clone();
+            self.store(|db, flight| db.insert_fused(flight, &position));
+        }
+
+        // Only hands the angles over, the pointing thread talks to the devices
+        if let Ok(angles) = self.look_angles() {
+            self.pointing.point(angles.azimuth, angles.elevation);
+        }
+    }
+    pub fn get_position_with_filtering(&self, method: EstimationType) -> (f64, f64, f64, f64, f64) {
+        // The filter runs over every fix received, the fusion needs the type and reliability of the sources
+        let stateful = match method {
+            EstimationType::Kalman => self.kalman.pos_time(),
+            EstimationType::Weighted => self.fused.as_ref().map(|f| f.pos_time()),
+            _ => None,
+        };
+        if let Some(filtered_pos) = stateful {
+            return (filtered_pos.lat, filtered_pos.lon, filtered_pos.alt, filtered_pos.horiz_vel, filtered_pos.vert_vel);
+        }
+
+        let positions: Vec<PositionTime> = if method.uses_history() {
+            self.history.positions()
+        } else {
+            self.accepted_snapshots().into_iter()
+                .filter(|s| s.get_last_update() != 0)
+                .map(|s| s.pos_time)
+                .collect()
+        };
+
+        if let Some(filtered_pos) = PositionTime::return_valid_pos_time(positions, method) {
+            (filtered_pos.lat, filtered_pos.lon, filtered_pos.alt, filtered_pos.horiz_vel, filtered_pos.vert_vel)
+        } else {
+            (self.position_time.lat, self.position_time.lon, self.position_time.alt, self.position_time.horiz_vel, self.position_time.vert_vel)
+        }
+    }
+
+
+
+
+    /// Function to print the data of the Tracker
+    pub fn print(&self){
+                // Print current position data
+                let current_time = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                
+                let age_seconds = current_time.saturating_sub(self.position_time.last_update);
+
+        println!("Latitude: {}, Longitude: {}, Altitude: {}, Last Update: {}s ago", self.position_time.lat, self.position_time.lon, self.position_time.alt, age_seconds);
+    }
+
+    // ------------------------Getter Functions------------------------
+
+    pub fn get_position(&self)->(f64,f64,f64){(self.position_time.lat,self.position_time.lon,self.position_time.alt)}
+    pub fn get_velocities(&self) -> (f64, f64) {(self.position_time.horiz_vel, self.position_time.vert_vel)}
+    pub fn get_course(&self) -> Option<f64> {self.position_time.heading}
+    pub fn velocity(&self) -> Option<VelocityEstimate> {self.velocity}
+    pub fn get_last_update(&self)->u64{self.position_time.last_update}
+
+}
+
+impl Default for Tracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,136 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::track_lib::aprs_parser::TelemetryChannel;
+use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::tracking_type::TrackingType;
+
+//Seconds without a new fix before a source is reported as stale
+const STALE_AFTER_SECS: u64 = 300;
+
+//Poll interval used by sources that do not specify their own
+const DEFAULT_POLL_SECS: u64 = 15;
+
+/// Common interface for every receiver that can feed positions into the Tracker
+pub trait TrackingSource: Send {
+    /// Identifier of the tracked object (callsign, modem name, ...)
+    fn id(&self) -> &str;
+
+    fn tracking_type(&self) -> TrackingType;
+
+    /// Fetch the newest fix from the upstream
+    fn update_position(&mut self) -> Result<(), Box<dyn std::error::Error>>;
+
+    fn get_pos_time(&self) -> PositionTime;
+
+    fn get_last_update(&self) -> u64;
+
+    /// Current health of the source, as tracked by the source itself
+    fn health(&self) -> SourceHealth;
+
+    /// Free text comment sent along with the last fix, if the source has one
+    fn get_comment(&self) -> &str {
+        ""
+    }
+
+    /// Telemetry decoded by the source itself with the last fix
+    fn get_telemetry(&self) -> &[TelemetryChannel] {
+        &[]
+    }
+
+    /// Horizontal 1-sigma precision in meters declared with the last fix (HDOP, position ambiguity, ...), if the source has one
+    fn precision(&self) -> Option<f64> {
+        None
+    }
+
+    /// Time between two `update_position` calls of the background poller
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(DEFAULT_POLL_SECS)
+    }
+}
+
+/// Overall state of a tracking source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum HealthStatus {
+    /// No fix has been received yet
+    Waiting,
+    Ok,
+    /// Last fix is older than `STALE_AFTER_SECS`
+    Stale,
+    /// The last update attempt failed
+    Error,
+}
+
+/** Struct to keep track of the health of a tracking source.
+
+last_success -> Unix timestamp of the last successful update attempt
+
+consecutive_failures -> Number of failed update attempts since the last success
+
+last_error -> Message of the most recent failure
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceHealth {
+    pub status: HealthStatus,
+    pub last_success: u64,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+}
+
+impl SourceHealth {
+    pub fn new() -> Self {
+        Self { status: HealthStatus::Waiting, last_success: 0, consecutive_failures: 0, last_error: None }
+    }
+
+    /// Record the outcome of an update attempt
+    pub fn record(&mut self, result: &Result<(), Box<dyn std::error::Error>>) {
+        match result {
+            Ok(()) => {
+                self.last_success = now();
+                self.consecutive_failures = 0;
+                self.last_error = None;
+            }
+            Err(e) => {
+                self.consecutive_failures += 1;
+                self.last_error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Return a copy of the health with its status evaluated against the last fix time
+    pub fn evaluate(&self, last_update: u64) -> SourceHealth {
+        let status = if self.consecutive_failures > 0 {
+            HealthStatus::Error
+        } else if last_update == 0 {
+            HealthStatus::Waiting
+        } else if now().saturating_sub(last_update) > STALE_AFTER_SECS {
+            HealthStatus::Stale
+        } else {
+            HealthStatus::Ok
+        };
+        SourceHealth { status, ..self.clone() }
+    }
+}
+
+impl Default for SourceHealth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Serializable summary of a source for the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceStatus {
+    pub id: String,
+    pub tracking_type: String,
+    pub last_update: u64,
+    pub health: SourceHealth,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
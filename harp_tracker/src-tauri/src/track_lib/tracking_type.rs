@@ -2,11 +2,26 @@
 use std::fmt::Display;
 
 /// Helper function for the Tracker Module
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TrackingType{
     APRS,
     Iridium,
     SondeHub,
+    KISS,
+    AprsIs,
+    Horus,
+    UKHAS,
+    RFD,
+}
+
+impl TrackingType{
+    /// Parse the name shown by `Display`, ignoring case
+    pub fn from_name(name: &str) -> Option<Self>{
+        [TrackingType::APRS, TrackingType::Iridium, TrackingType::SondeHub, TrackingType::KISS,
+         TrackingType::AprsIs, TrackingType::Horus, TrackingType::UKHAS, TrackingType::RFD]
+            .into_iter()
+            .find(|t| t.to_string().eq_ignore_ascii_case(name.trim()))
+    }
 }
 
 impl Display for TrackingType{
@@ -15,6 +30,11 @@ impl Display for TrackingType{
             TrackingType::APRS => "APRS",
             TrackingType::Iridium => "Iridium",
             TrackingType::SondeHub => "SondeHub",
+            TrackingType::KISS => "KISS",
+            TrackingType::AprsIs => "APRS-IS",
+            TrackingType::Horus => "Horus",
+            TrackingType::UKHAS => "UKHAS",
+            TrackingType::RFD => "RFD",
         };
         write!(f, "{}", name)
     }
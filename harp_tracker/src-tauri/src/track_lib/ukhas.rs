@@ -0,0 +1,341 @@
+use std::{
+    error::Error,
+    io::{ErrorKind, Read},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::track_lib::aprs_parser::TelemetryChannel;
+use crate::track_lib::horus::timestamp_from_utc_time;
+use crate::track_lib::look_angle::ground_distance_m;
+use crate::track_lib::position_time::PositionTime;
+use crate::track_lib::stream_link::{LineBuffer, StreamLink, MAX_LINE_LEN};
+use crate::track_lib::tracking_source::{SourceHealth, TrackingSource};
+use crate::track_lib::tracking_type::TrackingType;
+
+//Sentences are pushed by the receiver, so drain it often
+const POLL_INTERVAL_SECS: u64 = 1;
+
+/** Struct holding a decoded UKHAS telemetry sentence.
+
+`$$CALL,count,hh:mm:ss,lat,lon,alt,extra,...*CRC16`
+
+extra -> Payload specific fields following the altitude, in order
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct UkhasSentence {
+    pub call_sign: String,
+    pub count: u32,
+    pub time: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub alt: f64,
+    pub extra: Vec<String>,
+}
+
+impl UkhasSentence {
+    /// Parse a sentence and check its CRC16-CCITT, anything before the `$$` is ignored
+    pub fn parse(line: &str) -> Result<Self, Box<dyn Error>> {
+        let start = line.find("$$").ok_or("No UKHAS sentence start")?;
+        let sentence = line[start..].trim_start_matches('$').trim_end();
+        let (body, checksum) = sentence.rsplit_once('*').ok_or("UKHAS sentence without checksum")?;
+
+        if checksum.len() != 4 {
+            return Err(format!("Unsupported UKHAS checksum: {}", checksum).into());
+        }
+        let expected = u16::from_str_radix(checksum, 16)?;
+        let actual = crc16_ccitt(body.as_bytes());
+        if expected != actual {
+            return Err(format!("UKHAS CRC mismatch: got {:04X}, computed {:04X}", expected, actual).into());
+        }
+
+        let fields: Vec<&str> = body.split(',').collect();
+        if fields.len() < 6 {
+            return Err("UKHAS sentence with fewer than 6 fields".into());
+        }
+        Ok(Self {
+            call_sign: fields[0].to_string(),
+            count: fields[1].trim().parse()?,
+            time: normalize_time(fields[2].trim()).ok_or("Invalid UKHAS time")?,
+            lat: fields[3].trim().parse()?,
+            lon: fields[4].trim().parse()?,
+            alt: fields[5].trim().parse()?,
+            extra: fields[6..].iter().map(|f| f.trim().to_string()).collect(),
+        })
+    }
+}
+
+/// CRC16-CCITT (polynomial 0x1021, initial value 0xFFFF) used by UKHAS sentences
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// Accept both `hh:mm:ss` and `hhmmss`
+fn normalize_time(time: &str) -> Option<String> {
+    match time.len() {
+        8 => Some(time.to_string()),
+        6 if time.bytes().all(|b| b.is_ascii_digit()) => Some(format!("{}:{}:{}", &time[0..2], &time[2..4], &time[4..6])),
+        _ => None,
+    }
+}
+
+/// UKHAS telemetry received from dl-fldigi, a LoRa gateway or any receiver printing `$$` sentences
+pub struct Ukhas {
+    tracking_type: TrackingType,
+    link: StreamLink,
+    call_sign: String,
+    field_names: Vec<String>,
+    stream: Option<Box<dyn Read + Send>>,
+    lines: LineBuffer,
+    last_count: Option<u32>,
+    position_time: PositionTime,
+    comment: String,
+    telemetry: Vec<TelemetryChannel>,
+    health: SourceHealth,
+}
+
+impl Ukhas {
+    /// `field_names` name the fields following the altitude, unnamed fields are called `field7`, `field8`, ...
+    pub fn new(link: StreamLink, call_sign: &str, field_names: Vec<String>) -> Self {
+        Self {
+            tracking_type: TrackingType::UKHAS,
+            link,
+            call_sign: call_sign.to_uppercase(),
+            field_names,
+            stream: None,
+            lines: LineBuffer::new(MAX_LINE_LEN),
+            last_count: None,
+            position_time: PositionTime::new(),
+            comment: String::new(),
+            telemetry: vec![],
+            health: SourceHealth::new(),
+        }
+    }
+
+    fn fetch_position(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.stream.is_none() {
+            self.stream = Some(self.link.open()?);
+            self.lines.clear();
+            println!("UKHAS receiver connected: {:?}", self.link);
+        }
+
+        let stream = self.stream.as_mut().ok_or("UKHAS receiver not connected")?;
+        let mut lines = vec![];
+        let result = self.lines.read_lines(stream, &mut lines);
+        for line in lines {
+            if !line.contains("$$") {
+                continue;
+            }
+            if let Err(e) = self.handle_line(&line) {
+                eprintln!("UKHAS: rejecting sentence: {}", e);
+            }
+        }
+        match result {
+            // Noise on the link, the next line may be fine
+            Err(e) if e.kind() == ErrorKind::InvalidData => eprintln!("UKHAS: dropping {}", e),
+            Err(e) => {
+                self.stream = None;
+                return Err(format!("UKHAS receiver: {}", e).into());
+            }
+            Ok(_) => {}
+        }
+        Ok(())
+    }
+
+    fn handle_line(&mut self, line: &str) -> Result<(), Box<dyn Error>> {
+        let sentence = UkhasSentence::parse(line)?;
+        if !sentence.call_sign.eq_ignore_ascii_case(&self.call_sign) {
+            return Ok(());
+        }
+        // Repeated sentence, eg. heard by two receivers
+        if self.last_count == Some(sentence.count) {
+            return Ok(());
+        }
+        // No fix yet on the payload GPS
+        if sentence.lat == 0.0 && sentence.lon == 0.0 {
+            return Ok(());
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let time = timestamp_from_utc_time(&sentence.time, now).unwrap_or(now);
+        let previous = &self.position_time;
+        let dt = time.saturating_sub(previous.last_update) as f64;
+        let (horiz_vel, vert_vel) = if previous.last_update != 0 && dt > 0.0 {
+            (ground_distance_m(previous.lat, previous.lon, sentence.lat, sentence.lon) / dt, (sentence.alt - previous.alt) / dt)
+        } else {
+            (0.0, 0.0)
+        };
+
+        self.position_time.update(sentence.lat, sentence.lon, sentence.alt, time, horiz_vel, vert_vel);
+        self.last_count = Some(sentence.count);
+        self.comment = sentence.extra.join(",");
+        self.telemetry = sentence.extra.iter().enumerate()
+            .filter_map(|(i, field)| {
+                let value = field.parse::<f64>().ok()?;
+                let name = self.field_names.get(i).cloned().unwrap_or_else(|| format!("field{}", i + 7));
+                Some(TelemetryChannel { name, unit: String::new(), value })
+            })
+            .collect();
+
+        println!(
+            "UKHAS Position: Call: {}, Count: {}, Lat: {}, Lon: {}, Alt: {}m",
+            sentence.call_sign, sentence.count, sentence.lat, sentence.lon, sentence.alt
+        );
+        Ok(())
+    }
+}
+
+impl TrackingSource for Ukhas {
+    fn id(&self) -> &str {
+        &self.call_sign
+    }
+
+    fn tracking_type(&self) -> TrackingType {
+        self.tracking_type
+    }
+
+    fn update_position(&mut self) -> Result<(), Box<dyn Error>> {
+        let result = self.fetch_position();
+        self.health.record(&result);
+        result
+    }
+
+    fn get_pos_time(&self) -> PositionTime {
+        self.position_time.clone()
+    }
+
+    fn get_last_update(&self) -> u64 {
+        self.position_time.last_update
+    }
+
+    fn health(&self) -> SourceHealth {
+        self.health.evaluate(self.position_time.last_update)
+    }
+
+    fn get_comment(&self) -> &str {
+        &self.comment
+    }
+
+    fn get_telemetry(&self) -> &[TelemetryChannel] {
+        &self.telemetry
+    }
+
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(POLL_INTERVAL_SECS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        net::TcpListener,
+        thread,
+        time::Instant,
+    };
+
+    use chrono::Utc;
+
+    use super::*;
+
+    /// `$$body*CRC` with the checksum computed over the body
+    fn sentence(body: &str) -> String {
+        format!("$${}*{:04X}", body, crc16_ccitt(body.as_bytes()))
+    }
+
+    #[test]
+    fn computes_the_ccitt_check_value() {
+        assert_eq!(crc16_ccitt(b"123456789"), 0x29B1);
+        assert_eq!(crc16_ccitt(b""), 0xFFFF);
+    }
+
+    #[test]
+    fn parses_a_valid_sentence() {
+        let line = format!("noise before {}\r\n", sentence("HARP,42,120304,52.1234,-1.5678,12345,3.7,ok"));
+        let parsed = UkhasSentence::parse(&line).unwrap();
+        assert_eq!(parsed, UkhasSentence {
+            call_sign: "HARP".to_string(),
+            count: 42,
+            time: "12:03:04".to_string(),
+            lat: 52.1234,
+            lon: -1.5678,
+            alt: 12345.0,
+            extra: vec!["3.7".to_string(), "ok".to_string()],
+        });
+
+        let parsed = UkhasSentence::parse(&sentence("HARP,43,12:03:09,52.1,-1.5,100")).unwrap();
+        assert_eq!(parsed.time, "12:03:09");
+        assert!(parsed.extra.is_empty());
+    }
+
+    #[test]
+    fn rejects_corrupted_sentences() {
+        let valid = sentence("HARP,42,12:03:04,52.1234,-1.5678,12345");
+        // One character flipped in transit
+        let corrupted = valid.replacen("52.1234", "52.1235", 1);
+        assert!(UkhasSentence::parse(&corrupted).unwrap_err().to_string().contains("CRC mismatch"));
+
+        let (body, checksum) = valid.rsplit_once('*').unwrap();
+        assert!(UkhasSentence::parse(body).is_err());
+        assert!(UkhasSentence::parse(&format!("{}*{}", body, &checksum[..2])).is_err());
+        assert!(UkhasSentence::parse(&format!("{}*", body)).is_err());
+
+        assert!(UkhasSentence::parse(&sentence("HARP,42,12:03:04,52.1")).unwrap_err().to_string().contains("fewer than 6"));
+        assert!(UkhasSentence::parse(&sentence("HARP,42,1203,52.1,-1.5,100")).unwrap_err().to_string().contains("time"));
+    }
+
+    #[test]
+    fn normalizes_both_time_layouts() {
+        assert_eq!(normalize_time("235959").as_deref(), Some("23:59:59"));
+        assert_eq!(normalize_time("23:59:59").as_deref(), Some("23:59:59"));
+        assert_eq!(normalize_time("23595"), None);
+        assert_eq!(normalize_time("23a959"), None);
+    }
+
+    #[test]
+    fn replays_sentences_from_a_tcp_receiver() {
+        let time = Utc::now().format("%H%M%S").to_string();
+        let lines = [
+            sentence(&format!("OTHER,1,{},10.0,10.0,100", time)),
+            sentence(&format!("HARP,1,{},52.0,-1.0,1000,3.7,on,21.5", time)),
+            // The same sentence heard again, and a corrupted one
+            sentence(&format!("HARP,1,{},53.0,-1.0,1000", time)),
+            sentence(&format!("HARP,2,{},54.0,-1.0,1000", time)).replacen("54.0", "55.0", 1),
+        ];
+        let data = lines.join("\r\n") + "\r\n";
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(data.as_bytes()).unwrap();
+            // Keep the connection open while the source drains it
+            thread::sleep(Duration::from_secs(2));
+        });
+
+        let mut source = Ukhas::new(StreamLink::Tcp(address), "harp", vec!["battery".to_string()]);
+        let started = Instant::now();
+        while source.get_last_update() == 0 && started.elapsed() < Duration::from_secs(2) {
+            source.update_position().unwrap();
+        }
+        source.update_position().unwrap();
+
+        let pos = source.get_pos_time();
+        assert_eq!((pos.lat, pos.lon, pos.alt), (52.0, -1.0, 1000.0));
+        // Time of day from the sentence, on today's date
+        assert!(pos.last_update.abs_diff(Utc::now().timestamp() as u64) < 5);
+        assert_eq!(source.last_count, Some(1));
+        assert_eq!(source.get_comment(), "3.7,on,21.5");
+        let channels: Vec<(&str, f64)> = source.get_telemetry().iter().map(|c| (c.name.as_str(), c.value)).collect();
+        assert_eq!(channels, vec![("battery", 3.7), ("field9", 21.5)]);
+    }
+}
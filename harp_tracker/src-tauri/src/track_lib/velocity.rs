@@ -0,0 +1,216 @@
+use serde::Serialize;
+
+use crate::track_lib::look_angle::local_offset_m;
+use crate::track_lib::position_time::PositionTime;
+
+//Fixes used for the velocity, seconds behind the newest one
+const VELOCITY_WINDOW_SECS: u64 = 60;
+
+//Fixes always kept, for sources reporting less than once a minute
+const MIN_VELOCITY_FIXES: usize = 3;
+
+//Fixes further apart than this (s) are not part of the same stretch of track
+const MAX_GAP_SECS: u64 = 600;
+
+//Below this ground speed (m/s) the direction of the displacement is only noise
+const MIN_COURSE_SPEED: f64 = 0.5;
+
+/** Struct holding the velocity vector of the payload.
+
+ground_speed -> Horizontal speed in m/s
+
+course -> Course over ground in degrees clockwise from true north, None when standing still
+
+vert_rate -> Vertical rate in m/s over the last minute of fixes (at least the last three), positive up
+
+reported -> True if the ground speed and course come from the sources, false if derived from the positions
+
+last_update -> Unix timestamp of the newest fix used
+*/
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct VelocityEstimate {
+    pub ground_speed: f64,
+    pub course: Option<f64>,
+    pub vert_rate: f64,
+    pub reported: bool,
+    pub last_update: u64,
+}
+
+/// Velocity over the newest fixes of every source, given newest first. None without two fixes or a reported speed
+pub fn estimate_velocity<'a>(fixes: impl Iterator<Item = &'a PositionTime>) -> Option<VelocityEstimate> {
+    let window = window(fixes);
+    let newest = window.first()?;
+
+    // Least squares over the positions of every source, the noise of one source is averaged out by the others
+    let points = |value: &dyn Fn(&PositionTime) -> f64| -> Vec<(f64, f64)> {
+        window.iter().map(|f| (f.last_update as f64 - newest.last_update as f64, value(f))).collect()
+    };
+    let derived = slope(&points(&|f| local_offset(newest, f).0)).zip(slope(&points(&|f| local_offset(newest, f).1)));
+    let derived_vert = slope(&points(&|f| f.alt));
+
+    // Speeds and courses sent by the payload beat differences of noisy positions
+    let vectors: Vec<(f64, f64)> = window.iter()
+        .filter(|f| f.horiz_vel > 0.0)
+        .filter_map(|f| f.heading.map(|h| (f.horiz_vel * h.to_radians().sin(), f.horiz_vel * h.to_radians().cos())))
+        .collect();
+    let speeds: Vec<f64> = window.iter().map(|f| f.horiz_vel).filter(|&v| v > 0.0).collect();
+
+    let (ground_speed, course, reported) = if !vectors.is_empty() {
+        let (e, n) = mean_vector(&vectors);
+        let speed = speeds.iter().sum::<f64>() / speeds.len() as f64;
+        (speed, course_of(e, n), true)
+    } else if !speeds.is_empty() {
+        let speed = speeds.iter().sum::<f64>() / speeds.len() as f64;
+        (speed, derived.and_then(|(e, n)| course_of(e, n)), true)
+    } else {
+        let (e, n) = derived?;
+        (e.hypot(n), course_of(e, n), false)
+    };
+
+    let vert_rate = derived_vert.unwrap_or_else(|| {
+        let rates: Vec<f64> = window.iter().map(|f| f.vert_vel).filter(|&v| v != 0.0).collect();
+        if rates.is_empty() { 0.0 } else { rates.iter().sum::<f64>() / rates.len() as f64 }
+    });
+
+    Some(VelocityEstimate { ground_speed, course, vert_rate, reported, last_update: newest.last_update })
+}
+
+//------------------------Helper Functions------------------------
+
+/// Fixes of the last minute, at least the last three, stopping at a gap in the track
+fn window<'a>(fixes: impl Iterator<Item = &'a PositionTime>) -> Vec<&'a PositionTime> {
+    let mut window: Vec<&PositionTime> = vec![];
+    for fix in fixes {
+        if let Some(last) = window.last() {
+            let newest = window[0].last_update;
+            if last.last_update.saturating_sub(fix.last_update) > MAX_GAP_SECS
+                || (window.len() >= MIN_VELOCITY_FIXES && fix.last_update + VELOCITY_WINDOW_SECS < newest)
+            {
+                break;
+            }
+        }
+        window.push(fix);
+    }
+    window
+}
+
+/// East and north offset in meters of `fix` from `origin`
+fn local_offset(origin: &PositionTime, fix: &PositionTime) -> (f64, f64) {
+    local_offset_m(origin.lat, origin.lon, fix.lat, fix.lon)
+}
+
+/// Least squares slope of (time, value) points, None unless they span more than one time
+fn slope(points: &[(f64, f64)]) -> Option<f64> {
+    let n = points.len() as f64;
+    let (mut st, mut sv, mut stt, mut stv) = (0.0, 0.0, 0.0, 0.0);
+    for (t, v) in points {
+        st += t;
+        sv += v;
+        stt += t * t;
+        stv += t * v;
+    }
+    let denominator = n * stt - st * st;
+    (denominator > 0.0).then(|| (n * stv - st * sv) / denominator)
+}
+
+fn mean_vector(vectors: &[(f64, f64)]) -> (f64, f64) {
+    let count = vectors.len() as f64;
+    let (east, north) = vectors.iter().fold((0.0, 0.0), |(e, n), v| (e + v.0, n + v.1));
+    (east / count, north / count)
+}
+
+/// Course in degrees of an east/north velocity in m/s, None when too slow to have a direction
+pub fn course_of(east: f64, north: f64) -> Option<f64> {
+    (east.hypot(north) >= MIN_COURSE_SPEED).then(|| east.atan2(north).to_degrees().rem_euclid(360.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Meters in one degree of latitude
+    const M_PER_DEG: f64 = 111_320.0;
+
+    /// Fixes every 10 s moving `east` and `north` m/s and climbing 5 m/s, newest first
+    fn track(east: f64, north: f64, count: u64) -> Vec<PositionTime> {
+        (0..count).rev().map(|i| {
+            let t = i as f64 * 10.0;
+            let lat = 40.0 + north * t / M_PER_DEG;
+            let lon = -80.0 + east * t / (M_PER_DEG * 40f64.to_radians().cos());
+            PositionTime::new_with_value(lat, lon, 1000.0 + 5.0 * t, 1000 + i * 10, 0.0, 0.0)
+        }).collect()
+    }
+
+    fn times(fixes: &[PositionTime]) -> Vec<u64> {
+        window(fixes.iter()).iter().map(|f| f.last_update).collect()
+    }
+
+    #[test]
+    fn derives_speed_and_course_from_positions() {
+        let east = estimate_velocity(track(10.0, 0.0, 6).iter()).unwrap();
+        assert!((east.ground_speed - 10.0).abs() < 0.1, "{east:?}");
+        assert!((east.course.unwrap() - 90.0).abs() < 1.0, "{east:?}");
+        assert!((east.vert_rate - 5.0).abs() < 1e-6);
+        assert!(!east.reported);
+        assert_eq!(east.last_update, 1050);
+
+        let north = estimate_velocity(track(0.0, 20.0, 6).iter()).unwrap();
+        assert!((north.ground_speed - 20.0).abs() < 0.1, "{north:?}");
+        let course = north.course.unwrap();
+        assert!(!(1.0..=359.0).contains(&course), "{north:?}");
+    }
+
+    #[test]
+    fn prefers_reported_speed_and_heading() {
+        let mut fixes = track(10.0, 0.0, 6);
+        for fix in &mut fixes {
+            fix.horiz_vel = 15.0;
+            fix.heading = Some(180.0);
+        }
+        let reported = estimate_velocity(fixes.iter()).unwrap();
+        assert!(reported.reported);
+        assert!((reported.ground_speed - 15.0).abs() < 1e-9);
+        assert!((reported.course.unwrap() - 180.0).abs() < 1e-6);
+
+        // A speed without a heading keeps the course of the positions
+        for fix in &mut fixes {
+            fix.heading = None;
+        }
+        let speed_only = estimate_velocity(fixes.iter()).unwrap();
+        assert!(speed_only.reported);
+        assert!((speed_only.ground_speed - 15.0).abs() < 1e-9);
+        assert!((speed_only.course.unwrap() - 90.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn window_stops_at_a_gap() {
+        let fixes = [1000, 990, 300, 290].map(|t| PositionTime::new_with_value(40.0, -80.0, 1000.0, t, 0.0, 0.0));
+        assert_eq!(times(&fixes), vec![1000, 990]);
+    }
+
+    #[test]
+    fn window_keeps_the_minimum_fixes() {
+        // Sparse fixes, all older than the window but within the gap
+        let sparse = [1000, 700, 400, 100].map(|t| PositionTime::new_with_value(40.0, -80.0, 1000.0, t, 0.0, 0.0));
+        assert_eq!(times(&sparse), vec![1000, 700, 400]);
+
+        // Dense fixes stop at the window once the minimum is reached
+        assert_eq!(times(&track(0.0, 0.0, 10)), vec![1090, 1080, 1070, 1060, 1050, 1040, 1030]);
+    }
+
+    #[test]
+    fn no_course_when_too_slow() {
+        assert_eq!(course_of(0.3, 0.3), None);
+        assert_eq!(course_of(0.0, 0.0), None);
+        assert!((course_of(-1.0, 0.0).unwrap() - 270.0).abs() < 1e-9);
+        assert!(estimate_velocity(track(0.1, 0.0, 6).iter()).unwrap().course.is_none());
+    }
+
+    #[test]
+    fn single_fix_has_no_velocity() {
+        let fixes = track(10.0, 0.0, 1);
+        assert!(estimate_velocity(fixes.iter()).is_none());
+        assert!(estimate_velocity(std::iter::empty()).is_none());
+        assert_eq!(slope(&[(0.0, 1.0)]), None);
+    }
+}
@@ -1,1445 +1,1956 @@
-import { setCompassAngle, createCompass } from './compass.js';
-
-
-// allow quick dev call
-window.updateInfo = updateInfo;
-const { invoke } = window.__TAURI__.core;
-
-// Automatic unit converstion
-// All internal storage is in metric: meters, kg, m/s; user sees metric or imperial based on what they choose
-let currentUnits = 'metric'; // 'metric' or 'imperial'
-
-// Unit conversion constants
-const UNITS = {
-  ALTITUDE: {
-    metric: { name: 'm', factor: 1 },
-    imperial: { name: 'ft', factor: 3.28084 }
-  },
-  VELOCITY_HORIZ: {
-    metric: { name: 'm/s', factor: 1 },
-    imperial: { name: 'mph', factor: 2.237 }
-  },
-  VELOCITY_VERT: {
-    metric: { name: 'm/s', factor: 1 },
-    imperial: { name: 'ft/min', factor: 196.85 }
-  },
-  MASS: {
-    metric: { name: 'kg', factor: 1 },
-    imperial: { name: 'lbs', factor: 2.20462 }
-  }
-};
-
-//Convert metric value to display units
-function convertToDisplay(metricValue, unitType) {
-  if (metricValue === 0 || metricValue === null || metricValue === undefined) return metricValue;
-  return metricValue * UNITS[unitType][currentUnits].factor;
-}
-
-//Convert display value to metric
-function convertToMetric(displayValue, unitType) {
-  if (displayValue === 0 || displayValue === null || displayValue === undefined) return displayValue;
-  return displayValue / UNITS[unitType][currentUnits].factor;
-}
-
-//Get unit label for display
-function getUnitLabel(unitType) {
-  return UNITS[unitType][currentUnits].name;
-}
-
-//Update all unit labels in the UI
-function updateUnitLabels() {
-  // Prediction parameters
-  const labelPayloadMass = document.getElementById('label-payload-mass');
-  if (labelPayloadMass) labelPayloadMass.textContent = `Payload mass (${getUnitLabel('MASS')})`;
-  
-  const labelBalloonMass = document.getElementById('label-balloon-mass');
-  if (labelBalloonMass) labelBalloonMass.textContent = `Balloon mass (${getUnitLabel('MASS')})`;
-  
-  const labelBurstAlt = document.getElementById('label-burst-alt');
-  if (labelBurstAlt) labelBurstAlt.textContent = `Burst altitude (${getUnitLabel('ALTITUDE')})`;
-  
-  const labelAscentRate = document.getElementById('label-ascent-rate');
-  if (labelAscentRate) labelAscentRate.textContent = `Ascent rate (${getUnitLabel('VELOCITY_VERT')})`;
-  
-  const labelDescentRate = document.getElementById('label-descent-rate');
-  if (labelDescentRate) labelDescentRate.textContent = `Descent rate (${getUnitLabel('VELOCITY_VERT')})`;
-  
-  // Ground station
-  const labelGroundAlt = document.getElementById('label-ground-alt');
-  if (labelGroundAlt) labelGroundAlt.textContent = `Alt (${getUnitLabel('ALTITUDE')})`;
-}
-
-//Update all displayed values after unit change
-function updateDisplayedValuesForUnits() {
-  //update prediction parameters
-  updatePredictionParametersDisplay();
-  
-  //update position display
-  if (lastKnownPosition) {
-    updateMapWithCurrentPosition();
-  }
-  
-  //update ground station display
-  updateGroundStationDisplay();
-}
-
-//Update ground station display with correct units
-function updateGroundStationDisplay() {
-  const groundAltInput = document.querySelector('#ground-alt');
-  if (groundAltInput && groundAltInput.value) {
-    const metricAlt = parseFloat(groundAltInput.dataset.metricValue);
-    if (metricAlt !== undefined && !isNaN(metricAlt)) {
-      const displayAlt = convertToDisplay(metricAlt, 'ALTITUDE');
-      groundAltInput.value = displayAlt.toFixed(0);
-    }
-  }
-}
-
-// DOM elements
-let utcMsg;
-let dateMsg;
-let ir_mod;
-let aprs_call;
-let lat, long, alt;
-let last_update;
-let citystate;
-let aprs_butt, iridium_butt;
-let console_text;
-let radioDropdown;
-let radioInput;
-
-// Last known position for unit conversion updates
-let lastKnownPosition = null;
-
-// Track previous values
-let previousIridiumValue = "";
-let previousAprsValue = "";
-let previousLat = null;
-let previousLong = null;
-
-// Track active instances
-let activeAprsCallsigns = [];
-let activeIridiumModems = [];
-
-// Interval IDs
-let utcIntervalId;
-let trackerIntervalId;
-let statusIntervalId;
-let predictionIntervalId;
-
-// geocoding API rate limiting
-let lastGeocodeTime = 0;
-const GEOCODE_RATE_LIMIT = 10000; 
-
-// Prediction parameters (stored internally in metric)
-let predictionParams = {
-  payloadMass: 2.0,        // kg
-  balloonMass: 1.5,        // kg
-  parachuteDragCoeff: 0.5, // unitless
-  burstAltitude: 30000.0,  // m
-  ascentRate: null,        // m/s
-  descentRate: 5.0         // m/s
-};
-
-// Initialize app
-async function init() {
-  try {
-    const sideTabs = document.querySelectorAll('.side-tab, .sidebar-tab');
-    const panelContents = document.getElementById('panel-contents');
-    let activeTab = null;
-
-    sideTabs.forEach(btn => {
-      btn.addEventListener('click', () => {
-        const panelId = btn.dataset.panel;
-        const panel = document.getElementById(panelId);
-
-        if (activeTab === btn) {
-          btn.classList.remove('active');
-          activeTab = null;
-          if (panelContents) {
-            panelContents.classList.remove('open');
-            panelContents.setAttribute('aria-hidden', 'true');
-          }
-          if (panel) panel.classList.remove('active');
-          document.body.classList.remove('panel-open');
-          return;
-        }
-
-        sideTabs.forEach(b => b.classList.remove('active'));
-        btn.classList.add('active');
-        activeTab = btn;
-
-        document.querySelectorAll('.panel').forEach(p => p.classList.remove('active'));
-        if (panel) panel.classList.add('active');
-        if (panelContents) {
-          panelContents.classList.add('open');
-          panelContents.setAttribute('aria-hidden', 'false');
-        }
-        document.body.classList.add('panel-open');
-      });
-    });
-
-    document.addEventListener('click', (e) => {
-      const target = e.target;
-      if (!target.closest('.panel-contents') && !target.closest('.side-tab') && !target.closest('.sidebar-tab')) {
-        if (panelContents) {
-          panelContents.classList.remove('open');
-          panelContents.setAttribute('aria-hidden', 'true');
-        }
-        sideTabs.forEach(b => b.classList.remove('active'));
-        activeTab = null;
-        document.querySelectorAll('.panel').forEach(p => p.classList.remove('active'));
-        document.body.classList.remove('panel-open');
-      }
-    });
-
-    const addBtn = document.getElementById('add-connection');
-    const list = document.getElementById('connections-list');
-    if (addBtn && list) {
-      addBtn.addEventListener('click', () => addConnection(list));
-      addConnection(list);
-    }
-
-    createCompass(document.getElementById('compass-top-left'));
-    window.setCompassAngle = setCompassAngle;
-
-    try {
-      async function pollHeading() {
-        try {
-          const heading = await invoke('get_heading');
-          if (typeof heading === 'number' || !Number.isNaN(Number(heading))) {
-            setCompassAngle(Number(heading));
-          }
-        } catch (err) {}
-      }
-      pollHeading();
-      setInterval(pollHeading, 1000);
-
-      initThemeSelector();
-    } catch (e) {}
-  } catch (err) {
-    console.warn('UI init warning:', err);
-  }
-
-  // Get DOM elements
-  utcMsg = document.querySelector("#utc-msg");
-  dateMsg = document.querySelector("#date-msg");
-  ir_mod = document.querySelector("#iridium_field");
-  aprs_call = document.querySelector("#aprs_field");
-  lat = document.querySelector("#lat");
-  long = document.querySelector("#long");
-  alt = document.querySelector("#alt");
-  last_update = document.querySelector("#last-update");
-  citystate = document.querySelector("#citystate");
-  aprs_butt = document.querySelector("#aprs_butt");
-  iridium_butt = document.querySelector("#iridium_butt");
-  console_text = document.querySelector("#console-text");
-  radioDropdown = document.querySelector("#radio-method");
-  radioInput = document.querySelector(".dropdown input[type='text']");
-  
-  // Setup prediction controls
-  setupPredictionControls();
-
-  // Disable context menu on non-text elements to avoid accidental right-click UI interactions
-  document.addEventListener('contextmenu', (e) => {
-    try {
-      const tgt = e.target;
-      if (!tgt) { e.preventDefault(); return; }
-      // allow on form controls or editable regions
-      if (tgt.closest && (tgt.closest('input') || tgt.closest('textarea') || tgt.closest('select') || tgt.isContentEditable)) return;
-      // otherwise prevent
-      e.preventDefault();
-    } catch (err) { e.preventDefault(); }
-  });
-  
-  const filteringMethod = document.querySelector("#filtering-method");
-  if (filteringMethod) {
-    filteringMethod.addEventListener("change", handleFilteringMethodChange);
-    try {
-      const savedMethod = await invoke("get_filtering_method");
-      if (savedMethod) {
-        filteringMethod.value = savedMethod;
-      }
-    } catch (error) {
-      console.error("Error loading filtering method:", error);
-    }
-  }
-
-  //Setup units selector
-  const unitsSelector = document.querySelector("#units-selector");
-  if (unitsSelector) {
-    unitsSelector.addEventListener("change", handleUnitsChange);
-    const savedUnits = localStorage.getItem('harp-units') || 'metric';
-    currentUnits = savedUnits;
-    unitsSelector.value = savedUnits;
-    updateUnitLabels();
-  }
-
-  // Setup ground station input saves
-  const groundLatInput = document.querySelector('#ground-lat');
-  const groundLonInput = document.querySelector('#ground-lon');
-  const groundAltInput = document.querySelector('#ground-alt');
-  
-  if (groundLatInput) {
-    groundLatInput.addEventListener('blur', () => {
-      localStorage.setItem('ground_station_lat', groundLatInput.value);
-    });
-  }
-  
-  if (groundLonInput) {
-    groundLonInput.addEventListener('blur', () => {
-      localStorage.setItem('ground_station_lon', groundLonInput.value);
-    });
-  }
-  
-  if (groundAltInput) {
-    groundAltInput.addEventListener('blur', () => {
-      const displayValue = parseFloat(groundAltInput.value);
-      if (!isNaN(displayValue)) {
-        const metricValue = convertToMetric(displayValue, 'ALTITUDE');
-        localStorage.setItem('ground_station_alt', metricValue.toString());
-        groundAltInput.dataset.metricValue = metricValue;
-      }
-    });
-  }
-
-  // Setup aircraft radius control
-  const aircraftRadiusInput = document.querySelector("#aircraft-radius-km");
-  if (aircraftRadiusInput) {
-    aircraftRadiusInput.addEventListener("change", (e) => {
-      const radiusKm = parseFloat(e.target.value) || 100;
-      const radiusMeters = radiusKm * 1000;
-      const mapIframe = document.querySelector('.screen');
-      if (mapIframe && mapIframe.contentWindow) {
-        mapIframe.contentWindow.postMessage({
-          type: 'SET_AIRCRAFT_RADIUS',
-          radiusMeters: radiusMeters
-        }, '*');
-      }
-      if (console_text) console_text.textContent = `Aircraft display radius set to ${radiusKm} km`;
-    });
-  }
-
-  if (radioDropdown && radioInput) {
-    radioDropdown.addEventListener("change", handleRadioDropdownChange);
-    updateInputPlaceholder(radioDropdown.value);
-    radioInput.addEventListener("blur", handleRadioInputBlur);
-    radioInput.addEventListener("keypress", function(event) {
-      if (event.key === "Enter") {
-        handleRadioInputBlur(event);
-      }
-    });
-    loadRadioInputValue(radioDropdown.value);
-  }
-
-  function handleRadioDropdownChange(event) {
-    const selectedRadio = event.target.value;
-    updateInputPlaceholder(selectedRadio);
-    loadRadioInputValue(selectedRadio);
-  }
-
-  function updateInputPlaceholder(radioType) {
-    const radioInput = document.querySelector(".dropdown input[type='text']");
-    if (!radioInput) return;
-    
-    if (radioType === "iridium_field") {
-      radioInput.placeholder = "Enter Iridium Modem ID";
-    } else if (radioType === "aprs_field") {
-      radioInput.placeholder = "Enter APRS Callsign";
-    }
-  }
-
-  async function loadRadioInputValue(radioType) {
-    const radioInput = document.querySelector(".dropdown input[type='text']");
-    if (!radioInput) return;
-    
-    try {
-      if (radioType === "iridium_field") {
-        const savedIridium = await invoke("get_irr_modem");
-        radioInput.value = savedIridium || "";
-      } else if (radioType === "aprs_field") {
-        const savedAprs = await invoke("get_aprs_callsign");
-        radioInput.value = savedAprs || "";
-      }
-    } catch (error) {
-      if (console_text) console_text.textContent = "Error loading radio value: " + error;
-      else console.error("Error loading radio value:", error);
-    }
-  }
-
-  async function handleRadioInputBlur(event) {
-    const radioDropdown = document.querySelector("#radio-method");
-    if (!radioDropdown) return;
-    
-    const selectedRadio = radioDropdown.value;
-    const newValue = event.target.value.trim();
-    
-    if (selectedRadio === "iridium_field") {
-      await handleIridiumUpdate(newValue);
-    } else if (selectedRadio === "aprs_field") {
-      await handleAprsUpdate(newValue);
-    }
-    
-    event.target.value = "";
-  }
-  
-  // Initialize the map iframe
-  initMapIframe();
-  
-  // Set up event listeners for input fields
-  if (ir_mod) {
-    ir_mod.addEventListener("blur", handleIridiumInput);
-    ir_mod.addEventListener("keypress", function(event) {
-      if (event.key === "Enter") {
-        handleIridiumInput(event);
-      }
-    });
-  }
-
-  if (aprs_call) {
-    aprs_call.addEventListener("blur", handleAprsInput);
-    aprs_call.addEventListener("keypress", function(event) {
-      if (event.key === "Enter") {
-        handleAprsInput(event);
-      }
-    });
-  }
-
-  //Stadia Maps API key field
-  const stadiaInput = document.querySelector('#stadia-api-key');
-  if (stadiaInput) {
-    stadiaInput.addEventListener('blur', async () => {
-      const val = stadiaInput.value.trim();
-      localStorage.setItem('stadia_api_key', val);
-      if (val) {
-        sendStadiaKeyToMap(val);
-      } else {
-        //user cleared key -> go back to .env value
-        let envK = '';
-        try { envK = await invoke('get_stadia_api_key'); } catch(e){}
-        if (envK) sendStadiaKeyToMap(envK);
-      }
-      console.log('Stadia API key updated to', val ? '<hidden>' : '(cleared)');
-    });
-    stadiaInput.addEventListener('keypress', (e) => {
-      if (e.key === 'Enter') {
-        stadiaInput.blur();
-      }
-    });
-  }
-
-  //APRS.FI API key input 
-  const aprsfiInput = document.querySelector('#aprsfi-api-key');
-  if (aprsfiInput) {
-    aprsfiInput.addEventListener('blur', async () => {
-      const val = aprsfiInput.value.trim();
-      localStorage.setItem('aprsfi_api_key', val);
-      try {
-        await invoke('set_aprsfi_api_key', { key: val });
-        console.log('APRS.FI API key updated');
-        // if APRS is already active, re-init
-        await invoke('set_aprs');
-      } catch (e) {
-        console.error('Failed to set APRS.FI API key:', e);
-      }
-    });
-    aprsfiInput.addEventListener('keypress', (e) => {
-      if (e.key === 'Enter') {
-        aprsfiInput.blur();
-      }
-    });
-  }
-  
-
-  await loadSavedValues();
-  
-  // Initial Updates
-  await date();
-  await updateTracker();
-  await updateUtc();
-  await updateActiveStatus();
-  await updateConnectedClients();
-  
-  // Send current units to the altitude graph iframe
-  setTimeout(() => {
-    sendUnitsToAltitudeGraph();
-  }, 500);
-  
-  // Start timers
-  utcIntervalId = setInterval(updateUtc, 100);
-  trackerIntervalId = setInterval(updateTracker, 15000);
-  statusIntervalId = setInterval(updateActiveStatus, 1000);
-  
-  // Start prediction timer (every 30 seconds)
-  predictionIntervalId = setInterval(runPrediction, 30000);
-}
-
-// Setup prediction controls
-function setupPredictionControls() {
-  updatePredictionParametersDisplay();
-  
-  // Get prediction panel inputs
-  const payloadMassInput = document.querySelector('#param-payload-mass');
-  const balloonMassInput = document.querySelector('#param-balloon-mass');
-  const parachuteDragInput = document.querySelector('#param-parachute-drag');
-  const burstAltInput = document.querySelector('#param-burst-alt');
-  const ascentRateInput = document.querySelector('#param-ascent-rate');
-  const descentRateInput = document.querySelector('#param-descent-rate');
-  
-  // Add event listeners for parameter changes
-  if (payloadMassInput) {
-    payloadMassInput.addEventListener('change', (e) => {
-      const displayValue = parseFloat(e.target.value) || 2.0;
-      predictionParams.payloadMass = convertToMetric(displayValue, 'MASS');
-      updatePredictionParams();
-    });
-  }
-  
-  if (balloonMassInput) {
-    balloonMassInput.addEventListener('change', (e) => {
-      const displayValue = parseFloat(e.target.value) || 1.5;
-      predictionParams.balloonMass = convertToMetric(displayValue, 'MASS');
-      updatePredictionParams();
-    });
-  }
-  
-  if (parachuteDragInput) {
-    parachuteDragInput.addEventListener('change', (e) => {
-      predictionParams.parachuteDragCoeff = parseFloat(e.target.value) || 0.5;
-      updatePredictionParams();
-    });
-  }
-  
-  if (burstAltInput) {
-    burstAltInput.addEventListener('change', (e) => {
-      const displayValue = parseFloat(e.target.value) || 30000.0;
-      predictionParams.burstAltitude = convertToMetric(displayValue, 'ALTITUDE');
-      updatePredictionParams();
-    });
-  }
-  
-  if (ascentRateInput) {
-    ascentRateInput.addEventListener('change', (e) => {
-      const value = e.target.value.trim();
-      const displayValue = value === '' ? null : parseFloat(value);
-      predictionParams.ascentRate = displayValue === null ? null : convertToMetric(displayValue, 'VELOCITY_VERT');
-      updatePredictionParams();
-    });
-  }
-  
-  if (descentRateInput) {
-    descentRateInput.addEventListener('change', (e) => {
-      const displayValue = parseFloat(e.target.value) || 5.0;
-      predictionParams.descentRate = convertToMetric(displayValue, 'VELOCITY_VERT');
-      updatePredictionParams();
-    });
-  }
-  
-  // Get run prediction button
-  const runBtn = document.querySelector('#run-prediction-btn');
-  if (runBtn) {
-    runBtn.addEventListener('click', async () => {
-      await runPrediction();
-    });
-  }
-  
-  // Algorithm selector
-  const algoSelect = document.querySelector('#prediction-algo');
-  if (algoSelect) {
-    algoSelect.addEventListener('change', async (e) => {
-      const algorithm = e.target.value;
-      try {
-        await invoke('set_predictor', { name: algorithm });
-        if (console_text) console_text.textContent = `Predictor set to: ${algorithm}`;
-      } catch (error) {
-        if (console_text) console_text.textContent = `Error setting predictor: ${error}`;
-      }
-    });
-  }
-}
-
-//update the prediction parameters to show correct units and values
-function updatePredictionParametersDisplay() {
-  const payloadMassInput = document.querySelector('#param-payload-mass');
-  const balloonMassInput = document.querySelector('#param-balloon-mass');
-  const burstAltInput = document.querySelector('#param-burst-alt');
-  const ascentRateInput = document.querySelector('#param-ascent-rate');
-  const descentRateInput = document.querySelector('#param-descent-rate');
-  
-  if (payloadMassInput) payloadMassInput.value = (convertToDisplay(predictionParams.payloadMass, 'MASS')).toFixed(1);
-  if (balloonMassInput) balloonMassInput.value = (convertToDisplay(predictionParams.balloonMass, 'MASS')).toFixed(1);
-  if (burstAltInput) burstAltInput.value = (convertToDisplay(predictionParams.burstAltitude, 'ALTITUDE')).toFixed(0);
-  if (ascentRateInput) ascentRateInput.value = predictionParams.ascentRate === null ? '' : (convertToDisplay(predictionParams.ascentRate, 'VELOCITY_VERT')).toFixed(1);
-  if (descentRateInput) descentRateInput.value = (convertToDisplay(predictionParams.descentRate, 'VELOCITY_VERT')).toFixed(1);
-}
-
-// Update prediction parameters in backend
-async function updatePredictionParams() {
-  try {
-    await invoke('set_prediction_params', {
-      payloadMass: predictionParams.payloadMass,
-      balloonMass: predictionParams.balloonMass,
-      parachuteDragCoeff: predictionParams.parachuteDragCoeff,
-      burstAltitude: predictionParams.burstAltitude,
-      ascentRate: predictionParams.ascentRate,
-      descentRate: predictionParams.descentRate
-    });
-  } catch (error) {
-    console.error('Error updating prediction params:', error);
-  }
-}
-
-// Run prediction
-async function runPrediction() {
-  try {
-    if (console_text) console_text.textContent = "Starting predictions...";
-    
-    // Update parameters first
-    await updatePredictionParams();
-    
-    // Run prediction
-    const result = await invoke('run_prediction');
-    
-    if (console_text) console_text.textContent = "Predictions complete!";
-    
-    // Send prediction data to map
-    const mapIframe = document.querySelector('.screen');
-    if (mapIframe && mapIframe.contentWindow) {
-      mapIframe.contentWindow.postMessage({
-        type: 'UPDATE_PREDICTION',
-        data: result
-      }, '*');
-    }
-    
-    console.log('Prediction result:', result);
-  } catch (error) {
-    if (console_text) console_text.textContent = `Prediction error: ${error}`;
-    console.error('Prediction error:', error);
-  }
-}
-
-async function loadSavedValues() {
-  try {
-    const savedIridium = await invoke("get_irr_modem");
-    if (savedIridium) {
-      if (ir_mod) ir_mod.value = savedIridium;
-      previousIridiumValue = savedIridium;
-    }
-    
-    const savedAprs = await invoke("get_aprs_callsign");
-    if (savedAprs) {
-      if (aprs_call) aprs_call.value = savedAprs;
-      previousAprsValue = savedAprs;
-    }
-
-    // load/stash Stadia Maps key from local storage
-    const stadiaInput = document.querySelector('#stadia-api-key');
-    const storedKey = localStorage.getItem('stadia_api_key') || '';
-    if (stadiaInput) stadiaInput.value = storedKey;
-    if (storedKey) {
-      sendStadiaKeyToMap(storedKey);
-    }
-
-    // load/stash APRS.FI key from local storage
-    const aprsfiInput = document.querySelector('#aprsfi-api-key');
-    const storedAprsKey = localStorage.getItem('aprsfi_api_key') || '';
-    if (aprsfiInput) aprsfiInput.value = storedAprsKey;
-    if (storedAprsKey) {
-      try { await invoke('set_aprsfi_api_key', { key: storedAprsKey }); } catch(e){}
-    }
-    
-    // load ground station values
-    const groundLat = localStorage.getItem('ground_station_lat') || '';
-    const groundLon = localStorage.getItem('ground_station_lon') || '';
-    const groundAltMetric = localStorage.getItem('ground_station_alt') || '';
-    
-    const groundLatInput = document.querySelector('#ground-lat');
-    const groundLonInput = document.querySelector('#ground-lon');
-    const groundAltInput = document.querySelector('#ground-alt');
-    
-    if (groundLatInput) groundLatInput.value = groundLat;
-    if (groundLonInput) groundLonInput.value = groundLon;
-    if (groundAltInput && groundAltMetric) {
-      const metricAlt = parseFloat(groundAltMetric);
-      if (!isNaN(metricAlt)) {
-        groundAltInput.dataset.metricValue = metricAlt;
-        groundAltInput.value = convertToDisplay(metricAlt, 'ALTITUDE').toFixed(0);
-      }
-    }
-  } catch (error) {
-    if (console_text) console_text.textContent = "Failed to load saved values:" + error;
-    else console.error("Failed to load saved values:", error);
-  }
-}
-
-//------------------------------Update Functions------------------------------
-// Update date
-async function date() {
-  try {
-    dateMsg.textContent = await invoke("date");
-  } catch (error) {
-    console_text.textContent = "Error updating date:" + error;
-  }
-}
-
-async function updateUtc() {
-  try {
-    utcMsg.textContent = await invoke("utc");
-    await updateLastUpdate();
-  } catch (error) {
-    console_text.textContent = "Error updating timing:" + error;
-  }
-}
-
-async function updateTracker() {
-  try {
-    // Update tracker data
-    await invoke("update");
-    
-    // Update position display
-    await getPosition();
-    
-    const now = new Date();
-    const timeStr = now.toLocaleTimeString('en-US', { hour: '2-digit', minute: '2-digit' });
-    if (console_text) console_text.textContent = `${timeStr}: Tracker data updated`;
-  } catch (error) {
-    console_text.textContent = "Error in tracker update cycle:" + error;
-  }
-}
-
-// Update status indicators for active services
-async function updateActiveStatus() {
-  try {
-    // Check if active and show button on the Connected Clients
-    const isAprsActive = await invoke("is_aprs_active");
-    if (aprs_butt) {
-      if (isAprsActive) aprs_butt.style.display = "inline";
-      else aprs_butt.style.display = "none";
-    }
-    
-    try {
-      const isIridiumActive = await invoke("is_iridium_active");
-      if (iridium_butt) {
-        if (isIridiumActive) iridium_butt.style.display = "inline";
-        else iridium_butt.style.display = "none";
-      }
-    } catch (error) {
-      console_text.textContent = "Error checking Iridium status:" + error;
-    }
-    
-    // Update the connection display with fresh last update time
-    await updateConnectedClients();
-    try { await updateConnectionIndicators(); } catch(e) { }
-  } catch (error) {
-    console_text.textContent = "Error updating active status:" + error;
-  }
-}
-
-// Update the display of connected clients
-async function updateConnectedClients() {
-  try {
-    const signalFlexbox = document.querySelector(".signal_flexbox");
-    if (!signalFlexbox) return;
-    
-    //Clear the existing stuff
-    const existingConnections = signalFlexbox.querySelectorAll('.connection-item');
-    existingConnections.forEach(item => item.remove());
-    
-    //validity data
-    const aprsValidity = await invoke("get_aprs_validity");
-    const iridiumValidity = await invoke("get_iridium_validity");
-    
-    // Update APRS button if there are active APRS connections
-    if (activeAprsCallsigns.length > 0 && aprs_butt) {
-      const callsign = activeAprsCallsigns[0];
-      aprs_butt.textContent = `APRS\n${callsign}`;
-      const isValid = aprsValidity[0];
-      aprs_butt.style.backgroundColor = isValid ? "#90EE90" : "white";
-      aprs_butt.style.display = "inline";
-      
-      // Add additional APRS connections
-      for (let i = 1; i < activeAprsCallsigns.length; i++) {
-        const item = document.createElement("button");
-        item.className = "connection-item";
-        item.textContent = `APRS\n${activeAprsCallsigns[i]}`;
-        const isValid = aprsValidity[i];
-        item.style.backgroundColor = isValid ? "#90EE90" : "white";
-        signalFlexbox.appendChild(item);
-      }
-    }
-    
-    // Update Iridium button if there are active Iridium connections
-    if (activeIridiumModems.length > 0 && iridium_butt) {
-      const modem = activeIridiumModems[0]; 
-      iridium_butt.textContent = `Iridium | ${modem}`;
-      const isValid = iridiumValidity[0]; 
-      iridium_butt.style.backgroundColor = isValid ? "#90EE90" : "white";
-      iridium_butt.style.display = "inline";
-      
-      // Add additional Iridium connections
-      for (let i = 1; i < activeIridiumModems.length; i++) {
-        const item = document.createElement("button");
-        item.className = "connection-item";
-        item.textContent = `Iridium | ${activeIridiumModems[i]}`;
-        const isValid = iridiumValidity[i];
-        item.style.backgroundColor = isValid ? "#90EE90" : "white";
-        signalFlexbox.appendChild(item);
-      }
-    }
-  } catch (error) {
-    console.error("Error updating connected clients:", error);
-  }
-}
-
-// Look up city and state based on coordinates
-async function updateCityAndState(latitude, longitude) {
-  // Check rate limiting
-  const now = Date.now();
-  if (now - lastGeocodeTime < GEOCODE_RATE_LIMIT) {
-    return;
-  }
-  
-  lastGeocodeTime = now;
-  
-  try {
-    const response = await fetch(`https://nominatim.openstreetmap.org/reverse?format=json&lat=${latitude}&lon=${longitude}&zoom=10&addressdetails=1`, {
-      headers: {
-        'User-Agent': 'HARP-Tracker-App/1.0'
-      }
-    });
-
-    if (!response.ok) {
-      if (response.status === 429) {
-        // rate limited
-        if (citystate) citystate.textContent = "Location, Rate limited";
-        return;
-      }
-      throw new Error(`Geocoding API error: ${response.status}`);
-    }
-
-    const data = await response.json();
-    
-    // Extract city and state information
-    let cityName = data.address.city || 
-                   data.address.town || 
-                   data.address.village || 
-                   data.address.hamlet ||
-                   "Unknown";
-                   
-    let stateName = data.address.state || 
-                    data.address.province || 
-                    data.address.region ||
-                    "";
-    
-    // Update UI
-    if (citystate) citystate.textContent = cityName + ", " + stateName;
-
-    console.log(`Updated location: ${cityName}, ${stateName}`);
-  } catch (error) {
-    console.error("Error getting city/state:", error);
-    if (citystate) citystate.textContent = "Location, Unknown";
-  }
-}
-
-// Update the "last update" text
-async function updateLastUpdate() {
-  try {
-    const seconds = await invoke("get_last_update");
-    if (last_update) last_update.textContent = `Last update: ${seconds}s ago`;
-  } catch (error) {
-    console.error("Error updating last update time:", error);
-  }
-}
-
-
-
-//------------------------------Input Handlers------------------------------
-
-
-// Handle Iridium input changes
-async function handleIridiumUpdate(newValue) {
-  try {
-    if (newValue !== "") {
-      await invoke("set_irr_modem", { id: newValue });
-      await invoke("set_iridium");
-      
-      // Add to active instances list if not already present
-      if (!activeIridiumModems.includes(newValue)) {
-        activeIridiumModems.push(newValue);
-      }
-      
-      if (console_text) console_text.textContent = "Iridium modem updated: " + newValue;
-      else console.log("Iridium modem updated:", newValue);
-      
-      // Update the display of connected clients
-      await updateConnectedClients();
-    }
-  } catch (error) {
-    if (console_text) console_text.textContent = "Error updating Iridium settings: " + error;
-    else console.error("Error updating Iridium settings:", error);
-  }
-}
-
-//for handling filtering method changes
-async function handleFilteringMethodChange(event) {
-  const newValue = event.target.value;
-  try {
-    await invoke("set_filtering_method", { method: newValue });
-    if (console_text) console_text.textContent = "Filtering method updated: " + newValue;
-  } catch (error) {
-    if (console_text) console_text.textContent = "Error updating filtering method: " + error;
-    else console.error("Error updating filtering method:", error);
-  }
-}
-
-// Handle units selector change
-function handleUnitsChange(event) {
-  const newUnits = event.target.value;
-  currentUnits = newUnits;
-  localStorage.setItem('harp-units', newUnits);
-  updateUnitLabels();
-  updateDisplayedValuesForUnits();
-  
-  //send unit change message to map iframe
-  const mapIframe = document.querySelector('.screen');
-  if (mapIframe && mapIframe.contentWindow) {
-    mapIframe.contentWindow.postMessage({
-      type: 'SET_UNITS',
-      units: newUnits
-    }, '*');
-  }
-  
-  //send unit change message to altitude graph iframe
-  const altGraphIframe = document.querySelector('#altitude-graph');
-  if (altGraphIframe && altGraphIframe.contentWindow) {
-    altGraphIframe.contentWindow.postMessage({
-      type: 'SET_UNITS',
-      units: newUnits
-    }, '*');
-  }
-  //send to display console
-  if (console_text) console_text.textContent = `Units changed to ${newUnits === 'metric' ? 'Metric' : 'Imperial'}`;
-  runPrediction();//run predictions again so that it updates the units
-}
-
-// Handle APRS input changes
-async function handleAprsUpdate(newValue) {
-  try {
-    if (newValue !== "") {
-      await invoke("set_aprs_callsign", { id: newValue });
-      await invoke("set_aprs");
-      
-      // Add to active instances list if not already present
-      if (!activeAprsCallsigns.includes(newValue)) {
-        activeAprsCallsigns.push(newValue);
-      }
-      
-      if (console_text) console_text.textContent = "APRS callsign updated: " + newValue;
-      else console.log("APRS callsign updated:", newValue);
-      
-      // Update the display of connected clients
-      await updateConnectedClients();
-    }
-  } catch (error) {
-    if (console_text) console_text.textContent = "Error updating APRS settings: " + error;
-    else console.error("Error updating APRS settings:", error);
-  }
-}
-
-// Get and display current position
-async function getPosition() {
-  try {
-    const currentLat = await invoke("get_lat");
-    const currentLong = await invoke("get_long");
-    const altitude = await invoke("get_alt");
-    const horiz_vel = await invoke("get_horiz_vel");  
-    const vert_vel = await invoke("get_vert_vel"); 
-
-    const numLat = Number(currentLat);
-    const numLong = Number(currentLong);
-    const numAlt = Number(altitude);
-    const numHoriz = Number(horiz_vel);
-    const numVert = Number(vert_vel);
-    
-    // Store for later unit conversions
-    lastKnownPosition = {
-      lat: numLat,
-      lon: numLong,
-      alt: numAlt,
-      horiz_vel: numHoriz,
-      vert_vel: numVert
-    };
-
-    if (lat && !Number.isNaN(numLat)) lat.textContent = numLat + ",";
-    if (long && !Number.isNaN(numLong)) long.textContent = numLong;
-    if (alt && !Number.isNaN(numAlt)){
-      const displayAlt = convertToDisplay(numAlt, 'ALTITUDE');
-      const altUnit = getUnitLabel('ALTITUDE');
-      alt.textContent = displayAlt.toFixed(0) + altUnit;
-    }
-
-    // Update map with metric values for internal use
-    if (!Number.isNaN(numLat) && !Number.isNaN(numLong) && !Number.isNaN(numAlt) && numAlt != 0.0)  {
-      updateMap(numLat, numLong, numAlt, numHoriz, numVert); 
-
-      // Check if coordinates have changed significantly before updating city
-      const hasLocationChanged = 
-        previousLat === null || 
-        previousLong === null ||
-        (typeof numLat === 'number' && typeof previousLat === 'number' && Math.abs(numLat - previousLat) > 0.01) ||
-        (typeof numLong === 'number' && typeof previousLong === 'number' && Math.abs(numLong - previousLong) > 0.01);
-      
-      // Update elements if location has changed
-      if (hasLocationChanged) {
-        if (!Number.isNaN(numLat) && !Number.isNaN(numLong)) {
-          //update city and state
-          updateCityAndState(numLat, numLong);
-
-          // make UTC timestamp 
-          const now = new Date();
-          const utcTimeStr = now.getUTCHours().toString().padStart(2, '0') + ":" + 
-                            now.getUTCMinutes().toString().padStart(2, '0') + ":" + 
-                            now.getUTCSeconds().toString().padStart(2, '0');
-          //update compass          
-          try { await updateCompass(numLat, numLong); } catch(e){}
-          
-          // update alt graph with the metric altitude (graph handles its own conversion)
-          try { updateAltitudeGraph(utcTimeStr, numAlt); } catch(e){}
-        }
-        previousLat = Number.isFinite(numLat) ? numLat : previousLat;
-        previousLong = Number.isFinite(numLong) ? numLong : previousLong;
-      }
-    }
-  } catch (error) {
-    if (console_text) console_text.textContent = "Error getting position:" + error;
-    else console.error("Error getting position:", error);
-  }
-}
-
-//function that adds a connection to the tracker
-function addConnection(container) {
-  const entry = document.createElement('div');
-  entry.className = 'connection-entry';
-
-  const indicator = document.createElement('div');
-  indicator.className = 'conn-indicator';
-
-  const type = document.createElement('select');
-  ['None','APRS','Iridium'].forEach(n => {
-    const o = document.createElement('option'); o.value = n; o.textContent = n; type.appendChild(o);
-  });
-
-  const ident = document.createElement('input');
-  ident.type = 'text';
-  ident.placeholder = 'Identifier (callsign / IMEI)';
-
-  const remove = document.createElement('button');
-  remove.className = 'remove';
-  remove.innerText = '✕';
-
-  const activate = document.createElement('button');
-  activate.className = 'activate';
-  activate.innerText = 'Activate';
-
-  async function commitConnection() {
-    const val = ident.value.trim();
-    const t = type.value;
-    if (!val || t === 'None') return;
-
-    try {
-      if (t === 'APRS') {
-        await invoke('set_aprs_callsign', { id: val });
-        await invoke('set_aprs');
-        if (!activeAprsCallsigns.includes(val)) activeAprsCallsigns.push(val);
-      } else if (t === 'Iridium') {
-        await invoke('set_irr_modem', { id: val });
-        await invoke('set_iridium');
-        if (!activeIridiumModems.includes(val)) activeIridiumModems.push(val);
-      }
-
-      await updateConnectedClients();
-      try { await invoke('update'); } catch(e) {}
-
-      setTimeout(()=>{ updateConnectionIndicators().catch(()=>{}); }, 800);
-    } catch (err) {
-      if (console_text) console_text.textContent = 'Error saving connection: ' + err;
-      else console.error('Error saving connection:', err);
-    }
-  }
-
-  remove.addEventListener('click', async () => {
-    const val = ident.value.trim();
-    const t = type.value;
-    if (t === 'APRS') {
-      const idx = activeAprsCallsigns.indexOf(val);
-      if (idx >= 0) activeAprsCallsigns.splice(idx, 1);
-      try { await invoke('set_aprs_callsign', { id: '' }); await invoke('set_aprs'); } catch(e){}
-    } else if (t === 'Iridium') {
-      const idx = activeIridiumModems.indexOf(val);
-      if (idx >= 0) activeIridiumModems.splice(idx, 1);
-      try { await invoke('set_irr_modem', { id: '' }); await invoke('set_iridium'); } catch(e){}
-    }
-    container.removeChild(entry);
-    await updateConnectedClients();
-    try { await invoke('update'); } catch(e) {}
-    setTimeout(()=>{ updateConnectionIndicators().catch(()=>{}); }, 800);
-  });
-
-  ident.addEventListener('blur', commitConnection);
-  ident.addEventListener('keypress', (e) => { if (e.key === 'Enter') commitConnection(); });
-  type.addEventListener('change', () => { });
-
-  activate.addEventListener('click', async () => {
-    const val = ident.value.trim();
-    const t = type.value;
-    if (!val || t === 'None') { showConsole('Enter identifier and select method first'); return; }
-    if (activate.dataset.active === '1') {
-      activate.dataset.active = '0';
-      activate.innerText = 'Activate';
-      if (t === 'APRS') {
-        const idx = activeAprsCallsigns.indexOf(val); if (idx >= 0) activeAprsCallsigns.splice(idx, 1);
-        try { await invoke('set_aprs_callsign', { id: '' }); await invoke('set_aprs'); } catch(e) { console.error(e); }
-      } else if (t === 'Iridium') {
-        const idx = activeIridiumModems.indexOf(val); if (idx >= 0) activeIridiumModems.splice(idx, 1);
-        try { await invoke('set_irr_modem', { id: '' }); await invoke('set_iridium'); } catch(e) { console.error(e); }
-      }
-      indicator.classList.remove('ok');
-      showConsole('Deactivated ' + val);
-      await updateConnectedClients();
-      return;
-    }
-
-    showConsole('Activating ' + val + '...');
-    await commitConnection();
-    activate.dataset.active = '1';
-    activate.innerText = 'Deactivate';
-    indicator.classList.add('pending');
-    setTimeout(async () => { await updateConnectionIndicators(); indicator.classList.remove('pending'); }, 1500);
-  });
-
-  entry.appendChild(indicator);
-  entry.appendChild(type);
-  entry.appendChild(ident);
-  entry.appendChild(activate);
-  entry.appendChild(remove);
-
-  container.appendChild(entry);
-  ident.focus();
-}
-
-//------------------------------Connection Handlers------------------------------
-
-
-// update indicators for all connection entries by querying backend validity
-async function updateConnectionIndicators() {
-  try {
-    const entries = document.querySelectorAll('.connection-entry');
-    if (!entries || entries.length === 0) return;
-
-    const aprsValidity = await invoke('get_aprs_validity').catch(() => []);
-    const iridiumValidity = await invoke('get_iridium_validity').catch(() => []);
-    const savedAprsCallsign = await invoke('get_aprs_callsign').catch(()=>null);
-    const savedIrrModem = await invoke('get_irr_modem').catch(()=>null);
-
-    entries.forEach(entry => {
-      const sel = entry.querySelector('select');
-      const input = entry.querySelector('input');
-      const indicator = entry.querySelector('.conn-indicator');
-      if (!sel || !input || !indicator) return;
-      const t = sel.value;
-      const id = input.value.trim();
-
-      // clear pending marker if any
-      indicator.classList.remove('pending');
-      if (t === 'APRS') {
-
-        // Prefer exact match with the backend's stored callsign if available
-        let isValid = false;
-        if (savedAprsCallsign && id === savedAprsCallsign) {
-          isValid = aprsValidity.some(v => v === true);
-        } else if (aprsValidity.length > 0 && activeAprsCallsigns.length === aprsValidity.length) {
-          const idx = activeAprsCallsigns.indexOf(id);
-          isValid = (idx >= 0 && aprsValidity[idx]);
-        } else {
-          // fallback: if any validity true, and we have only one active entry, mark it
-          if (aprsValidity.filter(Boolean).length === 1 && activeAprsCallsigns.length === 1 && activeAprsCallsigns[0] === id) isValid = true;
-        }
-        if (isValid) indicator.classList.add('ok'); else indicator.classList.remove('ok');
-      } else if (t === 'Iridium') {
-        let isValid = false;
-        if (savedIrrModem && id === savedIrrModem) {
-          isValid = iridiumValidity.some(v => v === true);
-        } else if (iridiumValidity.length > 0 && activeIridiumModems.length === iridiumValidity.length) {
-          const idx = activeIridiumModems.indexOf(id);
-          isValid = (idx >= 0 && iridiumValidity[idx]);
-        } else {
-          if (iridiumValidity.filter(Boolean).length === 1 && activeIridiumModems.length === 1 && activeIridiumModems[0] === id) isValid = true;
-        }
-        if (isValid) indicator.classList.add('ok'); else indicator.classList.remove('ok');
-      } else {
-        indicator.classList.remove('ok');
-      }
-    });
-  } catch (err) {
-    console.error('Error updating connection indicators:', err);
-  }
-}
-
-//update UTC text and last-update placeholder
-export function updateInfo({utcText, lastUpdate, cityState}){
-    const u = document.getElementById('utc-msg');
-    const l = document.getElementById('last-update');
-    const c = document.getElementById('citystate');
-    if(u && utcText) u.textContent = utcText;
-    if(l && lastUpdate) l.textContent = lastUpdate;
-    if(c && cityState) c.textContent = cityState;
-}
-
-//helper to show short messages in the console area
-function showConsole(msg, timeout=4000) {
-  if (console_text) {
-    console_text.textContent = msg;
-    if (timeout > 0) setTimeout(()=>{ if (console_text && console_text.textContent === msg) console_text.textContent = ''; }, timeout);
-  } else {
-    console.log(msg);
-  }
-}
-
-
-//------------------------------Map Functions/Handlers------------------------------
-
-
-//Init the map iframe
-function initMapIframe() {
-  const mapIframe = document.querySelector('.screen');
-  
-  // Set the iframe source to the map HTML file
-  mapIframe.src = 'map.html';
-  
-  window.addEventListener('message', async (event) => {
-    // Check if the map is ready
-    if (event.data && event.data.type === 'MAP_READY') {
-      console.log('Map is ready');
-      
-      //send current units
-      mapIframe.contentWindow.postMessage({
-        type: 'SET_UNITS',
-        units: currentUnits
-      }, '*');
-      
-      // Send current position if we have it
-      updateMapWithCurrentPosition();
-      
-      // figure out which key to use: user override stored, else env value
-      const storedKey = localStorage.getItem('stadia_api_key') || '';
-      let envKey = '';
-      try {
-        envKey = await invoke('get_stadia_api_key');
-      } catch (e) {
-        console.warn('Unable to fetch env Stadia API key:', e);
-      }
-      const keyToSend = storedKey || envKey || '';
-      if (keyToSend) {
-        sendStadiaKeyToMap(keyToSend);
-      }
-    }
-  });
-}
-
-// Update the map with current position
-async function updateMapWithCurrentPosition() {
-  try {
-    const currentLat = await invoke("get_lat");
-    const currentLong = await invoke("get_long");
-    const altitude = await invoke("get_alt");
-    const horiz = await invoke("get_horiz_vel");
-    const vert = await invoke("get_vert_vel");
-    
-    console.log(`Fetched velocities: H:${horiz} V:${vert}`);
-    
-    if (currentLat !== 0 || currentLong !== 0) {
-      updateMap(currentLat, currentLong, altitude, horiz, vert);
-    }
-  } catch (error) {
-    if (console_text) console_text.textContent = "Error getting position for map update:" + error;
-    else console.error("Error getting position for map update:", error);
-  }
-}
-
-async function updateMap(latitude, longitude, altitude, horiz_vel, vert_vel) {
-  const mapIframe = document.querySelector('.screen');
-  
-  // Make sure iframe is loaded
-  if (!mapIframe || !mapIframe.contentWindow) {
-    console.warn('Map iframe not ready');
-    return;
-  }
-  
-  
-  if (typeof horiz_vel === 'undefined' || typeof vert_vel === 'undefined') {
-    try {
-      horiz_vel = await invoke("get_horiz_vel");
-      vert_vel = await invoke("get_vert_vel");
-    } catch (error) {
-      console.error("Error fetching velocities:", error);
-      horiz_vel = 0;
-      vert_vel = 0;
-    }
-  }
-  mapIframe.contentWindow.postMessage({
-    type: 'UPDATE_POSITION',
-    lat: latitude,
-    lng: longitude,
-    lng: longitude,
-    alt: altitude,
-    horiz_vel: horiz_vel,
-  }, '*');
-}
-
-// send updated Stadia API key to map iframe
-function sendStadiaKeyToMap(key) {
-  const mapIframe = document.querySelector('.screen');
-  if (mapIframe && mapIframe.contentWindow) {
-    mapIframe.contentWindow.postMessage({
-      type: 'SET_STADIA_KEY',
-      key: key || ''
-    }, '*');
-  }
-}
-
-// send current units to the altitude graph iframe
-function sendUnitsToAltitudeGraph() {
-  const altGraphIframe = document.querySelector('#altitude-graph');
-  if (altGraphIframe && altGraphIframe.contentWindow) {
-    altGraphIframe.contentWindow.postMessage({
-      type: 'SET_UNITS',
-      units: currentUnits
-    }, '*');
-  }
-}
-  
-
-
-
-//------------------------------Compass Functions/Handlers------------------------------
-
-//gets the user location using a couple different methods
-async function getUserLocation(){
-  try {
-    // 1) Prefer explicit Ground Station inputs if the user puts it in the Settings panel
-    const gsInputs = document.querySelectorAll('.ground-station input');
-    if (gsInputs && gsInputs.length >= 2) {
-      const latVal = gsInputs[0].value && gsInputs[0].value.trim();
-      const lonVal = gsInputs[1].value && gsInputs[1].value.trim();
-      const latNum = Number(latVal);
-      const lonNum = Number(lonVal);
-      if (!Number.isNaN(latNum) && !Number.isNaN(lonNum) && latVal !== '' && lonVal !== '') {
-        return { latitude: latNum, longitude: lonNum };
-      }
-    }
-    // 2) Fallback to browser geolocation (wrapped as a Promise)
-    return await new Promise((resolve, reject) => {
-      if (!navigator.geolocation) return resolve(null);
-      const options = { timeout: 7000, maximumAge: 0 };
-      navigator.geolocation.getCurrentPosition(
-        (pos) => resolve(pos && pos.coords ? { latitude: pos.coords.latitude, longitude: pos.coords.longitude } : null),
-        (err) => { console.warn(`Geolocation error: ${err && err.message}`); resolve(null); },
-        options
-      );
-    });
-  } catch (err) {
-    console.warn('getUserLocation error:', err);
-    return null;
-  }
-}
-
-function angleFromCoordinate(lat1, long1, lat2, long2){
-  // compute bearing from (lat1,long1) -> (lat2,long2) in degrees (0 = north)
-  const toRad = (d) => d * Math.PI / 180;
-  const toDeg = (r) => r * 180 / Math.PI;
-  const t1 = toRad(lat1);
-  const t2 = toRad(lat2);
-  const delta = toRad(long2 - long1);
-  const y = Math.sin(delta) * Math.cos(t2);
-  const x = Math.cos(t1) * Math.sin(t2) - Math.sin(t1) * Math.cos(t2) * Math.cos(delta);
-  let theta = Math.atan2(y, x);
-  theta = toDeg(theta);
-  return (theta + 360) % 360;
-}
-
-async function updateCompass(lat, long){
-  try {
-    const ucoords = await getUserLocation();
-    if (ucoords && typeof ucoords.latitude === 'number' && typeof ucoords.longitude === 'number'){
-      const ulat = ucoords.latitude;
-      const ulong = ucoords.longitude;
-      const bearing = angleFromCoordinate(ulat, ulong, lat, long);
-      if (typeof setCompassAngle === 'function') setCompassAngle(bearing);
-    } else {
-      if (console_text) console_text.textContent = 'Could not determine user location for compass';
-    }
-  } catch (err) {
-    console.error('updateCompass error:', err);
-  }
-}
-
-
-//------------------------------Altitude Graph------------------------------
-
-//updates the alt graph
-function updateAltitudeGraph(time, alt) {
-    const iframe = document.getElementById('altitude-graph');
-    
-    iframe.contentWindow.postMessage({
-        type: 'ADD_DATA',
-        time: time,
-        alt: alt
-    }, '*'); 
-}
-
-
-
-
-
-
-function initThemeSelector() {
-    const themeSelect = document.querySelector('#settings select');
-
-    if (!themeSelect) return;
-
-    const savedTheme = localStorage.getItem('harp-theme') || 'light';
-    setTheme(savedTheme);
-    themeSelect.value = savedTheme.charAt(0).toUpperCase() + savedTheme.slice(1);
-
-    themeSelect.addEventListener('change', (e) => {
-        const selectedValue = e.target.value.toLowerCase();
-        setTheme(selectedValue);
-    });
-}
-
-function setTheme(theme) {
-    document.documentElement.setAttribute('data-theme', theme);
-    localStorage.setItem('harp-theme', theme);
-    updateIframes(theme);
-}
-
-function updateIframes(theme) {
-    const iframes = document.querySelectorAll('iframe');
-    iframes.forEach(iframe => {
-        iframe.contentWindow.postMessage({ type: 'THEME_CHANGE', theme: theme }, '*');
-    });
-}
-function cleanup() {
-  if (utcIntervalId) clearInterval(utcIntervalId);
-  if (trackerIntervalId) clearInterval(trackerIntervalId);
-  if (statusIntervalId) clearInterval(statusIntervalId);
-  if (statusIntervalId) clearInterval(statusIntervalId);
-}
-
-
-
-
-// init app once DOM is loaded
-window.addEventListener("DOMContentLoaded", init);
-// Cleanup on page unload if needed
+import { setCompassAngle, createCompass } from './compass.js';
+
+
+// allow quick dev call
+window.updateInfo = updateInfo;
+const { invoke } = window.__TAURI__.core;
+const { listen } = window.__TAURI__.event;
+
+// Automatic unit converstion
+// All internal storage is in metric: meters, kg, m/s; user sees metric or imperial based on what they choose
+let currentUnits = 'metric'; // 'metric' or 'imperial'
+
+// Unit conversion constants
+const UNITS = {
+  ALTITUDE: {
+    metric: { name: 'm', factor: 1 },
+    imperial: { name: 'ft', factor: 3.28084 }
+  },
+  VELOCITY_HORIZ: {
+    metric: { name: 'm/s', factor: 1 },
+    imperial: { name: 'mph', factor: 2.237 }
+  },
+  VELOCITY_VERT: {
+    metric: { name: 'm/s', factor: 1 },
+    imperial: { name: 'ft/min', factor: 196.85 }
+  },
+  MASS: {
+    metric: { name: 'kg', factor: 1 },
+    imperial: { name: 'lbs', factor: 2.20462 }
+  }
+};
+
+//Convert metric value to display units
+function convertToDisplay(metricValue, unitType) {
+  if (metricValue === 0 || metricValue === null || metricValue === undefined) return metricValue;
+  return metricValue * UNITS[unitType][currentUnits].factor;
+}
+
+//Convert display value to metric
+function convertToMetric(displayValue, unitType) {
+  if (displayValue === 0 || displayValue === null || displayValue === undefined) return displayValue;
+  return displayValue / UNITS[unitType][currentUnits].factor;
+}
+
+//Get unit label for display
+function getUnitLabel(unitType) {
+  return UNITS[unitType][currentUnits].name;
+}
+
+//Update all unit labels in the UI
+function updateUnitLabels() {
+  // Prediction parameters
+  const labelPayloadMass = document.getElementById('label-payload-mass');
+  if (labelPayloadMass) labelPayloadMass.textContent = `Payload mass (${getUnitLabel('MASS')})`;
+  
+  const labelBalloonMass = document.getElementById('label-balloon-mass');
+  if (labelBalloonMass) labelBalloonMass.textContent = `Balloon mass (${getUnitLabel('MASS')})`;
+  
+  const labelBurstAlt = document.getElementById('label-burst-alt');
+  if (labelBurstAlt) labelBurstAlt.textContent = `Burst altitude (${getUnitLabel('ALTITUDE')})`;
+  
+  const labelAscentRate = document.getElementById('label-ascent-rate');
+  if (labelAscentRate) labelAscentRate.textContent = `Ascent rate (${getUnitLabel('VELOCITY_VERT')})`;
+  
+  const labelDescentRate = document.getElementById('label-descent-rate');
+  if (labelDescentRate) labelDescentRate.textContent = `Descent rate (${getUnitLabel('VELOCITY_VERT')})`;
+  
+  // Ground station
+  const labelGroundAlt = document.getElementById('label-ground-alt');
+  if (labelGroundAlt) labelGroundAlt.textContent = `Alt (${getUnitLabel('ALTITUDE')})`;
+}
+
+//Update all displayed values after unit change
+function updateDisplayedValuesForUnits() {
+  //update prediction parameters
+  updatePredictionParametersDisplay();
+  
+  //update position display
+  if (lastKnownPosition) {
+    updateMapWithCurrentPosition();
+  }
+  
+  //update ground station display
+  updateGroundStationDisplay();
+}
+
+//Update ground station display with correct units
+function updateGroundStationDisplay() {
+  const groundAltInput = document.querySelector('#ground-alt');
+  if (groundAltInput && groundAltInput.value) {
+    const metricAlt = parseFloat(groundAltInput.dataset.metricValue);
+    if (metricAlt !== undefined && !isNaN(metricAlt)) {
+      const displayAlt = convertToDisplay(metricAlt, 'ALTITUDE');
+      groundAltInput.value = displayAlt.toFixed(0);
+    }
+  }
+}
+
+// DOM elements
+let utcMsg;
+let dateMsg;
+let ir_mod;
+let aprs_call;
+let lat, long, alt;
+let last_update;
+let citystate;
+let aprs_butt, iridium_butt;
+let console_text;
+let radioDropdown;
+let radioInput;
+
+// Last known position for unit conversion updates
+let lastKnownPosition = null;
+
+// Track previous values
+let previousIridiumValue = "";
+let previousAprsValue = "";
+let previousLat = null;
+let previousLong = null;
+
+// Track active instances
+let activeAprsCallsigns = [];
+let activeIridiumModems = [];
+
+// Interval IDs
+let utcIntervalId;
+let unlistenPositionUpdate;
+let unlistenFixRejected;
+let statusIntervalId;
+let predictionIntervalId;
+
+// geocoding API rate limiting
+let lastGeocodeTime = 0;
+const GEOCODE_RATE_LIMIT = 10000; 
+
+// Prediction parameters (stored internally in metric)
+let predictionParams = {
+  payloadMass: 2.0,        // kg
+  balloonMass: 1.5,        // kg
+  parachuteDragCoeff: 0.5, // unitless
+  burstAltitude: 30000.0,  // m
+  ascentRate: null,        // m/s
+  descentRate: 5.0         // m/s
+};
+
+// Initialize app
+async function init() {
+  try {
+    const sideTabs = document.querySelectorAll('.side-tab, .sidebar-tab');
+    const panelContents = document.getElementById('panel-contents');
+    let activeTab = null;
+
+    sideTabs.forEach(btn => {
+      btn.addEventListener('click', () => {
+        const panelId = btn.dataset.panel;
+        const panel = document.getElementById(panelId);
+
+        if (activeTab === btn) {
+          btn.classList.remove('active');
+          activeTab = null;
+          if (panelContents) {
+            panelContents.classList.remove('open');
+            panelContents.setAttribute('aria-hidden', 'true');
+          }
+          if (panel) panel.classList.remove('active');
+          document.body.classList.remove('panel-open');
+          return;
+        }
+
+        sideTabs.forEach(b => b.classList.remove('active'));
+        btn.classList.add('active');
+        activeTab = btn;
+
+        document.querySelectorAll('.panel').forEach(p => p.classList.remove('active'));
+        if (panel) panel.classList.add('active');
+        if (panelContents) {
+          panelContents.classList.add('open');
+          panelContents.setAttribute('aria-hidden', 'false');
+        }
+        document.body.classList.add('panel-open');
+      });
+    });
+
+    document.addEventListener('click', (e) => {
+      const target = e.target;
+      if (!target.closest('.panel-contents') && !target.closest('.side-tab') && !target.closest('.sidebar-tab')) {
+        if (panelContents) {
+          panelContents.classList.remove('open');
+          panelContents.setAttribute('aria-hidden', 'true');
+        }
+        sideTabs.forEach(b => b.classList.remove('active'));
+        activeTab = null;
+        document.querySelectorAll('.panel').forEach(p => p.classList.remove('active'));
+        document.body.classList.remove('panel-open');
+      }
+    });
+
+    const addBtn = document.getElementById('add-connection');
+    const list = document.getElementById('connections-list');
+    if (addBtn && list) {
+      addBtn.addEventListener('click', () => addConnection(list));
+      addConnection(list);
+    }
+
+    createCompass(document.getElementById('compass-top-left'));
+    window.setCompassAngle = setCompassAngle;
+
+    try {
+      async function pollHeading() {
+        try {
+          const heading = await invoke('get_heading');
+          if (heading && !Number.isNaN(Number(heading.azimuth))) {
+            setCompassAngle(Number(heading.azimuth));
+          }
+        } catch (err) {}
+      }
+      pollHeading();
+      setInterval(pollHeading, 1000);
+
+      initThemeSelector();
+    } catch (e) {}
+  } catch (err) {
+    console.warn('UI init warning:', err);
+  }
+
+  // Get DOM elements
+  utcMsg = document.querySelector("#utc-msg");
+  dateMsg = document.querySelector("#date-msg");
+  ir_mod = document.querySelector("#iridium_field");
+  aprs_call = document.querySelector("#aprs_field");
+  lat = document.querySelector("#lat");
+  long = document.querySelector("#long");
+  alt = document.querySelector("#alt");
+  last_update = document.querySelector("#last-update");
+  citystate = document.querySelector("#citystate");
+  aprs_butt = document.querySelector("#aprs_butt");
+  iridium_butt = document.querySelector("#iridium_butt");
+  console_text = document.querySelector("#console-text");
+  radioDropdown = document.querySelector("#radio-method");
+  radioInput = document.querySelector(".dropdown input[type='text']");
+  
+  // Setup prediction controls
+  setupPredictionControls();
+  setupFlightControls();
+  setupTelemetryControls();
+  setupStationControls();
+  setupArduinoControls();
+
+  // Disable context menu on non-text elements to avoid accidental right-click UI interactions
+  document.addEventListener('contextmenu', (e) => {
+    try {
+      const tgt = e.target;
+      if (!tgt) { e.preventDefault(); return; }
+      // allow on form controls or editable regions
+      if (tgt.closest && (tgt.closest('input') || tgt.closest('textarea') || tgt.closest('select') || tgt.isContentEditable)) return;
+      // otherwise prevent
+      e.preventDefault();
+    } catch (err) { e.preventDefault(); }
+  });
+  
+  const filteringMethod = document.querySelector("#filtering-method");
+  if (filteringMethod) {
+    filteringMethod.addEventListener("change", handleFilteringMethodChange);
+    try {
+      const savedMethod = await invoke("get_filtering_method");
+      if (savedMethod) {
+        filteringMethod.value = savedMethod;
+      }
+    } catch (error) {
+      console.error("Error loading filtering method:", error);
+    }
+  }
+
+  //Setup units selector
+  const unitsSelector = document.querySelector("#units-selector");
+  if (unitsSelector) {
+    unitsSelector.addEventListener("change", handleUnitsChange);
+    const savedUnits = localStorage.getItem('harp-units') || 'metric';
+    currentUnits = savedUnits;
+    unitsSelector.value = savedUnits;
+    updateUnitLabels();
+  }
+
+  // Setup ground station input saves
+  const groundLatInput = document.querySelector('#ground-lat');
+  const groundLonInput = document.querySelector('#ground-lon');
+  const groundAltInput = document.querySelector('#ground-alt');
+  
+  if (groundLatInput) {
+    groundLatInput.addEventListener('blur', () => {
+      localStorage.setItem('ground_station_lat', groundLatInput.value);
+      syncGroundStation();
+    });
+  }
+  
+  if (groundLonInput) {
+    groundLonInput.addEventListener('blur', () => {
+      localStorage.setItem('ground_station_lon', groundLonInput.value);
+      syncGroundStation();
+    });
+  }
+  
+  if (groundAltInput) {
+    groundAltInput.addEventListener('blur', () => {
+      const displayValue = parseFloat(groundAltInput.value);
+      if (!isNaN(displayValue)) {
+        const metricValue = convertToMetric(displayValue, 'ALTITUDE');
+        localStorage.setItem('ground_station_alt', metricValue.toString());
+        groundAltInput.dataset.metricValue = metricValue;
+        syncGroundStation();
+      }
+    });
+  }
+
+  // Setup aircraft radius control
+  const aircraftRadiusInput = document.querySelector("#aircraft-radius-km");
+  if (aircraftRadiusInput) {
+    aircraftRadiusInput.addEventListener("change", (e) => {
+      const radiusKm = parseFloat(e.target.value) || 100;
+      const radiusMeters = radiusKm * 1000;
+      const mapIframe = document.querySelector('.screen');
+      if (mapIframe && mapIframe.contentWindow) {
+        mapIframe.contentWindow.postMessage({
+          type: 'SET_AIRCRAFT_RADIUS',
+          radiusMeters: radiusMeters
+        }, '*');
+      }
+      if (console_text) console_text.textContent = `Aircraft display radius set to ${radiusKm} km`;
+    });
+  }
+
+  if (radioDropdown && radioInput) {
+    radioDropdown.addEventListener("change", handleRadioDropdownChange);
+    updateInputPlaceholder(radioDropdown.value);
+    radioInput.addEventListener("blur", handleRadioInputBlur);
+    radioInput.addEventListener("keypress", function(event) {
+      if (event.key === "Enter") {
+        handleRadioInputBlur(event);
+      }
+    });
+    loadRadioInputValue(radioDropdown.value);
+  }
+
+  function handleRadioDropdownChange(event) {
+    const selectedRadio = event.target.value;
+    updateInputPlaceholder(selectedRadio);
+    loadRadioInputValue(selectedRadio);
+  }
+
+  function updateInputPlaceholder(radioType) {
+    const radioInput = document.querySelector(".dropdown input[type='text']");
+    if (!radioInput) return;
+    
+    if (radioType === "iridium_field") {
+      radioInput.placeholder = "Enter Iridium Modem ID";
+    } else if (radioType === "aprs_field") {
+      radioInput.placeholder = "Enter APRS Callsign";
+    }
+  }
+
+  async function loadRadioInputValue(radioType) {
+    const radioInput = document.querySelector(".dropdown input[type='text']");
+    if (!radioInput) return;
+    
+    try {
+      if (radioType === "iridium_field") {
+        const savedIridium = await invoke("get_irr_modem");
+        radioInput.value = savedIridium || "";
+      } else if (radioType === "aprs_field") {
+        const savedAprs = await invoke("get_aprs_callsign");
+        radioInput.value = savedAprs || "";
+      }
+    } catch (error) {
+      if (console_text) console_text.textContent = "Error loading radio value: " + error;
+      else console.error("Error loading radio value:", error);
+    }
+  }
+
+  async function handleRadioInputBlur(event) {
+    const radioDropdown = document.querySelector("#radio-method");
+    if (!radioDropdown) return;
+    
+    const selectedRadio = radioDropdown.value;
+    const newValue = event.target.value.trim();
+    
+    if (selectedRadio === "iridium_field") {
+      await handleIridiumUpdate(newValue);
+    } else if (selectedRadio === "aprs_field") {
+      await handleAprsUpdate(newValue);
+    }
+    
+    event.target.value = "";
+  }
+  
+  // Initialize the map iframe
+  initMapIframe();
+  
+  // Set up event listeners for input fields
+  if (ir_mod) {
+    ir_mod.addEventListener("blur", handleIridiumInput);
+    ir_mod.addEventListener("keypress", function(event) {
+      if (event.key === "Enter") {
+        handleIridiumInput(event);
+      }
+    });
+  }
+
+  if (aprs_call) {
+    aprs_call.addEventListener("blur", handleAprsInput);
+    aprs_call.addEventListener("keypress", function(event) {
+      if (event.key === "Enter") {
+        handleAprsInput(event);
+      }
+    });
+  }
+
+  //Stadia Maps API key field
+  const stadiaInput = document.querySelector('#stadia-api-key');
+  if (stadiaInput) {
+    stadiaInput.addEventListener('blur', async () => {
+      const val = stadiaInput.value.trim();
+      localStorage.setItem('stadia_api_key', val);
+      if (val) {
+        sendStadiaKeyToMap(val);
+      } else {
+        //user cleared key -> go back to .env value
+        let envK = '';
+        try { envK = await invoke('get_stadia_api_key'); } catch(e){}
+        if (envK) sendStadiaKeyToMap(envK);
+      }
+      console.log('Stadia API key updated to', val ? '<hidden>' : '(cleared)');
+    });
+    stadiaInput.addEventListener('keypress', (e) => {
+      if (e.key === 'Enter') {
+        stadiaInput.blur();
+      }
+    });
+  }
+
+  //APRS.FI API key input 
+  const aprsfiInput = document.querySelector('#aprsfi-api-key');
+  if (aprsfiInput) {
+    aprsfiInput.addEventListener('blur', async () => {
+      const val = aprsfiInput.value.trim();
+      localStorage.setItem('aprsfi_api_key', val);
+      try {
+        await invoke('set_aprsfi_api_key', { key: val });
+        console.log('APRS.FI API key updated');
+        // if APRS is already active, re-init
+        await invoke('set_aprs');
+      } catch (e) {
+        console.error('Failed to set APRS.FI API key:', e);
+      }
+    });
+    aprsfiInput.addEventListener('keypress', (e) => {
+      if (e.key === 'Enter') {
+        aprsfiInput.blur();
+      }
+    });
+  }
+  
+  //Data folder input, empty goes back to the default folder
+  const dataDirInput = document.querySelector('#data-dir');
+  if (dataDirInput) {
+    dataDirInput.addEventListener('change', async () => {
+      try {
+        const status = await invoke('set_data_dir', { path: dataDirInput.value.trim() });
+        showStorageStatus(status);
+      } catch (e) {
+        showConsole(`Error changing data folder: ${e}`);
+      }
+    });
+  }
+  invoke('get_storage_status').then(showStorageStatus).catch(e => console.error('Failed to get storage status:', e));
+
+  await loadSavedValues();
+  
+  // Initial Updates
+  await date();
+  await updateTracker();
+  await updateUtc();
+  await updateActiveStatus();
+  await updateConnectedClients();
+  
+  // Send current units to the altitude graph iframe
+  setTimeout(() => {
+    sendUnitsToAltitudeGraph();
+  }, 500);
+  
+  // Start timers
+  utcIntervalId = setInterval(updateUtc, 100);
+  // New fixes are pushed by the backend pollers instead of polled from here
+  unlistenPositionUpdate = await listen('position-update', () => {
+    updateTracker();
+    updateTelemetry();
+  });
+  // Fixes dropped by the backend validation (0,0, impossible jumps, bad altitude or time)
+  unlistenFixRejected = await listen('fix-rejected', (event) => {
+    const fix = event.payload;
+    showConsole(`Rejected ${fix.track_type} fix from ${fix.source}: ${fix.reason}`, 8000);
+  });
+  statusIntervalId = setInterval(updateActiveStatus, 1000);
+  
+  // Start prediction timer (every 30 seconds)
+  predictionIntervalId = setInterval(runPrediction, 30000);
+}
+
+// Setup prediction controls
+function setupPredictionControls() {
+  updatePredictionParametersDisplay();
+  
+  // Get prediction panel inputs
+  const payloadMassInput = document.querySelector('#param-payload-mass');
+  const balloonMassInput = document.querySelector('#param-balloon-mass');
+  const parachuteDragInput = document.querySelector('#param-parachute-drag');
+  const burstAltInput = document.querySelector('#param-burst-alt');
+  const ascentRateInput = document.querySelector('#param-ascent-rate');
+  const descentRateInput = document.querySelector('#param-descent-rate');
+  
+  // Add event listeners for parameter changes
+  if (payloadMassInput) {
+    payloadMassInput.addEventListener('change', (e) => {
+      const displayValue = parseFloat(e.target.value) || 2.0;
+      predictionParams.payloadMass = convertToMetric(displayValue, 'MASS');
+      updatePredictionParams();
+    });
+  }
+  
+  if (balloonMassInput) {
+    balloonMassInput.addEventListener('change', (e) => {
+      const displayValue = parseFloat(e.target.value) || 1.5;
+      predictionParams.balloonMass = convertToMetric(displayValue, 'MASS');
+      updatePredictionParams();
+    });
+  }
+  
+  if (parachuteDragInput) {
+    parachuteDragInput.addEventListener('change', (e) => {
+      predictionParams.parachuteDragCoeff = parseFloat(e.target.value) || 0.5;
+      updatePredictionParams();
+    });
+  }
+  
+  if (burstAltInput) {
+    burstAltInput.addEventListener('change', (e) => {
+      const displayValue = parseFloat(e.target.value) || 30000.0;
+      predictionParams.burstAltitude = convertToMetric(displayValue, 'ALTITUDE');
+      updatePredictionParams();
+    });
+  }
+  
+  if (ascentRateInput) {
+    ascentRateInput.addEventListener('change', (e) => {
+      const value = e.target.value.trim();
+      const displayValue = value === '' ? null : parseFloat(value);
+      predictionParams.ascentRate = displayValue === null ? null : convertToMetric(displayValue, 'VELOCITY_VERT');
+      updatePredictionParams();
+    });
+  }
+  
+  if (descentRateInput) {
+    descentRateInput.addEventListener('change', (e) => {
+      const displayValue = parseFloat(e.target.value) || 5.0;
+      predictionParams.descentRate = convertToMetric(displayValue, 'VELOCITY_VERT');
+      updatePredictionParams();
+    });
+  }
+  
+  // Get run prediction button
+  const runBtn = document.querySelector('#run-prediction-btn');
+  if (runBtn) {
+    runBtn.addEventListener('click', async () => {
+      await runPrediction();
+    });
+  }
+  
+  // Algorithm selector
+  const algoSelect = document.querySelector('#prediction-algo');
+  if (algoSelect) {
+    algoSelect.addEventListener('change', async (e) => {
+      const algorithm = e.target.value;
+      try {
+        await invoke('set_predictor', { name: algorithm });
+        if (console_text) console_text.textContent = `Predictor set to: ${algorithm}`;
+      } catch (error) {
+        if (console_text) console_text.textContent = `Error setting predictor: ${error}`;
+      }
+    });
+  }
+}
+
+// Setup flight session controls
+function setupFlightControls() {
+  const splitIds = (selector) => (document.querySelector(selector)?.value || '')
+    .split(',').map(id => id.trim()).filter(id => id.length > 0);
+
+  const startBtn = document.querySelector('#start-flight-btn');
+  if (startBtn) {
+    startBtn.addEventListener('click', async () => {
+      try {
+        const flight = await invoke('start_flight', {
+          name: document.querySelector('#flight-name')?.value || '',
+          callSigns: splitIds('#flight-callsigns'),
+          imeis: splitIds('#flight-imeis')
+        });
+        showConsole(`Started flight ${flight.name}`);
+      } catch (error) {
+        showConsole(`Error starting flight: ${error}`);
+      }
+      await refreshFlights();
+    });
+  }
+
+  const endBtn = document.querySelector('#end-flight-btn');
+  if (endBtn) {
+    endBtn.addEventListener('click', async () => {
+      try {
+        await invoke('end_flight');
+        showConsole('Flight ended');
+      } catch (error) {
+        showConsole(`Error ending flight: ${error}`);
+      }
+      await refreshFlights();
+    });
+  }
+
+  const resumeBtn = document.querySelector('#resume-flight-btn');
+  if (resumeBtn) {
+    resumeBtn.addEventListener('click', async () => {
+      const id = Number(document.querySelector('#flight-list')?.value);
+      if (!id) return;
+      try {
+        const flight = await invoke('resume_flight', { id });
+        showConsole(`Resumed flight ${flight.name}`);
+        await updateTracker();
+        await updateTelemetry();
+      } catch (error) {
+        showConsole(`Error resuming flight: ${error}`);
+      }
+      await refreshFlights();
+    });
+  }
+
+  const exportBtn = document.querySelector('#export-flight-btn');
+  if (exportBtn) {
+    exportBtn.addEventListener('click', exportFlight);
+  }
+
+  refreshFlights();
+}
+
+// Write the shown flight to the chosen file, adding the extension of the format if the path has none
+async function exportFlight() {
+  const format = document.querySelector('#export-format')?.value || 'kml';
+  let path = (document.querySelector('#export-path')?.value || '').trim();
+  if (!path) {
+    showConsole('Enter a file path to export the flight to');
+    return;
+  }
+  if (!/\.[^./\\]+$/.test(path)) {
+    path += `.${format}`;
+  }
+  try {
+    const count = format === 'csv'
+      ? await invoke('export_flight_csv', { path })
+      : await invoke('export_flight', {
+          path,
+          format,
+          includePrediction: document.querySelector('#export-prediction')?.checked ?? false
+        });
+    showConsole(`Exported ${count} points to ${path}`);
+  } catch (error) {
+    showConsole(`Error exporting flight: ${error}`);
+  }
+}
+
+// Fill the past flight list and show the flight in progress
+async function refreshFlights() {
+  try {
+    const flights = await invoke('get_flights');
+    const current = await invoke('get_current_flight');
+
+    const list = document.querySelector('#flight-list');
+    if (list) {
+      list.innerHTML = '';
+      for (const flight of flights) {
+        const option = document.createElement('option');
+        option.value = flight.id;
+        const started = new Date(flight.started * 1000).toLocaleString();
+        option.textContent = `${flight.name} (${started})${flight.ended === null ? ' - in progress' : ''}`;
+        list.appendChild(option);
+      }
+    }
+
+    const label = document.querySelector('#current-flight');
+    if (label) {
+      label.textContent = current ? `Recording: ${current.name}` : 'No flight in progress';
+    }
+  } catch (error) {
+    console.error('Error loading flights:', error);
+  }
+}
+
+// Setup the comment rule editor of the telemetry panel
+function setupTelemetryControls() {
+  const callsignInput = document.querySelector('#rules-callsign');
+  const list = document.querySelector('#comment-rules-list');
+  if (!callsignInput || !list) return;
+
+  // Load the saved rules whenever another callsign is entered
+  callsignInput.addEventListener('change', async () => {
+    list.innerHTML = '';
+    const callsign = callsignInput.value.trim();
+    if (!callsign) return;
+    try {
+      const rules = await invoke('get_comment_rules', { callsign });
+      rules.forEach(rule => addRuleRow(list, rule));
+    } catch (error) {
+      showConsole(`Error loading comment rules: ${error}`);
+    }
+  });
+
+  document.querySelector('#add-rule-btn')?.addEventListener('click', () => addRuleRow(list));
+
+  document.querySelector('#save-rules-btn')?.addEventListener('click', async () => {
+    const callsign = callsignInput.value.trim();
+    if (!callsign) { showConsole('Enter a callsign first'); return; }
+    const rules = Array.from(list.querySelectorAll('.rule-entry')).map(row => ({
+      channel: row.querySelector('.rule-channel').value.trim(),
+      unit: row.querySelector('.rule-unit').value.trim(),
+      kind: row.querySelector('.rule-kind').value,
+      pattern: row.querySelector('.rule-pattern').value,
+      scale: Number(row.querySelector('.rule-scale').value || 1),
+      offset: Number(row.querySelector('.rule-offset').value || 0)
+    })).filter(rule => rule.channel && rule.pattern);
+    try {
+      await invoke('set_comment_rules', { callsign, rules });
+      showConsole(`Saved ${rules.length} comment rule(s) for ${callsign}`);
+    } catch (error) {
+      showConsole(`Error saving comment rules: ${error}`);
+    }
+  });
+
+  updateTelemetry();
+}
+
+// Add an editable comment rule row, filled from `rule` when given
+function addRuleRow(container, rule = {}) {
+  const entry = document.createElement('div');
+  entry.className = 'connection-entry rule-entry';
+
+  const field = (className, placeholder, value) => {
+    const input = document.createElement('input');
+    input.type = 'text';
+    input.className = className;
+    input.placeholder = placeholder;
+    input.value = value ?? '';
+    entry.appendChild(input);
+  };
+
+  field('rule-channel', 'Channel', rule.channel);
+  field('rule-unit', 'Unit', rule.unit);
+
+  const kind = document.createElement('select');
+  kind.className = 'rule-kind';
+  [['KeyValue', 'Key'], ['Regex', 'Regex']].forEach(([value, text]) => {
+    const o = document.createElement('option'); o.value = value; o.textContent = text; kind.appendChild(o);
+  });
+  kind.value = rule.kind || 'KeyValue';
+  entry.appendChild(kind);
+
+  field('rule-pattern', 'Key (T=) or pattern', rule.pattern);
+  field('rule-scale', 'Scale', rule.scale);
+  field('rule-offset', 'Offset', rule.offset);
+
+  const remove = document.createElement('button');
+  remove.className = 'remove';
+  remove.innerText = '✕';
+  remove.addEventListener('click', () => container.removeChild(entry));
+  entry.appendChild(remove);
+
+  container.appendChild(entry);
+}
+
+// Show the latest value of every telemetry channel received so far
+async function updateTelemetry() {
+  const list = document.querySelector('#telemetry-list');
+  if (!list) return;
+  try {
+    const series = await invoke('get_telemetry_series');
+    list.innerHTML = '';
+    if (series.length === 0) {
+      list.innerHTML = '<p>No telemetry received</p>';
+      return;
+    }
+    for (const channel of series) {
+      const [time, value] = channel.points[channel.points.length - 1];
+      const entry = document.createElement('div');
+      entry.className = 'connection-entry telemetry-entry';
+
+      const name = document.createElement('span');
+      name.textContent = `${channel.channel} (${channel.source})`;
+      const reading = document.createElement('span');
+      reading.textContent = `${Number(value.toFixed(3))} ${channel.unit}`;
+      reading.title = `${channel.points.length} samples, last at ${new Date(time * 1000).toLocaleTimeString()}`;
+
+      entry.appendChild(name);
+      entry.appendChild(reading);
+      list.appendChild(entry);
+    }
+  } catch (error) {
+    console.error('Error loading telemetry:', error);
+  }
+}
+
+// Show where the flight data is stored
+function showStorageStatus(status) {
+  const label = document.querySelector('#data-dir-status');
+  if (!label) return;
+  const sources = { Custom: 'custom', Environment: 'from HARP_DATA_DIR', Default: 'default' };
+  label.textContent = status.data_dir
+    ? `${status.data_dir} (${sources[status.source]})${status.error ? ' - ' + status.error : ''}`
+    : (status.error || 'No data folder');
+}
+
+//update the prediction parameters to show correct units and values
+function updatePredictionParametersDisplay() {
+  const payloadMassInput = document.querySelector('#param-payload-mass');
+  const balloonMassInput = document.querySelector('#param-balloon-mass');
+  const burstAltInput = document.querySelector('#param-burst-alt');
+  const ascentRateInput = document.querySelector('#param-ascent-rate');
+  const descentRateInput = document.querySelector('#param-descent-rate');
+  
+  if (payloadMassInput) payloadMassInput.value = (convertToDisplay(predictionParams.payloadMass, 'MASS')).toFixed(1);
+  if (balloonMassInput) balloonMassInput.value = (convertToDisplay(predictionParams.balloonMass, 'MASS')).toFixed(1);
+  if (burstAltInput) burstAltInput.value = (convertToDisplay(predictionParams.burstAltitude, 'ALTITUDE')).toFixed(0);
+  if (ascentRateInput) ascentRateInput.value = predictionParams.ascentRate === null ? '' : (convertToDisplay(predictionParams.ascentRate, 'VELOCITY_VERT')).toFixed(1);
+  if (descentRateInput) descentRateInput.value = (convertToDisplay(predictionParams.descentRate, 'VELOCITY_VERT')).toFixed(1);
+}
+
+// Update prediction parameters in backend
+async function updatePredictionParams() {
+  try {
+    await invoke('set_prediction_params', {
+      payloadMass: predictionParams.payloadMass,
+      balloonMass: predictionParams.balloonMass,
+      parachuteDragCoeff: predictionParams.parachuteDragCoeff,
+      burstAltitude: predictionParams.burstAltitude,
+      ascentRate: predictionParams.ascentRate,
+      descentRate: predictionParams.descentRate
+    });
+  } catch (error) {
+    console.error('Error updating prediction params:', error);
+  }
+}
+
+// Run prediction
+async function runPrediction() {
+  try {
+    if (console_text) console_text.textContent = "Starting predictions...";
+    
+    // Update parameters first
+    await updatePredictionParams();
+    
+    // Run prediction
+    const result = await invoke('run_prediction');
+    
+    if (console_text) console_text.textContent = "Predictions complete!";
+    
+    // Send prediction data to map
+    const mapIframe = document.querySelector('.screen');
+    if (mapIframe && mapIframe.contentWindow) {
+      mapIframe.contentWindow.postMessage({
+        type: 'UPDATE_PREDICTION',
+        data: result
+      }, '*');
+    }
+    
+    console.log('Prediction result:', result);
+  } catch (error) {
+    if (console_text) console_text.textContent = `Prediction error: ${error}`;
+    console.error('Prediction error:', error);
+  }
+}
+
+async function loadSavedValues() {
+  try {
+    const savedIridium = await invoke("get_irr_modem");
+    if (savedIridium) {
+      if (ir_mod) ir_mod.value = savedIridium;
+      previousIridiumValue = savedIridium;
+    }
+    
+    const savedAprs = await invoke("get_aprs_callsign");
+    if (savedAprs) {
+      if (aprs_call) aprs_call.value = savedAprs;
+      previousAprsValue = savedAprs;
+    }
+
+    // load/stash Stadia Maps key from local storage
+    const stadiaInput = document.querySelector('#stadia-api-key');
+    const storedKey = localStorage.getItem('stadia_api_key') || '';
+    if (stadiaInput) stadiaInput.value = storedKey;
+    if (storedKey) {
+      sendStadiaKeyToMap(storedKey);
+    }
+
+    // load/stash APRS.FI key from local storage
+    const aprsfiInput = document.querySelector('#aprsfi-api-key');
+    const storedAprsKey = localStorage.getItem('aprsfi_api_key') || '';
+    if (aprsfiInput) aprsfiInput.value = storedAprsKey;
+    if (storedAprsKey) {
+      try { await invoke('set_aprsfi_api_key', { key: storedAprsKey }); } catch(e){}
+    }
+    
+    // load ground station values
+    const groundLat = localStorage.getItem('ground_station_lat') || '';
+    const groundLon = localStorage.getItem('ground_station_lon') || '';
+    const groundAltMetric = localStorage.getItem('ground_station_alt') || '';
+    
+    const groundLatInput = document.querySelector('#ground-lat');
+    const groundLonInput = document.querySelector('#ground-lon');
+    const groundAltInput = document.querySelector('#ground-alt');
+    
+    if (groundLatInput) groundLatInput.value = groundLat;
+    if (groundLonInput) groundLonInput.value = groundLon;
+    if (groundAltInput && groundAltMetric) {
+      const metricAlt = parseFloat(groundAltMetric);
+      if (!isNaN(metricAlt)) {
+        groundAltInput.dataset.metricValue = metricAlt;
+        groundAltInput.value = convertToDisplay(metricAlt, 'ALTITUDE').toFixed(0);
+      }
+    }
+    await syncStationSource();
+  } catch (error) {
+    if (console_text) console_text.textContent = "Failed to load saved values:" + error;
+    else console.error("Failed to load saved values:", error);
+  }
+}
+
+// Send the saved ground station location to the backend for antenna pointing
+async function syncGroundStation() {
+  // A GPS receiver owns the location until Manual is selected again
+  if ((localStorage.getItem('station_source') || 'manual') !== 'manual') return;
+  const lat = parseFloat(localStorage.getItem('ground_station_lat'));
+  const lon = parseFloat(localStorage.getItem('ground_station_lon'));
+  const alt = parseFloat(localStorage.getItem('ground_station_alt')) || 0;
+  if (isNaN(lat) || isNaN(lon)) return;
+  try {
+    await invoke('set_ground_station', { lat, lon, alt });
+  } catch (err) {
+    console.error('Failed to set ground station:', err);
+  }
+}
+
+// Show the receiver inputs of the selected ground station source and restore the saved ones
+function setupStationControls() {
+  const sourceSelect = document.querySelector('#station-source');
+  if (!sourceSelect) return;
+  const portInput = document.querySelector('#station-port');
+  const baudInput = document.querySelector('#station-baud');
+  const gpsdInput = document.querySelector('#station-gpsd-address');
+
+  const showFields = () => {
+    document.querySelectorAll('.station-nmea').forEach(el => el.style.display = sourceSelect.value === 'nmea' ? '' : 'none');
+    document.querySelectorAll('.station-gpsd').forEach(el => el.style.display = sourceSelect.value === 'gpsd' ? '' : 'none');
+  };
+  sourceSelect.value = localStorage.getItem('station_source') || 'manual';
+  portInput.value = localStorage.getItem('station_port') || '';
+  baudInput.value = localStorage.getItem('station_baud') || '';
+  gpsdInput.value = localStorage.getItem('station_gpsd_address') || '';
+  sourceSelect.addEventListener('change', showFields);
+  showFields();
+
+  document.querySelector('#station-apply-btn')?.addEventListener('click', async () => {
+    if (sourceSelect.value === 'nmea' && !portInput.value.trim()) { showConsole('Enter the GPS serial port'); return; }
+    localStorage.setItem('station_source', sourceSelect.value);
+    localStorage.setItem('station_port', portInput.value.trim());
+    localStorage.setItem('station_baud', baudInput.value.trim());
+    localStorage.setItem('station_gpsd_address', gpsdInput.value.trim());
+    await syncStationSource();
+    updateStationStatus();
+  });
+}
+
+// Setup the Arduino antenna tracker controls of the settings panel
+function setupArduinoControls() {
+  const portInput = document.querySelector('#arduino-port');
+  const trackingBox = document.querySelector('#antenna-tracking');
+  if (!portInput || !trackingBox) return;
+  portInput.value = localStorage.getItem('arduino_port') || '';
+
+  document.querySelector('#arduino-connect-btn')?.addEventListener('click', async () => {
+    localStorage.setItem('arduino_port', portInput.value.trim());
+    try {
+      const port = await invoke('connect_arduino', { port: portInput.value.trim() || null });
+      showConsole(`Arduino connected on ${port}`);
+    } catch (err) {
+      showConsole(`Error connecting Arduino: ${err}`);
+    }
+    updateArduinoStatus();
+  });
+
+  document.querySelector('#arduino-disconnect-btn')?.addEventListener('click', async () => {
+    try {
+      await invoke('disconnect_arduino');
+    } catch (err) {
+      showConsole(`Error disconnecting Arduino: ${err}`);
+    }
+    updateArduinoStatus();
+  });
+
+  trackingBox.addEventListener('change', async () => {
+    try {
+      await invoke('set_antenna_tracking', { enabled: trackingBox.checked });
+    } catch (err) {
+      showConsole(`Error setting antenna tracking: ${err}`);
+    }
+    updateArduinoStatus();
+  });
+  updateArduinoStatus();
+}
+
+// Show the Arduino port, the last command sent and the error that disconnected it, if any
+async function updateArduinoStatus() {
+  const statusText = document.querySelector('#arduino-status');
+  if (!statusText) return;
+  try {
+    const status = await invoke('get_arduino_status');
+    const trackingBox = document.querySelector('#antenna-tracking');
+    if (trackingBox) trackingBox.checked = status.tracking;
+    if (status.connected) {
+      const command = status.last_command ? `, pointing ${status.last_command[0]}° / ${status.last_command[1]}°` : '';
+      statusText.textContent = `Connected on ${status.port}${command}`;
+    } else {
+      statusText.textContent = status.last_error ? `Disconnected: ${status.last_error}` : 'Not connected';
+    }
+  } catch (err) {
+    console.error('Error getting Arduino status:', err);
+  }
+}
+
+// Start the saved ground station source: the manual location, an NMEA GPS or gpsd
+async function syncStationSource() {
+  const source = localStorage.getItem('station_source') || 'manual';
+  try {
+    if (source === 'nmea') {
+      const baud = parseInt(localStorage.getItem('station_baud'), 10);
+      await invoke('set_station_nmea', { port: localStorage.getItem('station_port') || '', baud: isNaN(baud) ? null : baud });
+    } else if (source === 'gpsd') {
+      await invoke('set_station_gpsd', { address: localStorage.getItem('station_gpsd_address') || null });
+    } else {
+      await syncGroundStation();
+    }
+  } catch (err) {
+    showConsole(`Error starting ground station ${source}: ${err}`);
+  }
+}
+
+// Show where the ground station location comes from and the receiver error, if any
+async function updateStationStatus() {
+  const statusText = document.querySelector('#station-status');
+  if (!statusText) return;
+  try {
+    const status = await invoke('get_station_status');
+    if (status.last_error) {
+      statusText.textContent = `GPS error: ${status.last_error}`;
+    } else if (status.fix) {
+      const { station, source, last_update, satellites } = status.fix;
+      const age = Math.max(0, Math.round(Date.now() / 1000 - last_update));
+      const sats = satellites != null ? `, ${satellites} sats` : '';
+      statusText.textContent = `${source}: ${station.lat.toFixed(5)}, ${station.lon.toFixed(5)} (${age}s ago${sats})`;
+    } else {
+      statusText.textContent = 'No location yet';
+    }
+  } catch (err) {
+    console.error('Failed to get station status:', err);
+  }
+}
+
+//------------------------------Update Functions------------------------------
+// Update date
+async function date() {
+  try {
+    dateMsg.textContent = await invoke("date");
+  } catch (error) {
+    console_text.textContent = "Error updating date:" + error;
+  }
+}
+
+async function updateUtc() {
+  try {
+    utcMsg.textContent = await invoke("utc");
+    await updateLastUpdate();
+  } catch (error) {
+    console_text.textContent = "Error updating timing:" + error;
+  }
+}
+
+async function updateTracker() {
+  try {
+    // Update tracker data
+    await invoke("update");
+    
+    // Update position display
+    await getPosition();
+    await updateFlightPhase();
+    
+    const now = new Date();
+    const timeStr = now.toLocaleTimeString('en-US', { hour: '2-digit', minute: '2-digit' });
+    if (console_text) console_text.textContent = `${timeStr}: Tracker data updated`;
+  } catch (error) {
+    console_text.textContent = "Error in tracker update cycle:" + error;
+  }
+}
+
+// Show the flight phase detected by the backend, with the burst altitude once descending
+async function updateFlightPhase() {
+  const el = document.getElementById('flight-phase');
+  if (!el) return;
+  try {
+    const status = await invoke("get_flight_status");
+    const names = { PreLaunch: 'Pre-launch', Ascent: 'Ascent', Float: 'Float', Descent: 'Descent', Landed: 'Landed' };
+    let text = names[status.phase] || status.phase;
+    if (status.since) text += ' since ' + new Date(status.since * 1000).toLocaleTimeString('en-US', { hour: '2-digit', minute: '2-digit' });
+    if (status.burst) {
+      const burstAlt = convertToDisplay(status.burst.alt, 'ALTITUDE');
+      text += ` (burst at ${burstAlt.toFixed(0)}${getUnitLabel('ALTITUDE')})`;
+    }
+    el.textContent = text;
+  } catch (error) {
+    console.error("Error getting flight phase:", error);
+  }
+}
+
+// Update status indicators for active services
+async function updateActiveStatus() {
+  try {
+    // Check if active and show button on the Connected Clients
+    const isAprsActive = await invoke("is_aprs_active");
+    if (aprs_butt) {
+      if (isAprsActive) aprs_butt.style.display = "inline";
+      else aprs_butt.style.display = "none";
+    }
+    
+    try {
+      const isIridiumActive = await invoke("is_iridium_active");
+      if (iridium_butt) {
+        if (isIridiumActive) iridium_butt.style.display = "inline";
+        else iridium_butt.style.display = "none";
+      }
+    } catch (error) {
+      console_text.textContent = "Error checking Iridium status:" + error;
+    }
+    
+    // Update the connection display with fresh last update time
+    await updateConnectedClients();
+    try { await updateConnectionIndicators(); } catch(e) { }
+    await updateStationStatus();
+    await updateArduinoStatus();
+  } catch (error) {
+    console_text.textContent = "Error updating active status:" + error;
+  }
+}
+
+// Update the display of connected clients
+async function updateConnectedClients() {
+  try {
+    const signalFlexbox = document.querySelector(".signal_flexbox");
+    if (!signalFlexbox) return;
+    
+    //Clear the existing stuff
+    const existingConnections = signalFlexbox.querySelectorAll('.connection-item');
+    existingConnections.forEach(item => item.remove());
+    
+    //validity data
+    const aprsValidity = await invoke("get_aprs_validity");
+    const iridiumValidity = await invoke("get_iridium_validity");
+    
+    // Update APRS button if there are active APRS connections
+    if (activeAprsCallsigns.length > 0 && aprs_butt) {
+      const callsign = activeAprsCallsigns[0];
+      aprs_butt.textContent = `APRS\n${callsign}`;
+      const isValid = aprsValidity[0];
+      aprs_butt.style.backgroundColor = isValid ? "#90EE90" : "white";
+      aprs_butt.style.display = "inline";
+      
+      // Add additional APRS connections
+      for (let i = 1; i < activeAprsCallsigns.length; i++) {
+        const item = document.createElement("button");
+        item.className = "connection-item";
+        item.textContent = `APRS\n${activeAprsCallsigns[i]}`;
+        const isValid = aprsValidity[i];
+        item.style.backgroundColor = isValid ? "#90EE90" : "white";
+        signalFlexbox.appendChild(item);
+      }
+    }
+    
+    // Update Iridium button if there are active Iridium connections
+    if (activeIridiumModems.length > 0 && iridium_butt) {
+      const modem = activeIridiumModems[0]; 
+      iridium_butt.textContent = `Iridium | ${modem}`;
+      const isValid = iridiumValidity[0]; 
+      iridium_butt.style.backgroundColor = isValid ? "#90EE90" : "white";
+      iridium_butt.style.display = "inline";
+      
+      // Add additional Iridium connections
+      for (let i = 1; i < activeIridiumModems.length; i++) {
+        const item = document.createElement("button");
+        item.className = "connection-item";
+        item.textContent = `Iridium | ${activeIridiumModems[i]}`;
+        const isValid = iridiumValidity[i];
+        item.style.backgroundColor = isValid ? "#90EE90" : "white";
+        signalFlexbox.appendChild(item);
+      }
+    }
+  } catch (error) {
+    console.error("Error updating connected clients:", error);
+  }
+}
+
+// Look up city and state based on coordinates
+async function updateCityAndState(latitude, longitude) {
+  // Check rate limiting
+  const now = Date.now();
+  if (now - lastGeocodeTime < GEOCODE_RATE_LIMIT) {
+    return;
+  }
+  
+  lastGeocodeTime = now;
+  
+  try {
+    const response = await fetch(`https://nominatim.openstreetmap.org/reverse?format=json&lat=${latitude}&lon=${longitude}&zoom=10&addressdetails=1`, {
+      headers: {
+        'User-Agent': 'HARP-Tracker-App/1.0'
+      }
+    });
+
+    if (!response.ok) {
+      if (response.status === 429) {
+        // rate limited
+        if (citystate) citystate.textContent = "Location, Rate limited";
+        return;
+      }
+      throw new Error(`Geocoding API error: ${response.status}`);
+    }
+
+    const data = await response.json();
+    
+    // Extract city and state information
+    let cityName = data.address.city || 
+                   data.address.town || 
+                   data.address.village || 
+                   data.address.hamlet ||
+                   "Unknown";
+                   
+    let stateName = data.address.state || 
+                    data.address.province || 
+                    data.address.region ||
+                    "";
+    
+    // Update UI
+    if (citystate) citystate.textContent = cityName + ", " + stateName;
+
+    console.log(`Updated location: ${cityName}, ${stateName}`);
+  } catch (error) {
+    console.error("Error getting city/state:", error);
+    if (citystate) citystate.textContent = "Location, Unknown";
+  }
+}
+
+// Update the "last update" text
+async function updateLastUpdate() {
+  try {
+    const seconds = await invoke("get_last_update");
+    if (last_update) last_update.textContent = `Last update: ${seconds}s ago`;
+  } catch (error) {
+    console.error("Error updating last update time:", error);
+  }
+}
+
+
+
+//------------------------------Input Handlers------------------------------
+
+
+// Handle Iridium input changes
+async function handleIridiumUpdate(newValue) {
+  try {
+    if (newValue !== "") {
+      await invoke("set_irr_modem", { id: newValue });
+      await invoke("set_iridium");
+      
+      // Add to active instances list if not already present
+      if (!activeIridiumModems.includes(newValue)) {
+        activeIridiumModems.push(newValue);
+      }
+      
+      if (console_text) console_text.textContent = "Iridium modem updated: " + newValue;
+      else console.log("Iridium modem updated:", newValue);
+      
+      // Update the display of connected clients
+      await updateConnectedClients();
+    }
+  } catch (error) {
+    if (console_text) console_text.textContent = "Error updating Iridium settings: " + error;
+    else console.error("Error updating Iridium settings:", error);
+  }
+}
+
+//for handling filtering method changes
+async function handleFilteringMethodChange(event) {
+  const newValue = event.target.value;
+  try {
+    await invoke("set_filtering_method", { method: newValue });
+    if (console_text) console_text.textContent = "Filtering method updated: " + newValue;
+  } catch (error) {
+    if (console_text) console_text.textContent = "Error updating filtering method: " + error;
+    else console.error("Error updating filtering method:", error);
+  }
+}
+
+// Handle units selector change
+function handleUnitsChange(event) {
+  const newUnits = event.target.value;
+  currentUnits = newUnits;
+  localStorage.setItem('harp-units', newUnits);
+  updateUnitLabels();
+  updateDisplayedValuesForUnits();
+  
+  //send unit change message to map iframe
+  const mapIframe = document.querySelector('.screen');
+  if (mapIframe && mapIframe.contentWindow) {
+    mapIframe.contentWindow.postMessage({
+      type: 'SET_UNITS',
+      units: newUnits
+    }, '*');
+  }
+  
+  //send unit change message to altitude graph iframe
+  const altGraphIframe = document.querySelector('#altitude-graph');
+  if (altGraphIframe && altGraphIframe.contentWindow) {
+    altGraphIframe.contentWindow.postMessage({
+      type: 'SET_UNITS',
+      units: newUnits
+    }, '*');
+  }
+  //send to display console
+  if (console_text) console_text.textContent = `Units changed to ${newUnits === 'metric' ? 'Metric' : 'Imperial'}`;
+  runPrediction();//run predictions again so that it updates the units
+}
+
+// Handle APRS input changes
+async function handleAprsUpdate(newValue) {
+  try {
+    if (newValue !== "") {
+      await invoke("set_aprs_callsign", { id: newValue });
+      await invoke("set_aprs");
+      
+      // Add to active instances list if not already present
+      if (!activeAprsCallsigns.includes(newValue)) {
+        activeAprsCallsigns.push(newValue);
+      }
+      
+      if (console_text) console_text.textContent = "APRS callsign updated: " + newValue;
+      else console.log("APRS callsign updated:", newValue);
+      
+      // Update the display of connected clients
+      await updateConnectedClients();
+    }
+  } catch (error) {
+    if (console_text) console_text.textContent = "Error updating APRS settings: " + error;
+    else console.error("Error updating APRS settings:", error);
+  }
+}
+
+// Get and display current position
+async function getPosition() {
+  try {
+    const currentLat = await invoke("get_lat");
+    const currentLong = await invoke("get_long");
+    const altitude = await invoke("get_alt");
+    const horiz_vel = await invoke("get_horiz_vel");  
+    const vert_vel = await invoke("get_vert_vel"); 
+
+    const numLat = Number(currentLat);
+    const numLong = Number(currentLong);
+    const numAlt = Number(altitude);
+    const numHoriz = Number(horiz_vel);
+    const numVert = Number(vert_vel);
+    
+    // Store for later unit conversions
+    lastKnownPosition = {
+      lat: numLat,
+      lon: numLong,
+      alt: numAlt,
+      horiz_vel: numHoriz,
+      vert_vel: numVert
+    };
+
+    if (lat && !Number.isNaN(numLat)) lat.textContent = numLat + ",";
+    if (long && !Number.isNaN(numLong)) long.textContent = numLong;
+    if (alt && !Number.isNaN(numAlt)){
+      const displayAlt = convertToDisplay(numAlt, 'ALTITUDE');
+      const altUnit = getUnitLabel('ALTITUDE');
+      alt.textContent = displayAlt.toFixed(0) + altUnit;
+    }
+
+    // Update map with metric values for internal use
+    if (!Number.isNaN(numLat) && !Number.isNaN(numLong) && !Number.isNaN(numAlt) && numAlt != 0.0)  {
+      updateMap(numLat, numLong, numAlt, numHoriz, numVert); 
+
+      // Check if coordinates have changed significantly before updating city
+      const hasLocationChanged = 
+        previousLat === null || 
+        previousLong === null ||
+        (typeof numLat === 'number' && typeof previousLat === 'number' && Math.abs(numLat - previousLat) > 0.01) ||
+        (typeof numLong === 'number' && typeof previousLong === 'number' && Math.abs(numLong - previousLong) > 0.01);
+      
+      // Update elements if location has changed
+      if (hasLocationChanged) {
+        if (!Number.isNaN(numLat) && !Number.isNaN(numLong)) {
+          //update city and state
+          updateCityAndState(numLat, numLong);
+
+          // make UTC timestamp 
+          const now = new Date();
+          const utcTimeStr = now.getUTCHours().toString().padStart(2, '0') + ":" + 
+                            now.getUTCMinutes().toString().padStart(2, '0') + ":" + 
+                            now.getUTCSeconds().toString().padStart(2, '0');
+          //update compass          
+          try { await updateCompass(numLat, numLong); } catch(e){}
+          
+          // update alt graph with the metric altitude (graph handles its own conversion)
+          try { updateAltitudeGraph(utcTimeStr, numAlt); } catch(e){}
+        }
+        previousLat = Number.isFinite(numLat) ? numLat : previousLat;
+        previousLong = Number.isFinite(numLong) ? numLong : previousLong;
+      }
+    }
+  } catch (error) {
+    if (console_text) console_text.textContent = "Error getting position:" + error;
+    else console.error("Error getting position:", error);
+  }
+}
+
+// Receivers started from the connections panel, with the settings they need besides the identifier
+const RECEIVER_FIELDS = {
+  'APRS-IS': { ident: 'Callsign to track', fields: [['login', 'Login callsign'], ['passcode', 'Passcode (optional)'], ['server', 'Server (optional)']] },
+  'KISS': { ident: 'Callsign to track', fields: [['link', 'host:port or serial port'], ['baud', 'Baud (serial)']] },
+  'Horus': { ident: 'Payload callsign', fields: [['port', 'UDP port (55672)']] },
+  'UKHAS': { ident: 'Payload callsign', fields: [['link', 'host:port or serial port'], ['baud', 'Baud (serial)'], ['fields', 'Fields after alt, comma separated']] },
+  'RFD': { ident: 'Serial port', fields: [['baud', 'Baud (57600)'], ['layout', 'Frame columns, eg. time,lat,lon,alt,temp']] }
+};
+
+// Start a receiver from its connection entry
+async function startReceiver(t, val, settings) {
+  const list = (text) => (text || '').split(',').map(s => s.trim()).filter(s => s.length > 0);
+  const serial = /^(COM\d+|\/dev\/)/i.test(settings.link || '');
+  const baud = settings.baud ? Number(settings.baud) : null;
+
+  switch (t) {
+    case 'APRS-IS':
+      return invoke('set_aprs_is', { login: settings.login || val, passcode: settings.passcode ? Number(settings.passcode) : null, callsign: val, server: settings.server || null });
+    case 'KISS':
+      return serial
+        ? invoke('set_kiss_serial', { port: settings.link, baud: baud || 9600, callsign: val })
+        : invoke('set_kiss_tcp', { address: settings.link || 'localhost:8001', callsign: val });
+    case 'Horus':
+      return invoke('set_horus', { port: settings.port ? Number(settings.port) : null, callsign: val });
+    case 'UKHAS':
+      if (!settings.link) throw 'Enter the receiver address or serial port';
+      return serial
+        ? invoke('set_ukhas_serial', { port: settings.link, baud: baud || 9600, callsign: val, fields: list(settings.fields) })
+        : invoke('set_ukhas_tcp', { address: settings.link, callsign: val, fields: list(settings.fields) });
+    case 'RFD':
+      if (list(settings.layout).length === 0) throw 'Enter the frame columns of the payload';
+      return invoke('set_rfd', { port: val, baud, layout: list(settings.layout) });
+  }
+}
+
+//function that adds a connection to the tracker
+function addConnection(container) {
+  const entry = document.createElement('div');
+  entry.className = 'connection-entry';
+
+  const indicator = document.createElement('div');
+  indicator.className = 'conn-indicator';
+
+  const type = document.createElement('select');
+  ['None','APRS','Iridium', ...Object.keys(RECEIVER_FIELDS)].forEach(n => {
+    const o = document.createElement('option'); o.value = n; o.textContent = n; type.appendChild(o);
+  });
+
+  const ident = document.createElement('input');
+  ident.type = 'text';
+  ident.placeholder = 'Identifier (callsign / IMEI)';
+
+  // Receiver settings, shown below the entry for the types that need them
+  const settings = document.createElement('div');
+  settings.className = 'receiver-settings';
+  const showSettings = () => {
+    settings.innerHTML = '';
+    const receiver = RECEIVER_FIELDS[type.value];
+    ident.placeholder = receiver ? receiver.ident : 'Identifier (callsign / IMEI)';
+    for (const [name, placeholder] of receiver ? receiver.fields : []) {
+      const input = document.createElement('input');
+      input.type = 'text';
+      input.name = name;
+      input.placeholder = placeholder;
+      settings.appendChild(input);
+    }
+  };
+  const settingValues = () => Object.fromEntries(
+    Array.from(settings.querySelectorAll('input')).map(input => [input.name, input.value.trim()])
+  );
+
+  const remove = document.createElement('button');
+  remove.className = 'remove';
+  remove.innerText = '✕';
+
+  const activate = document.createElement('button');
+  activate.className = 'activate';
+  activate.innerText = 'Activate';
+
+  async function commitConnection() {
+    const val = ident.value.trim();
+    const t = type.value;
+    // Receivers only start from Activate, once their settings are filled in
+    if (!val || t === 'None' || RECEIVER_FIELDS[t]) return;
+
+    try {
+      if (t === 'APRS') {
+        await invoke('set_aprs_callsign', { id: val });
+        await invoke('set_aprs');
+        if (!activeAprsCallsigns.includes(val)) activeAprsCallsigns.push(val);
+      } else if (t === 'Iridium') {
+        await invoke('set_irr_modem', { id: val });
+        await invoke('set_iridium');
+        if (!activeIridiumModems.includes(val)) activeIridiumModems.push(val);
+      }
+
+      await updateConnectedClients();
+      try { await invoke('update'); } catch(e) {}
+
+      setTimeout(()=>{ updateConnectionIndicators().catch(()=>{}); }, 800);
+    } catch (err) {
+      if (console_text) console_text.textContent = 'Error saving connection: ' + err;
+      else console.error('Error saving connection:', err);
+    }
+  }
+
+  remove.addEventListener('click', async () => {
+    const val = ident.value.trim();
+    const t = type.value;
+    if (t === 'APRS') {
+      const idx = activeAprsCallsigns.indexOf(val);
+      if (idx >= 0) activeAprsCallsigns.splice(idx, 1);
+      try { await invoke('set_aprs_callsign', { id: '' }); await invoke('set_aprs'); } catch(e){}
+    } else if (t === 'Iridium') {
+      const idx = activeIridiumModems.indexOf(val);
+      if (idx >= 0) activeIridiumModems.splice(idx, 1);
+      try { await invoke('set_irr_modem', { id: '' }); await invoke('set_iridium'); } catch(e){}
+    } else if (RECEIVER_FIELDS[t] && activate.dataset.active === '1') {
+      try { await invoke('remove_source', { trackType: t, id: val }); } catch(e){}
+    }
+    container.removeChild(entry);
+    await updateConnectedClients();
+    try { await invoke('update'); } catch(e) {}
+    setTimeout(()=>{ updateConnectionIndicators().catch(()=>{}); }, 800);
+  });
+
+  ident.addEventListener('blur', commitConnection);
+  ident.addEventListener('keypress', (e) => { if (e.key === 'Enter') commitConnection(); });
+  type.addEventListener('change', showSettings);
+
+  activate.addEventListener('click', async () => {
+    const val = ident.value.trim();
+    const t = type.value;
+    if (!val || t === 'None') { showConsole('Enter identifier and select method first'); return; }
+    if (activate.dataset.active === '1') {
+      activate.dataset.active = '0';
+      activate.innerText = 'Activate';
+      if (t === 'APRS') {
+        const idx = activeAprsCallsigns.indexOf(val); if (idx >= 0) activeAprsCallsigns.splice(idx, 1);
+        try { await invoke('set_aprs_callsign', { id: '' }); await invoke('set_aprs'); } catch(e) { console.error(e); }
+      } else if (t === 'Iridium') {
+        const idx = activeIridiumModems.indexOf(val); if (idx >= 0) activeIridiumModems.splice(idx, 1);
+        try { await invoke('set_irr_modem', { id: '' }); await invoke('set_iridium'); } catch(e) { console.error(e); }
+      } else if (RECEIVER_FIELDS[t]) {
+        try { await invoke('remove_source', { trackType: t, id: val }); } catch(e) { console.error(e); }
+      }
+      indicator.classList.remove('ok');
+      showConsole('Deactivated ' + val);
+      await updateConnectedClients();
+      return;
+    }
+
+    showConsole('Activating ' + val + '...');
+    if (RECEIVER_FIELDS[t]) {
+      try {
+        if (!await startReceiver(t, val, settingValues())) { showConsole(`Missing settings for ${t}`); return; }
+      } catch (err) {
+        showConsole(`Error starting ${t}: ${err}`);
+        return;
+      }
+    } else {
+      await commitConnection();
+    }
+    activate.dataset.active = '1';
+    activate.innerText = 'Deactivate';
+    indicator.classList.add('pending');
+    setTimeout(async () => { await updateConnectionIndicators(); indicator.classList.remove('pending'); }, 1500);
+  });
+
+  entry.appendChild(indicator);
+  entry.appendChild(type);
+  entry.appendChild(ident);
+  entry.appendChild(activate);
+  entry.appendChild(remove);
+  entry.appendChild(settings);
+
+  container.appendChild(entry);
+  ident.focus();
+}
+
+//------------------------------Connection Handlers------------------------------
+
+
+// update indicators for all connection entries by querying backend validity
+async function updateConnectionIndicators() {
+  try {
+    const entries = document.querySelectorAll('.connection-entry');
+    if (!entries || entries.length === 0) return;
+
+    const aprsValidity = await invoke('get_aprs_validity').catch(() => []);
+    const iridiumValidity = await invoke('get_iridium_validity').catch(() => []);
+    const savedAprsCallsign = await invoke('get_aprs_callsign').catch(()=>null);
+    const savedIrrModem = await invoke('get_irr_modem').catch(()=>null);
+    const sourceHealth = await invoke('get_source_health').catch(() => []);
+
+    entries.forEach(entry => {
+      const sel = entry.querySelector('select');
+      const input = entry.querySelector('input');
+      const indicator = entry.querySelector('.conn-indicator');
+      if (!sel || !input || !indicator) return;
+      const t = sel.value;
+      const id = input.value.trim();
+
+      // clear pending marker if any
+      indicator.classList.remove('pending');
+      if (t === 'APRS') {
+
+        // Prefer exact match with the backend's stored callsign if available
+        let isValid = false;
+        if (savedAprsCallsign && id === savedAprsCallsign) {
+          isValid = aprsValidity.some(v => v === true);
+        } else if (aprsValidity.length > 0 && activeAprsCallsigns.length === aprsValidity.length) {
+          const idx = activeAprsCallsigns.indexOf(id);
+          isValid = (idx >= 0 && aprsValidity[idx]);
+        } else {
+          // fallback: if any validity true, and we have only one active entry, mark it
+          if (aprsValidity.filter(Boolean).length === 1 && activeAprsCallsigns.length === 1 && activeAprsCallsigns[0] === id) isValid = true;
+        }
+        if (isValid) indicator.classList.add('ok'); else indicator.classList.remove('ok');
+      } else if (t === 'Iridium') {
+        let isValid = false;
+        if (savedIrrModem && id === savedIrrModem) {
+          isValid = iridiumValidity.some(v => v === true);
+        } else if (iridiumValidity.length > 0 && activeIridiumModems.length === iridiumValidity.length) {
+          const idx = activeIridiumModems.indexOf(id);
+          isValid = (idx >= 0 && iridiumValidity[idx]);
+        } else {
+          if (iridiumValidity.filter(Boolean).length === 1 && activeIridiumModems.length === 1 && activeIridiumModems[0] === id) isValid = true;
+        }
+        if (isValid) indicator.classList.add('ok'); else indicator.classList.remove('ok');
+      } else if (RECEIVER_FIELDS[t]) {
+        // Receivers report their own health once a frame has been decoded
+        const isValid = sourceHealth.some(s => s.tracking_type === t && s.id.toUpperCase() === id.toUpperCase() && s.health.status === 'Ok');
+        if (isValid) indicator.classList.add('ok'); else indicator.classList.remove('ok');
+      } else {
+        indicator.classList.remove('ok');
+      }
+    });
+  } catch (err) {
+    console.error('Error updating connection indicators:', err);
+  }
+}
+
+//update UTC text and last-update placeholder
+export function updateInfo({utcText, lastUpdate, cityState}){
+    const u = document.getElementById('utc-msg');
+    const l = document.getElementById('last-update');
+    const c = document.getElementById('citystate');
+    if(u && utcText) u.textContent = utcText;
+    if(l && lastUpdate) l.textContent = lastUpdate;
+    if(c && cityState) c.textContent = cityState;
+}
+
+//helper to show short messages in the console area
+function showConsole(msg, timeout=4000) {
+  if (console_text) {
+    console_text.textContent = msg;
+    if (timeout > 0) setTimeout(()=>{ if (console_text && console_text.textContent === msg) console_text.textContent = ''; }, timeout);
+  } else {
+    console.log(msg);
+  }
+}
+
+
+//------------------------------Map Functions/Handlers------------------------------
+
+
+//Init the map iframe
+function initMapIframe() {
+  const mapIframe = document.querySelector('.screen');
+  
+  // Set the iframe source to the map HTML file
+  mapIframe.src = 'map.html';
+  
+  window.addEventListener('message', async (event) => {
+    // Check if the map is ready
+    if (event.data && event.data.type === 'MAP_READY') {
+      console.log('Map is ready');
+      
+      //send current units
+      mapIframe.contentWindow.postMessage({
+        type: 'SET_UNITS',
+        units: currentUnits
+      }, '*');
+      
+      // Send current position if we have it
+      updateMapWithCurrentPosition();
+      
+      // figure out which key to use: user override stored, else env value
+      const storedKey = localStorage.getItem('stadia_api_key') || '';
+      let envKey = '';
+      try {
+        envKey = await invoke('get_stadia_api_key');
+      } catch (e) {
+        console.warn('Unable to fetch env Stadia API key:', e);
+      }
+      const keyToSend = storedKey || envKey || '';
+      if (keyToSend) {
+        sendStadiaKeyToMap(keyToSend);
+      }
+    }
+  });
+}
+
+// Update the map with current position
+async function updateMapWithCurrentPosition() {
+  try {
+    const currentLat = await invoke("get_lat");
+    const currentLong = await invoke("get_long");
+    const altitude = await invoke("get_alt");
+    const horiz = await invoke("get_horiz_vel");
+    const vert = await invoke("get_vert_vel");
+    
+    console.log(`Fetched velocities: H:${horiz} V:${vert}`);
+    
+    if (currentLat !== 0 || currentLong !== 0) {
+      updateMap(currentLat, currentLong, altitude, horiz, vert);
+    }
+  } catch (error) {
+    if (console_text) console_text.textContent = "Error getting position for map update:" + error;
+    else console.error("Error getting position for map update:", error);
+  }
+}
+
+async function updateMap(latitude, longitude, altitude, horiz_vel, vert_vel) {
+  const mapIframe = document.querySelector('.screen');
+  
+  // Make sure iframe is loaded
+  if (!mapIframe || !mapIframe.contentWindow) {
+    console.warn('Map iframe not ready');
+    return;
+  }
+  
+  
+  if (typeof horiz_vel === 'undefined' || typeof vert_vel === 'undefined') {
+    try {
+      horiz_vel = await invoke("get_horiz_vel");
+      vert_vel = await invoke("get_vert_vel");
+    } catch (error) {
+      console.error("Error fetching velocities:", error);
+      horiz_vel = 0;
+      vert_vel = 0;
+    }
+  }
+  // Course over ground in degrees, null while standing still
+  let heading = null;
+  try {
+    heading = await invoke("get_course");
+  } catch (error) {
+    console.error("Error fetching course:", error);
+  }
+  mapIframe.contentWindow.postMessage({
+    type: 'UPDATE_POSITION',
+    lat: latitude,
+    lng: longitude,
+    alt: altitude,
+    horiz_vel: horiz_vel,
+    vert_vel: vert_vel,
+    heading: heading,
+  }, '*');
+}
+
+// send updated Stadia API key to map iframe
+function sendStadiaKeyToMap(key) {
+  const mapIframe = document.querySelector('.screen');
+  if (mapIframe && mapIframe.contentWindow) {
+    mapIframe.contentWindow.postMessage({
+      type: 'SET_STADIA_KEY',
+      key: key || ''
+    }, '*');
+  }
+}
+
+// send current units to the altitude graph iframe
+function sendUnitsToAltitudeGraph() {
+  const altGraphIframe = document.querySelector('#altitude-graph');
+  if (altGraphIframe && altGraphIframe.contentWindow) {
+    altGraphIframe.contentWindow.postMessage({
+      type: 'SET_UNITS',
+      units: currentUnits
+    }, '*');
+  }
+}
+  
+
+
+
+//------------------------------Compass Functions/Handlers------------------------------
+
+//gets the user location using a couple different methods
+async function getUserLocation(){
+  try {
+    // 1) Prefer explicit Ground Station inputs if the user puts it in the Settings panel
+    const gsInputs = document.querySelectorAll('.ground-station input');
+    if (gsInputs && gsInputs.length >= 2) {
+      const latVal = gsInputs[0].value && gsInputs[0].value.trim();
+      const lonVal = gsInputs[1].value && gsInputs[1].value.trim();
+      const latNum = Number(latVal);
+      const lonNum = Number(lonVal);
+      if (!Number.isNaN(latNum) && !Number.isNaN(lonNum) && latVal !== '' && lonVal !== '') {
+        return { latitude: latNum, longitude: lonNum };
+      }
+    }
+    // 2) Fallback to browser geolocation (wrapped as a Promise)
+    return await new Promise((resolve, reject) => {
+      if (!navigator.geolocation) return resolve(null);
+      const options = { timeout: 7000, maximumAge: 0 };
+      navigator.geolocation.getCurrentPosition(
+        (pos) => resolve(pos && pos.coords ? { latitude: pos.coords.latitude, longitude: pos.coords.longitude } : null),
+        (err) => { console.warn(`Geolocation error: ${err && err.message}`); resolve(null); },
+        options
+      );
+    });
+  } catch (err) {
+    console.warn('getUserLocation error:', err);
+    return null;
+  }
+}
+
+function angleFromCoordinate(lat1, long1, lat2, long2){
+  // compute bearing from (lat1,long1) -> (lat2,long2) in degrees (0 = north)
+  const toRad = (d) => d * Math.PI / 180;
+  const toDeg = (r) => r * 180 / Math.PI;
+  const t1 = toRad(lat1);
+  const t2 = toRad(lat2);
+  const delta = toRad(long2 - long1);
+  const y = Math.sin(delta) * Math.cos(t2);
+  const x = Math.cos(t1) * Math.sin(t2) - Math.sin(t1) * Math.cos(t2) * Math.cos(delta);
+  let theta = Math.atan2(y, x);
+  theta = toDeg(theta);
+  return (theta + 360) % 360;
+}
+
+async function updateCompass(lat, long){
+  try {
+    const ucoords = await getUserLocation();
+    if (ucoords && typeof ucoords.latitude === 'number' && typeof ucoords.longitude === 'number'){
+      const ulat = ucoords.latitude;
+      const ulong = ucoords.longitude;
+      const bearing = angleFromCoordinate(ulat, ulong, lat, long);
+      if (typeof setCompassAngle === 'function') setCompassAngle(bearing);
+    } else {
+      if (console_text) console_text.textContent = 'Could not determine user location for compass';
+    }
+  } catch (err) {
+    console.error('updateCompass error:', err);
+  }
+}
+
+
+//------------------------------Altitude Graph------------------------------
+
+//updates the alt graph
+function updateAltitudeGraph(time, alt) {
+    const iframe = document.getElementById('altitude-graph');
+    
+    iframe.contentWindow.postMessage({
+        type: 'ADD_DATA',
+        time: time,
+        alt: alt
+    }, '*'); 
+}
+
+
+
+
+
+
+function initThemeSelector() {
+    const themeSelect = document.querySelector('#settings select');
+
+    if (!themeSelect) return;
+
+    const savedTheme = localStorage.getItem('harp-theme') || 'light';
+    setTheme(savedTheme);
+    themeSelect.value = savedTheme.charAt(0).toUpperCase() + savedTheme.slice(1);
+
+    themeSelect.addEventListener('change', (e) => {
+        const selectedValue = e.target.value.toLowerCase();
+        setTheme(selectedValue);
+    });
+}
+
+function setTheme(theme) {
+    document.documentElement.setAttribute('data-theme', theme);
+    localStorage.setItem('harp-theme', theme);
+    updateIframes(theme);
+}
+
+function updateIframes(theme) {
+    const iframes = document.querySelectorAll('iframe');
+    iframes.forEach(iframe => {
+        iframe.contentWindow.postMessage({ type: 'THEME_CHANGE', theme: theme }, '*');
+    });
+}
+function cleanup() {
+  if (utcIntervalId) clearInterval(utcIntervalId);
+  if (unlistenPositionUpdate) unlistenPositionUpdate();
+  if (unlistenFixRejected) unlistenFixRejected();
+  if (statusIntervalId) clearInterval(statusIntervalId);
+  if (statusIntervalId) clearInterval(statusIntervalId);
+}
+
+
+
+
+// init app once DOM is loaded
+window.addEventListener("DOMContentLoaded", init);
+// Cleanup on page unload if needed
 window.addEventListener("beforeunload", cleanup);
\ No newline at end of file
@@ -263,7 +263,7 @@ async function initMap() {
 function handleMessage(event) {
   const data = event.data;
   if (data && data.type === 'UPDATE_POSITION') {
-    updateMapPosition(data.lat, data.lng, data.alt, data.horiz_vel, data.vert_vel);
+    updateMapPosition(data.lat, data.lng, data.alt, data.horiz_vel, data.vert_vel, data.heading);
   } else if (data && data.type === 'UPDATE_PREDICTION') {
     updatePrediction(data.data);
   } else if (data && data.type === 'SET_AIRCRAFT_RADIUS') {
@@ -462,7 +462,7 @@ function getGmapsLink(lat, lon, zoom = 15) {
     return `https://www.google.com/maps/search/?api=1&query=${coordinates}`;
 }
 
-function updateMapPosition(lat, lng, alt, horiz_vel = 0, vert_vel = 0) {
+function updateMapPosition(lat, lng, alt, horiz_vel = 0, vert_vel = 0, heading = null) {
   if (!map || !marker) return;
   
   if (isNaN(lat) || isNaN(lng) || !isFinite(lat) || !isFinite(lng)) {
@@ -482,6 +482,7 @@ function updateMapPosition(lat, lng, alt, horiz_vel = 0, vert_vel = 0) {
   const altUnit = getUnitLabel('ALTITUDE');
   const horizUnit = getUnitLabel('VELOCITY_HORIZ');
   const vertUnit = getUnitLabel('VELOCITY_VERT');
+  const course = (heading === null || heading === undefined || !isFinite(heading)) ? '--' : `${heading.toFixed(0)}°`;
 
   if (lat !== lastLat || lng !== lastLng) {
     const gMapsUrl = getGmapsLink(lat, lng);
@@ -494,6 +495,7 @@ function updateMapPosition(lat, lng, alt, horiz_vel = 0, vert_vel = 0) {
           Alt: ${displayAlt.toFixed(1)}${altUnit}<br>
           H Vel: ${displayHorizVel.toFixed(2)} ${horizUnit}<br>
           V Vel: ${displayVertVel.toFixed(2)} ${vertUnit}<br>
+          Course: ${course}<br>
           <hr>
           <a href="${gMapsUrl}" target="_blank" style="color: #4285F4; font-weight: bold; text-decoration: none;">
             📍 Open in GMaps
@@ -516,6 +518,7 @@ function updateMapPosition(lat, lng, alt, horiz_vel = 0, vert_vel = 0) {
           Alt: ${displayAlt.toFixed(1)}${altUnit}<br>
           H Vel: ${displayHorizVel.toFixed(2)} ${horizUnit}<br>
           V Vel: ${displayVertVel.toFixed(2)} ${vertUnit}<br>
+          Course: ${course}<br>
           <hr>
           <a href="${gMapsUrl}" target="_blank" style="color: #4285F4; font-weight: bold; text-decoration: none;">
             📍 Open in GMaps